@@ -24,7 +24,7 @@ fn main() {
         eprintln!("ERROR: Failed to compile Slint UI");
         eprintln!("============================================");
         eprintln!("{}", e);
-        eprintln!("");
+        eprintln!();
         eprintln!("Make sure src/ui/main.slint exists and has valid syntax.");
         eprintln!("Check the Slint documentation: https://slint.dev/docs/");
         eprintln!("============================================");
@@ -799,7 +799,7 @@ pub fn install_packages(
 
     for (index, package_id) in install_order.iter().enumerate() {
         if let Some(package) = package_map.get(package_id.as_str()) {
-            progress(&package.display_name, index + 1, total);
+            progress(package.display_name, index + 1, total);
 
             let result = install_package(mount_path, adk_location, package);
             results.push(result);
@@ -0,0 +1,563 @@
+// ============================================
+// MasterBooter - appx.rs
+// ============================================
+// This module removes provisioned Appx packages (the Store apps Windows
+// installs for every new user) from the deployed image.
+//
+// The "bloatware" tweaks only set policy values — the packages stay
+// provisioned and come back for each new account. Removing the
+// provisioned package is the only way to get rid of them for good.
+//
+// Packages are matched by their family name (DisplayName in DISM), with
+// "*" as a wildcard:
+//
+//   Microsoft.BingNews        → exactly that app
+//   Microsoft.Xbox*           → every Xbox package
+//
+// Two ways to remove them (AppxRemovalMode):
+//
+//   Offline    — after setup.exe has applied the image, run
+//                dism /Image:<target> /Remove-ProvisionedAppxPackage
+//                from WinPE (see remove_offline)
+//   AnswerFile — embed a PowerShell script in the answer file's
+//                <Extensions> block and run it as SYSTEM during the
+//                specialize pass (see answer_file_script). Works when the
+//                answer file is used without MasterBooter, too.
+//
+// Both write one CSV row per package to the target:
+//   C:\Temp\MasterBooter\AppxRemoval.csv (Time, Pattern, Package, Result, Detail)
+//
+// Frameworks and the Store itself are protected — removing them breaks
+// every other app and can't be undone without reinstalling Windows.
+// ============================================
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Folder on the target (relative to its drive) that gets the result log
+pub const LOG_DIR: &str = r"Temp\MasterBooter";
+/// Result log file name inside LOG_DIR
+pub const LOG_FILE: &str = "AppxRemoval.csv";
+/// Name of the <Extensions> element holding the removal script
+pub const EXTENSION_NAME: &str = "AppxRemoval";
+
+/// Specialize command that runs the script embedded in the answer file.
+/// Setup copies the answer file to %WINDIR%\Panther\unattend.xml — the
+/// script itself is far too long for a RunSynchronousCommand (259 chars).
+pub const SPECIALIZE_COMMAND: &str = r#"powershell.exe -NoProfile -ExecutionPolicy Bypass -Command "$x = [xml](Get-Content -Raw (Join-Path $env:SystemRoot 'Panther\unattend.xml')); Invoke-Expression $x.unattend.Extensions.AppxRemoval""#;
+
+/// Where the packages are removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppxRemovalMode {
+    /// From WinPE with DISM, right after the image is applied
+    #[default]
+    Offline,
+    /// By a PowerShell step in the answer file (specialize pass)
+    #[serde(rename = "answer_file")]
+    AnswerFile,
+}
+
+impl AppxRemovalMode {
+    /// Name shown in the UI dropdown
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AppxRemovalMode::Offline => "Offline (DISM)",
+            AppxRemovalMode::AnswerFile => "Answer file (PowerShell)",
+        }
+    }
+
+    /// Parse the UI dropdown value
+    pub fn parse(value: &str) -> Option<AppxRemovalMode> {
+        let value = value.trim().to_ascii_lowercase();
+        if value.starts_with("offline") {
+            Some(AppxRemovalMode::Offline)
+        } else if value.starts_with("answer") {
+            Some(AppxRemovalMode::AnswerFile)
+        } else {
+            None
+        }
+    }
+}
+
+// ============================================
+// PRESETS & PROTECTED PACKAGES
+// ============================================
+
+/// A named set of package families the technician can add in one click
+pub struct AppxPreset {
+    pub name: &'static str,
+    pub packages: &'static [&'static str],
+}
+
+/// Presets shown in the UI dropdown
+pub const PRESETS: &[AppxPreset] = &[
+    AppxPreset {
+        name: "Consumer apps",
+        packages: &[
+            "Clipchamp.Clipchamp",
+            "Microsoft.549981C3F5F10", // Cortana
+            "Microsoft.BingNews",
+            "Microsoft.BingWeather",
+            "Microsoft.GetHelp",
+            "Microsoft.Getstarted",
+            "Microsoft.MicrosoftOfficeHub",
+            "Microsoft.MicrosoftSolitaireCollection",
+            "Microsoft.People",
+            "Microsoft.PowerAutomateDesktop",
+            "Microsoft.Todos",
+            "Microsoft.WindowsFeedbackHub",
+            "Microsoft.WindowsMaps",
+            "Microsoft.ZuneMusic",
+            "Microsoft.ZuneVideo",
+        ],
+    },
+    AppxPreset {
+        name: "Xbox & gaming",
+        packages: &[
+            "Microsoft.GamingApp",
+            "Microsoft.Xbox.TCUI",
+            "Microsoft.XboxGameOverlay",
+            "Microsoft.XboxGamingOverlay",
+            "Microsoft.XboxIdentityProvider",
+            "Microsoft.XboxSpeechToTextOverlay",
+        ],
+    },
+    AppxPreset {
+        name: "Communication",
+        packages: &[
+            "Microsoft.OutlookForWindows",
+            "Microsoft.SkypeApp",
+            "Microsoft.YourPhone",
+            "microsoft.windowscommunicationsapps", // Mail and Calendar
+            "MicrosoftTeams",
+            "MSTeams",
+        ],
+    },
+    AppxPreset {
+        name: "AI & Copilot",
+        packages: &[
+            "Microsoft.BingSearch",
+            "Microsoft.Copilot",
+            "Microsoft.Windows.Ai.Copilot.Provider",
+        ],
+    },
+];
+
+/// Packages that are never removed, even when a wildcard matches them
+pub const PROTECTED: &[&str] = &[
+    "Microsoft.DesktopAppInstaller", // winget
+    "Microsoft.NET.Native.*",
+    "Microsoft.SecHealthUI", // Windows Security
+    "Microsoft.StorePurchaseApp",
+    "Microsoft.UI.Xaml.*",
+    "Microsoft.VCLibs.*",
+    "Microsoft.WindowsAppRuntime.*",
+    "Microsoft.WindowsStore",
+];
+
+/// Find a preset by name (case-insensitive)
+pub fn find_preset(name: &str) -> Option<&'static AppxPreset> {
+    PRESETS.iter().find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Add a preset's packages to a list, skipping ones already in it
+pub fn add_preset(packages: &mut Vec<String>, preset: &AppxPreset) {
+    for package in preset.packages {
+        if !packages.iter().any(|p| p.eq_ignore_ascii_case(package)) {
+            packages.push(package.to_string());
+        }
+    }
+}
+
+/// Case-insensitive match of a package family name against a pattern
+/// where "*" matches any run of characters.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let name = name.to_ascii_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+
+    // First part anchors the start, last part anchors the end,
+    // the ones in between must appear in order
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !name.starts_with(first) || name.len() < first.len() + last.len() || !name.ends_with(last) {
+        return false;
+    }
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Is this package family protected from removal?
+pub fn is_protected(name: &str) -> bool {
+    PROTECTED.iter().any(|p| matches_pattern(p, name))
+}
+
+/// Check one entry of the removal list.
+///
+/// # Returns
+/// * `Ok(None)` — fine
+/// * `Ok(Some(warning))` — a wildcard that also matches protected packages
+///   (they're skipped at removal time)
+/// * `Err(String)` — not a package family name, or a protected package
+pub fn check_pattern(pattern: &str) -> Result<Option<String>, String> {
+    if pattern.is_empty() {
+        return Err("Empty Appx package name".to_string());
+    }
+    // Package family names only use these characters — anything else would
+    // also break out of the quoted strings in the generated script
+    if let Some(bad) = pattern.chars().find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '*'))) {
+        return Err(format!("Appx package \"{}\" contains an invalid character: '{}'", pattern, bad));
+    }
+    if pattern.chars().all(|c| c == '*' || c == '.') {
+        return Err(format!("Appx package \"{}\" would match every package", pattern));
+    }
+    if !pattern.contains('*') && is_protected(pattern) {
+        return Err(format!("Appx package \"{}\" is protected (removing it breaks other apps)", pattern));
+    }
+
+    // Protected entries use a wildcard too — compare against their fixed part
+    let hits: Vec<&str> = PROTECTED
+        .iter()
+        .filter(|p| matches_pattern(pattern, p.trim_end_matches(".*")))
+        .copied()
+        .collect();
+    if pattern.contains('*') && !hits.is_empty() {
+        return Ok(Some(format!(
+            "Appx package pattern \"{}\" also matches protected packages ({}) — they will be kept",
+            pattern,
+            hits.join(", ")
+        )));
+    }
+    Ok(None)
+}
+
+// ============================================
+// ANSWER FILE SCRIPT
+// ============================================
+
+/// PowerShell script that removes the packages and writes the result log.
+/// Embedded in the answer file's <Extensions> block (see SPECIALIZE_COMMAND).
+/// Patterns must have passed check_pattern() — they're quoted as is.
+pub fn answer_file_script(patterns: &[String]) -> String {
+    let quote = |items: Vec<&str>| {
+        items.iter().map(|p| format!("    '{}'", p)).collect::<Vec<_>>().join("\n")
+    };
+    let patterns = quote(patterns.iter().map(|p| p.as_str()).collect());
+    let protected = quote(PROTECTED.to_vec());
+
+    format!(
+        r#"
+# MasterBooter: remove provisioned Appx packages
+$patterns = @(
+{patterns}
+)
+$protected = @(
+{protected}
+)
+$log = Join-Path $env:SystemDrive '{log_dir}\{log_file}'
+New-Item -ItemType Directory -Force -Path (Split-Path $log) | Out-Null
+$provisioned = @(Get-AppxProvisionedPackage -Online)
+$results = foreach ($pattern in $patterns) {{
+    $found = @($provisioned | Where-Object {{ $_.DisplayName -like $pattern }})
+    if ($found.Count -eq 0) {{
+        [pscustomobject]@{{ Time = (Get-Date -Format s); Pattern = $pattern; Package = ''; Result = 'not provisioned'; Detail = '' }}
+    }}
+    foreach ($package in $found) {{
+        $row = [pscustomobject]@{{ Time = (Get-Date -Format s); Pattern = $pattern; Package = $package.PackageName; Result = 'removed'; Detail = '' }}
+        if (@($protected | Where-Object {{ $package.DisplayName -like $_ }}).Count -gt 0) {{
+            $row.Result = 'skipped'
+            $row.Detail = 'protected'
+        }} else {{
+            try {{
+                Remove-AppxProvisionedPackage -Online -PackageName $package.PackageName -ErrorAction Stop | Out-Null
+            }} catch {{
+                $row.Result = 'failed'
+                $row.Detail = $_.Exception.Message
+            }}
+        }}
+        $row
+    }}
+}}
+$results | Export-Csv -Path $log -NoTypeInformation -Encoding UTF8
+"#,
+        patterns = patterns,
+        protected = protected,
+        log_dir = LOG_DIR,
+        log_file = LOG_FILE,
+    )
+}
+
+/// Read the package list back out of an embedded removal script
+/// (used when importing an answer file). None if it isn't our script.
+pub fn parse_answer_file_script(script: &str) -> Option<Vec<String>> {
+    let start = script.find("$patterns = @(")?;
+    let body = &script[start..];
+    let end = body.find("\n)")?;
+    Some(
+        body[..end]
+            .lines()
+            .skip(1)
+            .map(|line| line.trim().trim_matches('\'').to_string())
+            .filter(|line| !line.is_empty())
+            .collect(),
+    )
+}
+
+// ============================================
+// OFFLINE REMOVAL (DISM)
+// ============================================
+
+/// One provisioned package as reported by DISM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvisionedPackage {
+    /// Family name (e.g., "Microsoft.BingNews")
+    pub display_name: String,
+    /// Full name (e.g., "Microsoft.BingNews_4.55.62231.0_neutral_~_8wekyb3d8bbwe")
+    pub package_name: String,
+}
+
+/// Result of one pattern/package for the log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovalResult {
+    pub pattern: String,
+    /// Full package name (empty if nothing matched)
+    pub package: String,
+    /// "removed", "not provisioned", "skipped" or "failed"
+    pub result: String,
+    pub detail: String,
+}
+
+/// Parse the package list from dism /Get-ProvisionedAppxPackages.
+/// Each package is a block of "Key : Value" lines.
+pub fn parse_provisioned_packages(dism_output: &str) -> Vec<ProvisionedPackage> {
+    let mut packages = Vec::new();
+    let mut display_name = String::new();
+
+    for line in dism_output.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        match key.trim() {
+            "DisplayName" => display_name = value.trim().to_string(),
+            "PackageName" if !display_name.is_empty() => packages.push(ProvisionedPackage {
+                display_name: std::mem::take(&mut display_name),
+                package_name: value.trim().to_string(),
+            }),
+            _ => {}
+        }
+    }
+    packages
+}
+
+/// Work out what to do with each pattern. `remove` is called for every
+/// package that should go and returns Err with the reason on failure.
+pub fn plan_and_remove(
+    patterns: &[String],
+    provisioned: &[ProvisionedPackage],
+    mut remove: impl FnMut(&ProvisionedPackage) -> Result<(), String>,
+) -> Vec<RemovalResult> {
+    let mut results = Vec::new();
+    for pattern in patterns {
+        let found: Vec<&ProvisionedPackage> = provisioned
+            .iter()
+            .filter(|p| matches_pattern(pattern, &p.display_name))
+            .collect();
+        if found.is_empty() {
+            results.push(RemovalResult {
+                pattern: pattern.clone(),
+                package: String::new(),
+                result: "not provisioned".to_string(),
+                detail: String::new(),
+            });
+        }
+        for package in found {
+            let (result, detail) = if is_protected(&package.display_name) {
+                ("skipped", "protected".to_string())
+            } else {
+                match remove(package) {
+                    Ok(()) => ("removed", String::new()),
+                    Err(e) => ("failed", e),
+                }
+            };
+            results.push(RemovalResult {
+                pattern: pattern.clone(),
+                package: package.package_name.clone(),
+                result: result.to_string(),
+                detail,
+            });
+        }
+    }
+    results
+}
+
+/// Remove the packages from an applied (offline) image with DISM and
+/// write the result log to the target.
+///
+/// BLOCKING — call from a worker thread, not the UI thread.
+///
+/// # Arguments
+/// * `target_drive` — Drive of the applied image (e.g., "C:")
+/// * `patterns` — Package families to remove
+///
+/// # Returns
+/// * `Ok(results)` — one row per package (failures are in the rows)
+/// * `Err(String)` — DISM couldn't list the packages
+pub fn remove_offline(target_drive: &str, patterns: &[String]) -> Result<Vec<RemovalResult>, String> {
+    let image = format!("/Image:{}\\", target_drive);
+    let output = Command::new("dism.exe")
+        .args([image.as_str(), "/English", "/Get-ProvisionedAppxPackages"])
+        .output()
+        .map_err(|e| format!("Failed to run DISM: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        return Err(format!(
+            "DISM couldn't list provisioned packages (exit code {}): {}",
+            output.status.code().unwrap_or(-1),
+            stdout.trim()
+        ));
+    }
+    let provisioned = parse_provisioned_packages(&stdout);
+    println!("[Appx] {} provisioned package(s) in {}", provisioned.len(), target_drive);
+
+    let results = plan_and_remove(patterns, &provisioned, |package| {
+        println!("[Appx] Removing {}", package.package_name);
+        let output = Command::new("dism.exe")
+            .args([
+                image.as_str(),
+                "/English",
+                "/Remove-ProvisionedAppxPackage",
+                &format!("/PackageName:{}", package.package_name),
+            ])
+            .output()
+            .map_err(|e| format!("Failed to run DISM: {}", e))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let message = stdout.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("").trim();
+            Err(format!("DISM exit code {}: {}", output.status.code().unwrap_or(-1), message))
+        }
+    });
+
+    write_log(target_drive, &results)?;
+    Ok(results)
+}
+
+/// Write the result log to <target>\Temp\MasterBooter\AppxRemoval.csv
+fn write_log(target_drive: &str, results: &[RemovalResult]) -> Result<(), String> {
+    let dir = PathBuf::from(format!("{}\\{}", target_drive, LOG_DIR));
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(LOG_FILE);
+
+    let mut writer = csv::Writer::from_path(&path).map_err(|e| format!("Failed to write {}: {}", LOG_FILE, e))?;
+    let time = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let mut write = || -> Result<(), csv::Error> {
+        writer.write_record(["Time", "Pattern", "Package", "Result", "Detail"])?;
+        for r in results {
+            writer.write_record([time.as_str(), &r.pattern, &r.package, &r.result, &r.detail])?;
+        }
+        writer.flush()?;
+        Ok(())
+    };
+    write().map_err(|e| format!("Failed to write {}: {}", LOG_FILE, e))?;
+
+    println!("[Appx] Wrote {}", path.display());
+    Ok(())
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_and_protection() {
+        assert!(matches_pattern("Microsoft.Xbox*", "Microsoft.XboxGamingOverlay"));
+        assert!(matches_pattern("microsoft.bingnews", "Microsoft.BingNews"));
+        assert!(matches_pattern("*Copilot*", "Microsoft.Windows.Ai.Copilot.Provider"));
+        assert!(!matches_pattern("Microsoft.Bing", "Microsoft.BingNews"));
+        assert!(!matches_pattern("Microsoft.Xbox*Overlay", "Microsoft.Xbox.TCUI"));
+
+        assert!(is_protected("Microsoft.VCLibs.140.00.UWPDesktop"));
+        assert!(is_protected("Microsoft.WindowsStore"));
+        assert!(!is_protected("Microsoft.BingNews"));
+
+        assert_eq!(check_pattern("Microsoft.BingNews"), Ok(None));
+        assert!(check_pattern("Microsoft.WindowsStore").is_err());
+        assert!(check_pattern("Microsoft.Bing'; rm").is_err());
+        assert!(check_pattern("*").is_err());
+        let warning = check_pattern("Microsoft.Windows*").unwrap().unwrap();
+        assert!(warning.contains("Microsoft.WindowsStore"), "{}", warning);
+
+        // Presets never contain protected or invalid entries
+        for preset in PRESETS {
+            for package in preset.packages {
+                assert_eq!(check_pattern(package), Ok(None), "{} in {}", package, preset.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_offline_plan_from_dism_output() {
+        let dism = "Deployment Image Servicing and Management tool\n\
+            \n\
+            DisplayName : Microsoft.BingNews\n\
+            Version : 4.55.62231.0\n\
+            PackageName : Microsoft.BingNews_4.55.62231.0_neutral_~_8wekyb3d8bbwe\n\
+            \n\
+            DisplayName : Microsoft.WindowsStore\n\
+            PackageName : Microsoft.WindowsStore_22403.1401.3.0_neutral_~_8wekyb3d8bbwe\n\
+            \n\
+            DisplayName : Microsoft.XboxGamingOverlay\n\
+            PackageName : Microsoft.XboxGamingOverlay_7.324.5171.0_neutral_~_8wekyb3d8bbwe\n\
+            \n\
+            The operation completed successfully.\n";
+        let provisioned = parse_provisioned_packages(dism);
+        assert_eq!(provisioned.len(), 3);
+        assert_eq!(provisioned[1].display_name, "Microsoft.WindowsStore");
+
+        let patterns: Vec<String> = ["Microsoft.BingNews", "Microsoft.Windows*", "Microsoft.Xbox*", "Microsoft.People"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let results = plan_and_remove(&patterns, &provisioned, |p| {
+            if p.display_name.starts_with("Microsoft.Xbox") {
+                Err("0x80070005".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        let summary: Vec<(&str, &str)> = results.iter().map(|r| (r.pattern.as_str(), r.result.as_str())).collect();
+        assert_eq!(summary, vec![
+            ("Microsoft.BingNews", "removed"),
+            ("Microsoft.Windows*", "skipped"),
+            ("Microsoft.Xbox*", "failed"),
+            ("Microsoft.People", "not provisioned"),
+        ]);
+        assert_eq!(results[2].detail, "0x80070005");
+    }
+
+    #[test]
+    fn test_answer_file_script_roundtrip() {
+        let patterns = vec!["Microsoft.BingNews".to_string(), "Microsoft.Xbox*".to_string()];
+        let script = answer_file_script(&patterns);
+        assert!(script.contains("    'Microsoft.Xbox*'\n"));
+        assert!(script.contains("'Microsoft.VCLibs.*'"));
+        assert!(script.contains(r"Join-Path $env:SystemDrive 'Temp\MasterBooter\AppxRemoval.csv'"));
+        assert_eq!(parse_answer_file_script(&script), Some(patterns));
+        assert_eq!(parse_answer_file_script("Write-Host hi"), None);
+        assert!(SPECIALIZE_COMMAND.len() <= 259);
+    }
+}
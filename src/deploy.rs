@@ -16,10 +16,28 @@
 // ============================================
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use crate::unattend::{
+    self, bool_str, AutoLogon, Component, CreatePartition, Extension, LocalAccount, ModifyPartition, Pass,
+    Password, RunSynchronousCommand, Setting, SynchronousCommand, UnattendDocument,
+};
+use crate::appx::{self, AppxRemovalMode};
+use crate::drivers;
+use crate::locale;
+use crate::machine;
+use crate::oem::{self, OemInfo};
+use crate::product_key;
+use crate::secrets;
+use crate::scripts;
+use crate::timezone;
+use crate::tweaks;
+use crate::wifi::{self, WifiProfile};
+use crate::winpe::ValidationResult;
+
 // ============================================
 // ENUMS
 // ============================================
@@ -64,6 +82,80 @@ impl std::fmt::Display for BootMode {
     }
 }
 
+/// Processor architecture of the Windows image being deployed.
+/// Every <component> in the answer file is tagged with this, and Windows
+/// Setup silently ignores components for a different architecture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Architecture {
+    /// x64 — almost every PC we deploy to
+    #[default]
+    Amd64,
+    Arm64,
+    X86,
+}
+
+impl Architecture {
+    /// All supported architectures (used for multi-arch answer files)
+    pub const ALL: [Architecture; 3] = [Architecture::Amd64, Architecture::Arm64, Architecture::X86];
+
+    /// The processorArchitecture="..." value used in answer files
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Architecture::Amd64 => "amd64",
+            Architecture::Arm64 => "arm64",
+            Architecture::X86 => "x86",
+        }
+    }
+
+    /// Parse an architecture name. Accepts the answer file names
+    /// (amd64, arm64, x86) and DISM's names (x64, ARM64, x86).
+    pub fn parse(value: &str) -> Option<Architecture> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "amd64" | "x64" => Some(Architecture::Amd64),
+            "arm64" => Some(Architecture::Arm64),
+            "x86" => Some(Architecture::X86),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Architecture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// What execute() does when machines.csv has no row for the machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnmatchedMachine {
+    /// Ask the technician whether to continue with the profile as is
+    #[default]
+    Ask,
+    /// Stop the deployment
+    Fail,
+}
+
+impl UnmatchedMachine {
+    /// Name shown in the UI dropdown
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnmatchedMachine::Ask => "Ask",
+            UnmatchedMachine::Fail => "Fail",
+        }
+    }
+
+    /// Parse the UI dropdown value
+    pub fn parse(value: &str) -> Option<UnmatchedMachine> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ask" => Some(UnmatchedMachine::Ask),
+            "fail" => Some(UnmatchedMachine::Fail),
+            _ => None,
+        }
+    }
+}
+
 // ============================================
 // GENERIC PRODUCT KEYS (Edition Selectors)
 // ============================================
@@ -192,10 +284,163 @@ impl DiskInfo {
     }
 }
 
+/// Partition layout for the target disk.
+/// Drives BOTH the diskpart pre-format script and the <DiskConfiguration>
+/// in autounattend.xml (see planned_partitions), so they always agree.
+///
+/// Partition order on disk:
+/// - UEFI: EFI → MSR → Windows → [Data] → [Recovery]
+/// - BIOS: System Reserved → Windows → [Data] → [Recovery]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartitionLayout {
+    /// EFI system partition size in MB (System Reserved size on BIOS)
+    pub efi_size_mb: u64,
+    /// Windows partition size in MB (0 = use the rest of the disk)
+    pub os_size_mb: u64,
+    /// WinRE recovery partition at the end of the disk, in MB (0 = none)
+    pub recovery_size_mb: u64,
+    /// Create a data partition after Windows that fills the remaining space
+    pub data_partition: bool,
+    /// Volume label for the data partition
+    pub data_label: String,
+    /// Drive letter for the data partition
+    pub data_letter: String,
+}
+
+impl Default for PartitionLayout {
+    /// The classic MasterBooter layout: 100MB system partition, Windows fills the disk.
+    fn default() -> Self {
+        Self {
+            efi_size_mb: 100,
+            os_size_mb: 0,
+            recovery_size_mb: 0,
+            data_partition: false,
+            data_label: "Data".to_string(),
+            data_letter: "D".to_string(),
+        }
+    }
+}
+
+impl PartitionLayout {
+    /// Check that the layout can actually be created.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.efi_size_mb < 100 {
+            return Err(format!("System partition must be at least 100 MB (got {} MB)", self.efi_size_mb));
+        }
+        if self.recovery_size_mb > 0 && self.recovery_size_mb < 300 {
+            return Err(format!(
+                "Recovery partition must be at least 300 MB for WinRE (got {} MB)",
+                self.recovery_size_mb
+            ));
+        }
+        if self.data_partition {
+            if self.os_size_mb == 0 {
+                return Err("A data partition needs a fixed Windows partition size".to_string());
+            }
+            // C: is Windows, S: is the temporary system letter, X: is WinPE
+            let letter = self.data_letter.trim().to_ascii_uppercase();
+            let valid = letter.len() == 1
+                && letter.chars().all(|c| c.is_ascii_alphabetic())
+                && !matches!(letter.as_str(), "C" | "S" | "X");
+            if !valid {
+                return Err(format!("Invalid data partition drive letter '{}'", self.data_letter));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether Windows Setup can create this layout itself from <CreatePartitions>.
+    /// Setup can't set the GPT attributes a recovery partition needs, so those
+    /// layouts are created by diskpart only and Setup keeps the partitions.
+    pub fn setup_can_create(&self) -> bool {
+        self.recovery_size_mb == 0
+    }
+}
+
+/// A local account to create during setup (one <LocalAccount> entry).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserAccount {
+    /// Account name (e.g., "Admin") — max 20 chars
+    pub name: String,
+    /// Display name shown on the login screen
+    #[serde(default)]
+    pub display_name: String,
+    /// Password (empty = no password)
+    #[serde(default)]
+    pub password: String,
+    /// Local group(s) the account joins, e.g. "Administrators" or "Users".
+    /// Several groups are separated with ';' (same as the answer file).
+    #[serde(default = "default_account_group")]
+    pub group: String,
+    /// Set "password never expires" on this account after setup
+    #[serde(default)]
+    pub password_never_expires: bool,
+}
+
+fn default_account_group() -> String {
+    "Users".to_string()
+}
+
+impl UserAccount {
+    /// Whether the account is a member of the local Administrators group
+    pub fn is_admin(&self) -> bool {
+        self.group.split(';').any(|g| g.trim().eq_ignore_ascii_case("Administrators"))
+    }
+}
+
+/// A command run as SYSTEM in the specialize pass (one <RunSynchronousCommand>),
+/// before any user logs on — for machine-wide settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecializeCommand {
+    /// Order number (1-based, unique and contiguous like FirstLogon commands)
+    pub order: u32,
+    /// Command line (max 259 characters)
+    pub path: String,
+    /// Description shown in Setup logs
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Characters Windows doesn't allow in local account names
+const ILLEGAL_ACCOUNT_NAME_CHARS: &[char] = &['"', '/', '\\', '[', ']', ':', ';', '|', '=', ',', '+', '*', '?', '<', '>', '@'];
+
+/// Check a list of accounts and the autologon choice.
+///
+/// # Returns
+/// * `Ok(())` — all accounts can be created
+/// * `Err(String)` — the first problem found
+pub fn validate_accounts(accounts: &[UserAccount], autologon_account: &str) -> Result<(), String> {
+    for (i, account) in accounts.iter().enumerate() {
+        let name = account.name.trim();
+        if name.is_empty() {
+            return Err(format!("Account #{} has no name", i + 1));
+        }
+        if name.chars().count() > 20 {
+            return Err(format!("Account name '{}' is longer than 20 characters", name));
+        }
+        if let Some(c) = name.chars().find(|c| ILLEGAL_ACCOUNT_NAME_CHARS.contains(c)) {
+            return Err(format!("Account name '{}' contains illegal character '{}'", name, c));
+        }
+        if accounts[..i].iter().any(|other| other.name.trim().eq_ignore_ascii_case(name)) {
+            return Err(format!("Account '{}' is listed twice", name));
+        }
+        if account.group.trim().is_empty() {
+            return Err(format!("Account '{}' has no group", name));
+        }
+    }
+    if !autologon_account.is_empty()
+        && !accounts.iter().any(|a| a.name.trim().eq_ignore_ascii_case(autologon_account.trim()))
+    {
+        return Err(format!("Autologon account '{}' is not one of the local accounts", autologon_account));
+    }
+    Ok(())
+}
+
 /// Main configuration struct — holds ALL deployment settings.
 /// Maps 1:1 to UI properties for easy reading/writing.
 /// Derives Serialize/Deserialize for profile save/load as JSON.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DeployConfig {
     // ============================================
     // Image Selection
@@ -209,40 +454,75 @@ pub struct DeployConfig {
     /// Selected edition index in the WIM (1-based)
     #[serde(default)]
     pub edition_index: u32,
+    /// Windows build of the selected edition (e.g., 22631), detected at
+    /// deploy time. Tweaks outside their min/max build are skipped. 0 = unknown.
+    #[serde(skip)]
+    pub image_build: u32,
+    /// Base profile this profile inherits from (empty = standalone).
+    /// Only fields that differ from the base are saved — see save_profile.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub parent: String,
 
     // ============================================
     // Machine Identity
     // ============================================
-    /// Computer name (max 15 chars, or "*" for auto-generate)
+    /// Computer name (max 15 chars), "*" for auto-generate, or a template
+    /// like "ACME-{SERIAL:8}" resolved at deploy time (see machine.rs)
     pub computer_name: String,
-    /// Windows timezone identifier (e.g., "Eastern Standard Time")
+    /// Windows timezone identifier (e.g., "Eastern Standard Time", see timezone.rs)
     pub timezone: String,
-    /// Language/locale code (e.g., "en-US")
-    pub language: String,
+    /// Display language (UILanguage) — must have a Windows language pack
+    /// (see locale.rs)
+    pub ui_language: String,
+    /// Keyboard(s): "LLLL:KKKKKKKK" pairs or locale names separated by ";"
+    /// (e.g., "0409:00000409;0c0c:00001009")
+    pub input_locale: String,
+    /// Language for non-Unicode programs (e.g., "en-US")
+    pub system_locale: String,
+    /// Date, time, number and currency formats (e.g., "fr-CA")
+    pub user_locale: String,
+    /// Languages installed in the selected image — runtime only, read from
+    /// the WIM at deploy time to warn when ui_language isn't one of them
+    #[serde(skip)]
+    pub image_languages: Vec<String>,
 
     // ============================================
     // Boot & Disk
     // ============================================
     /// Boot mode: UEFI (GPT) or BIOS (MBR)
     pub boot_mode: BootMode,
+    /// Processor architecture of the image (amd64, arm64, x86).
+    /// Inferred from the selected WIM edition at deploy time when possible.
+    #[serde(default)]
+    pub architecture: Architecture,
+    /// Emit every component for all architectures, so one answer file
+    /// works on amd64, arm64 and x86 machines
+    #[serde(default)]
+    pub multi_arch: bool,
     /// Target disk number (-1 = let Windows choose/prompt)
     pub disk_id: i32,
+    /// Partition sizes, recovery and data partitions for the target disk
+    #[serde(default)]
+    pub partition_layout: PartitionLayout,
     /// Enable Windows 11 hardware requirements bypass
     pub bypass_win11: bool,
 
     // ============================================
-    // User Account (creates one new local account)
+    // User Accounts
     // ============================================
-    /// Username for the new account (e.g., "Admin")
-    pub user_name: String,
-    /// Password for the new account (empty = no password)
-    pub user_password: String,
-    /// Display name shown on login screen
-    pub user_display_name: String,
-    /// Whether the user should be an Administrator (true) or standard User (false)
-    pub user_is_admin: bool,
-    /// Automatically log in as this user after setup
-    pub enable_autologon: bool,
+    /// Local accounts to create (e.g., a technician admin + an end-user account)
+    #[serde(default)]
+    pub accounts: Vec<UserAccount>,
+    /// Name of the account that logs in automatically after setup (empty = none)
+    #[serde(default)]
+    pub autologon_account: String,
+    /// Password for the built-in Administrator account (empty = leave it disabled)
+    #[serde(default)]
+    pub administrator_password: String,
+    /// Write passwords in Windows' encoded form (<PlainText>false</PlainText>)
+    /// instead of clear text. Obfuscation only — not encryption.
+    #[serde(default)]
+    pub obfuscate_passwords: bool,
 
     // ============================================
     // OOBE (Out-of-Box Experience) Control
@@ -254,6 +534,14 @@ pub struct DeployConfig {
     /// Skip network configuration (offline install)
     pub skip_network: bool,
 
+    // ============================================
+    // Wi-Fi (see wifi.rs)
+    // ============================================
+    /// Wi-Fi networks imported during specialize, so OOBE and the
+    /// FirstLogon scripts are already online
+    #[serde(default)]
+    pub wifi_profiles: Vec<WifiProfile>,
+
     // ============================================
     // Optional Registration Info
     // ============================================
@@ -266,80 +554,46 @@ pub struct DeployConfig {
     /// Owner name for Windows registration
     #[serde(default)]
     pub owner_name: String,
+    /// OEM details for System > About (see oem.rs)
+    #[serde(default)]
+    pub oem_info: OemInfo,
 
     // ============================================
-    // Privacy & Telemetry (6 toggles)
-    // ============================================
-    /// Disable telemetry and diagnostic data collection
-    pub disable_telemetry: bool,
-    /// Disable location history tracking
-    pub disable_location: bool,
-    /// Disable personalized advertising ID
-    pub disable_ads: bool,
-    /// Disable suggested apps and Content Delivery Manager
-    pub disable_suggested_apps: bool,
-    /// Disable Bing search results in Start menu
-    pub disable_bing_search: bool,
-    /// Disable Windows SmartScreen filter
-    pub disable_smartscreen: bool,
-
-    // ============================================
-    // System Security (6 toggles)
+    // Specialize Commands
     // ============================================
-    /// Enable Remote Desktop (RDP) for remote access
-    pub enable_rdp: bool,
-    /// Disable User Account Control prompts (not recommended)
-    pub disable_uac: bool,
-    /// Disable Windows Defender antivirus (not recommended)
-    pub disable_defender: bool,
-    /// Disable Windows Firewall (not recommended)
-    pub disable_firewall: bool,
-    /// Disable Core Isolation / Virtualization-Based Security
-    pub disable_vbs: bool,
-    /// Disable automatic BitLocker device encryption
-    pub disable_bitlocker: bool,
+    /// Commands run as SYSTEM in the specialize pass, before our own
+    /// specialize steps (Wi-Fi import, Appx removal)
+    #[serde(default)]
+    pub specialize_commands: Vec<SpecializeCommand>,
 
     // ============================================
-    // Performance & Power (3 toggles)
+    // Windows Tweaks (see tweaks.rs)
     // ============================================
-    /// Disable Fast Startup (forces clean cold boot every time)
-    pub disable_fast_startup: bool,
-    /// Enable High Performance power plan
-    pub high_performance: bool,
-    /// Disable System Restore (saves disk space)
-    pub disable_system_restore: bool,
+    /// Ids of the enabled tweaks from the tweak catalog (e.g., "disable_telemetry").
+    /// Applied as FirstLogonCommands in catalog order.
+    #[serde(default)]
+    pub enabled_tweaks: Vec<String>,
 
     // ============================================
-    // UI Customization (7 toggles)
+    // Provisioned Apps (see appx.rs)
     // ============================================
-    /// Show file extensions in Explorer (e.g., "document.docx" instead of "document")
-    pub show_file_extensions: bool,
-    /// Show hidden files and folders in Explorer
-    pub show_hidden_files: bool,
-    /// Use classic right-click context menu on Windows 11
-    pub classic_context_menu: bool,
-    /// Taskbar search display mode: 0=show box, 1=icon only, 2=hidden
-    pub taskbar_search_mode: u8,
-    /// Hide the Task View button on the taskbar
-    pub hide_task_view: bool,
-    /// Hide the Widgets button on the taskbar (Windows 11)
-    pub hide_widgets: bool,
-    /// Left-align the taskbar instead of centered (Windows 11)
-    pub taskbar_left_align: bool,
+    /// Appx package families to remove from the image, "*" as wildcard
+    /// (e.g., "Microsoft.BingNews", "Microsoft.Xbox*")
+    #[serde(default)]
+    pub remove_appx: Vec<String>,
+    /// Remove them offline with DISM after the image is applied, or with
+    /// a PowerShell step in the answer file
+    #[serde(default)]
+    pub appx_removal_mode: AppxRemovalMode,
 
     // ============================================
-    // Bloatware Removal (5 named apps)
+    // Drivers (see drivers.rs)
     // ============================================
-    /// Disable Cortana
-    pub disable_cortana: bool,
-    /// Disable OneDrive auto-install
-    pub disable_onedrive: bool,
-    /// Disable Teams chat icon
-    pub disable_teams: bool,
-    /// Disable Copilot AI assistant
-    pub disable_copilot: bool,
-    /// Disable Widgets service
-    pub disable_widgets_service: bool,
+    /// Folders with storage, chipset or network drivers for the installed
+    /// Windows, searched recursively for .inf files. Relative folders are
+    /// relative to the EXE (e.g., "Drivers\Dell").
+    #[serde(default)]
+    pub driver_paths: Vec<String>,
 
     // ============================================
     // Domain Join (enterprise)
@@ -355,6 +609,10 @@ pub struct DeployConfig {
     /// Domain join password
     #[serde(default)]
     pub domain_password: String,
+    /// Organizational unit for the computer account, e.g.
+    /// "OU=Clients,DC=contoso,DC=com" (empty = the domain's default container)
+    #[serde(default)]
+    pub domain_ou: String,
     /// Workgroup name (used if not joining domain)
     pub workgroup: String,
 
@@ -363,6 +621,13 @@ pub struct DeployConfig {
     // ============================================
     /// Prevent automatic device encryption during setup
     pub prevent_device_encryption: bool,
+    /// What to do when machines.csv exists but has no row for this machine
+    #[serde(default)]
+    pub unmatched_machine: UnmatchedMachine,
+    /// Encrypt passwords and the product key with the technician passphrase
+    /// when saving the profile (see secrets.rs)
+    #[serde(default)]
+    pub encrypt_secrets: bool,
 }
 
 impl Default for DeployConfig {
@@ -374,80 +639,78 @@ impl Default for DeployConfig {
             wim_path: PathBuf::new(),
             edition: String::new(),
             edition_index: 0,
+            image_build: 0,
+            parent: String::new(),
 
             // Machine identity
             computer_name: "*".to_string(), // "*" means auto-generate
             timezone: "Eastern Standard Time".to_string(),
-            language: "en-US".to_string(),
+            ui_language: "en-US".to_string(),
+            input_locale: "0409:00000409".to_string(),
+            system_locale: "en-US".to_string(),
+            user_locale: "en-US".to_string(),
+            image_languages: Vec::new(),
 
             // Boot & Disk
             boot_mode: BootMode::default(),
+            architecture: Architecture::default(),
+            multi_arch: false,
             disk_id: -1, // -1 = let Windows choose
+            partition_layout: PartitionLayout::default(),
             bypass_win11: true,
 
-            // User account — create "Admin" with admin rights
-            user_name: "Admin".to_string(),
-            user_password: String::new(),
-            user_display_name: "Administrator".to_string(),
-            user_is_admin: true,
-            enable_autologon: true,
+            // User accounts — create "Admin" with admin rights and log in as it
+            accounts: vec![UserAccount {
+                name: "Admin".to_string(),
+                display_name: "Administrator".to_string(),
+                password: String::new(),
+                group: "Administrators".to_string(),
+                password_never_expires: true,
+            }],
+            autologon_account: "Admin".to_string(),
+            administrator_password: String::new(),
+            obfuscate_passwords: false,
 
             // OOBE — skip everything for clean automated install
             skip_oobe: true,
             skip_eula: true,
             skip_network: false,
 
+            // Wi-Fi — none, connect by cable or by hand
+            wifi_profiles: Vec::new(),
+
             // Optional registration — empty by default
             product_key: String::new(),
             organization: String::new(),
             owner_name: String::new(),
+            oem_info: OemInfo::default(),
 
-            // Privacy — disable tracking/telemetry for IT deployment
-            disable_telemetry: true,
-            disable_location: true,
-            disable_ads: true,
-            disable_suggested_apps: true,
-            disable_bing_search: true,
-            disable_smartscreen: false, // Keep SmartScreen — it's useful for security
-
-            // Security — keep protections on, enable RDP for remote management
-            enable_rdp: true,
-            disable_uac: false,       // Keep UAC — it's important
-            disable_defender: false,   // Keep Defender — it's important
-            disable_firewall: false,   // Keep Firewall — it's important
-            disable_vbs: false,        // Keep VBS — it's a security feature
-            disable_bitlocker: true,   // Disable auto-encryption (IT controls this)
-
-            // Performance — high performance for workstations
-            disable_fast_startup: true,    // Clean boots are more reliable
-            high_performance: true,        // No sleep/throttling
-            disable_system_restore: false, // Keep System Restore
-
-            // UI — show extensions, classic menu, clean taskbar
-            show_file_extensions: true,
-            show_hidden_files: false,
-            classic_context_menu: true,  // Classic right-click on Win11
-            taskbar_search_mode: 2,      // Hide search box
-            hide_task_view: true,
-            hide_widgets: true,
-            taskbar_left_align: false,   // Keep Win11 centered (default)
-
-            // Bloatware — remove common annoyances
-            disable_cortana: true,
-            disable_onedrive: false,      // Keep OneDrive (many users need it)
-            disable_teams: true,
-            disable_copilot: true,
-            disable_widgets_service: true,
+            // Specialize commands — none
+            specialize_commands: Vec::new(),
+
+            // Tweaks — the catalog's defaults: privacy stuff disabled, RDP
+            // enabled, clean taskbar, bloatware removed, protections kept on
+            enabled_tweaks: tweaks::default_tweak_ids(),
+
+            // Provisioned apps — keep them all until the technician picks some
+            remove_appx: Vec::new(),
+            appx_removal_mode: AppxRemovalMode::default(),
+
+            // Drivers — Windows' own drivers only
+            driver_paths: Vec::new(),
 
             // Domain — workgroup by default
             join_domain: false,
             domain_name: String::new(),
             domain_username: String::new(),
             domain_password: String::new(),
+            domain_ou: String::new(),
             workgroup: "WORKGROUP".to_string(),
 
             // Advanced
             prevent_device_encryption: true,
+            unmatched_machine: UnmatchedMachine::default(),
+            encrypt_secrets: false,
         }
     }
 }
@@ -679,6 +942,94 @@ pub fn parse_wim_editions(image_path: &Path) -> Result<(Vec<WimEdition>, PathBuf
     Ok((editions, wim_path))
 }
 
+/// Architecture, Windows build and languages of one edition in a WIM/ESD
+#[derive(Debug, Clone, PartialEq)]
+pub struct WimDetails {
+    pub architecture: Architecture,
+    /// Windows build number (e.g., 22631), 0 if DISM didn't report it
+    pub build: u32,
+    /// Installed languages (e.g., ["en-US", "fr-FR"]), empty if DISM didn't report them
+    pub languages: Vec<String>,
+}
+
+/// Detect the processor architecture, Windows build and languages of one edition.
+/// Runs: dism.exe /Get-WimInfo /WimFile:<path> /Index:<n>
+/// (the summary without /Index doesn't include them).
+///
+/// BLOCKING — call from a worker thread, not the UI thread.
+///
+/// # Arguments
+/// * `wim_path` — Path to install.wim or install.esd (not an ISO)
+/// * `index` — Edition index (1-based)
+pub fn detect_wim_details(wim_path: &Path, index: u32) -> Result<WimDetails, String> {
+    let output = Command::new("dism.exe")
+        .args([
+            "/Get-WimInfo",
+            &format!("/WimFile:{}", wim_path.display()),
+            &format!("/Index:{}", index),
+        ])
+        .output()
+        .map_err(|e| format!("Failed to run DISM: {}. Is DISM installed?", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        return Err(format!(
+            "DISM failed (exit code {}): {}",
+            output.status.code().unwrap_or(-1),
+            stdout.trim()
+        ));
+    }
+
+    let architecture = parse_wim_architecture(&stdout)
+        .ok_or_else(|| "DISM output has no recognizable Architecture line".to_string())?;
+    let build = parse_wim_build(&stdout).unwrap_or(0);
+    let languages = parse_wim_languages(&stdout);
+    println!(
+        "[Deploy] Edition {} architecture: {}, build: {}, languages: {}",
+        index, architecture, build, languages.join(", ")
+    );
+    Ok(WimDetails { architecture, build, languages })
+}
+
+/// Parse the "Architecture : x64" line from DISM /Get-WimInfo /Index output.
+pub fn parse_wim_architecture(dism_output: &str) -> Option<Architecture> {
+    dism_output
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with("Architecture :") || line.starts_with("Architecture:"))
+        .and_then(|line| line.split(':').nth(1))
+        .and_then(Architecture::parse)
+}
+
+/// Parse the build from the "Version : 10.0.22631" line of DISM /Get-WimInfo /Index output.
+pub fn parse_wim_build(dism_output: &str) -> Option<u32> {
+    dism_output
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with("Version :") || line.starts_with("Version:"))
+        .and_then(|line| line.split(':').nth(1))
+        .and_then(|version| version.trim().split('.').nth(2))
+        .and_then(|build| build.parse().ok())
+}
+
+/// Parse the languages from DISM /Get-WimInfo /Index output:
+///
+/// ```text
+/// Languages :
+///         en-US (Default)
+///         fr-FR
+/// ```
+pub fn parse_wim_languages(dism_output: &str) -> Vec<String> {
+    dism_output
+        .lines()
+        .skip_while(|line| !line.trim().starts_with("Languages"))
+        .skip(1)
+        .take_while(|line| line.starts_with([' ', '\t']) && !line.trim().is_empty())
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
 // ============================================
 // DISK DETECTION
 // ============================================
@@ -881,7 +1232,8 @@ fn get_system_disk_number() -> Option<u32> {
 // ============================================
 
 /// Generate a complete autounattend.xml from the DeployConfig.
-/// Builds the XML from scratch — no template file needed.
+/// Builds a typed UnattendDocument (see unattend.rs) and serializes it —
+/// no template file or hand-indented XML strings needed.
 ///
 /// The XML has three passes:
 /// 1. windowsPE — disk configuration, language, install source
@@ -896,153 +1248,95 @@ fn get_system_disk_number() -> Option<u32> {
 pub fn generate_autounattend(config: &DeployConfig) -> String {
     println!("[Deploy] Generating autounattend.xml...");
 
-    let mut xml = String::new();
+    let xml = build_unattend_document(config).to_xml();
+
+    println!("[Deploy] Generated autounattend.xml ({} bytes)", xml.len());
+    xml
+}
 
-    // XML header
-    xml.push_str(r#"<?xml version="1.0" encoding="utf-8"?>"#);
-    xml.push('\n');
-    xml.push_str(r#"<unattend xmlns="urn:schemas-microsoft-com:unattend">"#);
-    xml.push('\n');
+/// Map a DeployConfig into the typed answer file model.
+/// generate_autounattend() is a thin wrapper that serializes this.
+pub fn build_unattend_document(config: &DeployConfig) -> UnattendDocument {
+    let arch = config.architecture.as_str();
+    let mut doc = UnattendDocument::default();
 
     // ============================================
     // PASS 1: windowsPE — Setup configuration
     // ============================================
-    xml.push_str(r#"    <settings pass="windowsPE">"#);
-    xml.push('\n');
+    let windows_pe = doc.pass_mut(Pass::WindowsPE);
 
     // Microsoft-Windows-International-Core-WinPE — Language settings
-    xml.push_str(r#"        <component name="Microsoft-Windows-International-Core-WinPE" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#);
-    xml.push('\n');
-    xml.push_str(&format!("            <SetupUILanguage>\n                <UILanguage>{}</UILanguage>\n            </SetupUILanguage>\n", escape_xml(&config.language)));
-    xml.push_str(&format!("            <InputLocale>{}</InputLocale>\n", escape_xml(&config.language)));
-    xml.push_str(&format!("            <SystemLocale>{}</SystemLocale>\n", escape_xml(&config.language)));
-    xml.push_str(&format!("            <UILanguage>{}</UILanguage>\n", escape_xml(&config.language)));
-    xml.push_str(&format!("            <UserLocale>{}</UserLocale>\n", escape_xml(&config.language)));
-    xml.push_str("        </component>\n");
+    windows_pe.add(
+        Component::new("Microsoft-Windows-International-Core-WinPE", arch)
+            .with(Setting::group("SetupUILanguage", vec![
+                Setting::value("UILanguage", &config.ui_language),
+            ]))
+            .with(Setting::value("InputLocale", &config.input_locale))
+            .with(Setting::value("SystemLocale", &config.system_locale))
+            .with(Setting::value("UILanguage", &config.ui_language))
+            .with(Setting::value("UserLocale", &config.user_locale)),
+    );
 
     // Microsoft-Windows-Setup — Disk config + image selection
-    xml.push_str(r#"        <component name="Microsoft-Windows-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#);
-    xml.push('\n');
+    let mut setup = Component::new("Microsoft-Windows-Setup", arch);
 
     // Disk configuration (only if user selected a specific disk)
+    // Same partition plan as the diskpart script — see planned_partitions()
+    let plan = planned_partitions(&config.partition_layout, config.boot_mode);
     if config.disk_id >= 0 {
-        xml.push_str("            <DiskConfiguration>\n");
-        xml.push_str("                <WillShowUI>OnError</WillShowUI>\n");
-        xml.push_str(&format!("                <Disk wcm:action=\"add\">\n                    <DiskID>{}</DiskID>\n                    <WillWipeDisk>true</WillWipeDisk>\n", config.disk_id));
+        let setup_creates = config.partition_layout.setup_can_create();
+        let mut disk = vec![
+            Setting::value("DiskID", config.disk_id.to_string()),
+            Setting::value("WillWipeDisk", bool_str(setup_creates)),
+        ];
+        if setup_creates {
+            let create = plan.iter().enumerate().map(|(i, part)| CreatePartition {
+                order: i as u32 + 1,
+                size_mb: part.size_mb,
+                partition_type: part.partition_type.into(),
+            });
+            disk.push(Setting::group("CreatePartitions",
+                create.map(CreatePartition::into_setting).collect()));
+        }
+        // When Setup can't create the layout, diskpart already created and
+        // formatted every partition — Setup only labels them and assigns letters
+        let modify = plan.iter().enumerate().map(|(i, part)| ModifyPartition {
+            order: i as u32 + 1,
+            partition_id: i as u32 + 1,
+            active: part.active,
+            format: part.format.filter(|_| setup_creates).map(Into::into),
+            label: part.label.clone(),
+            letter: part.letter.clone(),
+            type_id: part.type_id.map(Into::into),
+        });
+        disk.push(Setting::group("ModifyPartitions",
+            modify.map(ModifyPartition::into_setting).collect()));
 
-        match config.boot_mode {
-            BootMode::UEFI => {
-                // UEFI: EFI partition (100MB) + MSR (16MB) + OS partition (rest)
-                xml.push_str("                    <CreatePartitions>\n");
-                // Partition 1: EFI System Partition
-                xml.push_str("                        <CreatePartition wcm:action=\"add\">\n");
-                xml.push_str("                            <Order>1</Order>\n");
-                xml.push_str("                            <Size>100</Size>\n");
-                xml.push_str("                            <Type>EFI</Type>\n");
-                xml.push_str("                        </CreatePartition>\n");
-                // Partition 2: Microsoft Reserved
-                xml.push_str("                        <CreatePartition wcm:action=\"add\">\n");
-                xml.push_str("                            <Order>2</Order>\n");
-                xml.push_str("                            <Size>16</Size>\n");
-                xml.push_str("                            <Type>MSR</Type>\n");
-                xml.push_str("                        </CreatePartition>\n");
-                // Partition 3: Windows OS (rest of disk)
-                xml.push_str("                        <CreatePartition wcm:action=\"add\">\n");
-                xml.push_str("                            <Order>3</Order>\n");
-                xml.push_str("                            <Extend>true</Extend>\n");
-                xml.push_str("                            <Type>Primary</Type>\n");
-                xml.push_str("                        </CreatePartition>\n");
-                xml.push_str("                    </CreatePartitions>\n");
-                // Format partitions
-                xml.push_str("                    <ModifyPartitions>\n");
-                // Format EFI as FAT32
-                xml.push_str("                        <ModifyPartition wcm:action=\"add\">\n");
-                xml.push_str("                            <Order>1</Order>\n");
-                xml.push_str("                            <PartitionID>1</PartitionID>\n");
-                xml.push_str("                            <Format>FAT32</Format>\n");
-                xml.push_str("                            <Label>System</Label>\n");
-                xml.push_str("                        </ModifyPartition>\n");
-                // MSR doesn't need formatting (partition 2)
-                xml.push_str("                        <ModifyPartition wcm:action=\"add\">\n");
-                xml.push_str("                            <Order>2</Order>\n");
-                xml.push_str("                            <PartitionID>2</PartitionID>\n");
-                xml.push_str("                        </ModifyPartition>\n");
-                // Format OS partition as NTFS
-                xml.push_str("                        <ModifyPartition wcm:action=\"add\">\n");
-                xml.push_str("                            <Order>3</Order>\n");
-                xml.push_str("                            <PartitionID>3</PartitionID>\n");
-                xml.push_str("                            <Format>NTFS</Format>\n");
-                xml.push_str("                            <Label>Windows</Label>\n");
-                xml.push_str("                            <Letter>C</Letter>\n");
-                xml.push_str("                        </ModifyPartition>\n");
-                xml.push_str("                    </ModifyPartitions>\n");
-            }
-            BootMode::BIOS => {
-                // BIOS: System Reserved (100MB, active) + OS partition (rest)
-                xml.push_str("                    <CreatePartitions>\n");
-                // Partition 1: System Reserved (boot)
-                xml.push_str("                        <CreatePartition wcm:action=\"add\">\n");
-                xml.push_str("                            <Order>1</Order>\n");
-                xml.push_str("                            <Size>100</Size>\n");
-                xml.push_str("                            <Type>Primary</Type>\n");
-                xml.push_str("                        </CreatePartition>\n");
-                // Partition 2: Windows OS (rest of disk)
-                xml.push_str("                        <CreatePartition wcm:action=\"add\">\n");
-                xml.push_str("                            <Order>2</Order>\n");
-                xml.push_str("                            <Extend>true</Extend>\n");
-                xml.push_str("                            <Type>Primary</Type>\n");
-                xml.push_str("                        </CreatePartition>\n");
-                xml.push_str("                    </CreatePartitions>\n");
-                // Format partitions
-                xml.push_str("                    <ModifyPartitions>\n");
-                // System Reserved: NTFS, active
-                xml.push_str("                        <ModifyPartition wcm:action=\"add\">\n");
-                xml.push_str("                            <Order>1</Order>\n");
-                xml.push_str("                            <PartitionID>1</PartitionID>\n");
-                xml.push_str("                            <Active>true</Active>\n");
-                xml.push_str("                            <Format>NTFS</Format>\n");
-                xml.push_str("                            <Label>System Reserved</Label>\n");
-                xml.push_str("                        </ModifyPartition>\n");
-                // OS partition: NTFS
-                xml.push_str("                        <ModifyPartition wcm:action=\"add\">\n");
-                xml.push_str("                            <Order>2</Order>\n");
-                xml.push_str("                            <PartitionID>2</PartitionID>\n");
-                xml.push_str("                            <Format>NTFS</Format>\n");
-                xml.push_str("                            <Label>Windows</Label>\n");
-                xml.push_str("                            <Letter>C</Letter>\n");
-                xml.push_str("                        </ModifyPartition>\n");
-                xml.push_str("                    </ModifyPartitions>\n");
-            }
-        }
-        xml.push_str("                </Disk>\n");
-        xml.push_str("            </DiskConfiguration>\n");
+        setup.push(Setting::group("DiskConfiguration", vec![
+            Setting::value("WillShowUI", "OnError"),
+            Setting::group("Disk", disk).added(),
+        ]));
     }
 
     // Image Install — which edition to install
     if !config.edition.is_empty() {
-        // Tell Setup where to install Windows (which partition)
-        let install_partition = if config.disk_id >= 0 {
-            match config.boot_mode {
-                BootMode::UEFI => "3", // Partition 3 (after EFI and MSR)
-                BootMode::BIOS => "2", // Partition 2 (after System Reserved)
-            }
-        } else {
-            "" // Let Windows choose
-        };
-
-        xml.push_str("            <ImageInstall>\n");
-        xml.push_str("                <OSImage>\n");
+        let mut os_image = Vec::new();
         if config.disk_id >= 0 {
-            xml.push_str(&format!("                    <InstallTo>\n                        <DiskID>{}</DiskID>\n                        <PartitionID>{}</PartitionID>\n                    </InstallTo>\n", config.disk_id, install_partition));
+            // Tell Setup where to install Windows (which partition)
+            os_image.push(Setting::group("InstallTo", vec![
+                Setting::value("DiskID", config.disk_id.to_string()),
+                Setting::value("PartitionID", windows_partition_id(&plan).to_string()),
+            ]));
         }
-        xml.push_str("                    <InstallFrom>\n");
-        xml.push_str("                        <MetaData wcm:action=\"add\">\n");
-        xml.push_str("                            <Key>/IMAGE/NAME</Key>\n");
-        xml.push_str(&format!("                            <Value>{}</Value>\n", escape_xml(&config.edition)));
-        xml.push_str("                        </MetaData>\n");
-        xml.push_str("                    </InstallFrom>\n");
-        xml.push_str("                </OSImage>\n");
-        xml.push_str("            </ImageInstall>\n");
+        os_image.push(Setting::group("InstallFrom", vec![
+            Setting::group("MetaData", vec![
+                Setting::value("Key", "/IMAGE/NAME"),
+                Setting::value("Value", &config.edition),
+            ]).added(),
+        ]));
+        setup.push(Setting::group("ImageInstall", vec![
+            Setting::group("OSImage", os_image),
+        ]));
     }
 
     // Product key in windowsPE pass
@@ -1063,339 +1357,257 @@ pub fn generate_autounattend(config: &DeployConfig) -> String {
         String::new()
     };
 
-    xml.push_str("            <UserData>\n");
-    xml.push_str("                <AcceptEula>true</AcceptEula>\n");
+    let mut user_data = vec![Setting::value("AcceptEula", "true")];
     if !effective_key.is_empty() {
-        xml.push_str("                <ProductKey>\n");
-        xml.push_str(&format!("                    <Key>{}</Key>\n", escape_xml(&effective_key)));
-        xml.push_str("                </ProductKey>\n");
+        user_data.push(Setting::group("ProductKey", vec![
+            Setting::value("Key", effective_key),
+        ]));
+    }
+    setup.push(Setting::group("UserData", user_data));
+    windows_pe.add(setup);
+
+    // Microsoft-Windows-PnpCustomizationsWinPE — extra driver folders.
+    // Setup loads them in WinPE and adds them to the installed Windows.
+    if !config.driver_paths.is_empty() {
+        let paths = config.driver_paths.iter().enumerate().map(|(i, folder)| {
+            Setting::group("PathAndCredentials", vec![
+                Setting::value("Path", drivers::resolve_folder(folder)),
+            ]).added().keyed((i + 1).to_string())
+        });
+        windows_pe.add(
+            Component::new("Microsoft-Windows-PnpCustomizationsWinPE", arch)
+                .with(Setting::group("DriverPaths", paths.collect())),
+        );
     }
-    xml.push_str("            </UserData>\n");
-
-    xml.push_str("        </component>\n");
-    xml.push_str("    </settings>\n");
 
     // ============================================
     // PASS 2: specialize — Machine identity
     // ============================================
-    xml.push_str(r#"    <settings pass="specialize">"#);
-    xml.push('\n');
-    xml.push_str(r#"        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#);
-    xml.push('\n');
-    xml.push_str(&format!("            <ComputerName>{}</ComputerName>\n", escape_xml(&config.computer_name)));
-    xml.push_str(&format!("            <TimeZone>{}</TimeZone>\n", escape_xml(&config.timezone)));
+    let specialize = doc.pass_mut(Pass::Specialize);
 
+    let mut shell = Component::new("Microsoft-Windows-Shell-Setup", arch)
+        .with(Setting::value("ComputerName", &config.computer_name))
+        .with(Setting::value("TimeZone", &config.timezone));
     if !config.organization.is_empty() {
-        xml.push_str(&format!("            <RegisteredOrganization>{}</RegisteredOrganization>\n", escape_xml(&config.organization)));
+        shell.push(Setting::value("RegisteredOrganization", &config.organization));
     }
     if !config.owner_name.is_empty() {
-        xml.push_str(&format!("            <RegisteredOwner>{}</RegisteredOwner>\n", escape_xml(&config.owner_name)));
+        shell.push(Setting::value("RegisteredOwner", &config.owner_name));
     }
-
-    xml.push_str("        </component>\n");
+    // OEM details for System > About; the logo file is copied by execute()
+    if !config.oem_info.is_empty() {
+        let info = &config.oem_info;
+        let logo = if info.logo_path.is_empty() { "" } else { oem::LOGO_PATH };
+        let oem_settings = [
+            ("Logo", logo),
+            ("Manufacturer", info.manufacturer.as_str()),
+            ("Model", info.model.as_str()),
+            ("SupportHours", info.support_hours.as_str()),
+            ("SupportPhone", info.support_phone.as_str()),
+            ("SupportURL", info.support_url.as_str()),
+        ];
+        shell.push(Setting::group("OEMInformation", oem_settings
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| Setting::value(name, *value))
+            .collect()));
+    }
+    specialize.add(shell);
 
     // Prevent device encryption during specialize pass
     if config.prevent_device_encryption {
-        xml.push_str(r#"        <component name="Microsoft-Windows-SecureStartup-FilterDriver" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#);
-        xml.push('\n');
-        xml.push_str("            <PreventDeviceEncryption>true</PreventDeviceEncryption>\n");
-        xml.push_str("        </component>\n");
+        specialize.add(
+            Component::new("Microsoft-Windows-SecureStartup-FilterDriver", arch)
+                .with(Setting::value("PreventDeviceEncryption", "true")),
+        );
     }
 
-    xml.push_str("    </settings>\n");
+    // Commands run as SYSTEM before any user logs on. The technician's
+    // commands keep their own order numbers, so the linter checks them like
+    // FirstLogon commands; our own steps are numbered after them.
+    let mut commands: Vec<Setting> = config
+        .specialize_commands
+        .iter()
+        .map(|command| RunSynchronousCommand {
+            order: command.order,
+            path: command.path.clone(),
+            description: command.description.clone(),
+        }.into_setting())
+        .collect();
+    let next_order = config.specialize_commands.iter().map(|c| c.order).max().unwrap_or(0) + 1;
 
-    // ============================================
-    // PASS 3: oobeSystem — User, OOBE, Tweaks
-    // ============================================
-    xml.push_str(r#"    <settings pass="oobeSystem">"#);
-    xml.push('\n');
-    xml.push_str(r#"        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#);
-    xml.push('\n');
-
-    // Auto-logon configuration (optional)
-    if config.enable_autologon && !config.user_name.is_empty() {
-        xml.push_str("            <AutoLogon>\n");
-        xml.push_str("                <Enabled>true</Enabled>\n");
-        xml.push_str("                <LogonCount>1</LogonCount>\n");
-        xml.push_str(&format!("                <Username>{}</Username>\n", escape_xml(&config.user_name)));
-        if !config.user_password.is_empty() {
-            xml.push_str("                <Password>\n");
-            xml.push_str(&format!("                    <Value>{}</Value>\n", escape_xml(&config.user_password)));
-            xml.push_str("                    <PlainText>true</PlainText>\n");
-            xml.push_str("                </Password>\n");
-        }
-        xml.push_str("            </AutoLogon>\n");
-    }
-
-    // User account creation
-    if !config.user_name.is_empty() {
-        xml.push_str("            <UserAccounts>\n");
-        xml.push_str("                <LocalAccounts>\n");
-        xml.push_str("                    <LocalAccount wcm:action=\"add\">\n");
-        xml.push_str(&format!("                        <Name>{}</Name>\n", escape_xml(&config.user_name)));
-        if !config.user_display_name.is_empty() {
-            xml.push_str(&format!("                        <DisplayName>{}</DisplayName>\n", escape_xml(&config.user_display_name)));
-        }
-        // Group: Administrators or Users
-        let group = if config.user_is_admin { "Administrators" } else { "Users" };
-        xml.push_str(&format!("                        <Group>{}</Group>\n", group));
-        if !config.user_password.is_empty() {
-            xml.push_str("                        <Password>\n");
-            xml.push_str(&format!("                            <Value>{}</Value>\n", escape_xml(&config.user_password)));
-            xml.push_str("                            <PlainText>true</PlainText>\n");
-            xml.push_str("                        </Password>\n");
-        }
-        xml.push_str("                    </LocalAccount>\n");
-        xml.push_str("                </LocalAccounts>\n");
-        xml.push_str("            </UserAccounts>\n");
-    }
+    // Our own steps, as (path, description)
+    let mut run_synchronous: Vec<(&str, &str)> = Vec::new();
 
-    // OOBE settings
-    xml.push_str("            <OOBE>\n");
-    if config.skip_eula {
-        xml.push_str("                <HideEULAPage>true</HideEULAPage>\n");
-    }
-    if config.skip_oobe {
-        xml.push_str("                <HideOEMRegistrationScreen>true</HideOEMRegistrationScreen>\n");
-        xml.push_str("                <HideOnlineAccountScreens>true</HideOnlineAccountScreens>\n");
-        xml.push_str("                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>\n");
-        xml.push_str("                <SkipMachineOOBE>true</SkipMachineOOBE>\n");
-        xml.push_str("                <SkipUserOOBE>true</SkipUserOOBE>\n");
+    // Import the Wi-Fi profiles execute() wrote to the target, then delete
+    // the files (they hold the keys in clear text)
+    if !config.wifi_profiles.is_empty() {
+        run_synchronous.push((wifi::IMPORT_COMMAND, "Import Wi-Fi profiles"));
+        run_synchronous.push((wifi::CLEANUP_COMMAND, "Delete Wi-Fi profile files"));
     }
-    if config.skip_network {
-        xml.push_str("                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>\n");
-    }
-    xml.push_str("                <ProtectYourPC>3</ProtectYourPC>\n"); // 3 = Don't change settings
-    xml.push_str("                <NetworkLocation>Work</NetworkLocation>\n");
-    xml.push_str("            </OOBE>\n");
 
-    // FirstLogonCommands — all the tweaks run here after first login
-    let first_logon = build_first_logon_commands(config);
-    if !first_logon.is_empty() {
-        xml.push_str("            <FirstLogonCommands>\n");
-        xml.push_str(&first_logon);
-        xml.push_str("            </FirstLogonCommands>\n");
+    // Remove provisioned Appx packages. The script is too long for a
+    // command line, so it rides along in <Extensions>.
+    let appx_in_answer_file = config.appx_removal_mode == AppxRemovalMode::AnswerFile && !config.remove_appx.is_empty();
+    if appx_in_answer_file {
+        run_synchronous.push((appx::SPECIALIZE_COMMAND, "Remove provisioned Appx packages"));
     }
 
-    xml.push_str("        </component>\n");
-
-    // International settings for oobeSystem pass
-    xml.push_str(r#"        <component name="Microsoft-Windows-International-Core" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#);
-    xml.push('\n');
-    xml.push_str(&format!("            <InputLocale>{}</InputLocale>\n", escape_xml(&config.language)));
-    xml.push_str(&format!("            <SystemLocale>{}</SystemLocale>\n", escape_xml(&config.language)));
-    xml.push_str(&format!("            <UILanguage>{}</UILanguage>\n", escape_xml(&config.language)));
-    xml.push_str(&format!("            <UserLocale>{}</UserLocale>\n", escape_xml(&config.language)));
-    xml.push_str("        </component>\n");
-
-    xml.push_str("    </settings>\n");
-
-    // Close the root element
-    xml.push_str("</unattend>\n");
-
-    println!("[Deploy] Generated autounattend.xml ({} bytes)", xml.len());
-    xml
-}
-
-/// Build the <FirstLogonCommands> section from config tweak toggles.
-/// Each enabled tweak adds a <SynchronousCommand> with an incrementing Order number.
-/// Commands are organized by category: Privacy → Security → Performance → UI → Bloatware → Domain.
-fn build_first_logon_commands(config: &DeployConfig) -> String {
-    let mut commands = String::new();
-    let mut order: u32 = 1; // Order number for each command (must be unique)
+    commands.extend(run_synchronous.iter().enumerate().map(|(i, (path, description))| RunSynchronousCommand {
+        order: next_order + i as u32,
+        path: path.to_string(),
+        description: description.to_string(),
+    }.into_setting()));
+    if !commands.is_empty() {
+        specialize.add(
+            Component::new("Microsoft-Windows-Deployment", arch)
+                .with(Setting::group("RunSynchronous", commands)),
+        );
+    }
 
     // ============================================
-    // PRIVACY & TELEMETRY
+    // PASS 3: oobeSystem — User, OOBE, Tweaks
     // ============================================
-    if config.disable_telemetry {
-        add_reg_command(&mut commands, &mut order,"Disable Telemetry",
-            r"HKLM\SOFTWARE\Policies\Microsoft\Windows\DataCollection",
-            "AllowTelemetry", "REG_DWORD", "0");
-        add_reg_command(&mut commands, &mut order,"Disable Telemetry (user)",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Privacy",
-            "TailoredExperiencesWithDiagnosticDataEnabled", "REG_DWORD", "0");
-    }
-
-    if config.disable_location {
-        add_reg_command(&mut commands, &mut order,"Disable Location Tracking",
-            r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\location",
-            "Value", "REG_SZ", "Deny");
-    }
-
-    if config.disable_ads {
-        add_reg_command(&mut commands, &mut order,"Disable Advertising ID",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\AdvertisingInfo",
-            "Enabled", "REG_DWORD", "0");
-    }
-
-    if config.disable_suggested_apps {
-        add_reg_command(&mut commands, &mut order,"Disable Suggested Apps",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\ContentDeliveryManager",
-            "SubscribedContent-338388Enabled", "REG_DWORD", "0");
-        add_reg_command(&mut commands, &mut order,"Disable Suggested Apps (2)",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\ContentDeliveryManager",
-            "SubscribedContent-338389Enabled", "REG_DWORD", "0");
-        add_reg_command(&mut commands, &mut order,"Disable Suggested Apps (3)",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\ContentDeliveryManager",
-            "SystemPaneSuggestionsEnabled", "REG_DWORD", "0");
+    let oobe_system = doc.pass_mut(Pass::OobeSystem);
+    let mut shell = Component::new("Microsoft-Windows-Shell-Setup", arch);
+
+    // Passwords are written in clear text, or encoded when obfuscation is on.
+    // The encoding depends on the element name, so it's passed in.
+    let password_for = |value: &str, element: &str| {
+        if value.is_empty() {
+            None
+        } else if config.obfuscate_passwords {
+            Some(Password::encoded(value, element))
+        } else {
+            Some(Password::plain(value))
+        }
+    };
+    let password = |account: &UserAccount| password_for(&account.password, "Password");
+
+    // Auto-logon configuration (optional) — logs on as one of the accounts
+    let autologon = config.accounts.iter().find(|a| {
+        !config.autologon_account.is_empty() && a.name.eq_ignore_ascii_case(&config.autologon_account)
+    });
+    if let Some(account) = autologon {
+        shell.push(AutoLogon {
+            username: account.name.clone(),
+            password: password(account),
+            logon_count: 1,
+        }.into_setting());
     }
 
-    if config.disable_bing_search {
-        add_reg_command(&mut commands, &mut order,"Disable Bing Search in Start",
-            r"HKCU\SOFTWARE\Policies\Microsoft\Windows\Explorer",
-            "DisableSearchBoxSuggestions", "REG_DWORD", "1");
+    // User account creation — one <LocalAccount> per account
+    let local_accounts: Vec<Setting> = config
+        .accounts
+        .iter()
+        .filter(|a| !a.name.is_empty())
+        .map(|a| LocalAccount {
+            name: a.name.clone(),
+            display_name: a.display_name.clone(),
+            group: a.group.clone(),
+            password: password(a),
+        }.into_setting())
+        .collect();
+    let mut user_accounts = Vec::new();
+    if let Some(admin) = password_for(&config.administrator_password, "AdministratorPassword") {
+        user_accounts.push(admin.into_setting("AdministratorPassword"));
     }
-
-    if config.disable_smartscreen {
-        add_reg_command(&mut commands, &mut order,"Disable SmartScreen",
-            r"HKLM\SOFTWARE\Policies\Microsoft\Windows\System",
-            "EnableSmartScreen", "REG_DWORD", "0");
+    if !local_accounts.is_empty() {
+        user_accounts.push(Setting::group("LocalAccounts", local_accounts));
     }
-
-    // ============================================
-    // SECURITY
-    // ============================================
-    if config.enable_rdp {
-        add_reg_command(&mut commands, &mut order,"Enable RDP",
-            r"HKLM\SYSTEM\CurrentControlSet\Control\Terminal Server",
-            "fDenyTSConnections", "REG_DWORD", "0");
-        add_raw_command(&mut commands, &mut order, "Allow RDP through firewall",
-            "netsh advfirewall firewall set rule group=&quot;Remote Desktop&quot; new enable=Yes");
+    if !user_accounts.is_empty() {
+        shell.push(Setting::group("UserAccounts", user_accounts));
     }
 
-    if config.disable_uac {
-        add_reg_command(&mut commands, &mut order,"Disable UAC",
-            r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Policies\System",
-            "EnableLUA", "REG_DWORD", "0");
+    // OOBE settings
+    let mut oobe = Vec::new();
+    if config.skip_eula {
+        oobe.push(Setting::value("HideEULAPage", "true"));
     }
-
-    if config.disable_defender {
-        add_reg_command(&mut commands, &mut order,"Disable Windows Defender",
-            r"HKLM\SOFTWARE\Policies\Microsoft\Windows Defender",
-            "DisableAntiSpyware", "REG_DWORD", "1");
-        add_reg_command(&mut commands, &mut order,"Disable Real-Time Protection",
-            r"HKLM\SOFTWARE\Policies\Microsoft\Windows Defender\Real-Time Protection",
-            "DisableRealtimeMonitoring", "REG_DWORD", "1");
+    if config.skip_oobe {
+        oobe.push(Setting::value("HideOEMRegistrationScreen", "true"));
+        oobe.push(Setting::value("HideOnlineAccountScreens", "true"));
+        oobe.push(Setting::value("HideWirelessSetupInOOBE", "true"));
+        oobe.push(Setting::value("SkipMachineOOBE", "true"));
+        oobe.push(Setting::value("SkipUserOOBE", "true"));
     }
-
-    if config.disable_firewall {
-        add_raw_command(&mut commands, &mut order, "Disable Firewall (Domain)",
-            "netsh advfirewall set domainprofile state off");
-        add_raw_command(&mut commands, &mut order, "Disable Firewall (Private)",
-            "netsh advfirewall set privateprofile state off");
-        add_raw_command(&mut commands, &mut order, "Disable Firewall (Public)",
-            "netsh advfirewall set publicprofile state off");
+    if config.skip_network {
+        oobe.push(Setting::value("HideWirelessSetupInOOBE", "true"));
     }
+    oobe.push(Setting::value("ProtectYourPC", "3")); // 3 = Don't change settings
+    oobe.push(Setting::value("NetworkLocation", "Work"));
+    shell.push(Setting::group("OOBE", oobe));
 
-    if config.disable_vbs {
-        add_reg_command(&mut commands, &mut order,"Disable VBS/Core Isolation",
-            r"HKLM\SYSTEM\CurrentControlSet\Control\DeviceGuard",
-            "EnableVirtualizationBasedSecurity", "REG_DWORD", "0");
+    // FirstLogonCommands — all the tweaks run here after first login
+    let first_logon = build_first_logon_commands(config);
+    if !first_logon.is_empty() {
+        shell.push(Setting::group("FirstLogonCommands",
+            first_logon.into_iter().map(SynchronousCommand::into_setting).collect()));
     }
+    oobe_system.add(shell);
 
-    if config.disable_bitlocker {
-        add_reg_command(&mut commands, &mut order,"Disable BitLocker Auto-Encryption",
-            r"HKLM\SYSTEM\CurrentControlSet\Control\BitLocker",
-            "PreventDeviceEncryption", "REG_DWORD", "1");
+    // International settings for oobeSystem pass
+    oobe_system.add(
+        Component::new("Microsoft-Windows-International-Core", arch)
+            .with(Setting::value("InputLocale", &config.input_locale))
+            .with(Setting::value("SystemLocale", &config.system_locale))
+            .with(Setting::value("UILanguage", &config.ui_language))
+            .with(Setting::value("UserLocale", &config.user_locale)),
+    );
+
+    // Multi-arch: repeat every component for each architecture.
+    // Setup only applies the copy matching the machine it runs on.
+    if config.multi_arch {
+        let all: Vec<&str> = Architecture::ALL.iter().map(|a| a.as_str()).collect();
+        doc.expand_architectures(&all);
     }
 
-    // ============================================
-    // PERFORMANCE
-    // ============================================
-    if config.disable_fast_startup {
-        add_reg_command(&mut commands, &mut order,"Disable Fast Startup",
-            r"HKLM\SYSTEM\CurrentControlSet\Control\Session Manager\Power",
-            "HiberbootEnabled", "REG_DWORD", "0");
+    if appx_in_answer_file {
+        doc.extensions.push(Extension {
+            name: appx::EXTENSION_NAME.to_string(),
+            text: appx::answer_file_script(&config.remove_appx),
+        });
     }
 
-    if config.high_performance {
-        add_raw_command(&mut commands, &mut order, "Set High Performance Power Plan",
-            "powercfg /setactive 8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c");
-    }
+    doc
+}
 
-    if config.disable_system_restore {
-        add_reg_command(&mut commands, &mut order,"Disable System Restore",
-            r"HKLM\SOFTWARE\Policies\Microsoft\Windows NT\SystemRestore",
-            "DisableSR", "REG_DWORD", "1");
-    }
+/// Build the <FirstLogonCommands> list from config tweak toggles.
+/// Each enabled tweak adds a SynchronousCommand with an incrementing Order number.
+/// Commands are organized by category: Privacy → Security → Performance → UI → Bloatware → Accounts → Domain.
+fn build_first_logon_commands(config: &DeployConfig) -> Vec<SynchronousCommand> {
+    let mut commands = Vec::new();
+    let mut order: u32 = 1; // Order number for each command (must be unique)
 
     // ============================================
-    // UI CUSTOMIZATION
+    // WINDOWS TWEAKS (from the tweak catalog)
     // ============================================
-    if config.show_file_extensions {
-        add_reg_command(&mut commands, &mut order,"Show File Extensions",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Explorer\Advanced",
-            "HideFileExt", "REG_DWORD", "0");
-    }
-
-    if config.show_hidden_files {
-        add_reg_command(&mut commands, &mut order,"Show Hidden Files",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Explorer\Advanced",
-            "Hidden", "REG_DWORD", "1");
-    }
-
-    if config.classic_context_menu {
-        add_reg_command(&mut commands, &mut order,"Classic Context Menu (Win11)",
-            r"HKCU\Software\Classes\CLSID\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\InprocServer32",
-            "", "REG_SZ", "");
-    }
-
-    if config.taskbar_search_mode > 0 {
-        // 0=show, 1=icon, 2=hidden
-        add_reg_command(&mut commands, &mut order,"Configure Taskbar Search",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Search",
-            "SearchboxTaskbarMode", "REG_DWORD", &config.taskbar_search_mode.to_string());
-    }
-
-    if config.hide_task_view {
-        add_reg_command(&mut commands, &mut order,"Hide Task View Button",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Explorer\Advanced",
-            "ShowTaskViewButton", "REG_DWORD", "0");
-    }
-
-    if config.hide_widgets {
-        add_reg_command(&mut commands, &mut order,"Hide Widgets Button",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Explorer\Advanced",
-            "TaskbarDa", "REG_DWORD", "0");
-    }
-
-    if config.taskbar_left_align {
-        add_reg_command(&mut commands, &mut order,"Left-align Taskbar (Win11)",
-            r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Explorer\Advanced",
-            "TaskbarAl", "REG_DWORD", "0");
+    // Registry values first, then commands, tweak by tweak in catalog order.
+    // Tweaks that don't apply to the image's Windows build are skipped.
+    let catalog = tweaks::load_catalog();
+    for tweak in catalog.enabled(&config.enabled_tweaks) {
+        if !tweak.applies_to_build(config.image_build) {
+            println!("[Deploy] Skipping tweak '{}' ({} only, image is build {})",
+                tweak.id, tweak.build_range(), config.image_build);
+            continue;
+        }
+        let description = |text: &str| if text.is_empty() { tweak.name.clone() } else { text.to_string() };
+        for op in &tweak.registry {
+            add_reg_command(&mut commands, &mut order, &description(&op.description),
+                &op.full_key(), &op.value, &op.reg_type, &op.data);
+        }
+        for command in &tweak.commands {
+            add_raw_command(&mut commands, &mut order, &description(&command.description), &command.command);
+        }
     }
 
     // ============================================
-    // BLOATWARE REMOVAL
+    // ACCOUNTS: Password never expires (per account)
     // ============================================
-    if config.disable_cortana {
-        add_reg_command(&mut commands, &mut order,"Disable Cortana",
-            r"HKLM\SOFTWARE\Policies\Microsoft\Windows\Windows Search",
-            "AllowCortana", "REG_DWORD", "0");
-    }
-
-    if config.disable_onedrive {
-        add_reg_command(&mut commands, &mut order,"Disable OneDrive",
-            r"HKLM\SOFTWARE\Policies\Microsoft\Windows\OneDrive",
-            "DisableFileSyncNGSC", "REG_DWORD", "1");
-    }
-
-    if config.disable_teams {
-        add_reg_command(&mut commands, &mut order,"Disable Teams Chat",
-            r"HKLM\SOFTWARE\Policies\Microsoft\Windows\Windows Chat",
-            "ChatIcon", "REG_DWORD", "3");
-    }
-
-    if config.disable_copilot {
-        add_reg_command(&mut commands, &mut order,"Disable Copilot",
-            r"HKCU\SOFTWARE\Policies\Microsoft\Windows\WindowsCopilot",
-            "TurnOffWindowsCopilot", "REG_DWORD", "1");
-    }
-
-    if config.disable_widgets_service {
-        add_reg_command(&mut commands, &mut order,"Disable Widgets Service",
-            r"HKLM\SOFTWARE\Policies\Microsoft\Dsh",
-            "AllowNewsAndInterests", "REG_DWORD", "0");
+    for account in config.accounts.iter().filter(|a| a.password_never_expires && !a.name.is_empty()) {
+        add_ps_command(&mut commands, &mut order,
+            &format!("Password never expires: {}", account.name),
+            &format!("Set-LocalUser -Name '{}' -PasswordNeverExpires $true", account.name.replace('\'', "''")));
     }
 
     // ============================================
@@ -1410,27 +1622,24 @@ fn build_first_logon_commands(config: &DeployConfig) -> String {
             config.domain_password.replace('\'', "''")
         );
 
+        if !config.domain_ou.is_empty() {
+            ps_cmd.push_str(&format!(" -OUPath '{}'", config.domain_ou.replace('\'', "''")));
+        }
+
         // Force restart after domain join
         ps_cmd.push_str(" -Restart -Force");
 
         add_ps_command(&mut commands, &mut order, "Join Domain", &ps_cmd);
     }
 
-    // ============================================
-    // ALWAYS: Set password to never expire
-    // ============================================
-    add_raw_command(&mut commands, &mut order,
-        "Set password to never expire",
-        "net accounts /maxpwage:unlimited");
-
     // ============================================
     // POST-INSTALL SCRIPTS (if any exist)
     // ============================================
     // If the user added FirstLogon scripts, add a final command that
-    // runs RunAll.bat (which executes each script in order with logging).
-    // The scripts + RunAll.bat are copied to C:\Temp\MasterBooter\ by
+    // runs RunAll.bat (which starts the resumable runner, RunAll.ps1).
+    // The scripts + runner are copied to C:\Temp\MasterBooter\ by
     // copy_scripts_to_target(false) during Step 7 of the deployment pipeline.
-    // RunAll.bat logs all output to C:\Temp\MasterBooter\RunAll.log.
+    // The runner logs all output to C:\Temp\MasterBooter\RunAll.log.
     let firstlogon_scripts = list_scripts("FirstLogon");
     if !firstlogon_scripts.is_empty() {
         add_raw_command(&mut commands, &mut order,
@@ -1441,91 +1650,233 @@ fn build_first_logon_commands(config: &DeployConfig) -> String {
     commands
 }
 
-/// Escape special XML characters in a string.
-/// Replaces: & < > " '
-fn escape_xml(s: &str) -> String {
-    s.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&apos;")
-}
-
-/// Helper: Add a registry command to the FirstLogonCommands XML.
+/// Helper: Add a registry command to the FirstLogonCommands list.
 /// Builds: reg add "KEY" /v NAME /t TYPE /d DATA /f
-fn add_reg_command(commands: &mut String, order: &mut u32,
+fn add_reg_command(commands: &mut Vec<SynchronousCommand>, order: &mut u32,
     description: &str, key: &str, value: &str, reg_type: &str, data: &str)
 {
-    // RequiresUserInput=false tells Windows this command doesn't need
-    // user interaction, preventing unnecessary delays during setup.
-    commands.push_str(&format!(
-        "                <SynchronousCommand wcm:action=\"add\">\n\
-         \x20                   <Order>{}</Order>\n\
-         \x20                   <CommandLine>reg add &quot;{}&quot; /v {} /t {} /d {} /f</CommandLine>\n\
-         \x20                   <Description>{}</Description>\n\
-         \x20                   <RequiresUserInput>false</RequiresUserInput>\n\
-         \x20               </SynchronousCommand>\n",
-        order,
-        escape_xml(key),
-        escape_xml(value),
-        escape_xml(reg_type),
-        escape_xml(data),
-        escape_xml(description)
-    ));
-    *order += 1;
+    let command = format!("reg add \"{}\" /v {} /t {} /d {} /f", key, value, reg_type, data);
+    add_raw_command(commands, order, description, &command);
 }
 
-/// Helper: Add a raw command to the FirstLogonCommands XML.
-fn add_raw_command(commands: &mut String, order: &mut u32, description: &str, command: &str) {
-    commands.push_str(&format!(
-        "                <SynchronousCommand wcm:action=\"add\">\n\
-         \x20                   <Order>{}</Order>\n\
-         \x20                   <CommandLine>{}</CommandLine>\n\
-         \x20                   <Description>{}</Description>\n\
-         \x20                   <RequiresUserInput>false</RequiresUserInput>\n\
-         \x20               </SynchronousCommand>\n",
-        order,
-        escape_xml(command),
-        escape_xml(description)
-    ));
+/// Helper: Add a raw command to the FirstLogonCommands list.
+fn add_raw_command(commands: &mut Vec<SynchronousCommand>, order: &mut u32, description: &str, command: &str) {
+    commands.push(SynchronousCommand {
+        order: *order,
+        command_line: command.to_string(),
+        description: description.to_string(),
+    });
     *order += 1;
 }
 
-/// Helper: Add a PowerShell command to the FirstLogonCommands XML.
-fn add_ps_command(commands: &mut String, order: &mut u32, description: &str, ps_command: &str) {
+/// Helper: Add a PowerShell command to the FirstLogonCommands list.
+fn add_ps_command(commands: &mut Vec<SynchronousCommand>, order: &mut u32, description: &str, ps_command: &str) {
     // Use -ExecutionPolicy Bypass so PowerShell scripts run regardless of
     // the system's execution policy (default is Restricted = blocks all .ps1)
-    commands.push_str(&format!(
-        "                <SynchronousCommand wcm:action=\"add\">\n\
-         \x20                   <Order>{}</Order>\n\
-         \x20                   <CommandLine>powershell -ExecutionPolicy Bypass -NoProfile -Command &quot;{}&quot;</CommandLine>\n\
-         \x20                   <Description>{}</Description>\n\
-         \x20                   <RequiresUserInput>false</RequiresUserInput>\n\
-         \x20               </SynchronousCommand>\n",
-        order,
-        escape_xml(ps_command),
-        escape_xml(description)
-    ));
-    *order += 1;
+    let command = format!("powershell -ExecutionPolicy Bypass -NoProfile -Command \"{}\"", ps_command);
+    add_raw_command(commands, order, description, &command);
 }
 
 // ============================================
-// WIN11 BYPASS
+// ANSWER FILE VALIDATION
 // ============================================
 
-/// Apply Windows 11 hardware requirements bypass via registry keys.
-/// Sets 7 keys that allow installing Win11 on unsupported hardware:
-/// - TPM 2.0 check bypass
-/// - Secure Boot check bypass
-/// - CPU compatibility check bypass
-/// - RAM requirement bypass
-/// - Storage requirement bypass
-/// - Upgrade with unsupported TPM/CPU
-/// - OOBE network requirement bypass (BypassNRO)
+/// Validate an answer file before handing it to Windows Setup.
+/// Runs the structural checks from unattend::validate() and then checks
+/// the selected edition against the editions actually in the image.
+///
+/// # Arguments
+/// * `xml` — The answer file (generated or user-supplied)
+/// * `config` — Deployment config (edition_index, accounts and the partition layout)
+/// * `editions` — Editions found in the image (empty = unknown, skip image checks)
 ///
 /// # Returns
-/// * `Ok(())` — all keys set successfully
-/// * `Err(String)` — error message if any key fails
+/// `ValidationResult` with errors (Setup will fail) and warnings
+pub fn validate_autounattend(xml: &str, config: &DeployConfig, editions: &[WimEdition]) -> ValidationResult {
+    let mut result = unattend::validate(xml);
+
+    if let Err(e) = validate_accounts(&config.accounts, &config.autologon_account) {
+        result.errors.push(format!("User accounts: {}", e));
+    }
+    if !config.accounts.is_empty() && !config.accounts.iter().any(UserAccount::is_admin) {
+        result.warnings.push("User accounts: none of the accounts is an Administrator".to_string());
+    }
+
+    if !config.domain_ou.is_empty() && !config.join_domain {
+        result.warnings.push(format!("Domain OU \"{}\" is set but the machine doesn't join a domain", config.domain_ou));
+    }
+    // The join runs through PowerShell, which can't take an encoded password
+    if config.obfuscate_passwords && config.join_domain && !config.domain_password.is_empty() {
+        result.warnings.push(
+            "Domain join: \"Obfuscate passwords\" doesn't cover the domain password — it is in clear text in the FirstLogon command".to_string(),
+        );
+    }
+
+    // A mistyped product key stops Setup at the "enter a key" page
+    if !config.product_key.is_empty() {
+        if let Err(e) = product_key::validate(&config.product_key) {
+            result.errors.push(format!("Product key: {}", e));
+        }
+    }
+
+    // Windows falls back to UTC on a time zone id it doesn't know
+    match timezone::validate(&config.timezone) {
+        Ok(None) => {}
+        Ok(Some(warning)) => result.warnings.push(format!("Time zone: {}", warning)),
+        Err(e) => result.errors.push(format!("Time zone: {}", e)),
+    }
+
+    // Locales: Setup stops on malformed names and keyboard ids. Well-formed
+    // ones that aren't in the built-in tables may still be fine, so warn.
+    for (label, check) in [
+        ("UI language", locale::validate_ui_language(&config.ui_language)),
+        ("Keyboard", locale::validate_input_locale(&config.input_locale)),
+        ("System locale", locale::validate_locale(&config.system_locale)),
+        ("User locale", locale::validate_locale(&config.user_locale)),
+    ] {
+        match check {
+            Ok(None) => {}
+            Ok(Some(warning)) => result.warnings.push(format!("{}: {}", label, warning)),
+            Err(e) => result.errors.push(format!("{}: {}", label, e)),
+        }
+    }
+    if !config.image_languages.is_empty()
+        && !config.image_languages.iter().any(|l| l.eq_ignore_ascii_case(&config.ui_language))
+    {
+        result.warnings.push(format!(
+            "UI language {} is not installed in the image (it has {}) — Windows will fall back to the image's language",
+            config.ui_language,
+            config.image_languages.join(", ")
+        ));
+    }
+
+    // Tweaks: unknown ids, wrong Windows build, two tweaks fighting over one value
+    let catalog = tweaks::load_catalog();
+    for id in &config.enabled_tweaks {
+        match catalog.get(id) {
+            None => result.warnings.push(format!("Tweak \"{}\" is not in the tweak catalog and is skipped", id)),
+            Some(tweak) if !tweak.applies_to_build(config.image_build) => result.warnings.push(format!(
+                "Tweak \"{}\" is for {} and is skipped (image is build {})",
+                tweak.name, tweak.build_range(), config.image_build
+            )),
+            Some(_) => {}
+        }
+    }
+    for (first, second) in catalog.conflicts(&config.enabled_tweaks) {
+        result.warnings.push(format!("Tweaks \"{}\" and \"{}\" set the same registry value — \"{}\" wins", first, second, second));
+    }
+
+    // Wi-Fi: Windows rejects bad keys, and one SSID can only have one profile
+    for (i, profile) in config.wifi_profiles.iter().enumerate() {
+        if let Err(e) = profile.validate() {
+            result.errors.push(format!("Wi-Fi \"{}\": {}", profile.ssid, e));
+        }
+        if config.wifi_profiles[..i].iter().any(|p| p.ssid == profile.ssid) {
+            result.errors.push(format!("Wi-Fi \"{}\" is listed more than once", profile.ssid));
+        }
+    }
+
+    // Appx removal list: package names only, never the Store or frameworks
+    for pattern in &config.remove_appx {
+        match appx::check_pattern(pattern) {
+            Ok(None) => {}
+            Ok(Some(warning)) => result.warnings.push(warning),
+            Err(e) => result.errors.push(e),
+        }
+    }
+
+    // OEM information: the logo has to be a readable .bmp
+    match config.oem_info.validate() {
+        Ok(warnings) => result.warnings.extend(warnings.into_iter().map(|w| format!("OEM information: {}", w))),
+        Err(e) => result.errors.push(format!("OEM information: {}", e)),
+    }
+
+    // Specialize commands: the order numbers are checked by the linter
+    // (duplicates, gaps), like the FirstLogon commands
+    for command in &config.specialize_commands {
+        if command.path.trim().is_empty() {
+            result.errors.push(format!("Specialize command {}: command line is empty", command.order));
+        }
+    }
+
+    // Driver folders: Setup skips missing ones, so these are only warnings
+    for warning in drivers::check_folders(&config.driver_paths) {
+        result.warnings.push(format!("Driver folder {}", warning));
+    }
+
+    // The partition layout is only used when a target disk is selected
+    if config.disk_id >= 0 {
+        if let Err(e) = config.partition_layout.validate() {
+            result.errors.push(format!("Partition layout: {}", e));
+        }
+    }
+
+    if !editions.is_empty() {
+        let available: Vec<String> = editions
+            .iter()
+            .map(|e| format!("{} = {}", e.index, e.name))
+            .collect();
+
+        // The index the UI resolved for the selected edition
+        if config.edition_index > 0 && !editions.iter().any(|e| e.index == config.edition_index) {
+            result.errors.push(format!(
+                "Edition index {} does not exist in the image (available: {})",
+                config.edition_index,
+                available.join(", ")
+            ));
+        }
+
+        // /IMAGE/INDEX and /IMAGE/NAME selectors inside the answer file
+        if let Ok(doc) = UnattendDocument::parse(xml) {
+            let install_from = doc
+                .component(Pass::WindowsPE, "Microsoft-Windows-Setup")
+                .and_then(|setup| setup.find("ImageInstall/OSImage/InstallFrom"));
+            for metadata in install_from.iter().flat_map(|f| f.children_named("MetaData")) {
+                let key = metadata.child_text("Key").unwrap_or("").to_ascii_uppercase();
+                let value = metadata.child_text("Value").unwrap_or("");
+                let exists = match key.as_str() {
+                    "/IMAGE/INDEX" => value.parse::<u32>().is_ok_and(|i| editions.iter().any(|e| e.index == i)),
+                    "/IMAGE/NAME" => editions.iter().any(|e| e.name.eq_ignore_ascii_case(value)),
+                    _ => true,
+                };
+                if !exists {
+                    result.errors.push(format!(
+                        "windowsPE/Microsoft-Windows-Setup/ImageInstall: {} \"{}\" does not exist in the image (available: {})",
+                        key,
+                        value,
+                        available.join(", ")
+                    ));
+                }
+            }
+        }
+    }
+
+    result.valid = result.errors.is_empty();
+    println!(
+        "[Deploy] Answer file validation: {} error(s), {} warning(s)",
+        result.errors.len(),
+        result.warnings.len()
+    );
+    result
+}
+
+// ============================================
+// WIN11 BYPASS
+// ============================================
+
+/// Apply Windows 11 hardware requirements bypass via registry keys.
+/// Sets 7 keys that allow installing Win11 on unsupported hardware:
+/// - TPM 2.0 check bypass
+/// - Secure Boot check bypass
+/// - CPU compatibility check bypass
+/// - RAM requirement bypass
+/// - Storage requirement bypass
+/// - Upgrade with unsupported TPM/CPU
+/// - OOBE network requirement bypass (BypassNRO)
+///
+/// # Returns
+/// * `Ok(())` — all keys set successfully
+/// * `Err(String)` — error message if any key fails
 pub fn apply_win11_bypass() -> Result<(), String> {
     println!("[Deploy] Applying Windows 11 bypass registry keys...");
 
@@ -1574,58 +1925,211 @@ pub fn apply_win11_bypass() -> Result<(), String> {
 // DISK FORMATTING
 // ============================================
 
+/// GPT partition type of the WinRE recovery partition
+const RECOVERY_GPT_TYPE: &str = "de94bba4-06d1-4d40-a16a-bfd50179d6ac";
+/// GPT attributes for the recovery partition:
+/// 0x8000000000000000 (no drive letter) + 0x1 (required by the platform)
+const RECOVERY_GPT_ATTRIBUTES: &str = "0x8000000000000001";
+/// MBR partition ID of the recovery partition (hidden WinRE)
+const RECOVERY_MBR_TYPE: &str = "0x27";
+/// Microsoft Reserved partition size (UEFI only, always 16MB)
+const MSR_SIZE_MB: u64 = 16;
+
+/// What a planned partition is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionRole {
+    /// EFI system partition (UEFI) or System Reserved (BIOS)
+    System,
+    /// Microsoft Reserved partition (UEFI only)
+    Msr,
+    Windows,
+    Data,
+    /// WinRE recovery partition at the end of the disk
+    Recovery,
+}
+
+/// One partition of the planned layout, fully resolved for the boot mode.
+/// Partition numbers are the position in the plan + 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedPartition {
+    pub role: PartitionRole,
+    /// "EFI", "MSR" or "Primary" (answer file <Type>, diskpart "create partition ...")
+    pub partition_type: &'static str,
+    /// Size in MB (None = fill the rest of the disk)
+    pub size_mb: Option<u64>,
+    /// "FAT32" or "NTFS" (None = not formatted, e.g. MSR)
+    pub format: Option<&'static str>,
+    pub label: Option<String>,
+    pub letter: Option<String>,
+    /// Mark as active (BIOS System Reserved only)
+    pub active: bool,
+    /// Partition type override (recovery only)
+    pub type_id: Option<&'static str>,
+    /// GPT attributes (recovery on UEFI only — diskpart can set these, Setup can't)
+    pub gpt_attributes: Option<&'static str>,
+}
+
+impl PlannedPartition {
+    fn new(role: PartitionRole, partition_type: &'static str, size_mb: Option<u64>) -> Self {
+        Self {
+            role,
+            partition_type,
+            size_mb,
+            format: None,
+            label: None,
+            letter: None,
+            active: false,
+            type_id: None,
+            gpt_attributes: None,
+        }
+    }
+
+    /// Set format + volume label
+    fn formatted(mut self, format: &'static str, label: &str) -> Self {
+        self.format = Some(format);
+        self.label = Some(label.to_string());
+        self
+    }
+}
+
+/// Turn a PartitionLayout into the ordered list of partitions for the disk.
+/// Both build_unattend_document() and build_diskpart_script() use this.
+pub fn planned_partitions(layout: &PartitionLayout, boot_mode: BootMode) -> Vec<PlannedPartition> {
+    let mut plan = Vec::new();
+
+    match boot_mode {
+        BootMode::UEFI => {
+            plan.push(PlannedPartition::new(PartitionRole::System, "EFI", Some(layout.efi_size_mb))
+                .formatted("FAT32", "System"));
+            plan.push(PlannedPartition::new(PartitionRole::Msr, "MSR", Some(MSR_SIZE_MB)));
+        }
+        BootMode::BIOS => {
+            let mut system = PlannedPartition::new(PartitionRole::System, "Primary", Some(layout.efi_size_mb))
+                .formatted("NTFS", "System Reserved");
+            system.active = true;
+            plan.push(system);
+        }
+    }
+
+    // Windows: fixed size, or fill the disk when there's no data partition
+    let os_size = if layout.os_size_mb > 0 { Some(layout.os_size_mb) } else { None };
+    let mut windows = PlannedPartition::new(PartitionRole::Windows, "Primary", os_size)
+        .formatted("NTFS", "Windows");
+    windows.letter = Some("C".to_string());
+    plan.push(windows);
+
+    if layout.data_partition {
+        let mut data = PlannedPartition::new(PartitionRole::Data, "Primary", None)
+            .formatted("NTFS", &layout.data_label);
+        data.letter = Some(layout.data_letter.trim().to_ascii_uppercase());
+        plan.push(data);
+    }
+
+    if layout.recovery_size_mb > 0 {
+        let mut recovery = PlannedPartition::new(PartitionRole::Recovery, "Primary", Some(layout.recovery_size_mb))
+            .formatted("NTFS", "Recovery");
+        match boot_mode {
+            BootMode::UEFI => {
+                recovery.type_id = Some(RECOVERY_GPT_TYPE);
+                recovery.gpt_attributes = Some(RECOVERY_GPT_ATTRIBUTES);
+            }
+            BootMode::BIOS => recovery.type_id = Some(RECOVERY_MBR_TYPE),
+        }
+        plan.push(recovery);
+    }
+
+    plan
+}
+
+/// Partition number Windows gets installed to (<InstallTo><PartitionID>)
+fn windows_partition_id(plan: &[PlannedPartition]) -> u32 {
+    plan.iter()
+        .position(|p| p.role == PartitionRole::Windows)
+        .map(|i| i as u32 + 1)
+        .unwrap_or(1)
+}
+
+/// Build the diskpart script that creates the planned layout.
+///
+/// A partition that fills the disk is created first and then shrunk by the
+/// size of the partitions that follow it (the recovery partition), which is
+/// how diskpart puts a partition at the end of the disk.
+pub fn build_diskpart_script(disk_id: i32, boot_mode: BootMode, layout: &PartitionLayout) -> String {
+    let plan = planned_partitions(layout, boot_mode);
+    let mut script = format!("select disk {}\nclean\n", disk_id);
+    if boot_mode == BootMode::UEFI {
+        script.push_str("convert gpt\n");
+    }
+
+    for (i, part) in plan.iter().enumerate() {
+        let kind = part.partition_type.to_ascii_lowercase();
+        let after_fill = plan[..i].iter().any(|p| p.size_mb.is_none());
+        match part.size_mb {
+            // After a shrink, the free space is exactly what's left for this partition
+            Some(size) if !after_fill => script.push_str(&format!("create partition {} size={}\n", kind, size)),
+            _ => script.push_str(&format!("create partition {}\n", kind)),
+        }
+        if part.size_mb.is_none() {
+            let reserved: u64 = plan[i + 1..].iter().filter_map(|p| p.size_mb).sum();
+            if reserved > 0 {
+                script.push_str(&format!("shrink minimum={}\n", reserved));
+            }
+        }
+        if let Some(format) = part.format {
+            script.push_str(&format!(
+                "format quick fs={} label=\"{}\"\n",
+                format.to_ascii_lowercase(),
+                part.label.as_deref().unwrap_or("")
+            ));
+        }
+        if part.active {
+            script.push_str("active\n");
+        }
+        // The system partition gets a temporary S: so it's reachable from WinPE
+        let letter = match part.role {
+            PartitionRole::System => Some("S"),
+            _ => part.letter.as_deref(),
+        };
+        if let Some(letter) = letter {
+            script.push_str(&format!("assign letter={}\n", letter));
+        }
+        if let Some(type_id) = part.type_id {
+            let id = type_id.trim_start_matches("0x");
+            script.push_str(&format!("set id={} override\n", id));
+        }
+        if let Some(attributes) = part.gpt_attributes {
+            script.push_str(&format!("gpt attributes={}\n", attributes));
+        }
+    }
+
+    script.push_str("exit\n");
+    script
+}
+
 /// Pre-format a disk with diskpart before running Windows Setup.
 /// This avoids the error 0x80030024 that can happen when Setup tries
 /// to format a disk that's in use.
 ///
-/// Creates partition layout based on boot mode:
+/// The partitions come from planned_partitions(), the same plan the
+/// answer file uses. With the default layout:
 /// - UEFI: EFI(100MB, FAT32) + MSR(16MB) + Primary(rest, NTFS)
 /// - BIOS: System Reserved(100MB, NTFS, active) + Primary(rest, NTFS)
 ///
 /// # Arguments
 /// * `disk_id` — Disk number to format (from detect_disks)
 /// * `boot_mode` — UEFI or BIOS
+/// * `layout` — Partition sizes, recovery and data partitions
 ///
 /// # Returns
 /// * `Ok(())` — disk formatted successfully
 /// * `Err(String)` — error with details
-pub fn format_disk_with_diskpart(disk_id: i32, boot_mode: &BootMode) -> Result<(), String> {
+pub fn format_disk_with_diskpart(disk_id: i32, boot_mode: &BootMode, layout: &PartitionLayout) -> Result<(), String> {
     println!("[Deploy] Formatting Disk {} as {:?}...", disk_id, boot_mode);
 
+    layout.validate()?;
+
     // Build the diskpart script
-    let script = match boot_mode {
-        BootMode::UEFI => {
-            format!(
-                "select disk {}\n\
-                 clean\n\
-                 convert gpt\n\
-                 create partition efi size=100\n\
-                 format quick fs=fat32 label=\"System\"\n\
-                 assign letter=S\n\
-                 create partition msr size=16\n\
-                 create partition primary\n\
-                 format quick fs=ntfs label=\"Windows\"\n\
-                 assign letter=C\n\
-                 exit\n",
-                disk_id
-            )
-        }
-        BootMode::BIOS => {
-            format!(
-                "select disk {}\n\
-                 clean\n\
-                 create partition primary size=100\n\
-                 format quick fs=ntfs label=\"System Reserved\"\n\
-                 active\n\
-                 assign letter=S\n\
-                 create partition primary\n\
-                 format quick fs=ntfs label=\"Windows\"\n\
-                 assign letter=C\n\
-                 exit\n",
-                disk_id
-            )
-        }
-    };
+    let script = build_diskpart_script(disk_id, *boot_mode, layout);
 
     // Write the script to a temp file
     let temp_dir = std::env::temp_dir();
@@ -1724,6 +2228,102 @@ pub fn launch_setup(xml_path: &Path) -> Result<std::process::Child, String> {
     Ok(child)
 }
 
+// ============================================
+// PER-MACHINE OVERRIDES (machines.csv)
+// ============================================
+// If machines.csv sits next to the EXE, execute() looks up the machine it
+// runs on (by serial or MAC) and applies that row before anything else —
+// see machine.rs for the file format.
+// ============================================
+
+/// Apply a machines.csv row to the deployment settings.
+/// A row naming a profile deploys that saved profile instead (keeping the
+/// image and edition picked in the UI); the row's other non-empty cells
+/// override on top.
+pub fn apply_machine_entry(config: &DeployConfig, entry: &machine::MachineEntry) -> Result<DeployConfig, String> {
+    let mut result = if entry.profile.is_empty() {
+        config.clone()
+    } else {
+        let mut profile = load_profile(&entry.profile)
+            .map_err(|e| format!("machines.csv line {}: {}", entry.line, e))?;
+        profile.wim_path = config.wim_path.clone();
+        profile.edition = config.edition.clone();
+        profile.edition_index = config.edition_index;
+        profile
+    };
+
+    if !entry.computer_name.is_empty() {
+        result.computer_name = entry.computer_name.clone();
+    }
+
+    // The user gets the first account (keeping its password and groups)
+    if !entry.user.is_empty() {
+        match result.accounts.first_mut() {
+            Some(account) => {
+                if result.autologon_account.eq_ignore_ascii_case(&account.name) {
+                    result.autologon_account = entry.user.clone();
+                }
+                account.name = entry.user.clone();
+                account.display_name = entry.user.clone();
+            }
+            None => result.accounts.push(UserAccount {
+                name: entry.user.clone(),
+                display_name: entry.user.clone(),
+                password: String::new(),
+                group: default_account_group(),
+                password_never_expires: false,
+            }),
+        }
+    }
+
+    if !entry.domain_ou.is_empty() {
+        result.domain_ou = entry.domain_ou.clone();
+    }
+    Ok(result)
+}
+
+/// Look this machine up in machines.csv (if there is one) and apply its row.
+///
+/// # Returns
+/// * `Ok((config, row))` — updated settings, plus the row and firmware info
+///   to log once the deployment starts (None if no row was used)
+/// * `Err(String)` — bad machines.csv, or no row and the policy says stop
+fn apply_machine_list(
+    config: &DeployConfig,
+) -> Result<(DeployConfig, Option<(machine::MachineEntry, machine::MachineInfo)>), String> {
+    let Some(entries) = machine::load_machine_list()? else {
+        return Ok((config.clone(), None));
+    };
+    let info = machine::detect_machine_info()?;
+
+    if let Some(entry) = machine::find_machine_entry(&entries, &info)? {
+        println!("[Deploy] This machine is machines.csv line {}", entry.line);
+        return Ok((apply_machine_entry(config, entry)?, Some((entry.clone(), info))));
+    }
+
+    let machine = format!("serial \"{}\", MAC {}", info.serial, info.mac_addresses.join(" / "));
+    match config.unmatched_machine {
+        UnmatchedMachine::Fail => Err(format!("machines.csv has no row for this machine ({})", machine)),
+        UnmatchedMachine::Ask => {
+            let answer = rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Warning)
+                .set_title("Machine not in machines.csv")
+                .set_description(format!(
+                    "machines.csv has no row for this machine ({}).\n\nDeploy it with the current settings anyway?",
+                    machine
+                ))
+                .set_buttons(rfd::MessageButtons::YesNo)
+                .show();
+            if answer == rfd::MessageDialogResult::Yes {
+                println!("[Deploy] No machines.csv row — continuing with the current settings");
+                Ok((config.clone(), None))
+            } else {
+                Err(format!("Cancelled — machines.csv has no row for this machine ({})", machine))
+            }
+        }
+    }
+}
+
 // ============================================
 // EXECUTION PIPELINE
 // ============================================
@@ -1731,10 +2331,10 @@ pub fn launch_setup(xml_path: &Path) -> Result<std::process::Child, String> {
 /// Execute the full deployment pipeline with progress callbacks.
 ///
 /// Steps:
-/// 1. Validate config (0-5%)
+/// 1. Validate config + generate and lint autounattend.xml (0-5%)
 /// 2. Format disk with diskpart (5-15%) — if disk_id >= 0
 /// 3. Apply Win11 bypass registry keys (15-20%) — if enabled
-/// 4. Generate autounattend.xml (20-30%)
+/// 4. Report answer file warnings (20-30%)
 /// 5. Write XML to temp file (30-35%)
 /// 6. Launch setup.exe and wait (35-90%)
 /// 7. Post-install scripts (90-95%) — future
@@ -1781,6 +2381,100 @@ pub fn execute(
         };
     }
 
+    // Per-machine settings from machines.csv (if there is one)
+    progress_fn(1, "Checking machines.csv...");
+    let (mut config, machine_row) = match apply_machine_list(config) {
+        Ok(result) => result,
+        Err(e) => {
+            return DeployResult {
+                success: false,
+                message: e,
+            };
+        }
+    };
+
+    // Infer the architecture from the selected edition, so the answer
+    // file's components match the image (e.g., arm64 on Snapdragon laptops).
+    // The build decides which catalog tweaks apply (e.g., Windows 11 only).
+    if config.edition_index > 0 {
+        match detect_wim_details(&config.wim_path, config.edition_index) {
+            Ok(details) => {
+                if !config.multi_arch {
+                    if details.architecture != config.architecture {
+                        println!("[Deploy] Image is {} — overriding configured architecture {}", details.architecture, config.architecture);
+                    }
+                    config.architecture = details.architecture;
+                }
+                config.image_build = details.build;
+                config.image_languages = details.languages;
+            }
+            Err(e) => {
+                // Not fatal — keep the configured architecture, apply every tweak
+                println!("[Deploy] Warning: Could not detect image architecture and build: {}", e);
+            }
+        }
+    }
+
+    // Turn a computer name template into this machine's name. A bad
+    // template or a name that breaks the NetBIOS rules stops here.
+    let mut name_counter = None;
+    if machine::is_template(&config.computer_name) {
+        progress_fn(1, "Resolving computer name...");
+        match machine::resolve_for_this_machine(&config.computer_name) {
+            Ok(resolved) => {
+                name_counter = resolved.counter.map(|used| (config.computer_name.clone(), used));
+                config.computer_name = resolved.name;
+            }
+            Err(e) => {
+                return DeployResult {
+                    success: false,
+                    message: format!("Computer name template \"{}\" can't be used: {}", config.computer_name, e),
+                };
+            }
+        }
+    }
+    let config = &config;
+
+    // Generate and lint the answer file BEFORE touching the disk, so a bad
+    // setting fails here instead of halfway through Windows Setup
+    progress_fn(2, "Validating autounattend.xml...");
+    let xml = generate_autounattend(config);
+    let editions = match parse_wim_editions(&config.wim_path) {
+        Ok((editions, _)) => editions,
+        Err(e) => {
+            // Not fatal — just skip the edition checks
+            println!("[Deploy] Warning: Could not read image editions for validation: {}", e);
+            Vec::new()
+        }
+    };
+    let validation = validate_autounattend(&xml, config, &editions);
+    for warning in &validation.warnings {
+        println!("[Deploy] Warning: {}", warning);
+    }
+    if !validation.valid {
+        return DeployResult {
+            success: false,
+            message: format!(
+                "autounattend.xml is invalid:\n\n{}",
+                validation.errors.join("\n")
+            ),
+        };
+    }
+
+    // The name is final now — use up the counter value so the next
+    // machine gets the next number
+    if let Some((template, used)) = &name_counter {
+        if let Err(e) = machine::commit_counter(template, *used) {
+            println!("[Deploy] Warning: {}", e);
+        }
+    }
+    // Record on the USB drive that this machines.csv row has been used
+    if let Some((entry, info)) = &machine_row {
+        if let Err(e) = machine::log_machine_entry(entry, info, &config.computer_name) {
+            println!("[Deploy] Warning: {}", e);
+        }
+    }
+
     progress_fn(5, "Configuration validated");
 
     // ============================================
@@ -1789,7 +2483,7 @@ pub fn execute(
     if config.disk_id >= 0 {
         progress_fn(5, &format!("Formatting Disk {} ({})...", config.disk_id, config.boot_mode));
 
-        if let Err(e) = format_disk_with_diskpart(config.disk_id, &config.boot_mode) {
+        if let Err(e) = format_disk_with_diskpart(config.disk_id, &config.boot_mode, &config.partition_layout) {
             return DeployResult {
                 success: false,
                 message: format!("Disk formatting failed: {}", e),
@@ -1820,9 +2514,18 @@ pub fn execute(
     // ============================================
     // STEP 4: Generate XML (20-30%)
     // ============================================
+    // The XML was already generated and validated in Step 1
     progress_fn(20, "Generating autounattend.xml...");
 
-    let xml = generate_autounattend(config);
+    if !validation.warnings.is_empty() {
+        progress_fn(25, &format!("autounattend.xml has {} warning(s) (see log)", validation.warnings.len()));
+    }
+
+    // Setup adds the drivers itself (answer file DriverPaths) — log what it gets
+    if !config.driver_paths.is_empty() {
+        let packages = drivers::log_packages(&config.driver_paths);
+        progress_fn(30, &format!("{} driver package(s) will be added by Setup", packages));
+    }
 
     progress_fn(30, "autounattend.xml generated");
 
@@ -1885,6 +2588,51 @@ pub fn execute(
         }
     }
 
+    // Wi-Fi profiles for the specialize pass to import. Non-fatal: the
+    // machine just comes up without Wi-Fi.
+    if !config.wifi_profiles.is_empty() {
+        progress_fn(90, "Copying Wi-Fi profiles to target...");
+        let written = find_target_windows_drive()
+            .ok_or_else(|| "No installed Windows found".to_string())
+            .and_then(|drive| wifi::write_to_target(&drive, &config.wifi_profiles));
+        if let Err(e) = written {
+            println!("[Deploy] Warning: Wi-Fi profiles not copied: {}", e);
+            progress_fn(90, &format!("Warning: Wi-Fi profiles not copied: {}", e));
+        }
+    }
+
+    // OEM logo for System > About. Non-fatal: the text fields are still set.
+    if !config.oem_info.logo_path.is_empty() {
+        let copied = find_target_windows_drive()
+            .ok_or_else(|| "No installed Windows found".to_string())
+            .and_then(|drive| oem::copy_logo_to_target(&drive, &config.oem_info));
+        if let Err(e) = copied {
+            println!("[Deploy] Warning: OEM logo not copied: {}", e);
+            progress_fn(90, &format!("Warning: OEM logo not copied: {}", e));
+        }
+    }
+
+    // Remove provisioned Appx packages from the applied image (offline mode).
+    // Non-fatal: Windows is installed, the apps just stay.
+    if config.appx_removal_mode == AppxRemovalMode::Offline && !config.remove_appx.is_empty() {
+        progress_fn(90, "Removing provisioned apps...");
+        match find_target_windows_drive() {
+            Some(drive) => match appx::remove_offline(&drive, &config.remove_appx) {
+                Ok(results) => {
+                    let removed = results.iter().filter(|r| r.result == "removed").count();
+                    let failed = results.iter().filter(|r| r.result == "failed").count();
+                    println!("[Deploy] Appx removal: {} removed, {} failed", removed, failed);
+                    progress_fn(90, &format!("Removed {} provisioned app(s), {} failed", removed, failed));
+                }
+                Err(e) => {
+                    println!("[Deploy] Warning: Appx removal failed: {}", e);
+                    progress_fn(90, &format!("Warning: Appx removal failed: {}", e));
+                }
+            },
+            None => println!("[Deploy] Warning: No installed Windows found — provisioned apps not removed"),
+        }
+    }
+
     // ============================================
     // STEP 7: Post-install scripts (90-95%)
     // ============================================
@@ -1960,6 +2708,11 @@ fn get_profiles_dir() -> PathBuf {
 /// The profile is stored in profiles/<name>.json next to the EXE.
 /// Session-specific fields (wim_path, edition, edition_index) are cleared
 /// before saving — they don't make sense to persist.
+/// With encrypt_secrets on, secret fields are encrypted (needs an unlocked passphrase).
+///
+/// A profile with a `parent` only stores the fields that differ from its
+/// (fully resolved) parent, so later changes to the base profile carry
+/// through to every profile built on it.
 ///
 /// # Arguments
 /// * `name` — Profile name (used as filename, sanitized)
@@ -1977,15 +2730,17 @@ pub fn save_profile(name: &str, config: &DeployConfig) -> Result<(), String> {
         return Err("Profile name cannot be empty".to_string());
     }
 
-    // Clone config and clear session-specific fields
-    let mut profile_config = config.clone();
-    profile_config.wim_path = PathBuf::new();
-    profile_config.edition = String::new();
-    profile_config.edition_index = 0;
+    // Resolve the parent chain first — this also refuses a parent that
+    // (directly or further up) inherits from the profile being saved
+    let base = if config.parent.is_empty() {
+        None
+    } else {
+        let mut chain = vec![safe_name.clone()];
+        let value = resolve_named_profile(&config.parent, &read_profile_file, &mut chain)?;
+        Some(profile_from_value(value)?)
+    };
 
-    // Serialize to pretty JSON
-    let json = serde_json::to_string_pretty(&profile_config)
-        .map_err(|e| format!("Failed to serialize profile: {}", e))?;
+    let json = profile_to_json(config, base.as_ref())?;
 
     // Write to file
     let file_path = profiles_dir.join(format!("{}.json", safe_name));
@@ -1996,48 +2751,424 @@ pub fn save_profile(name: &str, config: &DeployConfig) -> Result<(), String> {
     Ok(())
 }
 
+/// Turn a DeployConfig into the pretty JSON stored in a profile file:
+/// session-specific fields cleared, secrets encrypted if requested,
+/// and stamped with the current schema_version.
+///
+/// # Arguments
+/// * `config` — The configuration to save
+/// * `base` — Resolved parent profile; only fields that differ from it are kept
+pub fn profile_to_json(config: &DeployConfig, base: Option<&DeployConfig>) -> Result<String, String> {
+    let mut value = profile_value(config)?;
+
+    // Keep only the overrides (compared before encryption, so an unchanged
+    // password isn't stored again just because its ciphertext differs)
+    if let Some(base) = base {
+        let base_value = profile_value(base)?;
+        if let (Some(object), Some(base_object)) = (value.as_object_mut(), base_value.as_object()) {
+            object.retain(|key, field| key == "parent" || base_object.get(key) != Some(field));
+        }
+    }
+
+    // Encrypt secret fields with the session passphrase if requested
+    if config.encrypt_secrets {
+        transform_profile_secrets(&mut value, &mut |text, field| secrets::encrypt(text, field))
+            .map_err(|e| format!("Can't encrypt profile secrets: {}", e))?;
+    }
+    value["schema_version"] = serde_json::Value::from(PROFILE_SCHEMA_VERSION);
+
+    // Serialize to pretty JSON
+    serde_json::to_string_pretty(&value)
+        .map_err(|e| format!("Failed to serialize profile: {}", e))
+}
+
+/// A DeployConfig as profile JSON, with the session-specific fields cleared
+fn profile_value(config: &DeployConfig) -> Result<serde_json::Value, String> {
+    let mut profile_config = config.clone();
+    profile_config.wim_path = PathBuf::new();
+    profile_config.edition = String::new();
+    profile_config.edition_index = 0;
+
+    serde_json::to_value(&profile_config)
+        .map_err(|e| format!("Failed to serialize profile: {}", e))
+}
+
 /// Load a DeployConfig from a named JSON profile.
 /// The wim_path, edition, and edition_index fields will be empty
 /// (they are session-specific and not saved in profiles).
+/// Parent profiles are loaded and the overrides applied on top.
 ///
 /// # Arguments
 /// * `name` — Profile name to load
 ///
 /// # Returns
 /// * `Ok(DeployConfig)` — the loaded configuration
-/// * `Err(String)` — error if file not found or invalid JSON
+/// * `Err(String)` — error if file not found, invalid JSON or an inheritance loop
 pub fn load_profile(name: &str) -> Result<DeployConfig, String> {
-    let profiles_dir = get_profiles_dir();
-    let file_path = profiles_dir.join(format!("{}.json", name));
+    let value = resolve_named_profile(name, &read_profile_file, &mut Vec::new())?;
+    let config = profile_from_value(value)?;
 
-    if !file_path.exists() {
-        return Err(format!("Profile '{}' not found", name));
-    }
+    println!("[Deploy] Loaded profile '{}' from: {}", name, get_profiles_dir().join(format!("{}.json", name)).display());
+    Ok(config)
+}
 
-    let json = fs::read_to_string(&file_path)
-        .map_err(|e| format!("Failed to read profile: {}", e))?;
+/// Parse a profile's JSON, upgrading profiles saved by older versions
+/// (see migrate_profile), applying it over its parent profile (if any)
+/// and decrypting encrypted secrets (needs an unlocked passphrase).
+pub fn parse_profile_json(json: &str) -> Result<DeployConfig, String> {
+    let value = resolve_profile_json(json, &read_profile_file, &mut Vec::new())?;
+    profile_from_value(value)
+}
 
-    let config: DeployConfig = serde_json::from_str(&json)
-        .map_err(|e| format!("Failed to parse profile: {}", e))?;
+/// Decrypt a resolved profile's secrets and deserialize it
+fn profile_from_value(mut value: serde_json::Value) -> Result<DeployConfig, String> {
+    // Decrypt any encrypted secrets (clear ones pass through unchanged).
+    // A profile with encrypted fields stays encrypted when saved again.
+    let mut any_encrypted = false;
+    transform_profile_secrets(&mut value, &mut |text, field| {
+        any_encrypted |= secrets::is_encrypted(text);
+        secrets::decrypt(text, field)
+    })
+    .map_err(|e| format!("Can't decrypt profile secrets: {}", e))?;
+    if any_encrypted {
+        value["encrypt_secrets"] = serde_json::Value::Bool(true);
+    }
 
-    println!("[Deploy] Loaded profile '{}' from: {}", name, file_path.display());
-    Ok(config)
+    serde_json::from_value(value).map_err(|e| format!("Failed to parse profile: {}", e))
 }
 
-/// List all saved profile names.
-/// Scans the profiles/ directory for .json files.
-///
-/// # Returns
-/// Vector of profile names (without .json extension), sorted alphabetically
-pub fn list_profiles() -> Vec<String> {
-    let profiles_dir = get_profiles_dir();
+// ============================================
+// PROFILE INHERITANCE
+// ============================================
+// A profile can name a "parent" and store only the fields it changes —
+// e.g. one "Base" profile plus a small "Customer A" profile that sets the
+// computer name prefix, organization and domain. Loading walks up the
+// chain (Customer A → Base → ...) and lays each profile's fields over its
+// parent's. Each file is migrated on its own before merging.
+// ============================================
 
-    let mut names: Vec<String> = Vec::new();
+/// Reads a profile file's JSON by name (the profiles folder, or a test map)
+type ProfileReader<'a> = &'a dyn Fn(&str) -> Result<String, String>;
 
-    if let Ok(entries) = fs::read_dir(&profiles_dir) {
+/// Read profiles/<name>.json
+fn read_profile_file(name: &str) -> Result<String, String> {
+    let file_path = get_profiles_dir().join(format!("{}.json", name));
+    if !file_path.exists() {
+        return Err(format!("Profile '{}' not found", name));
+    }
+    fs::read_to_string(&file_path).map_err(|e| format!("Failed to read profile: {}", e))
+}
+
+/// Load a profile by name and resolve its parents into one JSON object.
+///
+/// # Arguments
+/// * `chain` — Profiles already being resolved (used to detect loops)
+fn resolve_named_profile(name: &str, read: ProfileReader, chain: &mut Vec<String>) -> Result<serde_json::Value, String> {
+    // Profile names are file names, which Windows compares case-insensitively
+    if chain.iter().any(|seen| seen.eq_ignore_ascii_case(name)) {
+        chain.push(name.to_string());
+        return Err(format!("Profile inheritance loop: {}", chain.join(" → ")));
+    }
+    chain.push(name.to_string());
+    resolve_profile_json(&read(name)?, read, chain)
+}
+
+/// Parse one profile file and lay its fields over its resolved parent
+fn resolve_profile_json(json: &str, read: ProfileReader, chain: &mut Vec<String>) -> Result<serde_json::Value, String> {
+    let mut value: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| format!("Failed to parse profile: {}", e))?;
+    migrate_profile(&mut value)?;
+
+    let parent = value.get("parent").and_then(|v| v.as_str()).unwrap_or("").to_string();
+    if parent.is_empty() {
+        return Ok(value);
+    }
+
+    let mut resolved = resolve_named_profile(&parent, read, chain)
+        .map_err(|e| format!("Can't load base profile '{}': {}", parent, e))?;
+    if let (Some(base), serde_json::Value::Object(mut overrides)) = (resolved.as_object_mut(), value) {
+        // The child's own "parent" replaces the base's
+        base.remove("parent");
+        let tweak_delta = overrides.remove(TWEAK_DELTA_FIELD);
+        base.extend(overrides);
+        if let Some(delta) = tweak_delta {
+            apply_tweak_delta(base, &delta);
+        }
+    }
+    Ok(resolved)
+}
+
+/// Parent named in a saved profile file (empty if standalone or unreadable).
+/// Reads only the file itself — no resolving or decrypting.
+pub fn profile_parent(name: &str) -> String {
+    read_profile_file(name)
+        .ok()
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .and_then(|value| value.get("parent").and_then(|v| v.as_str()).map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Separator between a profile name and its parent in the profile list
+const PROFILE_LABEL_SEPARATOR: &str = " ← ";
+
+/// Label for the profile dropdown: "Customer A ← Base" for profiles with a
+/// parent, just the name otherwise
+pub fn profile_label(name: &str) -> String {
+    let parent = profile_parent(name);
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}{}{}", name, PROFILE_LABEL_SEPARATOR, parent)
+    }
+}
+
+/// Profile name from a dropdown label (see profile_label)
+pub fn profile_name_from_label(label: &str) -> &str {
+    label.split(PROFILE_LABEL_SEPARATOR).next().unwrap_or(label)
+}
+
+/// Labels for every saved profile, showing which base each one inherits from
+pub fn list_profile_labels() -> Vec<String> {
+    list_profiles().iter().map(|name| profile_label(name)).collect()
+}
+
+// ============================================
+// PROFILE SCHEMA MIGRATIONS
+// ============================================
+// Every saved profile carries a "schema_version". When a field is renamed
+// or restructured, bump PROFILE_SCHEMA_VERSION and add a migration that
+// rewrites the old JSON into the new shape. Loading runs the migrations
+// one step at a time (1 → 2 → 3 ...), so any old profile reaches the
+// current shape. New fields with #[serde(default)] don't need a bump.
+//
+// History:
+//   1 — MasterBooter 0.1.x: one account (user_name, user_password,
+//       user_display_name, user_is_admin, enable_autologon)
+//   2 — list of accounts + autologon_account
+//   3 — tweak booleans (disable_telemetry, ...) replaced by enabled_tweaks
+//   4 — language split into ui_language, input_locale, system_locale and
+//       user_locale
+
+/// Schema version written into saved profiles
+pub const PROFILE_SCHEMA_VERSION: u64 = 4;
+
+/// Profile migrations in order: entry N upgrades schema N+1 to N+2
+const PROFILE_MIGRATIONS: &[fn(&mut serde_json::Map<String, serde_json::Value>)] = &[
+    migrate_v1_single_account, // 1 → 2
+    migrate_v2_tweak_flags,    // 2 → 3
+    migrate_v3_split_locale,   // 3 → 4
+];
+
+/// Upgrade a profile's JSON to the current schema (in place).
+///
+/// Profiles from before schema_version existed are version 1, unless they
+/// already have an `accounts` list (written by pre-release builds of 2).
+/// Profiles from a newer MasterBooter are refused rather than half-loaded.
+fn migrate_profile(value: &mut serde_json::Value) -> Result<(), String> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| "Failed to parse profile: not a JSON object".to_string())?;
+
+    let version = match object.remove("schema_version") {
+        Some(v) => v
+            .as_u64()
+            .filter(|v| *v >= 1)
+            .ok_or_else(|| format!("Invalid profile schema_version: {}", v))?,
+        None if object.contains_key("accounts") => 2,
+        None => 1,
+    };
+
+    if version > PROFILE_SCHEMA_VERSION {
+        return Err(format!(
+            "This profile was saved by a newer MasterBooter (schema {}, this version reads up to {}) — update MasterBooter to load it",
+            version, PROFILE_SCHEMA_VERSION
+        ));
+    }
+
+    for from in version..PROFILE_SCHEMA_VERSION {
+        PROFILE_MIGRATIONS[(from - 1) as usize](object);
+        println!("[Deploy] Upgraded profile from schema {} to {}", from, from + 1);
+    }
+    Ok(())
+}
+
+/// Profile fields that hold secrets. Account passwords and Wi-Fi keys are
+/// handled separately (one per entry in `accounts` / `wifi_profiles`).
+const PROFILE_SECRET_FIELDS: &[&str] = &["product_key", "administrator_password", "domain_password"];
+
+/// Run `transform(value, field name)` on every secret string in a profile's JSON
+/// and store the result. Used to encrypt on save and decrypt on load.
+fn transform_profile_secrets(
+    value: &mut serde_json::Value,
+    transform: &mut dyn FnMut(&str, &str) -> Result<String, String>,
+) -> Result<(), String> {
+    let Some(object) = value.as_object_mut() else {
+        return Ok(());
+    };
+    for field in PROFILE_SECRET_FIELDS {
+        if let Some(serde_json::Value::String(text)) = object.get_mut(*field) {
+            *text = transform(text, field)?;
+        }
+    }
+    if let Some(serde_json::Value::Array(accounts)) = object.get_mut("accounts") {
+        for account in accounts {
+            if let Some(serde_json::Value::String(text)) = account.get_mut("password") {
+                *text = transform(text, "accounts.password")?;
+            }
+        }
+    }
+    if let Some(serde_json::Value::Array(networks)) = object.get_mut("wifi_profiles") {
+        for network in networks {
+            if let Some(serde_json::Value::String(text)) = network.get_mut("key") {
+                *text = transform(text, "wifi_profiles.key")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Schema 1 → 2: move the old single-account fields into `accounts` / `autologon_account`.
+fn migrate_v1_single_account(object: &mut serde_json::Map<String, serde_json::Value>) {
+    let text = |object: &mut serde_json::Map<String, serde_json::Value>, key: &str| {
+        object.remove(key).and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+    };
+    let name = text(object, "user_name");
+    let password = text(object, "user_password");
+    let display_name = text(object, "user_display_name");
+    let is_admin = object.remove("user_is_admin").and_then(|v| v.as_bool()).unwrap_or(true);
+    let autologon = object.remove("enable_autologon").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let mut accounts = Vec::new();
+    if !name.is_empty() {
+        accounts.push(serde_json::json!({
+            "name": name,
+            "display_name": display_name,
+            "password": password,
+            "group": if is_admin { "Administrators" } else { "Users" },
+            // Older versions set "never expires" for every account
+            "password_never_expires": true,
+        }));
+        println!("[Deploy] Migrated legacy account '{}' into the accounts list", name);
+    }
+    let autologon_account = if autologon { name } else { String::new() };
+    object.insert("accounts".to_string(), serde_json::Value::Array(accounts));
+    object.insert("autologon_account".to_string(), serde_json::Value::String(autologon_account));
+}
+
+/// Schema 2 tweak fields, in Deploy page order. Each boolean became the
+/// catalog tweak with the same id; taskbar_search_mode (0 = box, 1 = icon,
+/// 2 = hidden) became taskbar_search_icon / taskbar_search_hidden.
+const LEGACY_TWEAK_FIELDS: &[&str] = &[
+    "disable_telemetry", "disable_location", "disable_ads", "disable_suggested_apps", "disable_bing_search", "disable_smartscreen",
+    "enable_rdp", "disable_uac", "disable_defender", "disable_firewall", "disable_vbs", "disable_bitlocker",
+    "disable_fast_startup", "high_performance", "disable_system_restore",
+    "show_file_extensions", "show_hidden_files", "classic_context_menu", "taskbar_search_mode", "hide_task_view", "hide_widgets", "taskbar_left_align",
+    "disable_cortana", "disable_onedrive", "disable_teams", "disable_copilot", "disable_widgets_service",
+];
+
+/// Schema 2 → 3: move the tweak booleans into `enabled_tweaks`.
+///
+/// A layered profile only stored the toggles it changed, so its toggles
+/// can't become a whole list (that would replace every tweak of its base).
+/// They are kept as a TWEAK_DELTA_FIELD instead, which resolving applies to
+/// the base's `enabled_tweaks` (see apply_tweak_delta).
+fn migrate_v2_tweak_flags(object: &mut serde_json::Map<String, serde_json::Value>) {
+    let mut enabled = Vec::new();
+    let mut disabled = Vec::new();
+    let mut found = false;
+    for field in LEGACY_TWEAK_FIELDS {
+        let Some(value) = object.remove(*field) else {
+            continue;
+        };
+        found = true;
+        match (*field, value.as_u64()) {
+            ("taskbar_search_mode", mode) => {
+                let (icon, hidden) = ("taskbar_search_icon", "taskbar_search_hidden");
+                let (on, off) = match mode {
+                    Some(1) => (vec![icon], vec![hidden]),
+                    Some(2) => (vec![hidden], vec![icon]),
+                    _ => (vec![], vec![icon, hidden]),
+                };
+                enabled.extend(on);
+                disabled.extend(off);
+            }
+            _ if value.as_bool() == Some(true) => enabled.push(*field),
+            _ => disabled.push(*field),
+        }
+    }
+
+    // A layered profile that doesn't override any toggle keeps its base's tweaks
+    if !found {
+        return;
+    }
+    let ids = |ids: Vec<&str>| serde_json::Value::from(ids.into_iter().map(str::to_string).collect::<Vec<_>>());
+    if object.get("parent").and_then(|v| v.as_str()).is_some_and(|p| !p.is_empty()) {
+        println!("[Deploy] Migrated {} tweak toggle(s) as changes to the base profile's tweaks", enabled.len() + disabled.len());
+        object.insert(TWEAK_DELTA_FIELD.to_string(), serde_json::json!({ "enable": ids(enabled), "disable": ids(disabled) }));
+    } else {
+        println!("[Deploy] Migrated {} enabled tweak toggle(s) into enabled_tweaks", enabled.len());
+        object.insert("enabled_tweaks".to_string(), ids(enabled));
+    }
+}
+
+/// Tweaks a migrated schema 2 layered profile turns on and off relative to
+/// its base: {"enable": [...], "disable": [...]}. Only exists while resolving.
+const TWEAK_DELTA_FIELD: &str = "enabled_tweaks_delta";
+
+/// Apply a migrated layered profile's TWEAK_DELTA_FIELD to the resolved
+/// profile's `enabled_tweaks`
+fn apply_tweak_delta(object: &mut serde_json::Map<String, serde_json::Value>, delta: &serde_json::Value) {
+    let ids = |key: &str| -> Vec<String> {
+        delta[key].as_array().into_iter().flatten().filter_map(|v| v.as_str().map(str::to_string)).collect()
+    };
+    let (enable, disable) = (ids("enable"), ids("disable"));
+    let mut tweaks: Vec<String> = object
+        .get("enabled_tweaks")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str().map(str::to_string))
+        .filter(|id| !disable.contains(id))
+        .collect();
+    for id in enable {
+        if !tweaks.contains(&id) {
+            tweaks.push(id);
+        }
+    }
+    object.insert("enabled_tweaks".to_string(), serde_json::Value::from(tweaks));
+}
+
+/// Schema 3 → 4: `language` was written into every locale setting; copy it
+/// into the four separate fields. The keyboard becomes the language's
+/// default layout ("en-US" → "0409:00000409"), or the language name itself
+/// (which Windows also accepts) when it isn't in the locale table.
+fn migrate_v3_split_locale(object: &mut serde_json::Map<String, serde_json::Value>) {
+    // A layered profile that doesn't override the language keeps its base's locales
+    let Some(language) = object.remove("language").and_then(|v| v.as_str().map(str::to_string)) else {
+        return;
+    };
+    let input_locale = locale::default_input_locale(&language).unwrap_or_else(|| language.clone());
+    for field in ["ui_language", "system_locale", "user_locale"] {
+        object.insert(field.to_string(), serde_json::Value::String(language.clone()));
+    }
+    object.insert("input_locale".to_string(), serde_json::Value::String(input_locale));
+    println!("[Deploy] Migrated language '{}' into separate locale fields", language);
+}
+
+/// List all saved profile names.
+/// Scans the profiles/ directory for .json files.
+///
+/// # Returns
+/// Vector of profile names (without .json extension), sorted alphabetically
+pub fn list_profiles() -> Vec<String> {
+    let profiles_dir = get_profiles_dir();
+
+    let mut names: Vec<String> = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(&profiles_dir) {
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.extension().map_or(false, |ext| ext == "json") {
+            if path.extension().is_some_and(|ext| ext == "json") {
                 if let Some(stem) = path.file_stem() {
                     names.push(stem.to_string_lossy().to_string());
                 }
@@ -2065,6 +3196,15 @@ pub fn delete_profile(name: &str) -> Result<(), String> {
         return Err(format!("Profile '{}' not found", name));
     }
 
+    // Don't orphan profiles that inherit from this one
+    let children: Vec<String> = list_profiles()
+        .into_iter()
+        .filter(|other| profile_parent(other).eq_ignore_ascii_case(name))
+        .collect();
+    if !children.is_empty() {
+        return Err(format!("'{}' is the base of {} — change or delete those first", name, children.join(", ")));
+    }
+
     fs::remove_file(&file_path)
         .map_err(|e| format!("Failed to delete profile: {}", e))?;
 
@@ -2072,6 +3212,1165 @@ pub fn delete_profile(name: &str) -> Result<(), String> {
     Ok(())
 }
 
+// ============================================
+// PROFILE DIFF
+// ============================================
+// Compares two profiles field by field, grouped into the same sections as
+// the Deploy page and build_first_logon_commands (Privacy, Security, ...),
+// then shows how the generated autounattend.xml changes as a result.
+// Secrets are never printed — only whether they are set or changed.
+// ============================================
+
+/// Profile fields grouped by section, in display order.
+/// New DeployConfig fields belong in here too (a test checks that).
+const PROFILE_DIFF_SECTIONS: &[(&str, &[&str])] = &[
+    ("Machine", &["parent", "computer_name", "timezone"]),
+    ("Locale", &["ui_language", "input_locale", "system_locale", "user_locale"]),
+    ("Boot & Disk", &["boot_mode", "architecture", "multi_arch", "disk_id", "partition_layout", "bypass_win11"]),
+    ("Accounts", &["accounts", "autologon_account", "administrator_password", "obfuscate_passwords"]),
+    ("OOBE", &["skip_oobe", "skip_eula", "skip_network"]),
+    ("Wi-Fi", &["wifi_profiles"]),
+    ("Drivers", &["driver_paths"]),
+    ("Registration", &["product_key", "organization", "owner_name", "oem_info"]),
+    ("Specialize", &["specialize_commands"]),
+    // Tweaks are listed one by one under their catalog category (see diff_profiles)
+    ("Privacy", &[]),
+    ("Security", &[]),
+    ("Performance", &[]),
+    ("UI", &[]),
+    ("Bloatware", &["remove_appx", "appx_removal_mode"]),
+    ("Tweaks", &["enabled_tweaks"]),
+    ("Domain", &["join_domain", "domain_name", "domain_username", "domain_password", "domain_ou", "workgroup"]),
+    ("Advanced", &["prevent_device_encryption", "unmatched_machine", "encrypt_secrets"]),
+];
+
+/// One field that differs between two profiles
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// Section from PROFILE_DIFF_SECTIONS (or "Other")
+    pub section: &'static str,
+    /// Field path, e.g. "disable_telemetry" or "accounts[Admin].group"
+    pub field: String,
+    /// Value in the first profile ("(none)" if missing)
+    pub old: String,
+    /// Value in the second profile ("(none)" if missing)
+    pub new: String,
+}
+
+/// Differences between two profiles and their answer files
+#[derive(Debug, Clone, Default)]
+pub struct ProfileDiff {
+    /// Changed fields, in section order
+    pub changes: Vec<FieldChange>,
+    /// Changed answer file lines: "@@ line N" hunk headers, "- old", "+ new"
+    pub xml_changes: Vec<String>,
+}
+
+impl ProfileDiff {
+    /// True when the profiles are the same
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.xml_changes.is_empty()
+    }
+
+    /// Plain text report (console and the UI's profile_diff.txt)
+    pub fn to_text(&self, old_name: &str, new_name: &str) -> String {
+        if self.is_empty() {
+            return format!("No differences between '{}' and '{}'\n", old_name, new_name);
+        }
+
+        let mut text = format!("Profile diff: '{}' → '{}'\n", old_name, new_name);
+        text.push_str(&format!("{} field(s) differ\n", self.changes.len()));
+
+        let mut section = "";
+        for change in &self.changes {
+            if change.section != section {
+                section = change.section;
+                text.push_str(&format!("\n[{}]\n", section));
+            }
+            text.push_str(&format!("  {}: {} → {}\n", change.field, change.old, change.new));
+        }
+
+        if !self.xml_changes.is_empty() {
+            let changed = self.xml_changes.iter().filter(|l| !l.starts_with("@@")).count();
+            text.push_str(&format!("\nautounattend.xml ({} line(s) changed):\n", changed));
+            for line in &self.xml_changes {
+                text.push_str(&format!("  {}\n", line));
+            }
+        }
+        text
+    }
+}
+
+/// Compare two profiles: changed fields by section, then the answer file lines
+/// that change as a result. Session fields (image, edition) are ignored.
+pub fn diff_profiles(old: &DeployConfig, new: &DeployConfig) -> ProfileDiff {
+    // Tweaks are compared one by one below, not as one list
+    let flatten = |config: &DeployConfig| {
+        let mut value = profile_value(config).unwrap_or_default();
+        if let Some(object) = value.as_object_mut() {
+            object.remove("enabled_tweaks");
+        }
+        let mut fields = BTreeMap::new();
+        flatten_profile_value("", &value, &mut fields);
+        fields
+    };
+    let (old_fields, new_fields) = (flatten(old), flatten(new));
+
+    // Every field path from either side, grouped by its top-level field
+    let mut paths: Vec<&String> = old_fields.keys().chain(new_fields.keys()).collect();
+    paths.sort();
+    paths.dedup();
+    let top_level = |path: &str| path.split(['.', '[']).next().unwrap_or("").to_string();
+
+    let mut changes = Vec::new();
+    let add_changes = |changes: &mut Vec<FieldChange>, section: &'static str, path_filter: &dyn Fn(&str) -> bool| {
+        for path in paths.iter().filter(|p| path_filter(p)) {
+            let (old_value, new_value) = (old_fields.get(*path), new_fields.get(*path));
+            if old_value == new_value {
+                continue;
+            }
+            let (old_text, new_text) = if is_secret_field(path) {
+                let mask = |v: Option<&String>| match v.map(String::as_str) {
+                    None | Some("\"\"") => "(empty)",
+                    Some(_) => "(set)",
+                };
+                let (o, n) = (mask(old_value), mask(new_value));
+                (o.to_string(), if o == n { format!("{}, changed", n) } else { n.to_string() })
+            } else {
+                let show = |v: Option<&String>| v.cloned().unwrap_or_else(|| "(none)".to_string());
+                (show(old_value), show(new_value))
+            };
+            changes.push(FieldChange { section, field: path.to_string(), old: old_text, new: new_text });
+        }
+    };
+    let tweak_changes = diff_tweaks(&old.enabled_tweaks, &new.enabled_tweaks);
+    for (section, fields) in PROFILE_DIFF_SECTIONS {
+        for field in *fields {
+            add_changes(&mut changes, section, &|path| top_level(path) == *field);
+        }
+        changes.extend(tweak_changes.iter().filter(|c| c.section == *section).cloned());
+    }
+    let known = |path: &str| PROFILE_DIFF_SECTIONS.iter().any(|(_, fields)| fields.contains(&top_level(path).as_str()));
+    add_changes(&mut changes, "Other", &|path| !known(path));
+
+    // Generate both answer files with secrets masked, so a changed
+    // password shows up in the field list but never in the XML lines
+    let xml_changes = diff_lines(
+        &generate_autounattend(&mask_profile_secrets(old)),
+        &generate_autounattend(&mask_profile_secrets(new)),
+    );
+
+    ProfileDiff { changes, xml_changes }
+}
+
+/// Tweaks switched on or off, as "disable_telemetry: true → false" in the
+/// section matching the tweak's catalog category ("Tweaks" if none does)
+fn diff_tweaks(old: &[String], new: &[String]) -> Vec<FieldChange> {
+    let catalog = tweaks::load_catalog();
+
+    // Catalog order first, then ids the catalog doesn't know
+    let mut ids: Vec<&String> = catalog.tweaks.iter().map(|t| &t.id).collect();
+    for id in old.iter().chain(new) {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    ids.into_iter()
+        .filter(|id| old.contains(id) != new.contains(id))
+        .map(|id| {
+            let category = catalog.get(id).map(|t| t.category.as_str()).unwrap_or("");
+            let section = PROFILE_DIFF_SECTIONS
+                .iter()
+                .map(|(section, _)| *section)
+                .find(|section| section.eq_ignore_ascii_case(category))
+                .unwrap_or("Tweaks");
+            FieldChange {
+                section,
+                field: id.clone(),
+                old: old.contains(id).to_string(),
+                new: new.contains(id).to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Flatten profile JSON into "path → value" pairs. Lists of named items
+/// (accounts) are keyed by name so inserting one doesn't shift the rest.
+fn flatten_profile_value(path: &str, value: &serde_json::Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                let child_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                flatten_profile_value(&child_path, child, out);
+            }
+        }
+        serde_json::Value::Array(items) if !items.is_empty() => {
+            for (index, item) in items.iter().enumerate() {
+                let key = item
+                    .get("name")
+                    .or_else(|| item.get("ssid"))
+                    .and_then(|n| n.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| index.to_string());
+                flatten_profile_value(&format!("{}[{}]", path, key), item, out);
+            }
+        }
+        _ => {
+            out.insert(path.to_string(), value.to_string());
+        }
+    }
+}
+
+/// Whether a diff path holds a secret (passwords, product key, Wi-Fi keys)
+fn is_secret_field(path: &str) -> bool {
+    let field = path.rsplit('.').next().unwrap_or(path);
+    field.contains("password") || field == "product_key" || (path.starts_with("wifi_profiles[") && field == "key")
+}
+
+/// Copy of a config with every non-empty secret replaced by a placeholder
+fn mask_profile_secrets(config: &DeployConfig) -> DeployConfig {
+    let mask = |secret: &mut String| {
+        if !secret.is_empty() {
+            *secret = "********".to_string();
+        }
+    };
+    let mut masked = config.clone();
+    mask(&mut masked.product_key);
+    mask(&mut masked.administrator_password);
+    mask(&mut masked.domain_password);
+    for account in &mut masked.accounts {
+        mask(&mut account.password);
+    }
+    for network in &mut masked.wifi_profiles {
+        mask(&mut network.key);
+    }
+    masked
+}
+
+/// Line diff (longest common subsequence). Returns only the changed lines,
+/// each run starting with an "@@ line N" header (N = line in `old`).
+fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = length of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let mut in_hunk = false;
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+            in_hunk = false;
+            continue;
+        }
+        if !in_hunk {
+            out.push(format!("@@ line {}", i + 1));
+            in_hunk = true;
+        }
+        if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push(format!("- {}", a[i].trim()));
+            i += 1;
+        } else {
+            out.push(format!("+ {}", b[j].trim()));
+            j += 1;
+        }
+    }
+    out
+}
+
+/// Load a profile for the diff: a saved profile name, or a path to a
+/// profile .json or an answer file .xml
+pub fn load_profile_for_diff(name_or_path: &str) -> Result<DeployConfig, String> {
+    let path = Path::new(name_or_path);
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("json") if path.exists() => load_profile_from_path(path),
+        Some("xml") if path.exists() => import_autounattend(path).map(|report| report.config),
+        _ => load_profile(name_or_path),
+    }
+}
+
+// ============================================
+// ANSWER FILE IMPORT
+// ============================================
+// Converts an existing autounattend.xml (hand-written or from another tool)
+// into a DeployConfig so it can be saved as a MasterBooter profile.
+//
+// Every setting that maps onto a DeployConfig field is applied. Anything
+// else is listed in ImportReport::unmapped so the technician can see
+// exactly what was dropped during the import.
+// ============================================
+
+/// Result of importing an answer file.
+#[derive(Debug, Clone)]
+pub struct ImportReport {
+    /// Configuration built from the recognized settings
+    pub config: DeployConfig,
+    /// Human-readable list of settings that could not be mapped
+    /// (e.g., "specialize/Microsoft-Windows-Shell-Setup/CopyProfile = true")
+    pub unmapped: Vec<String>,
+}
+
+/// Commands MasterBooter always adds itself (not tied to a tweak).
+/// Recognized so they don't show up as unmapped.
+const BUILTIN_COMMANDS: &[&str] = &[
+    r#"cmd /c "C:\Temp\MasterBooter\RunAll.bat""#,
+];
+
+/// Import an answer file from disk into a DeployConfig.
+///
+/// # Arguments
+/// * `path` — Path to an autounattend.xml / unattend.xml file
+///
+/// # Returns
+/// * `Ok(ImportReport)` — the mapped config plus a list of unmapped settings
+/// * `Err(String)` — error if the file can't be read or isn't an answer file
+pub fn import_autounattend(path: &Path) -> Result<ImportReport, String> {
+    let xml = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read answer file: {}", e))?;
+
+    let report = import_autounattend_xml(&xml)?;
+    println!(
+        "[Deploy] Imported answer file from: {} ({} unmapped setting(s))",
+        path.display(),
+        report.unmapped.len()
+    );
+    Ok(report)
+}
+
+/// Import answer file XML into a DeployConfig (see import_autounattend).
+///
+/// Settings that aren't present in the file are left OFF (tweaks, OOBE
+/// flags, user account), so the profile matches what the legacy file did.
+pub fn import_autounattend_xml(xml: &str) -> Result<ImportReport, String> {
+    let doc = UnattendDocument::parse(xml)?;
+
+    // Start from a "nothing enabled" config — a legacy answer file that
+    // doesn't mention a tweak didn't apply it.
+    let mut config = DeployConfig {
+        accounts: Vec::new(),
+        autologon_account: String::new(),
+        administrator_password: String::new(),
+        obfuscate_passwords: false,
+        skip_oobe: false,
+        skip_eula: false,
+        skip_network: false,
+        enabled_tweaks: Vec::new(),
+        prevent_device_encryption: false,
+        ..DeployConfig::default()
+    };
+    let mut unmapped = Vec::new();
+
+    // Locale values seen across all international components (path, value).
+    // Each setting (UILanguage, InputLocale, ...) maps to its own field.
+    let mut locales: Vec<(String, String)> = Vec::new();
+
+    // The first component's architecture is the primary one. Copies of a
+    // component for other architectures mean the file is multi-arch —
+    // they carry the same settings, so only the primary copy is read.
+    let primary_arch = doc
+        .passes
+        .iter()
+        .flat_map(|p| p.components.iter())
+        .map(|c| c.architecture.clone())
+        .next()
+        .unwrap_or_default();
+    match Architecture::parse(&primary_arch) {
+        Some(arch) => config.architecture = arch,
+        None if !primary_arch.is_empty() => {
+            unmapped.push(format!("processorArchitecture = {} (not supported)", primary_arch));
+        }
+        None => {}
+    }
+
+    for pass in &doc.passes {
+        for component in &pass.components {
+            if component.architecture != primary_arch {
+                config.multi_arch = true;
+                continue;
+            }
+            let location = format!("{}/{}", pass.pass, component.name);
+            match (pass.pass, component.name.as_str()) {
+                (Pass::WindowsPE, "Microsoft-Windows-International-Core-WinPE")
+                | (Pass::Specialize, "Microsoft-Windows-International-Core")
+                | (Pass::OobeSystem, "Microsoft-Windows-International-Core") => {
+                    import_international(component, &location, &mut locales, &mut unmapped);
+                }
+                (Pass::WindowsPE, "Microsoft-Windows-Setup") => {
+                    import_setup(component, &location, &mut config, &mut unmapped);
+                }
+                (Pass::WindowsPE, "Microsoft-Windows-PnpCustomizationsWinPE") => {
+                    import_driver_paths(component, &location, &mut config, &mut unmapped);
+                }
+                (Pass::Specialize, "Microsoft-Windows-Shell-Setup")
+                | (Pass::OobeSystem, "Microsoft-Windows-Shell-Setup") => {
+                    import_shell_setup(component, &location, &mut config, &mut unmapped);
+                }
+                (Pass::Specialize, "Microsoft-Windows-SecureStartup-FilterDriver") => {
+                    for setting in &component.settings {
+                        if setting.name == "PreventDeviceEncryption" {
+                            config.prevent_device_encryption = setting_is_true(setting);
+                        } else {
+                            report_unmapped(&location, setting, &mut unmapped);
+                        }
+                    }
+                }
+                (Pass::Specialize, "Microsoft-Windows-UnattendedJoin") => {
+                    import_unattended_join(component, &location, &mut config, &mut unmapped);
+                }
+                (Pass::Specialize, "Microsoft-Windows-Deployment") => {
+                    for setting in &component.settings {
+                        if setting.name != "RunSynchronous" {
+                            report_unmapped(&location, setting, &mut unmapped);
+                            continue;
+                        }
+                        // Our own steps: the Appx package list is in <Extensions>,
+                        // Wi-Fi profiles are files on the target (not importable).
+                        // Every other command becomes a specialize command.
+                        let path = format!("{}/RunSynchronous", location);
+                        for command in &setting.children {
+                            match command.child_text("Path") {
+                                Some(appx::SPECIALIZE_COMMAND) | Some(wifi::CLEANUP_COMMAND) => {}
+                                Some(wifi::IMPORT_COMMAND) => unmapped.push(format!(
+                                    "{}: Wi-Fi profile import (the networks aren't stored in the answer file — add them again)",
+                                    path
+                                )),
+                                Some(line) => match command.child_text("Order").and_then(|o| o.parse().ok()) {
+                                    Some(order) => config.specialize_commands.push(SpecializeCommand {
+                                        order,
+                                        path: line.to_string(),
+                                        description: command.child_text("Description").unwrap_or("").to_string(),
+                                    }),
+                                    None => report_unmapped(&path, command, &mut unmapped),
+                                },
+                                None => report_unmapped(&path, command, &mut unmapped),
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    for setting in &component.settings {
+                        report_unmapped(&location, setting, &mut unmapped);
+                    }
+                }
+            }
+        }
+    }
+
+    for extension in &doc.extensions {
+        match appx::parse_answer_file_script(&extension.text).filter(|_| extension.name == appx::EXTENSION_NAME) {
+            Some(packages) => {
+                config.remove_appx = packages;
+                config.appx_removal_mode = AppxRemovalMode::AnswerFile;
+            }
+            None => unmapped.push(format!("Extensions/{}", extension.name)),
+        }
+    }
+
+    // One value per locale setting: the first one wins, and a different
+    // value in another pass (WinPE vs. oobeSystem) is reported
+    let mut pick = |kind: &str| {
+        let suffix = format!("/{}", kind);
+        let mut values = locales.iter().filter(|(path, _)| path.ends_with(&suffix));
+        let (_, chosen) = values.next()?;
+        for (path, value) in values {
+            if !value.eq_ignore_ascii_case(chosen) {
+                unmapped.push(format!("{} = {} (one value per locale setting is supported: {})", path, value, chosen));
+            }
+        }
+        Some(chosen.clone())
+    };
+    let ui_language = pick("UILanguage");
+    let input_locale = pick("InputLocale");
+    let system_locale = pick("SystemLocale");
+    let user_locale = pick("UserLocale");
+
+    // Settings missing from the file follow the display language
+    if let Some(fallback) = ui_language.clone().or_else(|| system_locale.clone()).or_else(|| user_locale.clone()) {
+        config.input_locale = input_locale
+            .or_else(|| locale::default_input_locale(&fallback))
+            .unwrap_or_else(|| fallback.clone());
+        config.system_locale = system_locale.unwrap_or_else(|| fallback.clone());
+        config.user_locale = user_locale.unwrap_or_else(|| fallback.clone());
+        config.ui_language = ui_language.unwrap_or(fallback);
+    } else if let Some(input_locale) = input_locale {
+        config.input_locale = input_locale;
+    }
+
+    // Autologon can only log on as one of the created accounts
+    if !config.autologon_account.is_empty()
+        && !config.accounts.iter().any(|a| a.name.eq_ignore_ascii_case(&config.autologon_account))
+    {
+        unmapped.push(format!(
+            "AutoLogon/Username = {} (not one of the local accounts)",
+            config.autologon_account
+        ));
+        config.autologon_account = String::new();
+    }
+
+    // A generic key only selects the edition — generate_autounattend()
+    // re-adds it automatically, so don't store it as a "real" key.
+    if get_generic_key(&config.edition).is_some_and(|generic| generic.eq_ignore_ascii_case(&config.product_key)) {
+        config.product_key = String::new();
+    }
+
+    Ok(ImportReport { config, unmapped })
+}
+
+/// Collect locale settings from an International-Core component.
+fn import_international(
+    component: &Component,
+    location: &str,
+    locales: &mut Vec<(String, String)>,
+    unmapped: &mut Vec<String>,
+) {
+    for setting in &component.settings {
+        match setting.name.as_str() {
+            "InputLocale" | "SystemLocale" | "UILanguage" | "UserLocale" => {
+                if let Some(value) = setting.value.as_deref().filter(|v| !v.is_empty()) {
+                    locales.push((format!("{}/{}", location, setting.name), value.to_string()));
+                }
+            }
+            "SetupUILanguage" => {
+                let path = format!("{}/SetupUILanguage", location);
+                for child in &setting.children {
+                    match (child.name.as_str(), child.value.as_deref()) {
+                        ("UILanguage", Some(value)) if !value.is_empty() => {
+                            locales.push((format!("{}/UILanguage", path), value.to_string()));
+                        }
+                        _ => report_unmapped(&path, child, unmapped),
+                    }
+                }
+            }
+            _ => report_unmapped(location, setting, unmapped),
+        }
+    }
+}
+
+/// Import the windowsPE Microsoft-Windows-PnpCustomizationsWinPE component:
+/// each DriverPaths entry becomes a driver folder. Network share
+/// credentials aren't kept in profiles and are reported.
+fn import_driver_paths(component: &Component, location: &str, config: &mut DeployConfig, unmapped: &mut Vec<String>) {
+    for setting in &component.settings {
+        if setting.name != "DriverPaths" {
+            report_unmapped(location, setting, unmapped);
+            continue;
+        }
+        let path = format!("{}/DriverPaths/PathAndCredentials", location);
+        for entry in setting.children_named("PathAndCredentials") {
+            for child in &entry.children {
+                match (child.name.as_str(), child.value.as_deref()) {
+                    ("Path", Some(folder)) if !folder.trim().is_empty() => config.driver_paths.push(folder.trim().to_string()),
+                    // Don't echo the share password into the report
+                    ("Credentials", _) => unmapped.push(format!("{}/Credentials (share credentials aren't supported)", path)),
+                    _ => report_unmapped(&path, child, unmapped),
+                }
+            }
+        }
+    }
+}
+
+/// Import the windowsPE Microsoft-Windows-Setup component:
+/// disk layout, image selection, product key, registration.
+fn import_setup(
+    component: &Component,
+    location: &str,
+    config: &mut DeployConfig,
+    unmapped: &mut Vec<String>,
+) {
+    for setting in &component.settings {
+        match setting.name.as_str() {
+            "DiskConfiguration" => import_disk_configuration(setting, location, config, unmapped),
+            "ImageInstall" => {
+                let path = format!("{}/ImageInstall", location);
+                for child in &setting.children {
+                    if child.name == "OSImage" {
+                        import_os_image(child, &path, config, unmapped);
+                    } else {
+                        report_unmapped(&path, child, unmapped);
+                    }
+                }
+            }
+            "UserData" => {
+                let path = format!("{}/UserData", location);
+                for child in &setting.children {
+                    match child.name.as_str() {
+                        "AcceptEula" if setting_is_true(child) => {}
+                        "ProductKey" => {
+                            for key_child in &child.children {
+                                match (key_child.name.as_str(), key_child.value.as_deref()) {
+                                    ("Key", Some(key)) => config.product_key = key.to_string(),
+                                    ("WillShowUI", Some("OnError")) => {}
+                                    _ => report_unmapped(&format!("{}/ProductKey", path), key_child, unmapped),
+                                }
+                            }
+                        }
+                        "FullName" if config.owner_name.is_empty() => {
+                            config.owner_name = child.value.clone().unwrap_or_default();
+                        }
+                        "Organization" if config.organization.is_empty() => {
+                            config.organization = child.value.clone().unwrap_or_default();
+                        }
+                        _ => report_unmapped(&path, child, unmapped),
+                    }
+                }
+            }
+            _ => report_unmapped(location, setting, unmapped),
+        }
+    }
+}
+
+/// Import <DiskConfiguration>: target disk, boot mode and partition layout.
+/// Only layouts MasterBooter can generate (see planned_partitions) can be
+/// represented — any other layout is reported as unmapped.
+fn import_disk_configuration(
+    setting: &Setting,
+    location: &str,
+    config: &mut DeployConfig,
+    unmapped: &mut Vec<String>,
+) {
+    let path = format!("{}/DiskConfiguration", location);
+    let mut disks = setting.children_named("Disk");
+
+    if let Some(disk) = disks.next() {
+        if let Some(id) = disk.child_text("DiskID").and_then(|v| v.parse::<i32>().ok()) {
+            config.disk_id = id;
+        }
+
+        // Partition types decide the boot mode: an EFI partition means UEFI
+        let created: Vec<(Option<&str>, Option<&str>)> = disk
+            .child("CreatePartitions")
+            .map(|list| {
+                list.children_named("CreatePartition")
+                    .map(|p| (p.child_text("Type"), p.child_text("Size")))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let modified: Vec<&Setting> = disk
+            .child("ModifyPartitions")
+            .map(|list| list.children_named("ModifyPartition").collect())
+            .unwrap_or_default();
+        let is_uefi = created.iter().any(|(kind, _)| kind.is_some_and(|k| k.eq_ignore_ascii_case("EFI")));
+        config.boot_mode = if is_uefi { BootMode::UEFI } else { BootMode::BIOS };
+
+        if !created.is_empty() {
+            match import_partition_layout(&created, &modified, config.boot_mode) {
+                Some(layout) => config.partition_layout = layout,
+                None => unmapped.push(format!(
+                    "{}/Disk/CreatePartitions: custom layout with {} partition(s) — replaced by the standard {} layout",
+                    path,
+                    created.len(),
+                    config.boot_mode
+                )),
+            }
+        }
+        if !disk.child_text("WillWipeDisk").is_some_and(|v| v.eq_ignore_ascii_case("true")) {
+            unmapped.push(format!(
+                "{}/Disk/WillWipeDisk = false (partition sizes aren't in the answer file — check the partition layout)",
+                path
+            ));
+        }
+        for child in &disk.children {
+            if !matches!(child.name.as_str(), "DiskID" | "WillWipeDisk" | "CreatePartitions" | "ModifyPartitions") {
+                report_unmapped(&format!("{}/Disk", path), child, unmapped);
+            }
+        }
+    }
+    for extra in disks {
+        report_unmapped(&path, extra, unmapped);
+    }
+
+    for child in &setting.children {
+        match (child.name.as_str(), child.value.as_deref()) {
+            ("Disk", _) | ("WillShowUI", Some("OnError")) => {}
+            _ => report_unmapped(&path, child, unmapped),
+        }
+    }
+}
+
+/// Rebuild a PartitionLayout from <CreatePartitions> (type, size) pairs.
+/// Returns None unless planned_partitions() would produce exactly that list.
+/// Recovery partitions never show up here — those layouts are created by diskpart.
+fn import_partition_layout(
+    created: &[(Option<&str>, Option<&str>)],
+    modified: &[&Setting],
+    boot_mode: BootMode,
+) -> Option<PartitionLayout> {
+    let size = |index: usize| -> Option<u64> { created.get(index)?.1?.parse().ok() };
+    let mut layout = PartitionLayout::default();
+
+    // System partition first, then MSR on UEFI
+    let windows_index = if boot_mode == BootMode::UEFI { 2 } else { 1 };
+    layout.efi_size_mb = size(0)?;
+    layout.os_size_mb = size(windows_index).unwrap_or(0);
+
+    // A partition after Windows is the data partition
+    if created.len() > windows_index + 1 {
+        layout.data_partition = true;
+        let partition_id = (windows_index + 2).to_string();
+        if let Some(data) = modified.iter().find(|m| m.child_text("PartitionID") == Some(partition_id.as_str())) {
+            if let Some(label) = data.child_text("Label") {
+                layout.data_label = label.to_string();
+            }
+            if let Some(letter) = data.child_text("Letter") {
+                layout.data_letter = letter.to_string();
+            }
+        }
+    }
+
+    // Only accept the layout if it round-trips exactly
+    let expected: Vec<(Option<&str>, Option<String>)> = planned_partitions(&layout, boot_mode)
+        .into_iter()
+        .map(|p| (Some(p.partition_type), p.size_mb.map(|s| s.to_string())))
+        .collect();
+    let actual: Vec<(Option<&str>, Option<String>)> = created
+        .iter()
+        .map(|(kind, size)| (*kind, size.map(str::to_string)))
+        .collect();
+    if expected == actual && layout.validate().is_ok() {
+        Some(layout)
+    } else {
+        None
+    }
+}
+
+/// Import <OSImage>: which edition to install.
+/// <InstallTo> is derived from the disk layout, so it is not stored.
+fn import_os_image(
+    setting: &Setting,
+    location: &str,
+    config: &mut DeployConfig,
+    unmapped: &mut Vec<String>,
+) {
+    let path = format!("{}/OSImage", location);
+    for child in &setting.children {
+        match child.name.as_str() {
+            "InstallTo" if config.disk_id >= 0 => {}
+            "InstallFrom" => {
+                for from in &child.children {
+                    let key = from.child_text("Key").unwrap_or("");
+                    let value = from.child_text("Value").unwrap_or("");
+                    match (from.name.as_str(), key.to_ascii_uppercase().as_str()) {
+                        ("MetaData", "/IMAGE/NAME") => config.edition = value.to_string(),
+                        ("MetaData", "/IMAGE/INDEX") => match value.parse::<u32>() {
+                            Ok(index) => config.edition_index = index,
+                            Err(_) => report_unmapped(&format!("{}/InstallFrom", path), from, unmapped),
+                        },
+                        _ => report_unmapped(&format!("{}/InstallFrom", path), from, unmapped),
+                    }
+                }
+            }
+            _ => report_unmapped(&path, child, unmapped),
+        }
+    }
+}
+
+/// Import Microsoft-Windows-Shell-Setup (specialize or oobeSystem):
+/// machine identity, user account, auto-logon, OOBE flags, FirstLogonCommands.
+fn import_shell_setup(
+    component: &Component,
+    location: &str,
+    config: &mut DeployConfig,
+    unmapped: &mut Vec<String>,
+) {
+    let mut first_logon = None;
+    for setting in &component.settings {
+        let value = setting.value.clone().unwrap_or_default();
+        match setting.name.as_str() {
+            "ComputerName" => config.computer_name = value,
+            "TimeZone" => config.timezone = value,
+            "RegisteredOrganization" => config.organization = value,
+            "RegisteredOwner" => config.owner_name = value,
+            "OEMInformation" => import_oem_information(setting, location, config, unmapped),
+            "ProductKey" if config.product_key.is_empty() => config.product_key = value,
+            "AutoLogon" => import_autologon(setting, location, config, unmapped),
+            "UserAccounts" => import_user_accounts(setting, location, config, unmapped),
+            "OOBE" => import_oobe(setting, location, config, unmapped),
+            // Handled last — some commands refer to the accounts
+            "FirstLogonCommands" => first_logon = Some(setting),
+            _ => report_unmapped(location, setting, unmapped),
+        }
+    }
+
+    if let Some(setting) = first_logon {
+        let path = format!("{}/FirstLogonCommands", location);
+        let catalog = tweaks::load_catalog();
+        for command in &setting.children {
+            let line = command.child_text("CommandLine").unwrap_or("");
+            if command.name != "SynchronousCommand" || !import_first_logon_command(line, &catalog, config) {
+                let description = command.child_text("Description").unwrap_or("");
+                unmapped.push(format!("{}: {} ({})", path, line, description));
+            }
+        }
+        // Catalog order, like the Deploy page
+        config.enabled_tweaks.sort_by_key(|id| catalog.tweaks.iter().position(|t| &t.id == id));
+    }
+}
+
+/// Import <OEMInformation>. The logo is a file on the target, not in the
+/// answer file, so it has to be picked again.
+fn import_oem_information(setting: &Setting, location: &str, config: &mut DeployConfig, unmapped: &mut Vec<String>) {
+    let path = format!("{}/OEMInformation", location);
+    for child in &setting.children {
+        let value = child.value.clone().unwrap_or_default();
+        let info = &mut config.oem_info;
+        match child.name.as_str() {
+            "Manufacturer" => info.manufacturer = value,
+            "Model" => info.model = value,
+            "SupportHours" => info.support_hours = value,
+            "SupportPhone" => info.support_phone = value,
+            "SupportURL" => info.support_url = value,
+            "Logo" if value == oem::LOGO_PATH => {}
+            "Logo" => unmapped.push(format!("{}/Logo = {} (pick the .bmp file again)", path, value)),
+            _ => report_unmapped(&path, child, unmapped),
+        }
+    }
+}
+
+/// Import <AutoLogon>. The password comes from the matching local account
+/// (MasterBooter logs on as one of the created accounts).
+fn import_autologon(setting: &Setting, location: &str, config: &mut DeployConfig, unmapped: &mut Vec<String>) {
+    let path = format!("{}/AutoLogon", location);
+    let enabled = setting.child("Enabled").is_some_and(setting_is_true);
+
+    for child in &setting.children {
+        match child.name.as_str() {
+            "Enabled" => {}
+            "LogonCount" if child.value.as_deref() == Some("1") => {}
+            "Username" if enabled => config.autologon_account = child.value.clone().unwrap_or_default(),
+            "Username" => {}
+            "Password" => {
+                if import_password(child, "Password", config).is_none() {
+                    report_unmapped(&path, child, unmapped);
+                }
+            }
+            _ => report_unmapped(&path, child, unmapped),
+        }
+    }
+}
+
+/// Import <UserAccounts>: every <LocalAccount> becomes a UserAccount.
+fn import_user_accounts(setting: &Setting, location: &str, config: &mut DeployConfig, unmapped: &mut Vec<String>) {
+    let path = format!("{}/UserAccounts", location);
+    for child in &setting.children {
+        if child.name == "AdministratorPassword" {
+            match import_password(child, "AdministratorPassword", config) {
+                Some(password) => config.administrator_password = password,
+                None => report_unmapped(&path, child, unmapped),
+            }
+            continue;
+        }
+        if child.name != "LocalAccounts" {
+            report_unmapped(&path, child, unmapped);
+            continue;
+        }
+
+        let accounts_path = format!("{}/LocalAccounts", path);
+        for account in &child.children {
+            if account.name != "LocalAccount" {
+                report_unmapped(&accounts_path, account, unmapped);
+                continue;
+            }
+
+            let account_path = format!("{}/LocalAccount", accounts_path);
+            let mut imported = UserAccount {
+                name: String::new(),
+                display_name: String::new(),
+                password: String::new(),
+                group: default_account_group(),
+                password_never_expires: false,
+            };
+            for field in &account.children {
+                let value = field.value.clone().unwrap_or_default();
+                match field.name.as_str() {
+                    "Name" => imported.name = value,
+                    "DisplayName" => imported.display_name = value,
+                    "Group" => imported.group = value,
+                    "Password" => match import_password(field, "Password", config) {
+                        Some(password) => imported.password = password,
+                        None => report_unmapped(&account_path, field, unmapped),
+                    },
+                    _ => report_unmapped(&account_path, field, unmapped),
+                }
+            }
+            config.accounts.push(imported);
+        }
+    }
+}
+
+/// Import <OOBE> flags.
+fn import_oobe(setting: &Setting, location: &str, config: &mut DeployConfig, unmapped: &mut Vec<String>) {
+    let path = format!("{}/OOBE", location);
+    let mut wireless_count = 0;
+
+    for child in &setting.children {
+        let enabled = setting_is_true(child);
+        match (child.name.as_str(), child.value.as_deref()) {
+            ("HideEULAPage", _) => config.skip_eula = enabled,
+            // Any of these means "skip OOBE" — MasterBooter sets all of them together
+            ("HideOEMRegistrationScreen", _)
+            | ("HideOnlineAccountScreens", _)
+            | ("SkipMachineOOBE", _)
+            | ("SkipUserOOBE", _) => config.skip_oobe |= enabled,
+            ("HideWirelessSetupInOOBE", _) if enabled => wireless_count += 1,
+            // Values MasterBooter always writes
+            ("ProtectYourPC", Some("3")) | ("NetworkLocation", Some("Work")) => {}
+            _ => report_unmapped(&path, child, unmapped),
+        }
+    }
+
+    // skip_oobe already hides wireless setup once; skip_network adds it
+    // again. So wireless is only a separate choice if it appears without
+    // skip_oobe, or twice.
+    config.skip_network = if config.skip_oobe { wireless_count > 1 } else { wireless_count > 0 };
+}
+
+/// Import Microsoft-Windows-UnattendedJoin (domain or workgroup join).
+fn import_unattended_join(component: &Component, location: &str, config: &mut DeployConfig, unmapped: &mut Vec<String>) {
+    for setting in &component.settings {
+        if setting.name != "Identification" {
+            report_unmapped(location, setting, unmapped);
+            continue;
+        }
+
+        let path = format!("{}/Identification", location);
+        for child in &setting.children {
+            let value = child.value.clone().unwrap_or_default();
+            match child.name.as_str() {
+                "JoinDomain" => {
+                    config.join_domain = true;
+                    config.domain_name = value;
+                }
+                "JoinWorkgroup" => config.workgroup = value,
+                "MachineObjectOU" => config.domain_ou = value,
+                "Credentials" => {
+                    let domain = child.child_text("Domain").unwrap_or("");
+                    let username = child.child_text("Username").unwrap_or("");
+                    config.domain_username = if domain.is_empty() {
+                        username.to_string()
+                    } else {
+                        format!("{}\\{}", domain, username)
+                    };
+                    config.domain_password = child.child_text("Password").unwrap_or("").to_string();
+                }
+                _ => report_unmapped(&path, child, unmapped),
+            }
+        }
+    }
+}
+
+/// Try to map one FirstLogon command line onto a catalog tweak or another
+/// DeployConfig setting. Returns false if the command isn't one MasterBooter knows.
+fn import_first_logon_command(command_line: &str, catalog: &tweaks::TweakCatalog, config: &mut DeployConfig) -> bool {
+    let line = command_line.trim();
+
+    if BUILTIN_COMMANDS.iter().any(|c| c.eq_ignore_ascii_case(line)) {
+        return true;
+    }
+
+    // Older versions set "never expires" for every account at once
+    if line.eq_ignore_ascii_case("net accounts /maxpwage:unlimited") {
+        for account in &mut config.accounts {
+            account.password_never_expires = true;
+        }
+        return true;
+    }
+
+    // Set-LocalUser -Name 'X' -PasswordNeverExpires $true
+    if line.contains("Set-LocalUser") && line.contains("-PasswordNeverExpires") {
+        let quoted = single_quoted_strings(line);
+        let account = quoted.first().and_then(|name| {
+            config.accounts.iter_mut().find(|a| a.name.eq_ignore_ascii_case(name))
+        });
+        if let Some(account) = account {
+            account.password_never_expires = true;
+            return true;
+        }
+        return false;
+    }
+    // Tweaks: any command or registry value from a catalog tweak turns it on
+    let reg_add = parse_reg_add(line);
+    let tweak = catalog.find_command(line).or_else(|| {
+        reg_add.as_ref().and_then(|(key, value, data)| catalog.find_registry_op(&normalize_reg_key(key), value, data))
+    });
+    if let Some(tweak) = tweak {
+        if !config.enabled_tweaks.contains(&tweak.id) {
+            config.enabled_tweaks.push(tweak.id.clone());
+        }
+        return true;
+    }
+    if reg_add.is_some() {
+        return false;
+    }
+
+    // Domain join: Add-Computer -DomainName 'X' -Credential (New-Object PSCredential('U', (ConvertTo-SecureString 'P' ...
+    if line.contains("Add-Computer") && line.contains("-DomainName") {
+        let quoted = single_quoted_strings(line);
+        if quoted.len() >= 3 {
+            config.join_domain = true;
+            config.domain_name = quoted[0].clone();
+            config.domain_username = quoted[1].clone();
+            config.domain_password = quoted[2].clone();
+            if line.contains("-OUPath") {
+                config.domain_ou = quoted.get(3).cloned().unwrap_or_default();
+            }
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Parse a `reg add "KEY" /v NAME /t TYPE /d DATA /f` command.
+/// Returns (key, value name, data). Quotes around each part are optional.
+fn parse_reg_add(line: &str) -> Option<(String, String, String)> {
+    let lower = line.to_ascii_lowercase();
+    let rest = if lower.starts_with("reg add ") {
+        &line[8..]
+    } else if lower.starts_with("reg.exe add ") {
+        &line[12..]
+    } else {
+        return None;
+    };
+
+    // Split into tokens, keeping "quoted strings" together
+    let mut tokens: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in rest.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ' ' if !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    let key = tokens.first()?.clone();
+    let mut value = String::new();
+    let mut data = String::new();
+    let mut i = 1;
+    while i < tokens.len() {
+        let flag = tokens[i].to_ascii_lowercase();
+        // An empty name/data leaves the next token as another /flag
+        let next = tokens.get(i + 1).filter(|t| !t.starts_with('/')).cloned();
+        match flag.as_str() {
+            "/v" | "/d" => {
+                let target = if flag == "/v" { &mut value } else { &mut data };
+                if let Some(next) = next {
+                    *target = next;
+                    i += 1;
+                }
+            }
+            "/t" => i += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    Some((key, value, data))
+}
+
+/// Normalize long hive names (HKEY_LOCAL_MACHINE\...) to the short form.
+fn normalize_reg_key(key: &str) -> String {
+    let upper = key.to_ascii_uppercase();
+    for (long, short) in [("HKEY_LOCAL_MACHINE", "HKLM"), ("HKEY_CURRENT_USER", "HKCU")] {
+        if upper.starts_with(long) {
+            return format!("{}{}", short, &key[long.len()..]);
+        }
+    }
+    key.to_string()
+}
+
+/// Extract every 'single-quoted' PowerShell string ('' = escaped quote).
+fn single_quoted_strings(line: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\'' {
+            continue;
+        }
+        let mut text = String::new();
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                if chars.peek() == Some(&'\'') {
+                    text.push('\'');
+                    chars.next();
+                } else {
+                    break;
+                }
+            } else {
+                text.push(c);
+            }
+        }
+        result.push(text);
+    }
+    result
+}
+
+/// Read a <Password> block (<Value> + <PlainText>) as clear text.
+/// Encoded passwords (PlainText=false) are decoded, and the profile is set
+/// to keep obfuscating passwords. Returns None if the value can't be decoded.
+fn import_password(setting: &Setting, element: &str, config: &mut DeployConfig) -> Option<String> {
+    let password = Password {
+        value: setting.child_text("Value").unwrap_or("").to_string(),
+        plain_text: setting.child("PlainText").is_none_or(setting_is_true),
+    };
+    if !password.plain_text {
+        config.obfuscate_passwords = true;
+    }
+    password.clear_text(element)
+}
+
+/// True if a leaf setting's value is "true" (any case).
+fn setting_is_true(setting: &Setting) -> bool {
+    setting.value.as_deref().is_some_and(|v| v.eq_ignore_ascii_case("true"))
+}
+
+/// Add a setting (and all its leaf values) to the unmapped list.
+/// Password values are hidden, whether they could be decoded or not.
+fn report_unmapped(location: &str, setting: &Setting, unmapped: &mut Vec<String>) {
+    let path = format!("{}/{}", location, setting.name);
+    match &setting.value {
+        Some(_) if setting.children.is_empty() && is_password_value(&path) => {
+            unmapped.push(format!("{} = (hidden)", path));
+        }
+        Some(value) if setting.children.is_empty() => {
+            unmapped.push(format!("{} = {}", path, value));
+        }
+        _ if setting.children.is_empty() => unmapped.push(path),
+        _ => {
+            for child in &setting.children {
+                report_unmapped(&path, child, unmapped);
+            }
+        }
+    }
+}
+
+/// True for a password leaf: "*Password*/Value" (AdministratorPassword,
+/// LocalAccount/Password, ...) or a setting named like a password itself.
+fn is_password_value(path: &str) -> bool {
+    let mut segments = path.rsplit('/').map(|s| s.to_ascii_lowercase());
+    let leaf = segments.next().unwrap_or_default();
+    let parent = segments.next().unwrap_or_default();
+    leaf.contains("password") || (leaf == "value" && parent.contains("password"))
+}
+
 // ============================================
 // FILE DIALOGS
 // ============================================
@@ -2093,8 +4392,7 @@ pub fn load_profile_from_path(path: &Path) -> Result<DeployConfig, String> {
     let json = fs::read_to_string(path)
         .map_err(|e| format!("Failed to read profile: {}", e))?;
 
-    let config: DeployConfig = serde_json::from_str(&json)
-        .map_err(|e| format!("Failed to parse profile: {}", e))?;
+    let config = parse_profile_json(&json)?;
 
     println!("[Deploy] Imported profile from: {}", path.display());
     Ok(config)
@@ -2115,7 +4413,8 @@ pub fn pick_image_file() -> Option<PathBuf> {
     dialog.pick_file()
 }
 
-/// Open a file picker dialog for importing a deploy profile (.json).
+/// Open a file picker dialog for importing a deploy profile (.json)
+/// or an existing answer file (.xml — see import_autounattend).
 ///
 /// # Returns
 /// * `Some(PathBuf)` — the selected profile file path
@@ -2125,7 +4424,9 @@ pub fn pick_profile_file() -> Option<PathBuf> {
     let profiles_dir = get_profiles_dir();
     let mut dialog = rfd::FileDialog::new()
         .set_title("Import Deploy Profile")
+        .add_filter("Profiles and Answer Files", &["json", "xml"])
         .add_filter("JSON Profiles", &["json"])
+        .add_filter("Answer Files", &["xml"])
         .add_filter("All Files", &["*"]);
 
     if profiles_dir.exists() {
@@ -2135,6 +4436,29 @@ pub fn pick_profile_file() -> Option<PathBuf> {
     dialog.pick_file()
 }
 
+/// Open a folder picker for a driver folder (searched recursively for .inf files).
+///
+/// # Returns
+/// * `Some(PathBuf)` — the selected folder
+/// * `None` — user cancelled the dialog
+pub fn pick_driver_folder() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title("Add Driver Folder")
+        .pick_folder()
+}
+
+/// Open a file picker for the OEM logo (.bmp, shown at 120x120 pixels).
+///
+/// # Returns
+/// * `Some(PathBuf)` — the selected bitmap
+/// * `None` — user cancelled the dialog
+pub fn pick_oem_logo_file() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title("Select OEM Logo")
+        .add_filter("Bitmap Images", &["bmp"])
+        .pick_file()
+}
+
 /// Open a file picker dialog for selecting a script file to add.
 ///
 /// # Returns
@@ -2223,6 +4547,15 @@ pub fn list_scripts(script_type: &str) -> Vec<String> {
     names
 }
 
+/// The FirstLogon scripts in the order RunAll.bat runs them, with their
+/// tags ([ORDER=n], [ONLINE], ... or FirstLogon\scripts.toml).
+///
+/// # Returns
+/// The tagged scripts, and warnings about tags that were ignored
+pub fn firstlogon_plan() -> (Vec<scripts::TaggedScript>, Vec<String>) {
+    scripts::plan(&get_scripts_dir("FirstLogon"), &list_scripts("FirstLogon"))
+}
+
 /// Add a script to the FirstLogon folder by copying it from a source path.
 /// Creates the folder if needed.
 ///
@@ -2275,14 +4608,17 @@ pub fn remove_script(script_type: &str, filename: &str) -> Result<(), String> {
 // ============================================
 
 /// Execute the normal (interactive) install pipeline.
-/// Simpler than automated: just find and launch setup.exe, then copy scripts.
+/// Simpler than automated: just find and launch setup.exe, then add drivers
+/// and copy scripts.
 ///
 /// # Arguments
+/// * `driver_paths` — Driver folders to add to the installed Windows with DISM
 /// * `progress_fn` — Callback for progress updates (percentage, message)
 ///
 /// # Returns
 /// * `DeployResult` — success or failure with message
 pub fn normal_execute(
+    driver_paths: &[String],
     progress_fn: impl Fn(i32, &str) + Send + 'static,
 ) -> DeployResult {
     println!("[Deploy] Starting NORMAL (interactive) installation...");
@@ -2337,7 +4673,23 @@ pub fn normal_execute(
         }
     }
 
-    // Step 4: Copy scripts to target (if any exist)
+    // Step 4: Add drivers to the applied image before its first boot.
+    // Non-fatal: Windows is installed, it just may lack some drivers.
+    if !driver_paths.is_empty() {
+        progress_fn(85, "Adding drivers to the installed Windows...");
+        match find_target_windows_drive() {
+            Some(drive) => {
+                let results = drivers::add_to_image(&drive, driver_paths);
+                let added = results.iter().filter(|r| r.added).count();
+                let failed = results.len() - added;
+                println!("[Deploy] Drivers: {} added, {} failed", added, failed);
+                progress_fn(88, &format!("Added {} driver(s), {} failed", added, failed));
+            }
+            None => println!("[Deploy] Warning: No installed Windows found — drivers not added"),
+        }
+    }
+
+    // Step 5: Copy scripts to target (if any exist)
     // In Normal mode there's no autounattend.xml, so we pass is_normal_mode=true
     // to inject a RunOnce registry key that triggers RunAll.bat on first logon.
     progress_fn(90, "Copying post-install scripts...");
@@ -2346,7 +4698,7 @@ pub fn normal_execute(
         // Non-fatal — installation itself succeeded
     }
 
-    // Step 5: Reboot
+    // Step 6: Reboot
     progress_fn(95, "Rebooting...");
     let _ = Command::new("shutdown")
         .args(["/r", "/t", "5", "/f", "/c", "MasterBooter: Installation complete, rebooting..."])
@@ -2368,8 +4720,10 @@ pub fn normal_execute(
 /// Copy FirstLogon scripts to the newly installed Windows.
 /// Called after setup.exe completes (both Normal and Automated modes).
 ///
-/// Scripts are copied to C:\Temp\MasterBooter\ on the target drive, and a
-/// RunAll.bat is generated that executes each script in order with full logging.
+/// Scripts are copied to C:\Temp\MasterBooter\ on the target drive, together
+/// with the runner (RunAll.bat, RunAll.ps1 and RunAll.state.json — see
+/// scripts.rs) that executes each script in order with full logging and
+/// continues after restarts.
 ///
 /// For **Automated mode**: The autounattend.xml already has a <FirstLogonCommands>
 /// entry that calls RunAll.bat — no extra work needed here.
@@ -2420,84 +4774,22 @@ pub fn copy_scripts_to_target(is_normal_mode: bool) -> Result<(), String> {
     }
 
     // ============================================
-    // Create RunAll.bat with logging
+    // Write the runner with its run plan
     // ============================================
-    // RunAll.bat executes each script in order, with full logging to a .log file
-    // so the user can troubleshoot if anything fails. Each script invocation is
-    // logged with a timestamp, and errors are captured but don't stop the batch.
-    let log_file = r"C:\Temp\MasterBooter\RunAll.log";
-    let mut bat_content = String::from("@echo off\r\n");
-    bat_content.push_str("REM ============================================\r\n");
-    bat_content.push_str("REM MasterBooter Post-Install Scripts\r\n");
-    bat_content.push_str("REM This file was generated by MasterBooter.\r\n");
-    bat_content.push_str("REM It runs all FirstLogon scripts in order.\r\n");
-    bat_content.push_str("REM ============================================\r\n\r\n");
-
-    // Log start time
-    bat_content.push_str(&format!(
-        "echo ============================================ >> \"{}\"\r\n", log_file));
-    bat_content.push_str(&format!(
-        "echo MasterBooter Scripts - Started: %DATE% %TIME% >> \"{}\"\r\n", log_file));
-    bat_content.push_str(&format!(
-        "echo ============================================ >> \"{}\"\r\n\r\n", log_file));
-
-    // Execute each script with logging
-    for script_name in &firstlogon_scripts {
-        let ext = Path::new(script_name).extension()
-            .map(|e| e.to_string_lossy().to_lowercase())
-            .unwrap_or_default();
-
-        // Log which script is running
-        bat_content.push_str(&format!(
-            "echo [%TIME%] Running: {} >> \"{}\"\r\n", script_name, log_file));
-
-        match ext.as_str() {
-            "ps1" => {
-                // PowerShell: use -ExecutionPolicy Bypass so scripts always run
-                // (default policy is Restricted which blocks all .ps1 files)
-                bat_content.push_str(&format!(
-                    "powershell.exe -ExecutionPolicy Bypass -NonInteractive -File \"%~dp0{}\" >> \"{}\" 2>&1\r\n",
-                    script_name, log_file
-                ));
-            }
-            "reg" => {
-                // Registry files: import silently
-                bat_content.push_str(&format!(
-                    "reg import \"%~dp0{}\" >> \"{}\" 2>&1\r\n",
-                    script_name, log_file
-                ));
-            }
-            _ => {
-                // Batch files, executables, VBS, etc: call them
-                bat_content.push_str(&format!(
-                    "call \"%~dp0{}\" >> \"{}\" 2>&1\r\n",
-                    script_name, log_file
-                ));
-            }
-        }
-
-        // Log the result of each script
-        bat_content.push_str(&format!(
-            "echo [%TIME%] Finished: {} (exit code: %ERRORLEVEL%) >> \"{}\"\r\n",
-            script_name, log_file
-        ));
-        bat_content.push_str(&format!("echo. >> \"{}\"\r\n\r\n", log_file));
+    // RunAll.ps1 executes each script in tag order ([ORDER=n], [ONLINE],
+    // [IF=LAPTOP], ... — see scripts.rs), with full logging to a .log file
+    // so the user can troubleshoot if anything fails. Its progress is kept
+    // in RunAll.state.json, so a restart doesn't lose the remaining scripts.
+    let (plan, warnings) = scripts::plan(&firstlogon_dir, &firstlogon_scripts);
+    for warning in &warnings {
+        println!("[Deploy] Warning: {}", warning);
     }
-
-    // Log completion
-    bat_content.push_str(&format!(
-        "echo ============================================ >> \"{}\"\r\n", log_file));
-    bat_content.push_str(&format!(
-        "echo All scripts finished: %DATE% %TIME% >> \"{}\"\r\n", log_file));
-    bat_content.push_str(&format!(
-        "echo ============================================ >> \"{}\"\r\n", log_file));
-
-    let runall_path = target_fl.join("RunAll.bat");
-    if let Err(e) = fs::write(&runall_path, &bat_content) {
-        return Err(format!("Failed to write RunAll.bat: {}", e));
+    for script in &plan {
+        println!("[Deploy] Script {}", script.label());
     }
-    println!("[Deploy] Created RunAll.bat with {} scripts (logging to {})",
-        firstlogon_scripts.len(), log_file);
+    scripts::write_runner(&target_fl, &plan)?;
+    println!("[Deploy] Created RunAll.bat/{} with {} scripts (state in {}, logging to C:\\Temp\\MasterBooter\\RunAll.log)",
+        scripts::RUNNER_SCRIPT, plan.len(), scripts::STATE_FILE);
 
     // ============================================
     // Normal mode: Inject RunOnce registry key
@@ -2630,7 +4922,7 @@ fn find_target_windows_drive() -> Option<String> {
 
 /// Information about detected Windows product keys.
 /// Saved to saved_keys.json next to the EXE for cross-session persistence.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WindowsKeyInfo {
     /// OEM/BIOS key embedded in firmware (may be empty on non-OEM machines)
     pub oem_key: String,
@@ -2642,27 +4934,98 @@ pub struct WindowsKeyInfo {
     pub status: String,
     /// Computer hostname (for reference — which machine was this backed up from?)
     pub hostname: String,
-    /// Date the backup was taken (e.g., "2026-02-18")
+    /// When the backup was taken, local time with UTC offset
+    /// (e.g., "2026-02-18 14:03:22 +01:00"; older entries have only the date)
     pub date: String,
+    /// Where the key was read from: empty for the running Windows,
+    /// e.g. "D:\Windows (offline)" for a rescued install
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source: String,
+    /// Every distinct key/edition combination backed up for this host,
+    /// oldest first (the fields above are the latest backup)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<KeyHistoryEntry>,
+}
+
+/// One key/edition combination seen for a host in saved_keys.json
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyHistoryEntry {
+    pub oem_key: String,
+    pub installed_key: String,
+    pub edition: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source: String,
+    /// First and latest backup that found this combination
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+impl KeyHistoryEntry {
+    /// Same keys and edition (the dates and source don't matter)
+    fn same_keys(&self, other: &KeyHistoryEntry) -> bool {
+        self.oem_key == other.oem_key && self.installed_key == other.installed_key && self.edition == other.edition
+    }
+}
+
+impl WindowsKeyInfo {
+    /// Every combination seen for this host. Entries saved before the
+    /// history existed count as one combination: their current values.
+    pub fn observations(&self) -> Vec<KeyHistoryEntry> {
+        if !self.history.is_empty() {
+            return self.history.clone();
+        }
+        vec![KeyHistoryEntry {
+            oem_key: self.oem_key.clone(),
+            installed_key: self.installed_key.clone(),
+            edition: self.edition.clone(),
+            source: self.source.clone(),
+            first_seen: self.date.clone(),
+            last_seen: self.date.clone(),
+        }]
+    }
 }
 
-/// Detect Windows product keys using PowerShell.
+/// Timestamp for key backups: local time with UTC offset
+/// (e.g., "2026-02-18 14:03:22 +01:00")
+pub fn backup_timestamp() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M:%S %:z").to_string()
+}
+
+/// When a backup was taken, for comparing dates from saved_keys.json.
+/// Dates from different machines can have different UTC offsets, so they
+/// are compared as instants, not as text. Entries saved before the offset
+/// was recorded are date-only ("2025-03-01") and count as midnight UTC;
+/// unreadable dates sort first.
+fn backup_instant(date: &str) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    if let Ok(instant) = chrono::DateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S %:z") {
+        return Some(instant);
+    }
+    let day = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(day.and_hms_opt(0, 0, 0)?.and_utc().fixed_offset())
+}
+
+/// Detect Windows product keys.
 /// Retrieves both the OEM/BIOS key and the currently installed key.
 ///
-/// The OEM key is read from the firmware (SoftwareLicensingService).
-/// The installed key is decoded from the registry's DigitalProductId
-/// using the standard 25-character decode algorithm.
+/// The installed key is read with reg.exe and decoded in Rust (see
+/// product_key.rs), so it works without PowerShell. The OEM key, edition
+/// and license status come from WMI through PowerShell; when PowerShell is
+/// missing (minimal WinPE), the edition falls back to the registry.
 ///
 /// # Returns
-/// * `Ok(WindowsKeyInfo)` — detected key information
-/// * `Err(String)` — error if PowerShell fails
+/// * `Ok(WindowsKeyInfo)` — detected key information (empty fields = not found)
+/// * `Err(String)` — neither reg.exe nor PowerShell could be run
 pub fn detect_windows_keys() -> Result<WindowsKeyInfo, String> {
     println!("[Deploy] Detecting Windows product keys...");
 
-    // PowerShell script that detects both keys and outputs structured data.
+    // Installed key: decoded from the registry's DigitalProductId
+    let installed_key = product_key::read_installed_key(product_key::CURRENT_VERSION_KEY);
+    if let Err(e) = &installed_key {
+        println!("[Deploy] Could not decode the installed key: {}", e);
+    }
+
+    // PowerShell script that detects the OEM key, edition and license status.
     // Each piece of data is on its own line prefixed with a label for easy parsing.
-    // The DigitalProductId decode algorithm is the standard well-known method used
-    // by every key detection tool (ProduKey, ShowKeyPlus, etc.).
     let ps_script = r#"
 # 1. OEM/BIOS key (embedded in firmware by manufacturer)
 try {
@@ -2671,51 +5034,7 @@ try {
     else { Write-Output "OEM_KEY:" }
 } catch { Write-Output "OEM_KEY:" }
 
-# 2. Installed key (decoded from registry DigitalProductId)
-try {
-    $value = (Get-ItemProperty 'HKLM:\SOFTWARE\Microsoft\Windows NT\CurrentVersion').DigitalProductId
-    if ($value) {
-        # Standard decode algorithm for Windows 8+ product keys
-        $key = ""
-        $chars = "BCDFGHJKMPQRTVWXY2346789"
-        $decoded = $value[52..66]
-
-        # The isWin8+ flag is stored in byte 66
-        $isWin8 = [math]::Floor($value[66] / 6) -band 1
-        $value[66] = ($value[66] -band 0xF7) -bor (($isWin8 -band 2) * 4)
-        $decoded = $value[52..66]
-
-        for ($i = 24; $i -ge 0; $i--) {
-            $current = 0
-            for ($j = 14; $j -ge 0; $j--) {
-                $current = $current * 256
-                $current = $decoded[$j] + $current
-                $decoded[$j] = [math]::Floor([double]($current / 24))
-                $current = $current % 24
-            }
-            $key = $chars[$current] + $key
-        }
-
-        # Insert 'N' for Win8+ keys at position determined by last decode
-        if ($isWin8 -eq 1) {
-            $keypart1 = $key.Substring(1, $current)
-            $keypart2 = $key.Substring($current + 1)
-            $key = $keypart1 + "N" + $keypart2
-        }
-
-        # Format as XXXXX-XXXXX-XXXXX-XXXXX-XXXXX
-        $formatted = ""
-        for ($i = 0; $i -lt 25; $i++) {
-            $formatted += $key[$i]
-            if (($i + 1) % 5 -eq 0 -and $i -lt 24) { $formatted += "-" }
-        }
-        Write-Output "INSTALLED_KEY:$formatted"
-    } else {
-        Write-Output "INSTALLED_KEY:"
-    }
-} catch { Write-Output "INSTALLED_KEY:" }
-
-# 3. Edition and license status
+# 2. Edition and license status
 # Uses server-side WQL filter (fast!) instead of client-side Where-Object (slow!).
 # The old approach downloaded all ~400 SoftwareLicensingProduct rows then filtered
 # in PowerShell, taking 30+ seconds. WQL filtering happens inside WMI, returning
@@ -2764,43 +5083,38 @@ try {
     Write-Output "STATUS:Error detecting"
 }
 
-# 4. Hostname
+# 3. Hostname
 Write-Output "HOSTNAME:$env:COMPUTERNAME"
 "#;
 
-    // Run the PowerShell script
-    let output = Command::new("powershell")
-        .args(["-ExecutionPolicy", "Bypass", "-NoProfile", "-Command", ps_script])
-        .output()
-        .map_err(|e| format!("Failed to run PowerShell: {}", e))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("PowerShell key detection failed: {}", stderr));
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Run the PowerShell script. Without PowerShell only the installed key
+    // and the registry fallbacks below are available.
+    let stdout = match Command::new("powershell")
+        .args(["-ExecutionPolicy", "Bypass", "-NoProfile", "-Command", ps_script])
+        .output()
+    {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).to_string(),
+        Ok(output) => {
+            println!("[Deploy] PowerShell key detection failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+            String::new()
+        }
+        Err(e) if installed_key.is_err() => return Err(format!("Failed to run PowerShell: {}", e)),
+        Err(e) => {
+            println!("[Deploy] PowerShell not available ({}) — OEM key and license status skipped", e);
+            String::new()
+        }
+    };
 
     // Parse the labeled output lines
     let mut info = WindowsKeyInfo {
         oem_key: String::new(),
-        installed_key: String::new(),
+        installed_key: installed_key.unwrap_or_default(),
         edition: String::new(),
         status: String::new(),
         hostname: String::new(),
-        // Get today's date for the backup timestamp
-        date: {
-            let now = std::time::SystemTime::now();
-            let duration = now.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
-            let secs = duration.as_secs();
-            // Simple date calculation (good enough for a human-readable timestamp)
-            let days = secs / 86400;
-            let years = 1970 + (days / 365);  // Approximate — close enough for display
-            let remaining_days = days % 365;
-            let month = remaining_days / 30 + 1;
-            let day = remaining_days % 30 + 1;
-            format!("{}-{:02}-{:02}", years, month.min(12), day.min(31))
-        },
+        date: backup_timestamp(),
+        source: String::new(),
+        history: Vec::new(),
     };
 
     // Parse each labeled line from PowerShell output
@@ -2808,8 +5122,6 @@ Write-Output "HOSTNAME:$env:COMPUTERNAME"
         let line = line.trim();
         if let Some(value) = line.strip_prefix("OEM_KEY:") {
             info.oem_key = value.trim().to_string();
-        } else if let Some(value) = line.strip_prefix("INSTALLED_KEY:") {
-            info.installed_key = value.trim().to_string();
         } else if let Some(value) = line.strip_prefix("EDITION:") {
             info.edition = value.trim().to_string();
         } else if let Some(value) = line.strip_prefix("STATUS:") {
@@ -2819,6 +5131,21 @@ Write-Output "HOSTNAME:$env:COMPUTERNAME"
         }
     }
 
+    // Registry fallbacks when PowerShell gave nothing
+    if info.edition.is_empty() {
+        if let Ok(output) = Command::new("reg")
+            .args(["query", product_key::CURRENT_VERSION_KEY, "/v", "ProductName"])
+            .output()
+        {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            info.edition = product_key::parse_reg_string(&stdout, "ProductName").unwrap_or_default();
+        }
+        info.status = "Unknown (PowerShell not available)".to_string();
+    }
+    if info.hostname.is_empty() {
+        info.hostname = std::env::var("COMPUTERNAME").unwrap_or_default();
+    }
+
     println!("[Deploy] Key detection complete:");
     println!("  OEM key: {}", if info.oem_key.is_empty() { "(none)" } else { &info.oem_key });
     println!("  Installed key: {}", if info.installed_key.is_empty() { "(none)" } else { &info.installed_key });
@@ -2829,6 +5156,132 @@ Write-Output "HOSTNAME:$env:COMPUTERNAME"
     Ok(info)
 }
 
+// ============================================
+// OFFLINE KEY RECOVERY
+// ============================================
+// A Windows that no longer boots still has its key in the registry hive
+// files on disk. From WinPE, the SOFTWARE and SYSTEM hives of that install
+// are loaded under temporary HKLM keys with `reg load`, read with
+// `reg query`, and unloaded again:
+//
+//   D:\Windows\System32\config\SOFTWARE → HKLM\MB_OFFLINE_SOFTWARE
+//       Microsoft\Windows NT\CurrentVersion: DigitalProductId, ProductName, EditionID
+//   D:\Windows\System32\config\SYSTEM   → HKLM\MB_OFFLINE_SYSTEM
+//       Select\Current → ControlSet00N\Control\ComputerName\ComputerName
+
+/// Temporary HKLM keys the offline hives are loaded under
+const OFFLINE_SOFTWARE_KEY: &str = r"HKLM\MB_OFFLINE_SOFTWARE";
+const OFFLINE_SYSTEM_KEY: &str = r"HKLM\MB_OFFLINE_SYSTEM";
+
+/// Drives with an offline Windows install (a SOFTWARE hive), excluding
+/// the running system (X: in WinPE, C: on a live Windows).
+pub fn find_offline_windows_drives() -> Vec<String> {
+    let system_drive = std::env::var("SystemDrive").unwrap_or_default();
+    (b'C'..=b'Z')
+        .map(|letter| format!("{}:", letter as char))
+        .filter(|drive| !drive.eq_ignore_ascii_case(&system_drive))
+        .filter(|drive| offline_hive_path(drive, "SOFTWARE").exists())
+        .collect()
+}
+
+/// Path of a registry hive file of the Windows on `drive`
+fn offline_hive_path(drive: &str, hive: &str) -> PathBuf {
+    PathBuf::from(format!("{}\\Windows\\System32\\config\\{}", drive, hive))
+}
+
+/// `reg query <key> /v <value>` output (empty if the query failed)
+fn reg_query(key: &str, value: &str) -> String {
+    Command::new("reg")
+        .args(["query", key, "/v", value])
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
+        .unwrap_or_default()
+}
+
+/// Load a hive file under `mount_key`, run `read` on it and unload it again
+/// (also when `read` fails, so the file isn't left locked).
+fn with_offline_hive<T>(
+    hive_file: &Path,
+    mount_key: &str,
+    read: impl FnOnce() -> Result<T, String>,
+) -> Result<T, String> {
+    let output = Command::new("reg")
+        .args(["load", mount_key, &hive_file.to_string_lossy()])
+        .output()
+        .map_err(|e| format!("Failed to run reg.exe: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Could not load {}: {}",
+            hive_file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let result = read();
+
+    let unloaded = Command::new("reg").args(["unload", mount_key]).output();
+    if !unloaded.is_ok_and(|out| out.status.success()) {
+        // Sometimes the hive is still in use for a moment
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        let _ = Command::new("reg").args(["unload", mount_key]).output();
+    }
+    result
+}
+
+/// Read the product key, edition and computer name of the (unbootable)
+/// Windows on another drive from its SOFTWARE and SYSTEM hive files.
+/// The OEM key lives in the firmware of the machine, not in the install,
+/// so it isn't part of the result.
+///
+/// # Arguments
+/// * `drive` — Drive of the offline Windows (e.g., "D:")
+///
+/// # Returns
+/// * `Ok(WindowsKeyInfo)` — with `source` set to the Windows folder
+/// * `Err(String)` — the SOFTWARE hive can't be loaded or holds no key
+pub fn detect_offline_windows_keys(drive: &str) -> Result<WindowsKeyInfo, String> {
+    println!("[Deploy] Reading product key from offline Windows on {}...", drive);
+
+    let current_version = format!(r"{}\Microsoft\Windows NT\CurrentVersion", OFFLINE_SOFTWARE_KEY);
+    let (installed_key, product_name, edition_id) =
+        with_offline_hive(&offline_hive_path(drive, "SOFTWARE"), OFFLINE_SOFTWARE_KEY, || {
+            let key = product_key::read_installed_key(&current_version)?;
+            let product_name = product_key::parse_reg_string(&reg_query(&current_version, "ProductName"), "ProductName");
+            let edition_id = product_key::parse_reg_string(&reg_query(&current_version, "EditionID"), "EditionID");
+            Ok((key, product_name.unwrap_or_default(), edition_id.unwrap_or_default()))
+        })?;
+
+    // The computer name is only a label for the saved entry — not fatal
+    let hostname = with_offline_hive(&offline_hive_path(drive, "SYSTEM"), OFFLINE_SYSTEM_KEY, || {
+        let select = format!(r"{}\Select", OFFLINE_SYSTEM_KEY);
+        let control_set = product_key::parse_reg_dword(&reg_query(&select, "Current"), "Current").unwrap_or(1);
+        let key = format!(r"{}\ControlSet{:03}\Control\ComputerName\ComputerName", OFFLINE_SYSTEM_KEY, control_set);
+        product_key::parse_reg_string(&reg_query(&key, "ComputerName"), "ComputerName")
+            .ok_or_else(|| "no ComputerName value".to_string())
+    })
+    .unwrap_or_else(|e| {
+        println!("[Deploy] Warning: Could not read the offline computer name: {}", e);
+        format!("Offline {}", drive)
+    });
+
+    let info = WindowsKeyInfo {
+        oem_key: String::new(),
+        installed_key,
+        edition: match (product_name.is_empty(), edition_id.is_empty()) {
+            (false, false) => format!("{} ({})", product_name, edition_id),
+            (true, _) => edition_id,
+            (false, true) => product_name,
+        },
+        status: "Offline install".to_string(),
+        hostname,
+        date: backup_timestamp(),
+        source: format!("{}\\Windows (offline)", drive),
+        history: Vec::new(),
+    };
+    println!("[Deploy] Offline key: {} ({}, {})", info.installed_key, info.edition, info.hostname);
+    Ok(info)
+}
+
 /// Get the path to saved_keys.json (next to the EXE).
 /// This file persists on the USB drive so keys survive reboots between
 /// live Windows (backup) and WinPE (deploy) sessions.
@@ -2841,8 +5294,8 @@ fn get_saved_keys_path() -> PathBuf {
 }
 
 /// Save detected Windows key info to saved_keys.json next to the EXE.
-/// Supports multiple keys: loads existing entries, adds/updates by hostname,
-/// then writes the full array back to the file.
+/// Supports multiple keys: loads existing entries, adds the backup to its
+/// host's entry (see merge_key_entry), then writes the full array back.
 ///
 /// **Backward compatibility**: If the file contains the old single-object format
 /// (from before multi-key support), it's automatically migrated to an array.
@@ -2859,27 +5312,102 @@ pub fn save_keys_to_file(info: &WindowsKeyInfo) -> Result<(), String> {
     // Load existing keys (may be empty on first use)
     let mut keys = load_saved_keys();
 
-    // Check if this hostname already exists — update it instead of duplicating
-    let existing_index = keys.iter().position(|k| k.hostname == info.hostname);
-    if let Some(idx) = existing_index {
-        // Replace the existing entry for this machine
-        keys[idx] = info.clone();
-        println!("[Deploy] Updated existing key for hostname: {}", info.hostname);
-    } else {
-        // New machine — add to the list
-        keys.push(info.clone());
-        println!("[Deploy] Added new key for hostname: {}", info.hostname);
-    }
+    let new_combinations = merge_key_entry(&mut keys, info.clone());
+    println!(
+        "[Deploy] Saved key for hostname: {} ({} new key/edition combination(s))",
+        info.hostname, new_combinations
+    );
 
     // Write the full array back to the file
+    write_saved_keys(&path, &keys)?;
+
+    println!("[Deploy] Saved {} key(s) to: {}", keys.len(), path.display());
+    Ok(())
+}
+
+/// Add a backup (or another store's entry for a host) to the saved keys.
+/// Entries are kept per hostname: the top-level fields show the latest
+/// backup, and the history keeps every distinct key/edition combination
+/// with the first and last time it was seen.
+///
+/// # Returns
+/// Number of key/edition combinations that weren't in the history yet
+pub fn merge_key_entry(keys: &mut Vec<WindowsKeyInfo>, incoming: WindowsKeyInfo) -> usize {
+    let seen = incoming.observations();
+    let Some(entry) = keys.iter_mut().find(|k| k.hostname == incoming.hostname) else {
+        let count = seen.len();
+        keys.push(WindowsKeyInfo { history: seen, ..incoming });
+        return count;
+    };
+
+    let mut history = entry.observations();
+    let mut added = 0;
+    for observation in seen {
+        match history.iter_mut().find(|h| h.same_keys(&observation)) {
+            Some(known) => {
+                if backup_instant(&observation.first_seen) < backup_instant(&known.first_seen) {
+                    known.first_seen = observation.first_seen;
+                }
+                if backup_instant(&observation.last_seen) > backup_instant(&known.last_seen) {
+                    known.last_seen = observation.last_seen;
+                }
+            }
+            None => {
+                history.push(observation);
+                added += 1;
+            }
+        }
+    }
+    history.sort_by_key(|h| backup_instant(&h.first_seen));
+
+    // The newer backup provides the current values
+    if backup_instant(&incoming.date) >= backup_instant(&entry.date) {
+        *entry = incoming;
+    }
+    entry.history = history;
+    added
+}
+
+/// Write saved_keys.json. When secrets are unlocked this session, the
+/// product keys are stored encrypted. Entries that are still encrypted
+/// (loaded while locked) are written back unchanged.
+fn write_saved_keys(path: &Path, keys: &[WindowsKeyInfo]) -> Result<(), String> {
+    let mut keys = keys.to_vec();
+    if secrets::is_unlocked() {
+        for key in &mut keys {
+            key.oem_key = secrets::encrypt(&key.oem_key, "oem_key")?;
+            key.installed_key = secrets::encrypt(&key.installed_key, "installed_key")?;
+            for seen in &mut key.history {
+                seen.oem_key = secrets::encrypt(&seen.oem_key, "oem_key")?;
+                seen.installed_key = secrets::encrypt(&seen.installed_key, "installed_key")?;
+            }
+        }
+    }
+
     let json = serde_json::to_string_pretty(&keys)
         .map_err(|e| format!("Failed to serialize key info: {}", e))?;
 
-    fs::write(&path, &json)
-        .map_err(|e| format!("Failed to write saved_keys.json: {}", e))?;
+    fs::write(path, &json)
+        .map_err(|e| format!("Failed to write saved_keys.json: {}", e))
+}
 
-    println!("[Deploy] Saved {} key(s) to: {}", keys.len(), path.display());
-    Ok(())
+/// Decrypt the product keys of a saved entry if secrets are unlocked.
+/// Keys that can't be decrypted stay in their "enc:" form (with a warning).
+fn decrypt_saved_key(mut key: WindowsKeyInfo) -> WindowsKeyInfo {
+    if secrets::is_unlocked() {
+        let mut values = vec![("oem_key", &mut key.oem_key), ("installed_key", &mut key.installed_key)];
+        for seen in &mut key.history {
+            values.push(("oem_key", &mut seen.oem_key));
+            values.push(("installed_key", &mut seen.installed_key));
+        }
+        for (field, value) in values {
+            match secrets::decrypt(value, field) {
+                Ok(clear) => *value = clear,
+                Err(e) => println!("[Deploy] Warning: saved key for {}: {}", key.hostname, e),
+            }
+        }
+    }
+    key
 }
 
 /// Load all previously saved Windows key entries from saved_keys.json.
@@ -2890,6 +5418,9 @@ pub fn save_keys_to_file(info: &WindowsKeyInfo) -> Result<(), String> {
 /// returned as a single-element Vec. The file is NOT rewritten here — it will
 /// be migrated to the array format on the next save.
 ///
+/// Encrypted product keys are decrypted when secrets are unlocked this
+/// session; otherwise they stay in their "enc:" form.
+///
 /// # Returns
 /// * `Vec<WindowsKeyInfo>` — all saved key entries (may be empty)
 pub fn load_saved_keys() -> Vec<WindowsKeyInfo> {
@@ -2904,21 +5435,140 @@ pub fn load_saved_keys() -> Vec<WindowsKeyInfo> {
         Err(_) => return Vec::new(),
     };
 
+    match parse_saved_keys(&content) {
+        Ok(keys) => {
+            println!("[Deploy] Loaded {} saved key(s) from: {}", keys.len(), path.display());
+            keys
+        }
+        Err(e) => {
+            // File is corrupt or unrecognized — return empty
+            println!("[Deploy] Warning: Could not parse saved_keys.json: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Parse the contents of a saved_keys.json (and decrypt it if unlocked).
+fn parse_saved_keys(content: &str) -> Result<Vec<WindowsKeyInfo>, String> {
     // First try: parse as an array of keys (new multi-key format)
-    if let Ok(keys) = serde_json::from_str::<Vec<WindowsKeyInfo>>(&content) {
-        println!("[Deploy] Loaded {} saved key(s) from: {}", keys.len(), path.display());
-        return keys;
+    if let Ok(keys) = serde_json::from_str::<Vec<WindowsKeyInfo>>(content) {
+        return Ok(keys.into_iter().map(decrypt_saved_key).collect());
     }
 
     // Second try: parse as a single key (old format — auto-migrate)
-    if let Ok(single_key) = serde_json::from_str::<WindowsKeyInfo>(&content) {
-        println!("[Deploy] Migrated old single-key format from: {}", path.display());
-        return vec![single_key];
+    match serde_json::from_str::<WindowsKeyInfo>(content) {
+        Ok(single_key) => {
+            println!("[Deploy] Migrated old single-key format");
+            Ok(vec![decrypt_saved_key(single_key)])
+        }
+        Err(e) => Err(format!("not a saved keys file: {}", e)),
+    }
+}
+
+/// Merge another technician's saved_keys.json into ours. Hosts we don't
+/// have are added; for known hosts the histories are combined and the
+/// newer backup becomes the current one.
+///
+/// Keys are compared in clear text, so the import is refused while either
+/// file still has encrypted keys (secrets locked, or saved with another
+/// passphrase) — otherwise the same key would be added a second time.
+///
+/// # Returns
+/// * `Ok((hosts, combinations))` — hosts in the imported file and key/edition
+///   combinations that were new to us
+/// * `Err(String)` — the file can't be read, isn't a saved keys file, or
+///   has keys that can't be decrypted
+pub fn import_saved_keys(path: &Path) -> Result<(usize, usize), String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let incoming = parse_saved_keys(&content)?;
+
+    let mut keys = load_saved_keys();
+    let hosts = incoming.len();
+    let added = merge_saved_keys(&mut keys, incoming)?;
+    write_saved_keys(&get_saved_keys_path(), &keys)?;
+
+    println!("[Deploy] Imported {} host(s) from {} ({} new key/edition combination(s))", hosts, path.display(), added);
+    Ok((hosts, added))
+}
+
+/// Merge imported entries into ours (see import_saved_keys).
+///
+/// # Returns
+/// * `Ok(usize)` — key/edition combinations that were new to us
+/// * `Err(String)` — one side still has encrypted keys
+fn merge_saved_keys(keys: &mut Vec<WindowsKeyInfo>, incoming: Vec<WindowsKeyInfo>) -> Result<usize, String> {
+    for (keys, file) in [(&*keys, "saved_keys.json"), (&incoming, "The imported file")] {
+        if let Some(key) = keys.iter().find(|k| has_encrypted_keys(k)) {
+            return Err(format!(
+                "{} has encrypted product keys (e.g. {}) — unlock secrets with the passphrase they were saved with before importing",
+                file, key.hostname
+            ));
+        }
     }
+    Ok(incoming.into_iter().map(|entry| merge_key_entry(keys, entry)).sum())
+}
+
+/// Whether any product key of a saved entry is still in its "enc:" form
+fn has_encrypted_keys(key: &WindowsKeyInfo) -> bool {
+    std::iter::once((&key.oem_key, &key.installed_key))
+        .chain(key.history.iter().map(|seen| (&seen.oem_key, &seen.installed_key)))
+        .any(|(oem, installed)| secrets::is_encrypted(oem) || secrets::is_encrypted(installed))
+}
+
+/// Write every host's key history to a CSV file, one row per key/edition
+/// combination. Keys that are still encrypted (secrets locked) are
+/// written in their "enc:" form.
+///
+/// # Returns
+/// * `Ok(usize)` — number of rows written
+pub fn export_saved_keys_csv(path: &Path) -> Result<usize, String> {
+    let keys = load_saved_keys();
+    let mut writer = csv::Writer::from_path(path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    let mut rows = 0;
+    let mut write = || -> Result<(), csv::Error> {
+        writer.write_record(["Hostname", "Installed Key", "OEM Key", "Edition", "Source", "First Seen", "Last Seen"])?;
+        for key in &keys {
+            for seen in key.observations() {
+                writer.write_record([
+                    &key.hostname, &seen.installed_key, &seen.oem_key, &seen.edition, &seen.source, &seen.first_seen, &seen.last_seen,
+                ])?;
+                rows += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    };
+    write().map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    println!("[Deploy] Exported {} saved key row(s) to {}", rows, path.display());
+    Ok(rows)
+}
+
+/// Open a file picker for another technician's saved_keys.json.
+///
+/// # Returns
+/// * `Some(PathBuf)` — the selected file
+/// * `None` — user cancelled the dialog
+pub fn pick_saved_keys_import_file() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title("Import Saved Keys")
+        .add_filter("Saved Keys", &["json"])
+        .add_filter("All Files", &["*"])
+        .pick_file()
+}
 
-    // File is corrupt or unrecognized — return empty
-    eprintln!("[Deploy] Warning: Could not parse saved_keys.json");
-    Vec::new()
+/// Open a save dialog for the CSV export of the saved keys.
+///
+/// # Returns
+/// * `Some(PathBuf)` — where to write the CSV
+/// * `None` — user cancelled the dialog
+pub fn pick_saved_keys_export_file() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title("Export Saved Keys")
+        .set_file_name("saved_keys.csv")
+        .add_filter("CSV Files", &["csv"])
+        .save_file()
 }
 
 /// Delete a saved key entry by hostname.
@@ -2944,11 +5594,7 @@ pub fn delete_saved_key(hostname: &str) -> Result<bool, String> {
     }
 
     // Write the remaining keys back to the file
-    let json = serde_json::to_string_pretty(&keys)
-        .map_err(|e| format!("Failed to serialize key info: {}", e))?;
-
-    fs::write(&path, &json)
-        .map_err(|e| format!("Failed to write saved_keys.json: {}", e))?;
+    write_saved_keys(&path, &keys)?;
 
     println!("[Deploy] Deleted key for '{}'. {} key(s) remaining.", hostname, keys.len());
     Ok(true)
@@ -2965,11 +5611,1023 @@ pub fn delete_saved_key(hostname: &str) -> Result<bool, String> {
 pub fn format_saved_key_labels(keys: &[WindowsKeyInfo]) -> Vec<String> {
     keys.iter()
         .map(|k| {
-            if k.date.is_empty() {
+            // Date and time without seconds and offset: "2026-02-18 14:03"
+            let mut label = if k.date.is_empty() {
                 k.hostname.clone()
             } else {
-                format!("{} ({})", k.hostname, k.date)
+                format!("{} ({})", k.hostname, k.date.get(..16).unwrap_or(&k.date))
+            };
+            if !k.source.is_empty() {
+                label = format!("{} — {}", label, k.source);
+            }
+            if k.history.len() > 1 {
+                label = format!("{} · {} keys seen", label, k.history.len());
             }
+            label
         })
         .collect()
 }
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse both sides and compare the typed documents, so the golden
+    /// files only break on real content changes (not whitespace/escaping).
+    fn assert_matches_golden(config: &DeployConfig, golden: &str) {
+        let generated = UnattendDocument::parse(&generate_autounattend(config)).unwrap();
+        let expected = UnattendDocument::parse(golden).unwrap();
+        assert_eq!(generated, expected);
+    }
+
+    #[test]
+    fn test_golden_rdp_firewall_command() {
+        // netsh must receive real quotes. The XML escapes them once (&quot;);
+        // the old string builder escaped them twice (&amp;quot;), so Windows
+        // passed the literal text "&quot;Remote Desktop&quot;" to netsh.
+        let xml = generate_autounattend(&DeployConfig::default());
+        assert!(!xml.contains("&amp;quot;"));
+        let document = roxmltree::Document::parse(&xml).unwrap();
+        assert!(document.descendants().any(|n| n.has_tag_name("CommandLine")
+            && n.text() == Some(r#"netsh advfirewall firewall set rule group="Remote Desktop" new enable=Yes"#)));
+    }
+
+    #[test]
+    fn test_golden_default_uefi() {
+        let config = DeployConfig { boot_mode: BootMode::UEFI, ..Default::default() };
+        assert_matches_golden(&config, include_str!("../tests/fixtures/unattend/default_uefi.xml"));
+    }
+
+    #[test]
+    fn test_golden_uefi_disk0_domain() {
+        let mut config = DeployConfig {
+            boot_mode: BootMode::UEFI,
+            disk_id: 0,
+            edition: "Windows 11 Pro".to_string(),
+            organization: "Contoso & Co".to_string(),
+            owner_name: "IT".to_string(),
+            join_domain: true,
+            domain_name: "contoso.local".to_string(),
+            domain_username: "CONTOSO\\joiner".to_string(),
+            domain_password: "it's secret".to_string(),
+            skip_network: true,
+            ..Default::default()
+        };
+        config.accounts[0].password = "P@ss<word>".to_string();
+        assert_matches_golden(&config, include_str!("../tests/fixtures/unattend/uefi_disk0_domain.xml"));
+    }
+
+    #[test]
+    fn test_golden_bios_disk1() {
+        let mut config = DeployConfig {
+            boot_mode: BootMode::BIOS,
+            disk_id: 1,
+            edition: "Windows 10 Home".to_string(),
+            autologon_account: String::new(),
+            ..Default::default()
+        };
+        config.accounts[0].group = "Users".to_string();
+        assert_matches_golden(&config, include_str!("../tests/fixtures/unattend/bios_disk1.xml"));
+    }
+
+    #[test]
+    fn test_import_roundtrip_generated_xml() {
+        // Everything MasterBooter generates should import back losslessly
+        let mut config = DeployConfig {
+            boot_mode: BootMode::BIOS,
+            disk_id: 2,
+            edition: "Windows 11 Pro".to_string(),
+            computer_name: "LAB-07".to_string(),
+            accounts: vec![
+                UserAccount {
+                    name: "Tech".to_string(),
+                    display_name: "Technician".to_string(),
+                    password: "P@ss<word>".to_string(),
+                    group: "Administrators".to_string(),
+                    password_never_expires: true,
+                },
+                UserAccount {
+                    name: "Kasse".to_string(),
+                    display_name: String::new(),
+                    password: "it's mine".to_string(),
+                    group: "Users".to_string(),
+                    password_never_expires: false,
+                },
+            ],
+            autologon_account: "Kasse".to_string(),
+            organization: "Contoso & Co".to_string(),
+            join_domain: true,
+            domain_name: "contoso.local".to_string(),
+            domain_username: "CONTOSO\\joiner".to_string(),
+            domain_password: "it's secret".to_string(),
+            skip_network: true,
+            remove_appx: vec!["Microsoft.BingNews".to_string(), "Microsoft.Xbox*".to_string()],
+            appx_removal_mode: AppxRemovalMode::AnswerFile,
+            ..Default::default()
+        };
+        // Defaults plus a command-only tweak (firewall), taskbar search as icon instead of hidden
+        config.enabled_tweaks = tweaks::TweakCatalog::builtin()
+            .tweaks
+            .iter()
+            .filter(|t| (t.default && t.id != "taskbar_search_hidden") || t.id == "disable_firewall" || t.id == "taskbar_search_icon")
+            .map(|t| t.id.clone())
+            .collect();
+
+        let report = import_autounattend_xml(&generate_autounattend(&config)).unwrap();
+        assert!(report.unmapped.is_empty(), "unexpected unmapped: {:?}", report.unmapped);
+        assert_eq!(report.config, config);
+    }
+
+    #[test]
+    fn test_appx_removal_in_answer_file() {
+        let mut config = DeployConfig {
+            edition: "Windows 11 Pro".to_string(),
+            remove_appx: vec!["Microsoft.BingNews".to_string(), "Microsoft.Windows*".to_string()],
+            ..Default::default()
+        };
+
+        // Offline mode leaves the answer file alone
+        let xml = generate_autounattend(&config);
+        assert!(!xml.contains("RunSynchronous"));
+        assert!(!xml.contains("<Extensions"));
+
+        config.appx_removal_mode = AppxRemovalMode::AnswerFile;
+        let xml = generate_autounattend(&config);
+        let doc = UnattendDocument::parse(&xml).unwrap();
+        let deployment = doc.component(Pass::Specialize, "Microsoft-Windows-Deployment").unwrap();
+        assert_eq!(deployment.text("RunSynchronous/RunSynchronousCommand/Path"), Some(appx::SPECIALIZE_COMMAND));
+        assert_eq!(doc.extensions[0].name, appx::EXTENSION_NAME);
+        assert!(doc.extensions[0].text.contains("    'Microsoft.Windows*'\n"));
+
+        let result = validate_autounattend(&xml, &config, &[]);
+        assert!(result.valid, "{:?}", result.errors);
+        assert!(result.warnings.iter().any(|w| w.contains("\"Microsoft.Windows*\" also matches protected")), "{:?}", result.warnings);
+
+        // The Store itself can't be removed
+        config.remove_appx.push("Microsoft.WindowsStore".to_string());
+        let result = validate_autounattend(&generate_autounattend(&config), &config, &[]);
+        assert!(!result.valid);
+        assert!(result.errors[0].contains("\"Microsoft.WindowsStore\" is protected"), "{:?}", result.errors);
+    }
+
+    #[test]
+    fn test_wifi_profiles() {
+        let office = WifiProfile {
+            ssid: "Office".to_string(),
+            auth: wifi::WifiAuth::WPA2,
+            key: "correct horse".to_string(),
+            hidden: false,
+        };
+        let mut config = DeployConfig {
+            edition: "Windows 11 Pro".to_string(),
+            wifi_profiles: vec![office.clone()],
+            remove_appx: vec!["Microsoft.BingNews".to_string()],
+            appx_removal_mode: AppxRemovalMode::AnswerFile,
+            ..Default::default()
+        };
+
+        // Imported before the Appx step; the key never goes into the answer file
+        let xml = generate_autounattend(&config);
+        assert!(!xml.contains("correct horse"));
+        let doc = UnattendDocument::parse(&xml).unwrap();
+        let deployment = doc.component(Pass::Specialize, "Microsoft-Windows-Deployment").unwrap();
+        let paths: Vec<&str> = deployment
+            .find("RunSynchronous")
+            .unwrap()
+            .children_named("RunSynchronousCommand")
+            .map(|c| c.child_text("Path").unwrap())
+            .collect();
+        assert_eq!(paths, vec![wifi::IMPORT_COMMAND, wifi::CLEANUP_COMMAND, appx::SPECIALIZE_COMMAND]);
+        assert!(validate_autounattend(&xml, &config, &[]).valid);
+
+        // The answer file only has the import step — the networks have to be re-added
+        let report = import_autounattend_xml(&xml).unwrap();
+        assert!(report.config.wifi_profiles.is_empty());
+        assert_eq!(report.unmapped.len(), 1, "{:?}", report.unmapped);
+
+        // Keys show up as changed in a diff, never in clear text
+        let mut changed = config.clone();
+        changed.wifi_profiles[0].key = "battery staple".to_string();
+        let diff = diff_profiles(&config, &changed);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].field, "wifi_profiles[Office].key");
+        assert_eq!(diff.changes[0].new, "(set), changed");
+
+        config.wifi_profiles.push(WifiProfile { key: "short".to_string(), ..office });
+        let result = validate_autounattend(&generate_autounattend(&config), &config, &[]);
+        assert!(result.errors.iter().any(|e| e.contains("Wi-Fi \"Office\": key must be")), "{:?}", result.errors);
+        assert!(result.errors.iter().any(|e| e.contains("Wi-Fi \"Office\" is listed more than once")), "{:?}", result.errors);
+    }
+
+    #[test]
+    fn test_oem_info_and_specialize_commands() {
+        let command = |order: u32, path: &str| SpecializeCommand {
+            order,
+            path: path.to_string(),
+            description: format!("Step {}", order),
+        };
+        let mut config = DeployConfig {
+            edition: "Windows 11 Pro".to_string(),
+            owner_name: "Front Desk".to_string(),
+            oem_info: OemInfo {
+                manufacturer: "Contoso IT".to_string(),
+                support_url: "https://support.contoso.com".to_string(),
+                ..Default::default()
+            },
+            specialize_commands: vec![
+                command(1, r"reg add HKLM\SOFTWARE\Contoso /v Site /d HQ /f"),
+                command(2, "powercfg /change standby-timeout-ac 0"),
+            ],
+            remove_appx: vec!["Microsoft.BingNews".to_string()],
+            appx_removal_mode: AppxRemovalMode::AnswerFile,
+            ..Default::default()
+        };
+
+        let xml = generate_autounattend(&config);
+        let doc = UnattendDocument::parse(&xml).unwrap();
+        let shell = doc.component(Pass::Specialize, "Microsoft-Windows-Shell-Setup").unwrap();
+        assert_eq!(shell.text("RegisteredOwner"), Some("Front Desk"));
+        assert_eq!(shell.text("OEMInformation/Manufacturer"), Some("Contoso IT"));
+        assert_eq!(shell.text("OEMInformation/SupportURL"), Some("https://support.contoso.com"));
+        assert!(shell.find("OEMInformation/Logo").is_none());
+
+        // The technician's commands run first, our own steps are numbered after them
+        let deployment = doc.component(Pass::Specialize, "Microsoft-Windows-Deployment").unwrap();
+        let orders: Vec<(&str, &str)> = deployment
+            .find("RunSynchronous")
+            .unwrap()
+            .children_named("RunSynchronousCommand")
+            .map(|c| (c.child_text("Order").unwrap(), c.child_text("Path").unwrap()))
+            .collect();
+        assert_eq!(orders[1], ("2", "powercfg /change standby-timeout-ac 0"));
+        assert_eq!(orders[2], ("3", appx::SPECIALIZE_COMMAND));
+        assert!(validate_autounattend(&xml, &config, &[]).valid);
+
+        let report = import_autounattend_xml(&xml).unwrap();
+        assert!(report.unmapped.is_empty(), "{:?}", report.unmapped);
+        assert_eq!(report.config.oem_info, config.oem_info);
+        assert_eq!(report.config.specialize_commands, config.specialize_commands);
+
+        // Orders are checked like FirstLogon ones: duplicates are errors
+        config.specialize_commands.push(command(2, "bcdedit /timeout 5"));
+        let result = validate_autounattend(&generate_autounattend(&config), &config, &[]);
+        assert!(result.errors.iter().any(|e| e.contains("RunSynchronous: duplicate <Order>2</Order>")), "{:?}", result.errors);
+    }
+
+    #[test]
+    fn test_driver_paths() {
+        let config = DeployConfig {
+            edition: "Windows 11 Pro".to_string(),
+            driver_paths: vec!["D:\\Drivers\\Storage".to_string(), "D:\\Drivers\\Net".to_string()],
+            ..Default::default()
+        };
+        let xml = generate_autounattend(&config);
+        assert!(xml.contains(r#"<PathAndCredentials wcm:action="add" wcm:keyValue="2">"#), "{}", xml);
+        let doc = UnattendDocument::parse(&xml).unwrap();
+        let pnp = doc.component(Pass::WindowsPE, "Microsoft-Windows-PnpCustomizationsWinPE").unwrap();
+        let paths: Vec<&str> = pnp
+            .find("DriverPaths")
+            .unwrap()
+            .children_named("PathAndCredentials")
+            .map(|p| p.child_text("Path").unwrap())
+            .collect();
+        assert_eq!(paths, ["D:\\Drivers\\Storage", "D:\\Drivers\\Net"]);
+
+        // Folders that aren't there are only warnings — Setup skips them
+        let result = validate_autounattend(&xml, &config, &[]);
+        assert!(result.valid, "{:?}", result.errors);
+        assert!(result.warnings.iter().any(|w| w.contains("Driver folder \"D:\\Drivers\\Net\" doesn't exist")), "{:?}", result.warnings);
+
+        let report = import_autounattend_xml(&xml).unwrap();
+        assert!(report.unmapped.is_empty(), "{:?}", report.unmapped);
+        assert_eq!(report.config.driver_paths, config.driver_paths);
+    }
+
+    #[test]
+    fn test_import_legacy_answer_file() {
+        let report = import_autounattend_xml(include_str!("../tests/fixtures/unattend/legacy_custom.xml")).unwrap();
+        let config = &report.config;
+
+        assert_eq!(config.ui_language, "de-DE");
+        assert_eq!(config.input_locale, "0407:00000407");
+        assert_eq!(config.system_locale, "de-DE");
+        assert_eq!(config.user_locale, "de-DE");
+        assert_eq!(config.boot_mode, BootMode::BIOS);
+        assert_eq!(config.disk_id, 0);
+        assert_eq!(config.edition_index, 6);
+        assert_eq!(config.computer_name, "BH-PC01");
+        assert_eq!(config.timezone, "W. Europe Standard Time");
+        assert_eq!(config.owner_name, "Buchhaltung");
+        assert_eq!(config.organization, "Beispiel GmbH");
+        assert_eq!(config.accounts.len(), 2);
+        assert_eq!(config.accounts[0].name, "Techniker");
+        assert_eq!(config.accounts[0].password, "Start123");
+        assert!(config.accounts[0].is_admin());
+        assert_eq!(config.accounts[1].name, "Kiosk");
+        assert!(!config.accounts[1].is_admin());
+        assert!(config.autologon_account.is_empty());
+        assert!(config.skip_eula && !config.skip_oobe && config.skip_network);
+        assert!(config.join_domain);
+        assert_eq!(config.domain_name, "beispiel.local");
+        assert_eq!(config.domain_username, "BEISPIEL\\joiner");
+        assert_eq!(config.domain_ou, "OU=Clients,DC=beispiel,DC=local");
+        assert!(config.enabled_tweaks.contains(&"disable_telemetry".to_string()));
+        assert!(config.enabled_tweaks.contains(&"taskbar_search_icon".to_string()));
+        assert!(!config.enabled_tweaks.contains(&"enable_rdp".to_string()));
+        assert!(!config.enabled_tweaks.contains(&"disable_cortana".to_string()));
+
+        // Everything that couldn't be mapped is listed
+        let unmapped = report.unmapped.join("\n");
+        for expected in [
+            "Microsoft-Windows-Setup/DynamicUpdate/Enable = false",
+            "specialize/Microsoft-Windows-Shell-Setup/CopyProfile = true",
+            "OOBE/ProtectYourPC = 1",
+            "office.vbs (Install Office)",
+        ] {
+            assert!(unmapped.contains(expected), "missing '{}' in:\n{}", expected, unmapped);
+        }
+        assert_eq!(report.unmapped.len(), 4, "{}", unmapped);
+    }
+
+    #[test]
+    fn test_report_unmapped_hides_passwords() {
+        let setting = Setting::group("Extra", vec![
+            // Not valid base64, so it can't be decoded — still hidden
+            Setting::group("AdministratorPassword", vec![
+                Setting::value("Value", "not base64!"),
+                Setting::value("PlainText", "false"),
+            ]),
+            Setting::group("Credentials", vec![Setting::value("Password", "hunter2")]),
+            Setting::value("Name", "kiosk"),
+        ]);
+        let mut unmapped = Vec::new();
+        report_unmapped("oobeSystem/Custom", &setting, &mut unmapped);
+        assert_eq!(unmapped, vec![
+            "oobeSystem/Custom/Extra/AdministratorPassword/Value = (hidden)",
+            "oobeSystem/Custom/Extra/AdministratorPassword/PlainText = false",
+            "oobeSystem/Custom/Extra/Credentials/Password = (hidden)",
+            "oobeSystem/Custom/Extra/Name = kiosk",
+        ]);
+    }
+
+    #[test]
+    fn test_validate_generated_xml() {
+        let editions = vec![
+            WimEdition { index: 1, name: "Windows 11 Home".to_string(), size_bytes: 0 },
+            WimEdition { index: 2, name: "Windows 11 Pro".to_string(), size_bytes: 0 },
+        ];
+        let mut config = DeployConfig {
+            disk_id: 0,
+            edition: "Windows 11 Pro".to_string(),
+            edition_index: 2,
+            ..Default::default()
+        };
+
+        // Default output must be clean
+        let result = validate_autounattend(&generate_autounattend(&config), &config, &editions);
+        assert!(result.valid, "{:?}", result.errors);
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+
+        // Edition that isn't in the image
+        config.edition = "Windows 11 Education".to_string();
+        config.edition_index = 5;
+        config.computer_name = "WAY-TOO-LONG-NAME-01".to_string();
+        let result = validate_autounattend(&generate_autounattend(&config), &config, &editions);
+        assert_eq!(result.errors.len(), 3, "{:?}", result.errors);
+        assert!(result.errors.iter().any(|e| e.starts_with("Edition index 5 does not exist")));
+        assert!(result.errors.iter().any(|e| e.contains("/IMAGE/NAME \"Windows 11 Education\" does not exist")));
+    }
+
+    #[test]
+    fn test_separate_locales() {
+        // English UI with a Canadian French keyboard and Quebec formats
+        let mut config = DeployConfig {
+            input_locale: "0409:00000409;0c0c:00001009".to_string(),
+            user_locale: "fr-CA".to_string(),
+            image_languages: vec!["en-US".to_string()],
+            ..Default::default()
+        };
+        let xml = generate_autounattend(&config);
+        let doc = UnattendDocument::parse(&xml).unwrap();
+        let international = doc.component(Pass::OobeSystem, "Microsoft-Windows-International-Core").unwrap();
+        assert_eq!(international.text("UILanguage"), Some("en-US"));
+        assert_eq!(international.text("InputLocale"), Some("0409:00000409;0c0c:00001009"));
+        assert_eq!(international.text("UserLocale"), Some("fr-CA"));
+        let result = validate_autounattend(&xml, &config, &[]);
+        assert!(result.valid && result.warnings.is_empty(), "{:?} {:?}", result.errors, result.warnings);
+
+        let report = import_autounattend_xml(&xml).unwrap();
+        assert!(report.unmapped.is_empty(), "{:?}", report.unmapped);
+        assert_eq!(report.config.input_locale, config.input_locale);
+        assert_eq!(report.config.user_locale, "fr-CA");
+
+        // Bad names and keyboard ids are errors, a missing language pack a warning
+        config.ui_language = "fr-FR".to_string();
+        config.system_locale = "fr_CA".to_string();
+        config.input_locale = "0c0c:1009".to_string();
+        let result = validate_autounattend(&generate_autounattend(&config), &config, &[]);
+        assert_eq!(result.errors.len(), 2, "{:?}", result.errors);
+        assert!(result.errors.iter().any(|e| e.starts_with("Keyboard: \"0c0c:1009\"")));
+        assert!(result.errors.iter().any(|e| e.starts_with("System locale: \"fr_CA\"")));
+        assert!(result.warnings.iter().any(|w| w.starts_with("UI language fr-FR is not installed in the image")));
+
+        // The time zone is checked the same way
+        config.timezone = "America/Toronto".to_string();
+        config.product_key = "VK7JG-NPHTM-C97JM-9MPGT-3V60T".to_string();
+        let result = validate_autounattend(&generate_autounattend(&config), &config, &[]);
+        assert!(result.errors.iter().any(|e| e.starts_with("Time zone: \"America/Toronto\"")), "{:?}", result.errors);
+        assert!(result.errors.iter().any(|e| e.starts_with("Product key: contains '0'")), "{:?}", result.errors);
+    }
+
+    #[test]
+    fn test_each_architecture_output() {
+        for arch in Architecture::ALL {
+            let config = DeployConfig { architecture: arch, disk_id: 0, ..Default::default() };
+            let xml = generate_autounattend(&config);
+            let doc = UnattendDocument::parse(&xml).unwrap();
+
+            let components: Vec<&Component> = doc.passes.iter().flat_map(|p| p.components.iter()).collect();
+            assert_eq!(components.len(), 6);
+            assert!(components.iter().all(|c| c.architecture == arch.as_str()), "{}", arch);
+            assert!(xml.contains(&format!("processorArchitecture=\"{}\"", arch.as_str())));
+            assert!(unattend::validate(&xml).valid);
+
+            // Round-trips through the importer
+            let report = import_autounattend_xml(&xml).unwrap();
+            assert_eq!(report.config.architecture, arch);
+            assert!(!report.config.multi_arch);
+        }
+    }
+
+    #[test]
+    fn test_multi_arch_output() {
+        let config = DeployConfig { multi_arch: true, ..Default::default() };
+        let xml = generate_autounattend(&config);
+        let doc = UnattendDocument::parse(&xml).unwrap();
+
+        // Every component appears once per architecture, copies side by side
+        let oobe = doc.passes.iter().find(|p| p.pass == Pass::OobeSystem).unwrap();
+        let archs: Vec<&str> = oobe.components.iter().map(|c| c.architecture.as_str()).collect();
+        assert_eq!(archs, ["amd64", "arm64", "x86", "amd64", "arm64", "x86"]);
+        assert_eq!(oobe.components[0].settings, oobe.components[1].settings);
+        assert!(unattend::validate(&xml).valid);
+
+        let report = import_autounattend_xml(&xml).unwrap();
+        assert!(report.unmapped.is_empty(), "{:?}", report.unmapped);
+        assert_eq!(report.config, config);
+    }
+
+    #[test]
+    fn test_saved_key_source() {
+        // Entries saved before offline recovery existed have no source
+        let old: WindowsKeyInfo = serde_json::from_str(
+            r#"{"oem_key":"","installed_key":"VK7JG-NPHTM-C97JM-9MPGT-3V66T","edition":"Windows 11 Pro","status":"Licensed","hostname":"FRONTDESK","date":"2026-02-18"}"#,
+        )
+        .unwrap();
+        assert!(old.source.is_empty());
+        assert!(!serde_json::to_string(&old).unwrap().contains("source"));
+
+        let rescued = WindowsKeyInfo { hostname: "DEAD-PC".to_string(), source: "D:\\Windows (offline)".to_string(), ..old.clone() };
+        assert_eq!(
+            format_saved_key_labels(&[old, rescued]),
+            vec!["FRONTDESK (2026-02-18)", "DEAD-PC (2026-02-18) — D:\\Windows (offline)"]
+        );
+    }
+
+    #[test]
+    fn test_saved_key_history() {
+        let backup = |key: &str, edition: &str, date: &str| WindowsKeyInfo {
+            oem_key: String::new(),
+            installed_key: key.to_string(),
+            edition: edition.to_string(),
+            status: "Licensed".to_string(),
+            hostname: "FRONTDESK".to_string(),
+            date: date.to_string(),
+            source: String::new(),
+            history: Vec::new(),
+        };
+        let pro = "VK7JG-NPHTM-C97JM-9MPGT-3V66T";
+        let enterprise = "NPPR9-FWDCX-D2C8J-H872K-2YT43";
+
+        // An entry saved before the history existed, then two newer backups
+        let mut keys = vec![backup(pro, "Windows 10 Pro", "2025-03-01")];
+        assert_eq!(merge_key_entry(&mut keys, backup(pro, "Windows 10 Pro", "2026-02-18 09:12:00 +01:00")), 0);
+        assert_eq!(merge_key_entry(&mut keys, backup(enterprise, "Windows 11 Enterprise", "2026-06-02 15:40:10 +02:00")), 1);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].installed_key, enterprise);
+        assert_eq!(keys[0].history.len(), 2);
+        assert_eq!(keys[0].history[0].first_seen, "2025-03-01");
+        assert_eq!(keys[0].history[0].last_seen, "2026-02-18 09:12:00 +01:00");
+
+        // An older entry from another store adds history but stays out of the current values
+        let mut theirs = backup(pro, "Windows 10 Pro", "2024-11-20 10:00:00 -05:00");
+        theirs.hostname = "FRONTDESK".to_string();
+        assert_eq!(merge_key_entry(&mut keys, theirs), 0);
+        assert_eq!(keys[0].installed_key, enterprise);
+        assert_eq!(keys[0].history[0].first_seen, "2024-11-20 10:00:00 -05:00");
+
+        let label = format_saved_key_labels(&keys);
+        assert_eq!(label, vec!["FRONTDESK (2026-06-02 15:40) · 2 keys seen"]);
+        let json = serde_json::to_string(&keys).unwrap();
+        assert_eq!(parse_saved_keys(&json).unwrap(), keys);
+
+        // Dates are compared as instants: 23:30 in Toronto is after 01:00 in Paris the next day
+        let mut keys = vec![backup(pro, "Windows 10 Pro", "2026-06-03 01:00:00 +02:00")];
+        assert_eq!(merge_key_entry(&mut keys, backup(enterprise, "Windows 11 Enterprise", "2026-06-02 23:30:00 -04:00")), 1);
+        assert_eq!(keys[0].installed_key, enterprise);
+        assert_eq!(keys[0].history[0].installed_key, pro);
+        assert_eq!(merge_key_entry(&mut keys, backup(pro, "Windows 10 Pro", "2026-06-02 22:00:00 -04:00")), 0);
+        assert_eq!(keys[0].installed_key, enterprise);
+        assert_eq!(keys[0].history[0].last_seen, "2026-06-02 22:00:00 -04:00");
+        assert!(backup_instant("2025-03-01") < backup_instant("2025-03-01 00:30:00 -01:00"));
+        assert_eq!(backup_instant("not a date"), None);
+
+        // Encrypted keys can't be compared, so a merge with them is refused
+        let mut theirs = backup("enc:v1:AAAA", "Windows 10 Pro", "2026-07-01");
+        theirs.hostname = "RECEPTION".to_string();
+        let err = merge_saved_keys(&mut keys, vec![theirs.clone()]).unwrap_err();
+        assert!(err.contains("RECEPTION"), "{}", err);
+        let mut locked = vec![theirs];
+        assert!(merge_saved_keys(&mut locked, vec![backup(pro, "Windows 10 Pro", "2026-07-01")]).is_err());
+        assert_eq!(merge_saved_keys(&mut keys, vec![backup(pro, "Windows 10 Pro", "2026-07-01")]), Ok(0));
+
+        let timestamp = backup_timestamp();
+        assert_eq!(timestamp.len(), "2026-02-18 09:12:00 +01:00".len(), "{}", timestamp);
+        assert!(backup_instant(&timestamp).is_some());
+    }
+
+    #[test]
+    fn test_parse_wim_architecture() {
+        let dism = "Details for image : install.wim\n\nIndex : 6\nName : Windows 11 Pro\nArchitecture : arm64\nHal : <undefined>\n";
+        assert_eq!(parse_wim_architecture(dism), Some(Architecture::Arm64));
+        assert_eq!(parse_wim_architecture("Architecture : x64"), Some(Architecture::Amd64));
+        assert_eq!(parse_wim_architecture("Architecture : x86"), Some(Architecture::X86));
+        assert_eq!(parse_wim_architecture("Index : 1\nName : Windows 11 Pro"), None);
+        assert_eq!(parse_wim_build("Architecture : x64\nVersion : 10.0.22631\nServicePack Build : 2861\n"), Some(22631));
+        assert_eq!(parse_wim_build(dism), None);
+
+        let dism = "Name : Windows 11 Pro\nLanguages :\n        en-US (Default)\n        fr-CA\nCreated : 01/01/2024\n";
+        assert_eq!(parse_wim_languages(dism), vec!["en-US", "fr-CA"]);
+        assert!(parse_wim_languages("Name : Windows 11 Pro\n").is_empty());
+    }
+
+    #[test]
+    fn test_default_diskpart_script_unchanged() {
+        let layout = PartitionLayout::default();
+        assert_eq!(
+            build_diskpart_script(0, BootMode::UEFI, &layout),
+            "select disk 0\nclean\nconvert gpt\n\
+             create partition efi size=100\nformat quick fs=fat32 label=\"System\"\nassign letter=S\n\
+             create partition msr size=16\n\
+             create partition primary\nformat quick fs=ntfs label=\"Windows\"\nassign letter=C\nexit\n"
+        );
+        assert_eq!(
+            build_diskpart_script(1, BootMode::BIOS, &layout),
+            "select disk 1\nclean\n\
+             create partition primary size=100\nformat quick fs=ntfs label=\"System Reserved\"\nactive\nassign letter=S\n\
+             create partition primary\nformat quick fs=ntfs label=\"Windows\"\nassign letter=C\nexit\n"
+        );
+    }
+
+    #[test]
+    fn test_recovery_layout_diskpart_and_unattend_agree() {
+        let config = DeployConfig {
+            disk_id: 0,
+            boot_mode: BootMode::UEFI,
+            edition: "Windows 11 Pro".to_string(),
+            partition_layout: PartitionLayout {
+                efi_size_mb: 260,
+                os_size_mb: 120_000,
+                recovery_size_mb: 1024,
+                data_partition: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // Data fills the disk, then gets shrunk so recovery lands at the end
+        let script = build_diskpart_script(0, config.boot_mode, &config.partition_layout);
+        assert!(script.contains("create partition efi size=260\n"));
+        assert!(script.contains("create partition primary size=120000\nformat quick fs=ntfs label=\"Windows\"\nassign letter=C\n"));
+        assert!(script.contains("create partition primary\nshrink minimum=1024\nformat quick fs=ntfs label=\"Data\"\nassign letter=D\n"));
+        assert!(script.contains(&format!(
+            "label=\"Recovery\"\nset id={} override\ngpt attributes=0x8000000000000001\nexit\n",
+            RECOVERY_GPT_TYPE
+        )));
+
+        // Setup can't set GPT attributes, so it keeps diskpart's partitions
+        let doc = build_unattend_document(&config);
+        let setup = doc.component(Pass::WindowsPE, "Microsoft-Windows-Setup").unwrap();
+        let disk = setup.find("DiskConfiguration/Disk").unwrap();
+        assert_eq!(disk.child_text("WillWipeDisk"), Some("false"));
+        assert!(disk.child("CreatePartitions").is_none());
+        let modify: Vec<&Setting> = disk.child("ModifyPartitions").unwrap().children_named("ModifyPartition").collect();
+        assert_eq!(modify.len(), 5);
+        assert_eq!(modify[4].child_text("TypeID"), Some(RECOVERY_GPT_TYPE));
+        assert!(modify.iter().all(|m| m.child("Format").is_none()));
+        assert_eq!(setup.text("ImageInstall/OSImage/InstallTo/PartitionID"), Some("3"));
+
+        let xml = doc.to_xml();
+        assert!(validate_autounattend(&xml, &config, &[]).valid);
+    }
+
+    #[test]
+    fn test_import_data_partition_layout() {
+        let config = DeployConfig {
+            disk_id: 1,
+            boot_mode: BootMode::BIOS,
+            partition_layout: PartitionLayout {
+                os_size_mb: 80_000,
+                data_partition: true,
+                data_label: "Daten".to_string(),
+                data_letter: "E".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let report = import_autounattend_xml(&generate_autounattend(&config)).unwrap();
+        assert_eq!(report.config.partition_layout, config.partition_layout);
+        assert!(!report.unmapped.iter().any(|u| u.contains("DiskConfiguration")), "{:?}", report.unmapped);
+    }
+
+    #[test]
+    fn test_partition_layout_validation() {
+        assert!(PartitionLayout::default().validate().is_ok());
+        let data_without_os_size = PartitionLayout { data_partition: true, ..Default::default() };
+        assert!(data_without_os_size.validate().is_err());
+        let tiny_recovery = PartitionLayout { recovery_size_mb: 100, ..Default::default() };
+        assert!(tiny_recovery.validate().is_err());
+        let bad_letter = PartitionLayout { os_size_mb: 50_000, data_partition: true, data_letter: "C".to_string(), ..Default::default() };
+        assert!(bad_letter.validate().is_err());
+    }
+
+    #[test]
+    fn test_legacy_profile_account_migration() {
+        // A profile saved before multiple accounts existed
+        let mut legacy = serde_json::to_value(DeployConfig::default()).unwrap();
+        let object = legacy.as_object_mut().unwrap();
+        object.remove("accounts");
+        object.remove("autologon_account");
+        object.insert("user_name".into(), "Tech".into());
+        object.insert("user_password".into(), "Start123".into());
+        object.insert("user_display_name".into(), "Technician".into());
+        object.insert("user_is_admin".into(), false.into());
+        object.insert("enable_autologon".into(), true.into());
+
+        let config = parse_profile_json(&legacy.to_string()).unwrap();
+        assert_eq!(config.accounts, vec![UserAccount {
+            name: "Tech".to_string(),
+            display_name: "Technician".to_string(),
+            password: "Start123".to_string(),
+            group: "Users".to_string(),
+            password_never_expires: true,
+        }]);
+        assert_eq!(config.autologon_account, "Tech");
+
+        // Current profiles load unchanged
+        let current = serde_json::to_string(&DeployConfig::default()).unwrap();
+        assert_eq!(parse_profile_json(&current).unwrap(), DeployConfig::default());
+    }
+
+    #[test]
+    fn test_profile_schema_fixtures() {
+        assert_eq!(PROFILE_MIGRATIONS.len() as u64 + 1, PROFILE_SCHEMA_VERSION);
+
+        // Schema 1: MasterBooter 0.1.x, single account
+        let v1 = parse_profile_json(include_str!("../tests/fixtures/profiles/v1_single_account.json")).unwrap();
+        assert_eq!(v1.accounts.len(), 1);
+        assert_eq!(v1.accounts[0].name, "Reception");
+        assert_eq!(v1.accounts[0].group, "Users");
+        assert!(v1.accounts[0].password_never_expires);
+        assert_eq!(v1.autologon_account, "Reception");
+        assert_eq!(v1.organization, "Acme Dental");
+        assert_eq!(v1.partition_layout, PartitionLayout::default());
+        // Schema 2 → 3: the tweak booleans became enabled_tweaks
+        for id in ["disable_telemetry", "enable_rdp", "taskbar_search_icon", "disable_onedrive"] {
+            assert!(v1.enabled_tweaks.contains(&id.to_string()), "{} not enabled", id);
+        }
+        assert!(!v1.enabled_tweaks.contains(&"taskbar_search_hidden".to_string()));
+        assert!(!v1.enabled_tweaks.contains(&"disable_uac".to_string()));
+        // Schema 3 → 4: the one language became four locale fields
+        assert_eq!(v1.ui_language, "en-US");
+        assert_eq!(v1.input_locale, "0409:00000409");
+
+        // Schema 2 written before schema_version existed
+        let unversioned = parse_profile_json(include_str!("../tests/fixtures/profiles/v2_unversioned.json")).unwrap();
+        assert_eq!(unversioned.accounts.len(), 2);
+        assert!(unversioned.accounts[1].is_admin());
+        assert_eq!(unversioned.architecture, Architecture::Arm64);
+        assert_eq!(unversioned.partition_layout.recovery_size_mb, 1024);
+
+        // Schema 2 with an explicit version
+        let v2 = parse_profile_json(include_str!("../tests/fixtures/profiles/v2.json")).unwrap();
+        assert_eq!(v2, DeployConfig {
+            administrator_password: "Adm1n!".to_string(),
+            obfuscate_passwords: true,
+            ..unversioned.clone()
+        });
+
+        // Schema 3: a French Canadian language gets its own keyboard
+        let v3 = parse_profile_json(include_str!("../tests/fixtures/profiles/v3.json")).unwrap();
+        assert_eq!(v3, DeployConfig {
+            computer_name: "ACCUEIL-01".to_string(),
+            ui_language: "fr-CA".to_string(),
+            input_locale: "0c0c:00001009".to_string(),
+            system_locale: "fr-CA".to_string(),
+            user_locale: "fr-CA".to_string(),
+            ..v2.clone()
+        });
+
+        // Saving stamps the current version, and every shape survives a save/load
+        for config in [v1, unversioned, v2, v3] {
+            let json = profile_to_json(&config, None).unwrap();
+            assert!(json.contains(&format!("\"schema_version\": {}", PROFILE_SCHEMA_VERSION)));
+            assert_eq!(parse_profile_json(&json).unwrap(), config);
+        }
+
+        // Profiles from a newer MasterBooter are refused
+        let err = parse_profile_json(r#"{"schema_version": 99}"#).unwrap_err();
+        assert!(err.contains("newer MasterBooter"), "{}", err);
+        assert!(parse_profile_json(r#"{"schema_version": "two"}"#).is_err());
+    }
+
+    #[test]
+    fn test_profile_inheritance() {
+        let base = DeployConfig { organization: "Acme MSP".to_string(), ..Default::default() };
+        let child = DeployConfig {
+            parent: "Base".to_string(),
+            organization: "Customer A".to_string(),
+            join_domain: true,
+            domain_name: "custa.local".to_string(),
+            ..base.clone()
+        };
+
+        // Only the overrides (plus parent and version) are stored
+        let child_json = profile_to_json(&child, Some(&base)).unwrap();
+        let stored: serde_json::Value = serde_json::from_str(&child_json).unwrap();
+        let mut keys: Vec<&String> = stored.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["domain_name", "join_domain", "organization", "parent", "schema_version"]);
+
+        let mut files: std::collections::HashMap<String, String> = Default::default();
+        files.insert("Base".into(), profile_to_json(&base, None).unwrap());
+        files.insert("Customer A".into(), child_json);
+        let resolve = |files: &std::collections::HashMap<String, String>, name: &str| {
+            let read = |n: &str| files.get(n).cloned().ok_or_else(|| format!("Profile '{}' not found", n));
+            resolve_named_profile(name, &read, &mut Vec::new()).and_then(profile_from_value)
+        };
+        assert_eq!(resolve(&files, "Customer A").unwrap(), child);
+
+        // Changing the base carries through to the child
+        let base = DeployConfig { timezone: "Pacific Standard Time".to_string(), ..base };
+        files.insert("Base".into(), profile_to_json(&base, None).unwrap());
+        let resolved = resolve(&files, "Customer A").unwrap();
+        assert_eq!(resolved.timezone, "Pacific Standard Time");
+        assert_eq!(resolved.organization, "Customer A");
+
+        // Loops and missing parents are reported
+        files.insert("A".into(), r#"{"schema_version": 2, "parent": "B"}"#.into());
+        files.insert("B".into(), r#"{"schema_version": 2, "parent": "A"}"#.into());
+        let err = resolve(&files, "A").unwrap_err();
+        assert!(err.contains("inheritance loop: A → B → A"), "{}", err);
+        files.insert("C".into(), r#"{"schema_version": 2, "parent": "Gone"}"#.into());
+        let err = resolve(&files, "C").unwrap_err();
+        assert!(err.contains("base profile 'Gone'"), "{}", err);
+    }
+
+    #[test]
+    fn test_layered_v2_profile_tweak_migration() {
+        let mut files: std::collections::HashMap<String, String> = Default::default();
+        files.insert(
+            "Base".into(),
+            r#"{"schema_version": 2, "computer_name": "*", "timezone": "UTC", "language": "en-US", "boot_mode": "UEFI",
+                "disk_id": -1, "bypass_win11": true, "skip_oobe": true, "skip_eula": true, "skip_network": false,
+                "join_domain": false, "workgroup": "WORKGROUP", "prevent_device_encryption": false,
+                "disable_telemetry": true, "enable_rdp": true, "show_file_extensions": true, "taskbar_search_mode": 1}"#
+                .into(),
+        );
+        // The child only changed three toggles
+        files.insert(
+            "Customer A".into(),
+            r#"{"schema_version": 2, "parent": "Base", "enable_rdp": false, "disable_cortana": true, "taskbar_search_mode": 2}"#.into(),
+        );
+        files.insert("Customer B".into(), r#"{"schema_version": 2, "parent": "Base", "computer_name": "B-*"}"#.into());
+        let read = |n: &str| files.get(n).cloned().ok_or_else(|| format!("Profile '{}' not found", n));
+        let tweaks = |name: &str| {
+            let mut tweaks = resolve_named_profile(name, &read, &mut Vec::new()).and_then(profile_from_value).unwrap().enabled_tweaks;
+            tweaks.sort();
+            tweaks
+        };
+
+        // The base's other tweaks stay on
+        assert_eq!(tweaks("Base"), ["disable_telemetry", "enable_rdp", "show_file_extensions", "taskbar_search_icon"]);
+        assert_eq!(tweaks("Customer A"), ["disable_cortana", "disable_telemetry", "show_file_extensions", "taskbar_search_hidden"]);
+        assert_eq!(tweaks("Customer B"), tweaks("Base"));
+    }
+
+    #[test]
+    fn test_unedited_child_profile_stores_only_parent() {
+        let base = DeployConfig {
+            organization: "ACME".to_string(),
+            owner_name: "IT Department".to_string(),
+            boot_mode: BootMode::BIOS,
+            disk_id: 1,
+            ..Default::default()
+        };
+        let mut files: std::collections::HashMap<String, String> = Default::default();
+        files.insert("Base".into(), profile_to_json(&base, None).unwrap());
+        files.insert("Customer A".into(), r#"{"schema_version": 4, "parent": "Base"}"#.into());
+        let read = |n: &str| files.get(n).cloned().ok_or_else(|| format!("Profile '{}' not found", n));
+
+        // Loading the child and saving it again without edits adds no overrides
+        let child = resolve_named_profile("Customer A", &read, &mut Vec::new()).and_then(profile_from_value).unwrap();
+        assert_eq!(child, DeployConfig { parent: "Base".to_string(), ..base.clone() });
+        let stored: serde_json::Value = serde_json::from_str(&profile_to_json(&child, Some(&base)).unwrap()).unwrap();
+        let mut keys: Vec<&String> = stored.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["parent", "schema_version"]);
+    }
+
+    #[test]
+    fn test_profile_diff() {
+        // Every profile field must belong to a diff section
+        let session_fields = ["wim_path", "edition", "edition_index"];
+        for key in serde_json::to_value(DeployConfig::default()).unwrap().as_object().unwrap().keys() {
+            let known = PROFILE_DIFF_SECTIONS.iter().any(|(_, fields)| fields.contains(&key.as_str()));
+            assert!(known || session_fields.contains(&key.as_str()), "{} has no diff section", key);
+        }
+
+        let old = DeployConfig::default();
+        assert!(diff_profiles(&old, &old.clone()).is_empty());
+
+        let mut new = DeployConfig {
+            join_domain: true,
+            domain_name: "corp.local".to_string(),
+            domain_password: "Secr3t!".to_string(),
+            ..old.clone()
+        };
+        new.enabled_tweaks.retain(|id| id != "disable_telemetry");
+        new.enabled_tweaks.push("customer_wallpaper".to_string());
+        new.accounts[0].group = "Users".to_string();
+        new.accounts.push(UserAccount {
+            name: "Kiosk".to_string(),
+            display_name: String::new(),
+            password: String::new(),
+            group: "Users".to_string(),
+            password_never_expires: false,
+        });
+
+        let diff = diff_profiles(&old, &new);
+        let change = |field: &str| diff.changes.iter().find(|c| c.field == field).unwrap_or_else(|| panic!("{} not in diff", field));
+        assert_eq!(change("disable_telemetry"), &FieldChange {
+            section: "Privacy",
+            field: "disable_telemetry".to_string(),
+            old: "true".to_string(),
+            new: "false".to_string(),
+        });
+        // A tweak the catalog doesn't know still shows up
+        assert_eq!((change("customer_wallpaper").section, change("customer_wallpaper").new.as_str()), ("Tweaks", "true"));
+        assert_eq!(change("accounts[Admin].group").new, "\"Users\"");
+        assert_eq!(change("accounts[Kiosk].name").old, "(none)");
+        assert_eq!((change("domain_password").old.as_str(), change("domain_password").new.as_str()), ("(empty)", "(set)"));
+        assert_eq!(change("domain_name").section, "Domain");
+
+        // Sections come out in Deploy page order
+        let sections: Vec<&str> = diff.changes.iter().map(|c| c.section).collect();
+        let privacy = sections.iter().position(|s| *s == "Privacy").unwrap();
+        assert!(sections.iter().rposition(|s| *s == "Accounts").unwrap() < privacy);
+        assert!(sections.iter().position(|s| *s == "Domain").unwrap() > privacy);
+
+        // The answer file side shows the dropped tweak, never the password
+        assert!(diff.xml_changes.iter().any(|l| l.starts_with("- ") && l.contains("AllowTelemetry")));
+        assert!(diff.xml_changes.iter().any(|l| l.starts_with("+ ") && l.contains("corp.local")));
+        assert!(!diff.to_text("A", "B").contains("Secr3t!"));
+    }
+
+    #[test]
+    fn test_apply_machine_entry() {
+        let config = DeployConfig {
+            join_domain: true,
+            domain_name: "corp.local".to_string(),
+            domain_username: "corp\\joiner".to_string(),
+            ..Default::default()
+        };
+        let entry = machine::MachineEntry {
+            line: 2,
+            serial: "5CG1234X".to_string(),
+            computer_name: "ACC-01".to_string(),
+            user: "jsmith".to_string(),
+            domain_ou: "OU=Accounting,DC=corp,DC=local".to_string(),
+            ..Default::default()
+        };
+
+        let applied = apply_machine_entry(&config, &entry).unwrap();
+        assert_eq!(applied.computer_name, "ACC-01");
+        assert_eq!(applied.accounts[0].name, "jsmith");
+        assert!(applied.accounts[0].is_admin());
+        assert_eq!(applied.autologon_account, "jsmith");
+
+        // The OU reaches Add-Computer and survives an import
+        let xml = generate_autounattend(&applied);
+        assert!(xml.contains("-OUPath &apos;OU=Accounting,DC=corp,DC=local&apos;"));
+        let report = import_autounattend_xml(&xml).unwrap();
+        assert_eq!(report.config.domain_ou, applied.domain_ou);
+    }
+
+    #[test]
+    fn test_validate_accounts() {
+        let accounts = DeployConfig::default().accounts;
+        assert!(validate_accounts(&accounts, "Admin").is_ok());
+        assert!(validate_accounts(&accounts, "").is_ok());
+        assert!(validate_accounts(&accounts, "Nobody").is_err());
+
+        let mut twice = accounts.clone();
+        twice.push(UserAccount { name: "admin".to_string(), ..accounts[0].clone() });
+        assert!(validate_accounts(&twice, "").is_err());
+
+        let bad_name = vec![UserAccount { name: "kiosk/1".to_string(), ..accounts[0].clone() }];
+        assert!(validate_accounts(&bad_name, "").is_err());
+    }
+
+    #[test]
+    fn test_obfuscated_passwords_roundtrip() {
+        let mut config = DeployConfig {
+            administrator_password: "Admin#1".to_string(),
+            obfuscate_passwords: true,
+            ..Default::default()
+        };
+        config.accounts[0].password = "P@ssw0rd".to_string();
+
+        let xml = generate_autounattend(&config);
+        assert!(!xml.contains("P@ssw0rd") && !xml.contains("Admin#1"));
+        assert!(xml.contains("<Value>UABAAHMAcwB3ADAAcgBkAFAAYQBzAHMAdwBvAHIAZAA=</Value>"));
+        assert!(!xml.contains("<PlainText>true</PlainText>"));
+        assert!(unattend::validate(&xml).valid);
+
+        let report = import_autounattend_xml(&xml).unwrap();
+        assert!(report.unmapped.is_empty(), "{:?}", report.unmapped);
+        assert_eq!(report.config.accounts, config.accounts);
+        assert_eq!(report.config.administrator_password, "Admin#1");
+        assert!(report.config.obfuscate_passwords);
+
+        // The domain join password can't be encoded, and validation says so
+        config.join_domain = true;
+        config.domain_name = "contoso.local".to_string();
+        config.domain_password = "Join#Pass".to_string();
+        let result = validate_autounattend(&generate_autounattend(&config), &config, &[]);
+        assert!(result.warnings.iter().any(|w| w.starts_with("Domain join: \"Obfuscate passwords\"")), "{:?}", result.warnings);
+    }
+
+    #[test]
+    fn test_encrypted_profile_roundtrip() {
+        // The only test that touches the global secrets session
+        secrets::unlock("technician passphrase").unwrap();
+
+        let mut config = DeployConfig {
+            domain_password: "Join#Pass".to_string(),
+            encrypt_secrets: true,
+            ..Default::default()
+        };
+        config.accounts[0].password = "P@ssw0rd".to_string();
+        config.wifi_profiles.push(WifiProfile {
+            ssid: "Office".to_string(),
+            auth: wifi::WifiAuth::WPA3,
+            key: "Wifi#Key2026".to_string(),
+            hidden: true,
+        });
+
+        let mut value = serde_json::to_value(&config).unwrap();
+        transform_profile_secrets(&mut value, &mut |text, field| secrets::encrypt(text, field)).unwrap();
+        // A hand-edited clear field next to encrypted ones still loads
+        value["product_key"] = serde_json::Value::String("AAAAA-BBBBB-CCCCC-DDDDD-EEEEE".to_string());
+        let json = serde_json::to_string(&value).unwrap();
+        assert!(!json.contains("Join#Pass") && !json.contains("P@ssw0rd") && !json.contains("Wifi#Key2026"));
+
+        let loaded = parse_profile_json(&json).unwrap();
+        assert_eq!(loaded.domain_password, "Join#Pass");
+        assert_eq!(loaded.accounts, config.accounts);
+        assert_eq!(loaded.wifi_profiles, config.wifi_profiles);
+        assert_eq!(loaded.product_key, "AAAAA-BBBBB-CCCCC-DDDDD-EEEEE");
+        assert!(loaded.encrypt_secrets);
+
+        // A saved product key is stored encrypted, also as a child profile's override
+        let base = DeployConfig::default();
+        let child = DeployConfig {
+            parent: "Base".to_string(),
+            product_key: "VK7JG-NPHTM-C97JM-9MPGT-3V66T".to_string(),
+            encrypt_secrets: true,
+            ..base.clone()
+        };
+        for (config, base) in [(&child, None), (&child, Some(&base))] {
+            let json = profile_to_json(config, base).unwrap();
+            let stored: serde_json::Value = serde_json::from_str(&json).unwrap();
+            let stored_key = stored["product_key"].as_str().unwrap();
+            assert!(secrets::is_encrypted(stored_key) && !json.contains("VK7JG"), "{}", json);
+            assert_eq!(secrets::decrypt(stored_key, "product_key").unwrap(), child.product_key);
+        }
+    }
+}
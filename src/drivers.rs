@@ -0,0 +1,220 @@
+// ============================================
+// MasterBooter - drivers.rs
+// ============================================
+// This module gets extra drivers (storage, chipset, network) into the
+// Windows being deployed. winpe.rs only injects drivers into the WinPE
+// boot image — without this, a freshly installed Windows can come up
+// without its NVMe/RAID or network driver.
+//
+// Driver folders come from the deploy profile (`driver_paths`) and are
+// searched recursively for .inf packages. How they get in:
+//
+//   Automated install — the answer file lists every folder under
+//       Microsoft-Windows-PnpCustomizationsWinPE/DriverPaths in the
+//       windowsPE pass. Setup loads them (so it can see the disks) and
+//       adds them to the installed Windows' driver store.
+//   Normal install — there's no answer file, so after setup.exe has
+//       applied the image, `dism /Image:<target> /Add-Driver /Recurse`
+//       adds them (add_to_image)
+//
+// Relative folders are relative to the EXE (e.g. "Drivers\Dell"), so a
+// profile keeps working when the USB drive gets another letter.
+// ============================================
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Outcome of adding one driver package with DISM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriverResult {
+    /// The .inf file (or the folder, when DISM failed for the whole folder)
+    pub inf: String,
+    /// true when DISM added the package
+    pub added: bool,
+    /// DISM's message for this package
+    pub detail: String,
+}
+
+/// Turn a driver folder from the profile into an absolute path.
+/// Relative folders are resolved against the EXE's folder.
+pub fn resolve_folder(folder: &str) -> String {
+    let path = Path::new(folder);
+    // "\Drivers" is rooted but has no drive — leave it alone too
+    if path.is_absolute() || folder.starts_with('\\') || folder.get(1..2) == Some(":") {
+        return folder.to_string();
+    }
+    exe_dir().join(path).display().to_string()
+}
+
+/// Folder the MasterBooter EXE is in
+fn exe_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|d| d.to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Every .inf file in a folder and its subfolders, sorted
+pub fn find_packages(folder: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let Ok(entries) = fs::read_dir(folder) else { return found };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            found.extend(find_packages(&path));
+        } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("inf")) {
+            found.push(path);
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Problems with the driver folders that don't stop a deployment
+/// (Setup just skips them), e.g. a folder on a USB drive that isn't plugged in.
+pub fn check_folders(folders: &[String]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (i, folder) in folders.iter().enumerate() {
+        if folders[..i].iter().any(|f| f.eq_ignore_ascii_case(folder)) {
+            warnings.push(format!("\"{}\" is listed twice", folder));
+            continue;
+        }
+        let path = PathBuf::from(resolve_folder(folder));
+        if !path.is_dir() {
+            warnings.push(format!("\"{}\" doesn't exist", path.display()));
+        } else if find_packages(&path).is_empty() {
+            warnings.push(format!("\"{}\" has no .inf driver packages", path.display()));
+        }
+    }
+    warnings
+}
+
+/// List the driver packages Setup will add from the answer file's
+/// DriverPaths in the log (Setup doesn't report them back to us).
+///
+/// # Returns
+/// Number of .inf packages found
+pub fn log_packages(folders: &[String]) -> usize {
+    let mut total = 0;
+    for folder in folders {
+        let folder = resolve_folder(folder);
+        let packages = find_packages(Path::new(&folder));
+        println!("[Drivers] {}: {} driver package(s) for Setup", folder, packages.len());
+        for inf in &packages {
+            println!("[Drivers]   {}", inf.display());
+        }
+        total += packages.len();
+    }
+    total
+}
+
+/// Add every driver package in the folders to the applied image.
+///
+/// # Arguments
+/// * `target_drive` — Drive of the installed Windows (e.g., "C:")
+/// * `folders` — Driver folders from the deploy profile
+///
+/// # Returns
+/// One row per driver package. A folder DISM couldn't process at all gets
+/// a single failed row with the folder as its name.
+pub fn add_to_image(target_drive: &str, folders: &[String]) -> Vec<DriverResult> {
+    let image = format!("/Image:{}\\", target_drive);
+    let mut results = Vec::new();
+
+    for folder in folders {
+        let folder = resolve_folder(folder);
+        println!("[Drivers] Adding drivers from {} to {}", folder, target_drive);
+        let output = Command::new("dism.exe")
+            .args([image.as_str(), "/English", "/Add-Driver", &format!("/Driver:{}", folder), "/Recurse"])
+            .output();
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => {
+                results.push(DriverResult { inf: folder, added: false, detail: format!("Failed to run DISM: {}", e) });
+                continue;
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let packages = parse_add_driver_output(&stdout);
+        if packages.is_empty() && !output.status.success() {
+            // DISM stopped before trying any package (bad path, no .inf files, ...)
+            let message = stdout.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("").trim();
+            results.push(DriverResult {
+                inf: folder,
+                added: false,
+                detail: format!("DISM exit code {}: {}", output.status.code().unwrap_or(-1), message),
+            });
+            continue;
+        }
+        results.extend(packages);
+    }
+
+    for result in &results {
+        if result.added {
+            println!("[Drivers] Added {}", result.inf);
+        } else {
+            println!("[Drivers] Failed {}: {}", result.inf, result.detail);
+        }
+    }
+    results
+}
+
+/// Read the per-package lines of `dism /English /Add-Driver` output:
+///
+///   Installing 1 of 2 - D:\Drivers\net\e1d.inf: The driver package was successfully installed.
+///   Installing 2 of 2 - D:\Drivers\raid\ia.inf: Error - The driver package could not be installed.
+fn parse_add_driver_output(output: &str) -> Vec<DriverResult> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix("Installing ")?;
+            let (_, rest) = rest.split_once(" - ")?;
+            // Split after ".inf" — the path itself has a colon after the drive letter
+            let end = rest.to_ascii_lowercase().find(".inf:")? + 4;
+            let detail = rest[end + 1..].trim().to_string();
+            Some(DriverResult {
+                inf: rest[..end].to_string(),
+                added: detail.contains("successfully installed"),
+                detail,
+            })
+        })
+        .collect()
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_add_driver_output() {
+        let output = "\
+Deployment Image Servicing and Management tool
+Version: 10.0.22621.1
+
+Image Version: 10.0.22631.2861
+
+Searching for driver packages to install...
+Found 2 driver package(s) to install.
+Installing 1 of 2 - D:\\Drivers\\Net\\e1d68x64.inf: The driver package was successfully installed.
+Installing 2 of 2 - D:\\Drivers\\RAID\\iaStorVD.INF: Error - An error occurred. The driver package could not be installed.
+The operation completed successfully.
+";
+        let results = parse_add_driver_output(output);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].inf, "D:\\Drivers\\Net\\e1d68x64.inf");
+        assert!(results[0].added);
+        assert_eq!(results[1].inf, "D:\\Drivers\\RAID\\iaStorVD.INF");
+        assert!(!results[1].added);
+        assert!(results[1].detail.starts_with("Error - "));
+
+        assert_eq!(resolve_folder("D:\\Drivers"), "D:\\Drivers");
+        assert_eq!(resolve_folder("\\\\server\\drivers"), "\\\\server\\drivers");
+        assert!(resolve_folder("Drivers").ends_with("Drivers") && resolve_folder("Drivers") != "Drivers");
+    }
+}
@@ -0,0 +1,357 @@
+// ============================================
+// MasterBooter - locale.rs
+// ============================================
+// Built-in table of the locales and keyboard layouts an answer file can
+// use, so a typo ("en_US", "0409:0409") is caught before Windows Setup
+// stops on it. The table covers the common ones, not every locale Windows
+// has: a well-formed name or id that isn't listed ("ga-IE",
+// "0422:00000422") is only a warning.
+//
+// The answer file has four separate locale settings:
+//
+//   UILanguage    Display language — must be a Windows language pack
+//                 (e.g., "en-US"; "en-CA" has no language pack)
+//   SystemLocale  Language for non-Unicode programs (any locale)
+//   UserLocale    Date, time, number and currency formats (any locale)
+//   InputLocale   Keyboard(s): "LLLL:KKKKKKKK" pairs (input language id +
+//                 keyboard layout id) or locale names, up to 5, separated
+//                 by ";" — e.g., "0409:00000409;0c0c:00001009" for US and
+//                 Canadian French keyboards
+//
+// The ids come from Microsoft's "Default Input Profiles (Input Locales)
+// in Windows" and "Available Language Packs for Windows" tables.
+// ============================================
+
+/// One locale: name, LCID (hex, as used in InputLocale), display name,
+/// its default keyboard layout, and whether Windows has a language pack
+/// for it (only those can be a UILanguage).
+pub struct Locale {
+    pub name: &'static str,
+    pub lcid: &'static str,
+    pub display_name: &'static str,
+    pub keyboard: &'static str,
+    pub language_pack: bool,
+}
+
+/// Shorthand for the LOCALES table
+const fn locale(name: &'static str, lcid: &'static str, display_name: &'static str, keyboard: &'static str, language_pack: bool) -> Locale {
+    Locale { name, lcid, display_name, keyboard, language_pack }
+}
+
+/// Locales MasterBooter knows, sorted by name
+pub const LOCALES: &[Locale] = &[
+    locale("ar-SA", "0401", "Arabic (Saudi Arabia)", "00000401", true),
+    locale("bg-BG", "0402", "Bulgarian", "00030402", true),
+    locale("ca-ES", "0403", "Catalan", "0000040a", false),
+    locale("cs-CZ", "0405", "Czech", "00000405", true),
+    locale("da-DK", "0406", "Danish", "00000406", true),
+    locale("de-AT", "0c07", "German (Austria)", "00000407", false),
+    locale("de-CH", "0807", "German (Switzerland)", "00000807", false),
+    locale("de-DE", "0407", "German (Germany)", "00000407", true),
+    locale("el-GR", "0408", "Greek", "00000408", true),
+    locale("en-AU", "0c09", "English (Australia)", "00000409", false),
+    locale("en-CA", "1009", "English (Canada)", "00000409", false),
+    locale("en-GB", "0809", "English (United Kingdom)", "00000809", true),
+    locale("en-IE", "1809", "English (Ireland)", "00001809", false),
+    locale("en-IN", "4009", "English (India)", "00004009", false),
+    locale("en-NZ", "1409", "English (New Zealand)", "00000409", false),
+    locale("en-SG", "4809", "English (Singapore)", "00000409", false),
+    locale("en-US", "0409", "English (United States)", "00000409", true),
+    locale("en-ZA", "1c09", "English (South Africa)", "00000409", false),
+    locale("es-AR", "2c0a", "Spanish (Argentina)", "0000080a", false),
+    locale("es-CO", "240a", "Spanish (Colombia)", "0000080a", false),
+    locale("es-ES", "0c0a", "Spanish (Spain)", "0000040a", true),
+    locale("es-MX", "080a", "Spanish (Mexico)", "0000080a", true),
+    locale("es-US", "540a", "Spanish (United States)", "0000080a", false),
+    locale("et-EE", "0425", "Estonian", "00000425", true),
+    locale("fi-FI", "040b", "Finnish", "0000040b", true),
+    locale("fr-BE", "080c", "French (Belgium)", "0000080c", false),
+    locale("fr-CA", "0c0c", "French (Canada)", "00001009", true),
+    locale("fr-CH", "100c", "French (Switzerland)", "0000100c", false),
+    locale("fr-FR", "040c", "French (France)", "0000040c", true),
+    locale("he-IL", "040d", "Hebrew", "0002040d", true),
+    locale("hi-IN", "0439", "Hindi", "00010439", false),
+    locale("hr-HR", "041a", "Croatian", "0000041a", true),
+    locale("hu-HU", "040e", "Hungarian", "0000040e", true),
+    locale("id-ID", "0421", "Indonesian", "00000409", false),
+    locale("is-IS", "040f", "Icelandic", "0000040f", false),
+    locale("it-IT", "0410", "Italian", "00000410", true),
+    locale("ja-JP", "0411", "Japanese", "00000411", true),
+    locale("ko-KR", "0412", "Korean", "00000412", true),
+    locale("lt-LT", "0427", "Lithuanian", "00010427", true),
+    locale("lv-LV", "0426", "Latvian", "00020426", true),
+    locale("ms-MY", "043e", "Malay (Malaysia)", "00000409", false),
+    locale("nb-NO", "0414", "Norwegian (Bokmål)", "00000414", true),
+    locale("nl-BE", "0813", "Dutch (Belgium)", "00000813", false),
+    locale("nl-NL", "0413", "Dutch (Netherlands)", "00020409", true),
+    locale("pl-PL", "0415", "Polish", "00000415", true),
+    locale("pt-BR", "0416", "Portuguese (Brazil)", "00000416", true),
+    locale("pt-PT", "0816", "Portuguese (Portugal)", "00000816", true),
+    locale("ro-RO", "0418", "Romanian", "00010418", true),
+    locale("ru-RU", "0419", "Russian", "00000419", true),
+    locale("sk-SK", "041b", "Slovak", "0000041b", true),
+    locale("sl-SI", "0424", "Slovenian", "00000424", true),
+    locale("sr-Latn-RS", "241a", "Serbian (Latin)", "0000081a", true),
+    locale("sv-SE", "041d", "Swedish", "0000041d", true),
+    locale("th-TH", "041e", "Thai", "0000041e", true),
+    locale("tr-TR", "041f", "Turkish", "0000041f", true),
+    locale("uk-UA", "0422", "Ukrainian", "00020422", true),
+    locale("vi-VN", "042a", "Vietnamese", "0000042a", false),
+    locale("zh-CN", "0804", "Chinese (Simplified)", "00000804", true),
+    locale("zh-TW", "0404", "Chinese (Traditional)", "00000404", true),
+];
+
+/// Keyboard layout ids and names
+pub const KEYBOARDS: &[(&str, &str)] = &[
+    ("00000401", "Arabic (101)"),
+    ("00000404", "Chinese (Traditional) - US"),
+    ("00000405", "Czech"),
+    ("00000406", "Danish"),
+    ("00000407", "German"),
+    ("00000408", "Greek"),
+    ("00000409", "US"),
+    ("0000040a", "Spanish"),
+    ("0000040b", "Finnish"),
+    ("0000040c", "French"),
+    ("0000040e", "Hungarian"),
+    ("0000040f", "Icelandic"),
+    ("00000410", "Italian"),
+    ("00000411", "Japanese"),
+    ("00000412", "Korean"),
+    ("00000413", "Dutch"),
+    ("00000414", "Norwegian"),
+    ("00000415", "Polish (Programmers)"),
+    ("00000416", "Portuguese (Brazil ABNT)"),
+    ("00000419", "Russian"),
+    ("0000041a", "Croatian"),
+    ("0000041b", "Slovak"),
+    ("0000041d", "Swedish"),
+    ("0000041e", "Thai Kedmanee"),
+    ("0000041f", "Turkish Q"),
+    ("00000424", "Slovenian"),
+    ("00000425", "Estonian"),
+    ("0000042a", "Vietnamese"),
+    ("00000804", "Chinese (Simplified) - US"),
+    ("00000807", "Swiss German"),
+    ("00000809", "United Kingdom"),
+    ("0000080a", "Latin American"),
+    ("0000080c", "Belgian French"),
+    ("00000813", "Belgian (Period)"),
+    ("00000816", "Portuguese"),
+    ("0000081a", "Serbian (Latin)"),
+    ("00001009", "Canadian French"),
+    ("0000100c", "Swiss French"),
+    ("00001809", "Irish"),
+    ("00004009", "India"),
+    ("00010409", "United States-Dvorak"),
+    ("00010418", "Romanian (Standard)"),
+    ("00010427", "Lithuanian"),
+    ("00010439", "Hindi Traditional"),
+    ("00011009", "Canadian Multilingual Standard"),
+    ("00020409", "United States-International"),
+    ("0002040d", "Hebrew (Standard)"),
+    ("00020422", "Ukrainian (Enhanced)"),
+    ("00020426", "Latvian (Standard)"),
+    ("00030402", "Bulgarian"),
+];
+
+/// Windows accepts at most this many keyboards in InputLocale
+const MAX_INPUT_LOCALES: usize = 5;
+
+/// Find a locale by name (case-insensitive, like Setup)
+pub fn find(name: &str) -> Option<&'static Locale> {
+    LOCALES.iter().find(|l| l.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Name of a keyboard layout id, if it's in the table
+pub fn keyboard_name(id: &str) -> Option<&'static str> {
+    KEYBOARDS.iter().find(|(k, _)| k.eq_ignore_ascii_case(id)).map(|(_, name)| *name)
+}
+
+/// The "LLLL:KKKKKKKK" InputLocale for a locale's default keyboard
+/// (e.g., "en-US" → "0409:00000409")
+pub fn default_input_locale(name: &str) -> Option<String> {
+    find(name).map(|l| format!("{}:{}", l.lcid, l.keyboard))
+}
+
+/// Keyboard choices for the UI: every locale's default keyboard as
+/// "0c0c:00001009  French (Canada) - Canadian French"
+pub fn keyboard_choices() -> Vec<String> {
+    LOCALES
+        .iter()
+        .map(|l| {
+            format!(
+                "{}:{}  {} - {}",
+                l.lcid,
+                l.keyboard,
+                l.display_name,
+                keyboard_name(l.keyboard).unwrap_or("?")
+            )
+        })
+        .collect()
+}
+
+/// Add a keyboard choice ("0c0c:00001009  French (Canada) - ...") to an
+/// InputLocale list. Already listed keyboards aren't added twice.
+pub fn add_keyboard(input_locale: &str, choice: &str) -> String {
+    let Some(pair) = choice.split_whitespace().next() else {
+        return input_locale.to_string();
+    };
+    let mut entries: Vec<&str> = input_locale.split(';').map(str::trim).filter(|e| !e.is_empty()).collect();
+    if !entries.iter().any(|e| e.eq_ignore_ascii_case(pair)) {
+        entries.push(pair);
+    }
+    entries.join(";")
+}
+
+/// True if a name has the shape of a locale name: a 2-3 letter language,
+/// an optional 4-letter script and an optional region ("ga-IE",
+/// "sr-Latn-RS", "es-419").
+fn is_locale_name(name: &str) -> bool {
+    let is_alpha = |s: &str, lengths: std::ops::RangeInclusive<usize>| {
+        lengths.contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphabetic())
+    };
+    let mut parts: Vec<&str> = name.split('-').collect();
+    if !is_alpha(parts.remove(0), 2..=3) {
+        return false;
+    }
+    if parts.first().is_some_and(|p| is_alpha(p, 4..=4)) {
+        parts.remove(0);
+    }
+    match parts.as_slice() {
+        [] => true,
+        [region] => is_alpha(region, 2..=2) || (region.len() == 3 && region.chars().all(|c| c.is_ascii_digit())),
+        _ => false,
+    }
+}
+
+/// True if a value is `len` hex digits (input language and keyboard ids)
+fn is_hex_id(value: &str, len: usize) -> bool {
+    value.len() == len && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Check a UILanguage value: a known locale with a language pack.
+/// A well-formed name that isn't in the table only gets a warning —
+/// the table doesn't list every language Windows has.
+///
+/// # Returns
+/// * `Ok(None)` — known and usable
+/// * `Ok(Some(warning))` — not in the table, but may be fine
+/// * `Err(String)` — not a locale name, or a locale without a language pack
+pub fn validate_ui_language(name: &str) -> Result<Option<String>, String> {
+    match find(name) {
+        None if is_locale_name(name.trim()) => Ok(Some(format!(
+            "\"{}\" is not in MasterBooter's locale table — make sure the image has this language pack",
+            name
+        ))),
+        None => Err(format!("\"{}\" is not a locale name (e.g., en-US)", name)),
+        Some(l) if !l.language_pack => Err(format!(
+            "\"{}\" has no Windows language pack and can't be the display language (use it as system/user locale instead)",
+            l.name
+        )),
+        Some(_) => Ok(None),
+    }
+}
+
+/// Check a SystemLocale or UserLocale value: any locale name. Names that
+/// aren't in the table get a warning (see validate_ui_language).
+pub fn validate_locale(name: &str) -> Result<Option<String>, String> {
+    match find(name) {
+        Some(_) => Ok(None),
+        None if is_locale_name(name.trim()) => Ok(Some(format!(
+            "\"{}\" is not in MasterBooter's locale table — make sure Windows knows it",
+            name
+        ))),
+        None => Err(format!("\"{}\" is not a locale name (e.g., en-US)", name)),
+    }
+}
+
+/// Check an InputLocale value: up to 5 "LLLL:KKKKKKKK" pairs or locale
+/// names separated by ";". Malformed entries are errors; well-formed
+/// language or keyboard ids that aren't in the tables are one warning.
+pub fn validate_input_locale(value: &str) -> Result<Option<String>, String> {
+    let entries: Vec<&str> = value.split(';').map(str::trim).collect();
+    if entries.iter().any(|e| e.is_empty()) {
+        return Err("empty keyboard entry (expected e.g. 0409:00000409)".to_string());
+    }
+    if entries.len() > MAX_INPUT_LOCALES {
+        return Err(format!("{} keyboards listed (Windows accepts at most {})", entries.len(), MAX_INPUT_LOCALES));
+    }
+    let mut unknown = Vec::new();
+    for entry in entries {
+        match entry.split_once(':') {
+            None => {
+                if validate_locale(entry)?.is_some() {
+                    unknown.push(entry.to_string());
+                }
+            }
+            Some((lcid, keyboard)) => {
+                if !is_hex_id(lcid, 4) || !is_hex_id(keyboard, 8) {
+                    return Err(format!("\"{}\" is not a keyboard id (expected LLLL:KKKKKKKK, e.g. 0409:00000409)", entry));
+                }
+                if !LOCALES.iter().any(|l| l.lcid.eq_ignore_ascii_case(lcid)) || keyboard_name(keyboard).is_none() {
+                    unknown.push(entry.to_string());
+                }
+            }
+        }
+    }
+    if unknown.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(format!(
+            "{} not in MasterBooter's keyboard table — make sure Windows knows {}",
+            unknown.iter().map(|e| format!("\"{}\"", e)).collect::<Vec<_>>().join(", "),
+            if unknown.len() == 1 { "it" } else { "them" }
+        )))
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_table() {
+        // Every default keyboard is in the keyboard table, names are unique
+        for l in LOCALES {
+            assert!(keyboard_name(l.keyboard).is_some(), "{} keyboard {}", l.name, l.keyboard);
+            assert_eq!(LOCALES.iter().filter(|o| o.name == l.name).count(), 1, "{}", l.name);
+        }
+        assert_eq!(default_input_locale("fr-ca").as_deref(), Some("0c0c:00001009"));
+        assert_eq!(default_input_locale("xx-XX"), None);
+
+        let choice = &keyboard_choices()[LOCALES.iter().position(|l| l.name == "fr-CA").unwrap()];
+        assert_eq!(choice, "0c0c:00001009  French (Canada) - Canadian French");
+        assert_eq!(add_keyboard("0409:00000409", choice), "0409:00000409;0c0c:00001009");
+        assert_eq!(add_keyboard("0409:00000409;0c0c:00001009", choice), "0409:00000409;0c0c:00001009");
+        assert_eq!(add_keyboard("", choice), "0c0c:00001009");
+    }
+
+    #[test]
+    fn test_validate_locales() {
+        assert_eq!(validate_ui_language("en-US"), Ok(None));
+        assert!(validate_ui_language("en-CA").is_err()); // no language pack
+        assert!(validate_ui_language("en_US").is_err());
+        assert_eq!(validate_locale("en-CA"), Ok(None));
+
+        // Well-formed but not in the tables: a warning, not an error
+        assert!(validate_ui_language("ga-IE").unwrap().is_some());
+        assert!(validate_locale("sr-Cyrl-RS").unwrap().is_some());
+        assert!(validate_locale("es-419").unwrap().is_some());
+        assert!(validate_locale("english").is_err());
+
+        // en-US UI with US and Canadian French keyboards
+        assert_eq!(validate_input_locale("0409:00000409;0c0c:00001009"), Ok(None));
+        assert_eq!(validate_input_locale("de-DE"), Ok(None));
+        let warning = validate_input_locale("0409:00000409;0422:00000422;ga-IE").unwrap().unwrap();
+        assert!(warning.starts_with("\"0422:00000422\", \"ga-IE\" not in"), "{}", warning);
+        assert!(validate_input_locale("0409:0409").is_err());
+        assert!(validate_input_locale("04x9:00000409").is_err());
+        assert!(validate_input_locale("0409:00000409;").is_err());
+        assert!(validate_input_locale(&["en-US"; 6].join(";")).is_err());
+    }
+}
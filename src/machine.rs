@@ -0,0 +1,616 @@
+// ============================================
+// MasterBooter - machine.rs
+// ============================================
+// This module identifies the machine we're deploying to and turns
+// computer name templates into real names.
+//
+// Firmware info (serial number, asset tag, chassis type, MAC addresses) is read through
+// WMI with PowerShell — works in full Windows and in our WinPE builds.
+//
+// Computer name templates mix literal text with placeholders:
+//
+//   ACME-{SERIAL:8}   → ACME-5CG1234X   (last 8 characters of the serial)
+//   LAB-{COUNTER:03}  → LAB-007         (counter, zero-padded to 3 digits)
+//   {ASSET}-{TYPE}    → 10442-LAPTOP    (asset tag + LAPTOP/DESKTOP)
+//
+// Placeholders:
+//   {SERIAL} / {SERIAL:n}   BIOS serial number (n = keep the LAST n chars,
+//                           which is where serials differ between machines)
+//   {ASSET} / {ASSET:n}     SMBIOS asset tag (last n chars)
+//   {TYPE} / {TYPE:n}       LAPTOP or DESKTOP (first n chars, e.g. {TYPE:1} = L/D)
+//   {COUNTER} / {COUNTER:n} Counter stored on the USB drive, zero-padded to n digits
+//
+// Counters live in name_counters.json next to the EXE (one per template),
+// so they keep counting across machines and reboots.
+//
+// For rollouts of pre-registered PCs, machines.csv next to the EXE maps a
+// serial number or MAC address to per-machine settings (see MACHINE LIST).
+// ============================================
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::unattend;
+
+// ============================================
+// MACHINE INFO
+// ============================================
+
+/// Laptop or desktop, from the SMBIOS chassis type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChassisKind {
+    Laptop,
+    Desktop,
+    /// Firmware didn't report a usable chassis type
+    #[default]
+    Unknown,
+}
+
+impl ChassisKind {
+    /// Map SMBIOS chassis type numbers (Win32_SystemEnclosure.ChassisTypes).
+    /// Portable, laptop, notebook, handheld, sub-notebook, tablet,
+    /// convertible and detachable count as laptops.
+    pub fn from_chassis_types(types: &[u32]) -> Self {
+        const LAPTOP: &[u32] = &[8, 9, 10, 11, 14, 30, 31, 32];
+        const DESKTOP: &[u32] = &[3, 4, 5, 6, 7, 13, 15, 16, 24, 35, 36];
+        if types.iter().any(|t| LAPTOP.contains(t)) {
+            ChassisKind::Laptop
+        } else if types.iter().any(|t| DESKTOP.contains(t)) {
+            ChassisKind::Desktop
+        } else {
+            ChassisKind::Unknown
+        }
+    }
+}
+
+/// Firmware identity of the machine we're deploying to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MachineInfo {
+    /// BIOS serial number / service tag (empty if the vendor didn't set one)
+    pub serial: String,
+    /// SMBIOS asset tag (empty if not set)
+    pub asset_tag: String,
+    /// Laptop or desktop
+    pub chassis: ChassisKind,
+    /// MAC addresses of the physical network adapters (see normalize_mac)
+    pub mac_addresses: Vec<String>,
+}
+
+/// MAC address as 12 upper-case hex digits, so "00:1a:2b:3c:4d:5e",
+/// "00-1A-2B-3C-4D-5E" and "001a.2b3c.4d5e" all compare equal
+pub fn normalize_mac(mac: &str) -> String {
+    mac.chars().filter(|c| c.is_ascii_hexdigit()).collect::<String>().to_uppercase()
+}
+
+/// Values vendors leave in unset firmware fields
+const PLACEHOLDER_VALUES: &[&str] = &[
+    "to be filled by o.e.m.",
+    "default string",
+    "system serial number",
+    "chassis serial number",
+    "no asset tag",
+    "no asset information",
+    "asset-1234567890",
+    "none",
+    "0",
+];
+
+/// Clean up a firmware string: blank out vendor placeholders
+fn firmware_value(raw: &str) -> String {
+    let value = raw.trim();
+    if PLACEHOLDER_VALUES.contains(&value.to_lowercase().as_str()) || value.chars().all(|c| c == '0' || c == ' ') {
+        String::new()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Read serial number, asset tag, chassis type and MAC addresses.
+///
+/// # Returns
+/// * `Ok(MachineInfo)` — fields the firmware doesn't provide are left empty
+/// * `Err(String)` — PowerShell/WMI couldn't be run
+pub fn detect_machine_info() -> Result<MachineInfo, String> {
+    println!("[Machine] Reading firmware info...");
+
+    // One labeled line per value, same style as the product key detection
+    let ps_script = r#"
+try { Write-Output "SERIAL:$((Get-CimInstance -ClassName Win32_BIOS).SerialNumber)" } catch { Write-Output "SERIAL:" }
+try {
+    $enclosure = Get-CimInstance -ClassName Win32_SystemEnclosure | Select-Object -First 1
+    Write-Output "ASSET:$($enclosure.SMBIOSAssetTag)"
+    Write-Output "CHASSIS:$($enclosure.ChassisTypes -join ',')"
+} catch { Write-Output "ASSET:"; Write-Output "CHASSIS:" }
+try {
+    Get-CimInstance -ClassName Win32_NetworkAdapter -Filter "PhysicalAdapter = True" |
+        Where-Object { $_.MACAddress } | ForEach-Object { Write-Output "MAC:$($_.MACAddress)" }
+} catch { }
+"#;
+
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", ps_script])
+        .output()
+        .map_err(|e| format!("Failed to run PowerShell: {}", e))?;
+
+    let info = parse_machine_info(&String::from_utf8_lossy(&output.stdout));
+    println!(
+        "[Machine] Serial: '{}', asset tag: '{}', chassis: {:?}, MACs: {}",
+        info.serial, info.asset_tag, info.chassis, info.mac_addresses.join(", ")
+    );
+    Ok(info)
+}
+
+/// Parse the "LABEL:value" lines printed by detect_machine_info's script
+fn parse_machine_info(output: &str) -> MachineInfo {
+    let mut info = MachineInfo::default();
+    for line in output.lines() {
+        if let Some(value) = line.strip_prefix("SERIAL:") {
+            info.serial = firmware_value(value);
+        } else if let Some(value) = line.strip_prefix("ASSET:") {
+            info.asset_tag = firmware_value(value);
+        } else if let Some(value) = line.strip_prefix("CHASSIS:") {
+            let types: Vec<u32> = value.split(',').filter_map(|t| t.trim().parse().ok()).collect();
+            info.chassis = ChassisKind::from_chassis_types(&types);
+        } else if let Some(value) = line.strip_prefix("MAC:") {
+            let mac = normalize_mac(value);
+            if mac.len() == 12 && !info.mac_addresses.contains(&mac) {
+                info.mac_addresses.push(mac);
+            }
+        }
+    }
+    info
+}
+
+// ============================================
+// COMPUTER NAME TEMPLATES
+// ============================================
+
+/// One piece of a parsed template
+#[derive(Debug, Clone, PartialEq)]
+enum TemplatePart {
+    Text(String),
+    Serial(Option<usize>),
+    Asset(Option<usize>),
+    Type(Option<usize>),
+    Counter(Option<usize>),
+}
+
+/// Whether a computer name is a template (vs. a literal name or "*")
+pub fn is_template(name: &str) -> bool {
+    name.contains('{') || name.contains('}')
+}
+
+/// Split a template into text and placeholders.
+///
+/// # Returns
+/// * `Err(String)` — unknown placeholder, bad width or unbalanced braces
+fn parse_template(template: &str) -> Result<Vec<TemplatePart>, String> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(format!("Unexpected '}}' in name template \"{}\"", template));
+        }
+        if start > 0 {
+            parts.push(TemplatePart::Text(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .map(|i| start + i)
+            .ok_or_else(|| format!("Missing '}}' in name template \"{}\"", template))?;
+
+        let placeholder = &rest[start + 1..end];
+        let (name, width) = match placeholder.split_once(':') {
+            Some((name, width)) => {
+                let width: usize = width
+                    .parse()
+                    .ok()
+                    .filter(|w| (1..=15).contains(w))
+                    .ok_or_else(|| format!("Bad width in {{{}}} — use a number from 1 to 15", placeholder))?;
+                (name, Some(width))
+            }
+            None => (placeholder, None),
+        };
+        parts.push(match name.to_ascii_uppercase().as_str() {
+            "SERIAL" => TemplatePart::Serial(width),
+            "ASSET" => TemplatePart::Asset(width),
+            "TYPE" => TemplatePart::Type(width),
+            "COUNTER" => TemplatePart::Counter(width),
+            _ => {
+                return Err(format!(
+                    "Unknown placeholder {{{}}} — use {{SERIAL}}, {{ASSET}}, {{TYPE}} or {{COUNTER}}",
+                    placeholder
+                ))
+            }
+        });
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(TemplatePart::Text(rest.to_string()));
+    }
+    Ok(parts)
+}
+
+/// Keep the last `width` characters (or all when no width)
+fn last_chars(value: &str, width: Option<usize>) -> String {
+    let count = value.chars().count();
+    let skip = width.map_or(0, |w| count.saturating_sub(w));
+    value.chars().skip(skip).collect()
+}
+
+/// Firmware strings can hold spaces, slashes etc. — keep letters, digits and '-'
+fn name_safe(value: &str) -> String {
+    value.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect()
+}
+
+/// Fill in a template and check the result against the NetBIOS rules.
+///
+/// # Arguments
+/// * `template` — e.g. "ACME-{SERIAL:8}"
+/// * `info` — Firmware info of the target machine
+/// * `counter` — Value for {COUNTER}
+///
+/// # Returns
+/// * `Ok(String)` — the upper-case computer name
+/// * `Err(String)` — bad template, missing firmware value, or invalid name
+pub fn resolve_template(template: &str, info: &MachineInfo, counter: u32) -> Result<String, String> {
+    let mut name = String::new();
+    for part in parse_template(template)? {
+        match part {
+            TemplatePart::Text(text) => name.push_str(&text),
+            TemplatePart::Serial(width) => {
+                let serial = name_safe(&info.serial);
+                if serial.is_empty() {
+                    return Err("This machine's firmware has no serial number for {SERIAL}".to_string());
+                }
+                name.push_str(&last_chars(&serial, width));
+            }
+            TemplatePart::Asset(width) => {
+                let asset = name_safe(&info.asset_tag);
+                if asset.is_empty() {
+                    return Err("This machine's firmware has no asset tag for {ASSET}".to_string());
+                }
+                name.push_str(&last_chars(&asset, width));
+            }
+            TemplatePart::Type(width) => {
+                let kind = match info.chassis {
+                    ChassisKind::Laptop => "LAPTOP",
+                    ChassisKind::Desktop => "DESKTOP",
+                    ChassisKind::Unknown => {
+                        return Err("Can't tell whether this machine is a laptop or desktop for {TYPE}".to_string())
+                    }
+                };
+                name.extend(kind.chars().take(width.unwrap_or(kind.len())));
+            }
+            TemplatePart::Counter(width) => {
+                name.push_str(&format!("{:0width$}", counter, width = width.unwrap_or(0)));
+            }
+        }
+    }
+
+    let name = name.to_uppercase();
+    unattend::validate_computer_name(&name).map_err(|e| format!("Template \"{}\" gives an invalid name: {}", template, e))?;
+    Ok(name)
+}
+
+/// Whether a template needs firmware info (so we can skip the WMI query)
+fn needs_firmware(parts: &[TemplatePart]) -> bool {
+    parts
+        .iter()
+        .any(|p| matches!(p, TemplatePart::Serial(_) | TemplatePart::Asset(_) | TemplatePart::Type(_)))
+}
+
+/// A template resolved for this machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedName {
+    /// The computer name to put in the answer file
+    pub name: String,
+    /// Counter value used, if the template has {COUNTER}.
+    /// Call commit_counter() once the deployment actually starts.
+    pub counter: Option<u32>,
+}
+
+/// Resolve a template on this machine: reads the firmware if needed and
+/// peeks at the next counter value (without using it up).
+pub fn resolve_for_this_machine(template: &str) -> Result<ResolvedName, String> {
+    let parts = parse_template(template)?;
+    let info = if needs_firmware(&parts) { detect_machine_info()? } else { MachineInfo::default() };
+    let uses_counter = parts.iter().any(|p| matches!(p, TemplatePart::Counter(_)));
+    let counter = if uses_counter { Some(next_counter(template)) } else { None };
+
+    let name = resolve_template(template, &info, counter.unwrap_or(0))?;
+    println!("[Machine] Computer name template \"{}\" → {}", template, name);
+    Ok(ResolvedName { name, counter })
+}
+
+// ============================================
+// COUNTERS
+// ============================================
+
+/// Folder the EXE lives in (the USB drive) — counters and the machine list live here
+fn exe_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|d| d.to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Path to name_counters.json (next to the EXE, so it stays on the USB drive)
+fn get_counters_path() -> PathBuf {
+    exe_dir().join("name_counters.json")
+}
+
+/// Next counter value per template ("LAB-{COUNTER:03}" → 8)
+fn load_counters() -> BTreeMap<String, u32> {
+    fs::read_to_string(get_counters_path())
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// The value {COUNTER} gets for the next deployment with this template (starts at 1)
+pub fn next_counter(template: &str) -> u32 {
+    load_counters().get(template).copied().unwrap_or(1)
+}
+
+/// Mark a counter value as used, so the next deployment gets the one after it
+pub fn commit_counter(template: &str, used: u32) -> Result<(), String> {
+    let mut counters = load_counters();
+    counters.insert(template.to_string(), used + 1);
+    let json = serde_json::to_string_pretty(&counters)
+        .map_err(|e| format!("Failed to serialize name counters: {}", e))?;
+    fs::write(get_counters_path(), json).map_err(|e| format!("Failed to save name counter: {}", e))?;
+    println!("[Machine] Counter for \"{}\" is now {}", template, used + 1);
+    Ok(())
+}
+
+// ============================================
+// MACHINE LIST (machines.csv)
+// ============================================
+// machines.csv maps a serial number or MAC address to per-machine settings:
+//
+//   serial,mac,computer_name,user,domain_ou,profile
+//   5CG1234X,,ACC-01,jsmith,"OU=Accounting,DC=corp,DC=local",Accounting
+//   ,00:1A:2B:3C:4D:5E,LAB-{COUNTER:02},,,
+//
+// - Headers are case-insensitive and columns can be in any order
+// - Empty cells keep the value from the profile
+// - computer_name may be a template too
+// - profile names a saved profile to deploy instead of the current settings
+//
+// Every row a deployment uses is appended to machines_log.csv, so the USB
+// drive keeps a record of which PCs have been done.
+// ============================================
+
+/// One row of machines.csv
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MachineEntry {
+    /// Line number in machines.csv (for messages)
+    #[serde(skip)]
+    pub line: u64,
+    /// BIOS serial number to match
+    pub serial: String,
+    /// MAC address to match (any format)
+    pub mac: String,
+    /// Computer name (or template) for this machine
+    pub computer_name: String,
+    /// Local account name for this machine's user (renames the first account)
+    pub user: String,
+    /// Organizational unit for the domain join
+    pub domain_ou: String,
+    /// Saved profile to deploy on this machine
+    pub profile: String,
+}
+
+/// Path to machines.csv (next to the EXE)
+pub fn get_machine_list_path() -> PathBuf {
+    exe_dir().join("machines.csv")
+}
+
+/// Path to machines_log.csv (next to the EXE)
+fn get_machine_log_path() -> PathBuf {
+    exe_dir().join("machines_log.csv")
+}
+
+/// Parse machines.csv.
+///
+/// # Returns
+/// * `Err(String)` — unreadable CSV, or a row with neither serial nor MAC
+pub fn parse_machine_list(data: &str) -> Result<Vec<MachineEntry>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(data.as_bytes());
+
+    // Headers are matched case-insensitively ("Serial", "MAC", ...)
+    let headers = reader.headers().map_err(|e| format!("machines.csv: {}", e))?;
+    let headers: csv::StringRecord = headers.iter().map(|h| h.to_lowercase()).collect();
+    if !headers.iter().any(|h| h == "serial" || h == "mac") {
+        return Err("machines.csv needs a \"serial\" or \"mac\" column".to_string());
+    }
+
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let mut record = record.map_err(|e| format!("machines.csv: {}", e))?;
+        // Short rows ("SERIAL,,NAME") just leave the remaining columns empty
+        while record.len() < headers.len() {
+            record.push_field("");
+        }
+        let mut entry: MachineEntry = record
+            .deserialize(Some(&headers))
+            .map_err(|e| format!("machines.csv: {}", e))?;
+        entry.line = record.position().map_or(entries.len() as u64 + 2, |p| p.line());
+        if entry.serial.is_empty() && entry.mac.is_empty() {
+            return Err(format!("machines.csv line {}: needs a serial or a MAC address", entry.line));
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Load machines.csv from next to the EXE.
+///
+/// # Returns
+/// * `Ok(None)` — there is no machines.csv (the feature is off)
+pub fn load_machine_list() -> Result<Option<Vec<MachineEntry>>, String> {
+    let path = get_machine_list_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read_to_string(&path).map_err(|e| format!("Failed to read machines.csv: {}", e))?;
+    let entries = parse_machine_list(&data)?;
+    println!("[Machine] Loaded {} row(s) from {}", entries.len(), path.display());
+    Ok(Some(entries))
+}
+
+/// Find the row for this machine (by serial or by any of its MACs).
+///
+/// # Returns
+/// * `Ok(None)` — no row matches
+/// * `Err(String)` — more than one row matches (ambiguous, so don't guess)
+pub fn find_machine_entry<'a>(entries: &'a [MachineEntry], info: &MachineInfo) -> Result<Option<&'a MachineEntry>, String> {
+    let matches: Vec<&MachineEntry> = entries
+        .iter()
+        .filter(|entry| {
+            let serial_match = !entry.serial.is_empty() && !info.serial.is_empty()
+                && entry.serial.eq_ignore_ascii_case(info.serial.trim());
+            let mac = normalize_mac(&entry.mac);
+            let mac_match = !mac.is_empty() && info.mac_addresses.contains(&mac);
+            serial_match || mac_match
+        })
+        .collect();
+
+    match matches.as_slice() {
+        [] => Ok(None),
+        [entry] => Ok(Some(entry)),
+        _ => Err(format!(
+            "machines.csv has {} rows for this machine (lines {})",
+            matches.len(),
+            matches.iter().map(|e| e.line.to_string()).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// Append a used row to machines_log.csv on the USB drive.
+///
+/// # Arguments
+/// * `entry` — The machines.csv row that was used
+/// * `info` — This machine's firmware info
+/// * `computer_name` — The final computer name (templates resolved)
+pub fn log_machine_entry(entry: &MachineEntry, info: &MachineInfo, computer_name: &str) -> Result<(), String> {
+    let path = get_machine_log_path();
+    let is_new = fs::metadata(&path).map(|m| m.len() == 0).unwrap_or(true);
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open machines_log.csv: {}", e))?;
+
+    let mut writer = csv::Writer::from_writer(file);
+    if is_new {
+        writer
+            .write_record(["time", "line", "serial", "macs", "computer_name", "user", "domain_ou", "profile"])
+            .map_err(|e| format!("Failed to write machines_log.csv: {}", e))?;
+    }
+    let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    writer
+        .write_record([
+            time.as_str(),
+            &entry.line.to_string(),
+            &info.serial,
+            &info.mac_addresses.join(" "),
+            computer_name,
+            &entry.user,
+            &entry.domain_ou,
+            &entry.profile,
+        ])
+        .and_then(|_| writer.flush().map_err(csv::Error::from))
+        .map_err(|e| format!("Failed to write machines_log.csv: {}", e))?;
+
+    println!("[Machine] Logged machines.csv line {} for {}", entry.line, computer_name);
+    Ok(())
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_templates() {
+        let laptop = MachineInfo {
+            serial: "CN 0X5CG1234X".to_string(),
+            asset_tag: "10442".to_string(),
+            chassis: ChassisKind::Laptop,
+            mac_addresses: Vec::new(),
+        };
+        assert_eq!(resolve_template("ACME-{SERIAL:8}", &laptop, 1).unwrap(), "ACME-5CG1234X");
+        assert_eq!(resolve_template("lab-{COUNTER:03}", &laptop, 7).unwrap(), "LAB-007");
+        assert_eq!(resolve_template("{ASSET}-{TYPE}", &laptop, 1).unwrap(), "10442-LAPTOP");
+        assert_eq!(resolve_template("{TYPE:1}{serial:4}", &laptop, 1).unwrap(), "L234X");
+
+        // Too long for NetBIOS
+        let err = resolve_template("ACME-{SERIAL}-{TYPE}", &laptop, 1).unwrap_err();
+        assert!(err.contains("15"), "{}", err);
+        // Missing firmware values and bad templates
+        let blank = MachineInfo::default();
+        assert!(resolve_template("PC-{SERIAL}", &blank, 1).unwrap_err().contains("serial"));
+        assert!(resolve_template("PC-{TYPE}", &blank, 1).is_err());
+        assert!(resolve_template("PC-{MAC}", &laptop, 1).unwrap_err().contains("Unknown placeholder"));
+        assert!(resolve_template("PC-{SERIAL", &laptop, 1).is_err());
+        assert!(resolve_template("PC-{COUNTER:x}", &laptop, 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_machine_info() {
+        let info = parse_machine_info("SERIAL:To be filled by O.E.M.\r\nASSET: 10442 \r\nCHASSIS:3\r\nMAC:00:1a:2B:3c:4D:5e\r\n");
+        assert_eq!(info, MachineInfo {
+            serial: String::new(),
+            asset_tag: "10442".to_string(),
+            chassis: ChassisKind::Desktop,
+            mac_addresses: vec!["001A2B3C4D5E".to_string()],
+        });
+        assert_eq!(ChassisKind::from_chassis_types(&[10]), ChassisKind::Laptop);
+        assert_eq!(ChassisKind::from_chassis_types(&[2]), ChassisKind::Unknown);
+    }
+
+    #[test]
+    fn test_machine_list_matching() {
+        let csv = "Serial, MAC ,Computer_Name,User,Domain_OU,Profile\n\
+                   5CG1234X,,ACC-01,jsmith,\"OU=Accounting,DC=corp,DC=local\",Accounting\n\
+                   ,00-1A-2B-3C-4D-5E,LAB-{COUNTER:02}\n\
+                   DUP1,,A\n\
+                   ,aa:bb:cc:dd:ee:ff,B\n";
+        let entries = parse_machine_list(csv).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].domain_ou, "OU=Accounting,DC=corp,DC=local");
+        assert_eq!(entries[1].line, 3);
+
+        let by_serial = MachineInfo { serial: "5cg1234x".to_string(), ..Default::default() };
+        assert_eq!(find_machine_entry(&entries, &by_serial).unwrap().unwrap().computer_name, "ACC-01");
+
+        let by_mac = MachineInfo { mac_addresses: vec!["001A2B3C4D5E".to_string()], ..Default::default() };
+        assert_eq!(find_machine_entry(&entries, &by_mac).unwrap().unwrap().line, 3);
+
+        let unknown = MachineInfo { serial: "NOPE".to_string(), ..Default::default() };
+        assert!(find_machine_entry(&entries, &unknown).unwrap().is_none());
+
+        // Two rows for the same machine is an error, not a guess
+        let ambiguous = MachineInfo {
+            serial: "DUP1".to_string(),
+            mac_addresses: vec!["AABBCCDDEEFF".to_string()],
+            ..Default::default()
+        };
+        assert!(find_machine_entry(&entries, &ambiguous).unwrap_err().contains("lines 4, 5"));
+
+        assert!(parse_machine_list("serial,mac,computer_name\n,,PC-01\n").unwrap_err().contains("line 2"));
+        assert!(parse_machine_list("name,user\nPC,bob\n").is_err());
+    }
+}
@@ -25,6 +25,18 @@ mod adk_packages;  // ADK package management for WinPE
 mod pe_fixes;      // PE fixes and workarounds
 mod deploy;        // Windows deployment module
 mod updater;       // Auto-update from GitHub releases
+mod unattend;      // Typed answer file (autounattend.xml) model
+mod secrets;       // Passphrase encryption for secrets in profiles/saved keys
+mod machine;       // Firmware info and computer name templates
+mod tweaks;        // Tweak catalog (FirstLogon registry tweaks from TOML)
+mod appx;          // Provisioned Appx package removal (DISM or answer file)
+mod wifi;          // Wi-Fi profiles (WLAN profile XML) for the installed Windows
+mod locale;        // Locale names and keyboard layout ids for the answer file
+mod timezone;      // Windows time zone ids with IANA equivalents
+mod product_key;   // Product key decoding (DigitalProductId) and format checks
+mod drivers;       // Driver folders for the deployed Windows (answer file + DISM)
+mod oem;           // OEM information (System > About) and logo
+mod scripts;       // Post-install script tags and the resumable RunAll runner
 
 // ============================================
 // MAIN FUNCTION
@@ -33,6 +45,12 @@ mod updater;       // Auto-update from GitHub releases
 // In Rust, `fn main()` is always the entry point.
 
 fn main() -> Result<(), slint::PlatformError> {
+    // Headless mode: `masterbooter --diff-profiles <A> <B>` prints the diff and exits
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|a| a == "--diff-profiles") {
+        std::process::exit(run_diff_profiles_cli(&args[pos + 1..]));
+    }
+
     // Print startup message to console (helpful for debugging)
     println!("============================================");
     println!("MasterBooter v{}", env!("CARGO_PKG_VERSION"));
@@ -264,7 +282,7 @@ fn main() -> Result<(), slint::PlatformError> {
                             }
                             Err(e) => {
                                 // Download or replace failed
-                                ui.set_update_error(format!("{}", e).into());
+                                ui.set_update_error(e.to_string().into());
                                 ui.set_status_text(
                                     format!(
                                         "Update failed: {}. Try downloading manually from GitHub.",
@@ -570,6 +588,8 @@ fn main() -> Result<(), slint::PlatformError> {
                                 ui.set_backup_installed_key(info.installed_key.clone().into());
                                 ui.set_backup_key_edition(info.edition.clone().into());
                                 ui.set_backup_key_status(info.status.clone().into());
+                                ui.set_backup_key_hostname(info.hostname.clone().into());
+                                ui.set_backup_key_source("".into());
                                 // Build a summary message
                                 let found = if !info.oem_key.is_empty() && !info.installed_key.is_empty() {
                                     "Found OEM key and installed key"
@@ -592,6 +612,97 @@ fn main() -> Result<(), slint::PlatformError> {
         }
     });
 
+    // Callback: Scan for offline Windows installs (other drives with a SOFTWARE hive)
+    ui.on_backup_scan_offline({
+        let ui = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui.upgrade() {
+                let drives = deploy::find_offline_windows_drives();
+                ui.set_status_text(match drives.len() {
+                    0 => "No offline Windows installs found".to_string(),
+                    n => format!("Found {} offline Windows install(s)", n),
+                }.into());
+                set_offline_drives_in_ui(&ui, drives);
+            }
+        }
+    });
+
+    // Callback: Read Offline Key — loads the hives of the Windows on the
+    // selected drive and shows its key, ready for "Save to File"
+    ui.on_backup_read_offline({
+        let ui = ui_handle.clone();
+        move |drive| {
+            let drive = drive.to_string();
+            if drive.is_empty() {
+                if let Some(ui) = ui.upgrade() {
+                    ui.set_status_text("Scan for offline Windows installs first".into());
+                }
+                return;
+            }
+            println!("Backup: Read key from offline Windows on {}", drive);
+            if let Some(ui) = ui.upgrade() {
+                ui.set_backup_key_detecting(true);
+                ui.set_status_text(format!("Reading product key from {}\\Windows...", drive).into());
+            }
+
+            // reg load/unload can take a few seconds — keep the UI responsive
+            let ui_bg = ui.clone();
+            std::thread::spawn(move || {
+                let result = deploy::detect_offline_windows_keys(&drive);
+                let _ = slint::invoke_from_event_loop(move || {
+                    if let Some(ui) = ui_bg.upgrade() {
+                        ui.set_backup_key_detecting(false);
+                        match result {
+                            Ok(info) => {
+                                ui.set_backup_oem_key("".into());
+                                ui.set_backup_installed_key(info.installed_key.into());
+                                ui.set_backup_key_edition(info.edition.into());
+                                ui.set_backup_key_status(info.status.into());
+                                ui.set_backup_key_hostname(info.hostname.clone().into());
+                                ui.set_backup_key_source(info.source.clone().into());
+                                ui.set_status_text(format!("Found installed key of {} on {}", info.hostname, info.source).into());
+                            }
+                            Err(e) => ui.set_status_text(format!("Offline key recovery failed: {}", e).into()),
+                        }
+                    }
+                });
+            });
+        }
+    });
+
+    // Callback: Export CSV — every host's key history as one spreadsheet
+    ui.on_backup_export_keys({
+        let ui = ui_handle.clone();
+        move || {
+            let Some(path) = deploy::pick_saved_keys_export_file() else { return };
+            println!("Backup: Export saved keys to {}", path.display());
+            if let Some(ui) = ui.upgrade() {
+                match deploy::export_saved_keys_csv(&path) {
+                    Ok(rows) => ui.set_status_text(format!("Exported {} key(s) to {}", rows, path.display()).into()),
+                    Err(e) => ui.set_status_text(format!("Failed to export keys: {}", e).into()),
+                }
+            }
+        }
+    });
+
+    // Callback: Import Keys — merges another technician's saved_keys.json
+    ui.on_backup_import_keys({
+        let ui = ui_handle.clone();
+        move || {
+            let Some(path) = deploy::pick_saved_keys_import_file() else { return };
+            println!("Backup: Import saved keys from {}", path.display());
+            if let Some(ui) = ui.upgrade() {
+                match deploy::import_saved_keys(&path) {
+                    Ok((hosts, added)) => {
+                        ui.set_status_text(format!("Imported {} host(s), {} new key(s)", hosts, added).into());
+                        refresh_saved_keys_ui(&ui);
+                    }
+                    Err(e) => ui.set_status_text(format!("Failed to import keys: {}", e).into()),
+                }
+            }
+        }
+    });
+
     // Callback: Copy Key — copies a key string to the clipboard
     ui.on_backup_copy_key({
         let ui = ui_handle.clone();
@@ -648,9 +759,10 @@ fn main() -> Result<(), slint::PlatformError> {
         }
     }
 
-    // On startup, populate the saved keys ComboBox
+    // On startup, populate the saved keys ComboBox and the offline Windows drives
     if let Some(ui) = ui_handle.upgrade() {
         refresh_saved_keys_ui(&ui);
+        set_offline_drives_in_ui(&ui, deploy::find_offline_windows_drives());
     }
 
     // Callback: Save Key — writes detected keys to saved_keys.json next to EXE
@@ -667,21 +779,18 @@ fn main() -> Result<(), slint::PlatformError> {
                     edition: ui.get_backup_key_edition().to_string(),
                     status: ui.get_backup_key_status().to_string(),
                     hostname: {
-                        // Quick hostname detection
-                        std::env::var("COMPUTERNAME").unwrap_or_else(|_| "Unknown".to_string())
-                    },
-                    date: {
-                        // Get current date
-                        let now = std::time::SystemTime::now();
-                        let duration = now.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
-                        let secs = duration.as_secs();
-                        let days = secs / 86400;
-                        let years = 1970 + (days / 365);
-                        let remaining_days = days % 365;
-                        let month = remaining_days / 30 + 1;
-                        let day = remaining_days % 30 + 1;
-                        format!("{}-{:02}-{:02}", years, month.min(12), day.min(31))
+                        // Detected name (the offline install's for a rescued Windows),
+                        // falling back to this machine's name
+                        let detected = ui.get_backup_key_hostname().to_string();
+                        if detected.is_empty() {
+                            std::env::var("COMPUTERNAME").unwrap_or_else(|_| "Unknown".to_string())
+                        } else {
+                            detected
+                        }
                     },
+                    date: deploy::backup_timestamp(),
+                    source: ui.get_backup_key_source().to_string(),
+                    history: Vec::new(),
                 };
 
                 match deploy::save_keys_to_file(&info) {
@@ -727,8 +836,11 @@ fn main() -> Result<(), slint::PlatformError> {
                     String::new()
                 };
 
-                if !key_to_use.is_empty() {
+                if secrets::is_encrypted(&key_to_use) {
+                    ui.set_status_text("This saved key is encrypted — unlock with the passphrase under Profiles first".into());
+                } else if !key_to_use.is_empty() {
                     ui.set_deploy_product_key(key_to_use.into());
+                    ui.set_deploy_product_key_error("".into());
                     ui.set_status_text(format!("Loaded key from {} (backed up {})",
                         info.hostname, info.date).into());
                 } else {
@@ -1566,6 +1678,199 @@ fn main() -> Result<(), slint::PlatformError> {
     // WINDOWS DEPLOY CALLBACKS
     // ============================================
 
+    // Callback: Unlock encrypted secrets with the technician passphrase.
+    // Needed once per session to load/save encrypted profiles and saved keys.
+    ui.on_deploy_unlock_secrets({
+        let ui = ui_handle.clone();
+        move |passphrase| {
+            if let Some(ui) = ui.upgrade() {
+                match secrets::unlock(&passphrase) {
+                    Ok(()) => {
+                        ui.set_deploy_passphrase_input("".into());
+                        ui.set_deploy_secrets_unlocked(true);
+                        ui.set_status_text("Secrets unlocked for this session".into());
+                    }
+                    Err(e) => ui.set_status_text(e.into()),
+                }
+            }
+        }
+    });
+
+    // Callback: Add an empty local account row
+    ui.on_deploy_add_account({
+        let ui = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui.upgrade() {
+                let mut accounts = read_accounts_from_ui(&ui);
+                accounts.push(deploy::UserAccount {
+                    name: String::new(),
+                    display_name: String::new(),
+                    password: String::new(),
+                    group: "Users".to_string(),
+                    password_never_expires: false,
+                });
+                set_accounts_in_ui(&ui, &accounts);
+            }
+        }
+    });
+
+    // Callback: Append an empty Wi-Fi row (WPA2, the most common)
+    ui.on_deploy_add_wifi({
+        let ui = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui.upgrade() {
+                let mut networks = read_wifi_from_ui(&ui);
+                networks.push(wifi::WifiProfile {
+                    ssid: String::new(),
+                    auth: wifi::WifiAuth::default(),
+                    key: String::new(),
+                    hidden: false,
+                });
+                set_wifi_in_ui(&ui, &networks);
+            }
+        }
+    });
+
+    // Callback: Remove the Wi-Fi row at the given index
+    ui.on_deploy_remove_wifi({
+        let ui = ui_handle.clone();
+        move |index| {
+            if let Some(ui) = ui.upgrade() {
+                let mut networks = read_wifi_from_ui(&ui);
+                if index >= 0 && (index as usize) < networks.len() {
+                    networks.remove(index as usize);
+                    set_wifi_in_ui(&ui, &networks);
+                }
+            }
+        }
+    });
+
+    // Callback: Add a specialize command row, numbered after the last one
+    ui.on_deploy_add_specialize_command({
+        let ui = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui.upgrade() {
+                let mut commands = read_specialize_commands_from_ui(&ui);
+                let order = commands.iter().map(|c| c.order).max().unwrap_or(0) + 1;
+                commands.push(deploy::SpecializeCommand { order, path: String::new(), description: String::new() });
+                set_specialize_commands_in_ui(&ui, &commands);
+            }
+        }
+    });
+
+    // Callback: Remove a specialize command row
+    ui.on_deploy_remove_specialize_command({
+        let ui = ui_handle.clone();
+        move |index| {
+            if let Some(ui) = ui.upgrade() {
+                let mut commands = read_specialize_commands_from_ui(&ui);
+                if index >= 0 && (index as usize) < commands.len() {
+                    commands.remove(index as usize);
+                    set_specialize_commands_in_ui(&ui, &commands);
+                }
+            }
+        }
+    });
+
+    // Callback: Browse for the OEM logo
+    ui.on_deploy_browse_oem_logo({
+        let ui = ui_handle.clone();
+        move || {
+            if let (Some(path), Some(ui)) = (deploy::pick_oem_logo_file(), ui.upgrade()) {
+                ui.set_deploy_oem_logo_path(path.display().to_string().into());
+            }
+        }
+    });
+
+    // Callback: Append the packages of the picked preset to the Appx removal list
+    ui.on_deploy_add_appx_preset({
+        let ui = ui_handle.clone();
+        move |name| {
+            if let Some(ui) = ui.upgrade() {
+                match appx::find_preset(&name) {
+                    Some(preset) => {
+                        let mut packages = read_appx_from_ui(&ui);
+                        appx::add_preset(&mut packages, preset);
+                        ui.set_deploy_remove_appx(packages.join("\n").into());
+                    }
+                    None => ui.set_status_text("Pick a preset first".into()),
+                }
+            }
+        }
+    });
+
+    // Callback: Add a driver folder to the list (folder picker)
+    ui.on_deploy_add_driver_folder({
+        let ui = ui_handle.clone();
+        move || {
+            let Some(folder) = deploy::pick_driver_folder() else { return };
+            if let Some(ui) = ui.upgrade() {
+                let mut folders = read_drivers_from_ui(&ui);
+                let folder = folder.display().to_string();
+                if !folders.iter().any(|f| f.eq_ignore_ascii_case(&folder)) {
+                    folders.push(folder);
+                }
+                ui.set_deploy_driver_paths(folders.join("\n").into());
+            }
+        }
+    });
+
+    // Callback: Add a keyboard from the dropdown to the keyboard list
+    ui.on_deploy_add_keyboard({
+        let ui = ui_handle.clone();
+        move |choice| {
+            if let Some(ui) = ui.upgrade() {
+                let input_locale = locale::add_keyboard(&ui.get_deploy_input_locale(), &choice);
+                ui.set_deploy_input_locale(input_locale.into());
+            }
+        }
+    });
+
+    // Callback: Check the product key format while it's typed
+    ui.on_deploy_product_key_edited({
+        let ui = ui_handle.clone();
+        move |key| {
+            if let Some(ui) = ui.upgrade() {
+                ui.set_deploy_product_key_error(product_key_error(&key).into());
+            }
+        }
+    });
+
+    // Callback: Pick the time zone that fits the selected formats locale
+    ui.on_deploy_suggest_timezone({
+        let ui = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui.upgrade() {
+                let user_locale = ui.get_deploy_user_locale().to_string();
+                match timezone::suggest_for_locale(&user_locale) {
+                    Some(zone) => {
+                        ui.set_deploy_timezone(zone.id.into());
+                        ui.set_status_text(format!("Time zone for {}: {} ({})", user_locale, zone.display_name, zone.iana).into());
+                    }
+                    None => ui.set_status_text(format!("No time zone suggestion for {}", user_locale).into()),
+                }
+            }
+        }
+    });
+
+    // Callback: Remove the local account row at the given index
+    ui.on_deploy_remove_account({
+        let ui = ui_handle.clone();
+        move |index| {
+            if let Some(ui) = ui.upgrade() {
+                let mut accounts = read_accounts_from_ui(&ui);
+                if index >= 0 && (index as usize) < accounts.len() {
+                    let removed = accounts.remove(index as usize);
+                    // Autologon can't point at an account that no longer exists
+                    if removed.name.eq_ignore_ascii_case(&ui.get_deploy_autologon_account()) {
+                        ui.set_deploy_autologon_account("".into());
+                    }
+                    set_accounts_in_ui(&ui, &accounts);
+                }
+            }
+        }
+    });
+
     // Callback: Browse for Windows image (WIM/ESD/ISO)
     ui.on_deploy_browse_image({
         let ui = ui_handle.clone();
@@ -1619,6 +1924,12 @@ fn main() -> Result<(), slint::PlatformError> {
                 let image_path = std::path::Path::new(&wim_path_str);
                 let result = deploy::parse_wim_editions(image_path);
 
+                // Detect the architecture of the first edition (all editions in
+                // one WIM normally share it). Failure just leaves the UI as-is.
+                let architecture = result.as_ref().ok().and_then(|(editions, wim)| {
+                    editions.first().and_then(|e| deploy::detect_wim_details(wim, e.index).ok()).map(|d| d.architecture)
+                });
+
                 // Update UI back on the main thread
                 let ui_final = ui_worker.clone();
                 let _ = slint::invoke_from_event_loop(move || {
@@ -1644,6 +1955,9 @@ fn main() -> Result<(), slint::PlatformError> {
                                         editions[0].name.clone().into()
                                     );
                                 }
+                                if let Some(arch) = architecture {
+                                    ui.set_deploy_architecture(arch.as_str().into());
+                                }
                                 ui.set_status_text(
                                     format!("Found {} edition(s)", editions.len()).into(),
                                 );
@@ -1730,7 +2044,7 @@ fn main() -> Result<(), slint::PlatformError> {
                 let edition_index = {
                     let mut idx = 0u32;
                     for i in 0..edition_list.row_count() {
-                        if edition_list.row_data(i).map_or(false, |v| v.as_str() == edition_name) {
+                        if edition_list.row_data(i).is_some_and(|v| v.as_str() == edition_name) {
                             idx = (i + 1) as u32; // DISM uses 1-based index
                             break;
                         }
@@ -1738,89 +2052,57 @@ fn main() -> Result<(), slint::PlatformError> {
                     idx
                 };
 
-                // Parse disk number from the ComboBox selection
-                // The display string starts with "Disk N:" — extract N
-                let disk_id = if ui.get_deploy_let_windows_choose() {
-                    -1i32
-                } else {
-                    let selected_disk: String = ui.get_deploy_selected_disk_name().to_string();
-                    // Parse "Disk 0: Samsung SSD (500 GB, GPT)" → extract "0"
-                    if selected_disk.starts_with("Disk ") {
-                        selected_disk
-                            .trim_start_matches("Disk ")
-                            .split(':')
-                            .next()
-                            .unwrap_or("")
-                            .trim()
-                            .parse::<i32>()
-                            .unwrap_or(-1)
-                    } else {
-                        -1i32
-                    }
-                };
-
-                // Parse boot mode
-                let boot_mode_str: String = ui.get_deploy_boot_mode().to_string();
-                let boot_mode = if boot_mode_str == "BIOS" {
-                    deploy::BootMode::BIOS
-                } else {
-                    deploy::BootMode::UEFI
-                };
+                let disk_id = disk_id_from_ui(&ui);
+                let boot_mode = boot_mode_from_ui(&ui);
+                let enabled_tweaks = read_tweaks_from_ui(&ui);
+                // Same as the BitLocker auto-encrypt tweak
+                let prevent_device_encryption = enabled_tweaks.iter().any(|id| id == "disable_bitlocker");
 
                 let config = deploy::DeployConfig {
                     wim_path: std::path::PathBuf::from(ui.get_deploy_wim_path().to_string()),
                     edition: edition_name,
                     edition_index,
+                    image_build: 0, // Detected from the image during deployment
+                    parent: String::new(),
                     computer_name: ui.get_deploy_computer_name().to_string(),
                     timezone: ui.get_deploy_timezone().to_string(),
-                    language: ui.get_deploy_language().to_string(),
+                    ui_language: ui.get_deploy_ui_language().to_string(),
+                    input_locale: ui.get_deploy_input_locale().to_string(),
+                    system_locale: ui.get_deploy_system_locale().to_string(),
+                    user_locale: ui.get_deploy_user_locale().to_string(),
+                    image_languages: Vec::new(), // Detected from the image during deployment
                     boot_mode,
+                    architecture: deploy::Architecture::parse(&ui.get_deploy_architecture()).unwrap_or_default(),
+                    multi_arch: ui.get_deploy_multi_arch(),
                     disk_id,
+                    partition_layout: read_partition_layout_from_ui(&ui),
                     bypass_win11: ui.get_deploy_bypass_win11(),
-                    user_name: ui.get_deploy_user_name().to_string(),
-                    user_password: ui.get_deploy_user_password().to_string(),
-                    user_display_name: ui.get_deploy_user_display_name().to_string(),
-                    user_is_admin: ui.get_deploy_user_is_admin(),
-                    enable_autologon: ui.get_deploy_enable_autologon(),
+                    accounts: read_accounts_from_ui(&ui),
+                    autologon_account: ui.get_deploy_autologon_account().to_string(),
+                    administrator_password: ui.get_deploy_administrator_password().to_string(),
+                    obfuscate_passwords: ui.get_deploy_obfuscate_passwords(),
                     skip_oobe: ui.get_deploy_skip_oobe(),
                     skip_eula: ui.get_deploy_skip_eula(),
                     skip_network: ui.get_deploy_skip_network(),
-                    product_key: ui.get_deploy_product_key().to_string(),
+                    wifi_profiles: read_wifi_from_ui(&ui),
+                    product_key: product_key::normalize(&ui.get_deploy_product_key()),
                     organization: ui.get_deploy_organization().to_string(),
                     owner_name: ui.get_deploy_owner_name().to_string(),
-                    disable_telemetry: ui.get_deploy_disable_telemetry(),
-                    disable_location: ui.get_deploy_disable_location(),
-                    disable_ads: ui.get_deploy_disable_ads(),
-                    disable_suggested_apps: ui.get_deploy_disable_suggested_apps(),
-                    disable_bing_search: ui.get_deploy_disable_bing_search(),
-                    disable_smartscreen: ui.get_deploy_disable_smartscreen(),
-                    enable_rdp: ui.get_deploy_enable_rdp(),
-                    disable_uac: ui.get_deploy_disable_uac(),
-                    disable_defender: ui.get_deploy_disable_defender(),
-                    disable_firewall: ui.get_deploy_disable_firewall(),
-                    disable_vbs: ui.get_deploy_disable_vbs(),
-                    disable_bitlocker: ui.get_deploy_disable_bitlocker(),
-                    disable_fast_startup: ui.get_deploy_disable_fast_startup(),
-                    high_performance: ui.get_deploy_high_performance(),
-                    disable_system_restore: ui.get_deploy_disable_system_restore(),
-                    show_file_extensions: ui.get_deploy_show_file_extensions(),
-                    show_hidden_files: ui.get_deploy_show_hidden_files(),
-                    classic_context_menu: ui.get_deploy_classic_context_menu(),
-                    taskbar_search_mode: ui.get_deploy_taskbar_search_mode() as u8,
-                    hide_task_view: ui.get_deploy_hide_task_view(),
-                    hide_widgets: ui.get_deploy_hide_widgets(),
-                    taskbar_left_align: ui.get_deploy_taskbar_left_align(),
-                    disable_cortana: ui.get_deploy_disable_cortana(),
-                    disable_onedrive: ui.get_deploy_disable_onedrive(),
-                    disable_teams: ui.get_deploy_disable_teams(),
-                    disable_copilot: ui.get_deploy_disable_copilot(),
-                    disable_widgets_service: ui.get_deploy_disable_widgets_service(),
+                    enabled_tweaks,
+                    remove_appx: read_appx_from_ui(&ui),
+                    appx_removal_mode: appx::AppxRemovalMode::parse(&ui.get_deploy_appx_removal_mode()).unwrap_or_default(),
+                    driver_paths: read_drivers_from_ui(&ui),
+                    oem_info: read_oem_from_ui(&ui),
+                    specialize_commands: read_specialize_commands_from_ui(&ui),
                     join_domain: ui.get_deploy_join_domain(),
                     domain_name: ui.get_deploy_domain_name().to_string(),
                     domain_username: ui.get_deploy_domain_username().to_string(),
                     domain_password: ui.get_deploy_domain_password().to_string(),
+                    domain_ou: ui.get_deploy_domain_ou().to_string(),
                     workgroup: ui.get_deploy_workgroup().to_string(),
-                    prevent_device_encryption: ui.get_deploy_disable_bitlocker(), // Same as bitlocker toggle
+                    prevent_device_encryption,
+                    unmatched_machine: deploy::UnmatchedMachine::parse(&ui.get_deploy_unmatched_machine()).unwrap_or_default(),
+                    encrypt_secrets: ui.get_deploy_encrypt_secrets(),
                 };
 
                 // Validate
@@ -1892,16 +2174,74 @@ fn main() -> Result<(), slint::PlatformError> {
                     edition_name
                 };
 
-                let boot_mode_str: String = ui.get_deploy_boot_mode().to_string();
-                let config = deploy::DeployConfig {
+                let mut config = deploy::DeployConfig {
                     edition: edition_name,
-                    boot_mode: if boot_mode_str == "BIOS" { deploy::BootMode::BIOS } else { deploy::BootMode::UEFI },
+                    boot_mode: boot_mode_from_ui(&ui),
                     computer_name: ui.get_deploy_computer_name().to_string(),
-                    user_name: ui.get_deploy_user_name().to_string(),
+                    timezone: ui.get_deploy_timezone().to_string(),
+                    ui_language: ui.get_deploy_ui_language().to_string(),
+                    input_locale: ui.get_deploy_input_locale().to_string(),
+                    system_locale: ui.get_deploy_system_locale().to_string(),
+                    user_locale: ui.get_deploy_user_locale().to_string(),
+                    accounts: read_accounts_from_ui(&ui),
+                    autologon_account: ui.get_deploy_autologon_account().to_string(),
+                    administrator_password: ui.get_deploy_administrator_password().to_string(),
+                    obfuscate_passwords: ui.get_deploy_obfuscate_passwords(),
+                    architecture: deploy::Architecture::parse(&ui.get_deploy_architecture()).unwrap_or_default(),
+                    multi_arch: ui.get_deploy_multi_arch(),
+                    partition_layout: read_partition_layout_from_ui(&ui),
+                    enabled_tweaks: read_tweaks_from_ui(&ui),
+                    wifi_profiles: read_wifi_from_ui(&ui),
+                    remove_appx: read_appx_from_ui(&ui),
+                    appx_removal_mode: appx::AppxRemovalMode::parse(&ui.get_deploy_appx_removal_mode()).unwrap_or_default(),
+                    driver_paths: read_drivers_from_ui(&ui),
+                    oem_info: read_oem_from_ui(&ui),
+                    specialize_commands: read_specialize_commands_from_ui(&ui),
                     ..deploy::DeployConfig::default()
                 };
 
+                // Show what a name template gives on this machine (the counter
+                // isn't used up — that only happens when deploying)
+                if machine::is_template(&config.computer_name) {
+                    match machine::resolve_for_this_machine(&config.computer_name) {
+                        Ok(resolved) => config.computer_name = resolved.name,
+                        Err(e) => {
+                            ui.set_status_text(format!("Computer name template can't be used: {}", e).into());
+                            return;
+                        }
+                    }
+                }
+
                 let xml = deploy::generate_autounattend(&config);
+
+                // Lint the XML against the editions currently in the dropdown
+                // (empty list = no image scanned yet, edition checks are skipped)
+                let edition_list = ui.get_deploy_edition_list();
+                let editions: Vec<deploy::WimEdition> = (0..edition_list.row_count())
+                    .filter_map(|i| edition_list.row_data(i).map(|name| deploy::WimEdition {
+                        index: (i + 1) as u32, // DISM uses 1-based index
+                        name: name.to_string(),
+                        size_bytes: 0,
+                    }))
+                    .collect();
+                let validation = deploy::validate_autounattend(&xml, &config, &editions);
+                for error in &validation.errors {
+                    println!("Deploy: XML error: {}", error);
+                }
+                for warning in &validation.warnings {
+                    println!("Deploy: XML warning: {}", warning);
+                }
+                let lint_summary = if validation.errors.is_empty() && validation.warnings.is_empty() {
+                    "no problems found".to_string()
+                } else {
+                    format!(
+                        "{} error(s), {} warning(s): {}",
+                        validation.errors.len(),
+                        validation.warnings.len(),
+                        validation.errors.first().or(validation.warnings.first()).map(|s| s.as_str()).unwrap_or("")
+                    )
+                };
+
                 // Save the preview XML next to the EXE (e.g., on the USB drive)
                 // instead of the temp folder. This way it's easy to find and stays
                 // with the deployment toolkit. Falls back to temp dir if we can't
@@ -1911,14 +2251,14 @@ fn main() -> Result<(), slint::PlatformError> {
                     .and_then(|p| p.parent().map(|d| d.to_path_buf()))
                     .unwrap_or_else(std::env::temp_dir)
                     .join("autounattend_preview.xml");
-                if let Ok(_) = std::fs::write(&preview_path, &xml) {
-                    ui.set_status_text(format!("XML preview saved to: {}", preview_path.display()).into());
+                if std::fs::write(&preview_path, &xml).is_ok() {
+                    ui.set_status_text(format!("XML preview saved to: {} — {}", preview_path.display(), lint_summary).into());
                     // Try to open it in Notepad so the user can review
                     let _ = std::process::Command::new("notepad.exe")
                         .arg(&preview_path)
                         .spawn();
                 } else {
-                    ui.set_status_text(format!("Generated {} bytes of XML", xml.len()).into());
+                    ui.set_status_text(format!("Generated {} bytes of XML — {}", xml.len(), lint_summary).into());
                 }
             }
         }
@@ -1935,66 +2275,61 @@ fn main() -> Result<(), slint::PlatformError> {
                     ui.set_status_text("Please enter a profile name".into());
                     return;
                 }
-                // Build config from all UI toggle/text states
+                // Build config from all UI toggle/text states. Every setting the
+                // Deploy page shows must be read here (and set again by
+                // apply_deploy_config_to_ui): one left at its default would be
+                // saved as an override of the base profile.
+                let enabled_tweaks = read_tweaks_from_ui(&ui);
+                let prevent_device_encryption = enabled_tweaks.iter().any(|id| id == "disable_bitlocker");
                 let config = deploy::DeployConfig {
+                    parent: parent_from_ui(&ui),
                     computer_name: ui.get_deploy_computer_name().to_string(),
                     timezone: ui.get_deploy_timezone().to_string(),
-                    language: ui.get_deploy_language().to_string(),
-                    user_name: ui.get_deploy_user_name().to_string(),
-                    user_password: ui.get_deploy_user_password().to_string(),
-                    user_display_name: ui.get_deploy_user_display_name().to_string(),
-                    user_is_admin: ui.get_deploy_user_is_admin(),
-                    enable_autologon: ui.get_deploy_enable_autologon(),
+                    ui_language: ui.get_deploy_ui_language().to_string(),
+                    input_locale: ui.get_deploy_input_locale().to_string(),
+                    system_locale: ui.get_deploy_system_locale().to_string(),
+                    user_locale: ui.get_deploy_user_locale().to_string(),
+                    boot_mode: boot_mode_from_ui(&ui),
+                    disk_id: disk_id_from_ui(&ui),
+                    accounts: read_accounts_from_ui(&ui),
+                    autologon_account: ui.get_deploy_autologon_account().to_string(),
+                    administrator_password: ui.get_deploy_administrator_password().to_string(),
+                    obfuscate_passwords: ui.get_deploy_obfuscate_passwords(),
                     skip_oobe: ui.get_deploy_skip_oobe(),
                     skip_eula: ui.get_deploy_skip_eula(),
                     skip_network: ui.get_deploy_skip_network(),
+                    wifi_profiles: read_wifi_from_ui(&ui),
                     bypass_win11: ui.get_deploy_bypass_win11(),
-                    disable_telemetry: ui.get_deploy_disable_telemetry(),
-                    disable_location: ui.get_deploy_disable_location(),
-                    disable_ads: ui.get_deploy_disable_ads(),
-                    disable_suggested_apps: ui.get_deploy_disable_suggested_apps(),
-                    disable_bing_search: ui.get_deploy_disable_bing_search(),
-                    disable_smartscreen: ui.get_deploy_disable_smartscreen(),
-                    enable_rdp: ui.get_deploy_enable_rdp(),
-                    disable_uac: ui.get_deploy_disable_uac(),
-                    disable_defender: ui.get_deploy_disable_defender(),
-                    disable_firewall: ui.get_deploy_disable_firewall(),
-                    disable_vbs: ui.get_deploy_disable_vbs(),
-                    disable_bitlocker: ui.get_deploy_disable_bitlocker(),
-                    disable_fast_startup: ui.get_deploy_disable_fast_startup(),
-                    high_performance: ui.get_deploy_high_performance(),
-                    disable_system_restore: ui.get_deploy_disable_system_restore(),
-                    show_file_extensions: ui.get_deploy_show_file_extensions(),
-                    show_hidden_files: ui.get_deploy_show_hidden_files(),
-                    classic_context_menu: ui.get_deploy_classic_context_menu(),
-                    taskbar_search_mode: ui.get_deploy_taskbar_search_mode() as u8,
-                    hide_task_view: ui.get_deploy_hide_task_view(),
-                    hide_widgets: ui.get_deploy_hide_widgets(),
-                    taskbar_left_align: ui.get_deploy_taskbar_left_align(),
-                    disable_cortana: ui.get_deploy_disable_cortana(),
-                    disable_onedrive: ui.get_deploy_disable_onedrive(),
-                    disable_teams: ui.get_deploy_disable_teams(),
-                    disable_copilot: ui.get_deploy_disable_copilot(),
-                    disable_widgets_service: ui.get_deploy_disable_widgets_service(),
+                    architecture: deploy::Architecture::parse(&ui.get_deploy_architecture()).unwrap_or_default(),
+                    multi_arch: ui.get_deploy_multi_arch(),
+                    partition_layout: read_partition_layout_from_ui(&ui),
+                    product_key: product_key::normalize(&ui.get_deploy_product_key()),
+                    organization: ui.get_deploy_organization().to_string(),
+                    owner_name: ui.get_deploy_owner_name().to_string(),
+                    enabled_tweaks,
+                    remove_appx: read_appx_from_ui(&ui),
+                    appx_removal_mode: appx::AppxRemovalMode::parse(&ui.get_deploy_appx_removal_mode()).unwrap_or_default(),
+                    driver_paths: read_drivers_from_ui(&ui),
+                    oem_info: read_oem_from_ui(&ui),
+                    specialize_commands: read_specialize_commands_from_ui(&ui),
                     join_domain: ui.get_deploy_join_domain(),
                     domain_name: ui.get_deploy_domain_name().to_string(),
                     domain_username: ui.get_deploy_domain_username().to_string(),
                     domain_password: ui.get_deploy_domain_password().to_string(),
+                    domain_ou: ui.get_deploy_domain_ou().to_string(),
                     workgroup: ui.get_deploy_workgroup().to_string(),
-                    prevent_device_encryption: ui.get_deploy_disable_bitlocker(),
+                    prevent_device_encryption,
+                    unmatched_machine: deploy::UnmatchedMachine::parse(&ui.get_deploy_unmatched_machine()).unwrap_or_default(),
+                    encrypt_secrets: ui.get_deploy_encrypt_secrets(),
                     ..deploy::DeployConfig::default()
                 };
 
                 match deploy::save_profile(&name_str, &config) {
                     Ok(()) => {
                         ui.set_status_text(format!("Profile '{}' saved", name_str).into());
-                        ui.set_deploy_active_profile(name_str.clone().into());
+                        ui.set_deploy_active_profile(deploy::profile_label(&name_str).into());
                         // Refresh the ComboBox dropdown list with all saved profiles
-                        let profiles = deploy::list_profiles();
-                        let model = std::rc::Rc::new(slint::VecModel::from(
-                            profiles.iter().map(|s| slint::SharedString::from(s.as_str())).collect::<Vec<_>>()
-                        ));
-                        ui.set_deploy_profile_list(model.into());
+                        set_profile_lists_in_ui(&ui);
                     }
                     Err(e) => {
                         ui.set_status_text(format!("Failed to save profile: {}", e).into());
@@ -2007,8 +2342,9 @@ fn main() -> Result<(), slint::PlatformError> {
     // Callback: Select profile from ComboBox — auto-loads the selected profile
     ui.on_deploy_select_profile({
         let ui = ui_handle.clone();
-        move |name| {
-            let name_str = name.to_string();
+        move |label| {
+            // Dropdown entries show the base profile too ("Customer A ← Base")
+            let name_str = deploy::profile_name_from_label(&label).to_string();
             println!("Deploy: Auto-loading profile '{}'", name_str);
             if let Some(ui) = ui.upgrade() {
                 if name_str.trim().is_empty() {
@@ -2018,57 +2354,9 @@ fn main() -> Result<(), slint::PlatformError> {
                 match deploy::load_profile(&name_str) {
                     Ok(config) => {
                         // Apply every saved setting back to the UI
-                        ui.set_deploy_computer_name(config.computer_name.into());
-                        ui.set_deploy_timezone(config.timezone.into());
-                        ui.set_deploy_language(config.language.into());
-                        ui.set_deploy_user_name(config.user_name.into());
-                        ui.set_deploy_user_password(config.user_password.into());
-                        ui.set_deploy_user_display_name(config.user_display_name.into());
-                        ui.set_deploy_user_is_admin(config.user_is_admin);
-                        ui.set_deploy_enable_autologon(config.enable_autologon);
-                        ui.set_deploy_skip_oobe(config.skip_oobe);
-                        ui.set_deploy_skip_eula(config.skip_eula);
-                        ui.set_deploy_skip_network(config.skip_network);
-                        ui.set_deploy_bypass_win11(config.bypass_win11);
-                        ui.set_deploy_disable_telemetry(config.disable_telemetry);
-                        ui.set_deploy_disable_location(config.disable_location);
-                        ui.set_deploy_disable_ads(config.disable_ads);
-                        ui.set_deploy_disable_suggested_apps(config.disable_suggested_apps);
-                        ui.set_deploy_disable_bing_search(config.disable_bing_search);
-                        ui.set_deploy_disable_smartscreen(config.disable_smartscreen);
-                        ui.set_deploy_enable_rdp(config.enable_rdp);
-                        ui.set_deploy_disable_uac(config.disable_uac);
-                        ui.set_deploy_disable_defender(config.disable_defender);
-                        ui.set_deploy_disable_firewall(config.disable_firewall);
-                        ui.set_deploy_disable_vbs(config.disable_vbs);
-                        ui.set_deploy_disable_bitlocker(config.disable_bitlocker);
-                        ui.set_deploy_disable_fast_startup(config.disable_fast_startup);
-                        ui.set_deploy_high_performance(config.high_performance);
-                        ui.set_deploy_disable_system_restore(config.disable_system_restore);
-                        ui.set_deploy_show_file_extensions(config.show_file_extensions);
-                        ui.set_deploy_show_hidden_files(config.show_hidden_files);
-                        ui.set_deploy_classic_context_menu(config.classic_context_menu);
-                        ui.set_deploy_taskbar_search_mode(config.taskbar_search_mode as i32);
-                        ui.set_deploy_hide_task_view(config.hide_task_view);
-                        ui.set_deploy_hide_widgets(config.hide_widgets);
-                        ui.set_deploy_taskbar_left_align(config.taskbar_left_align);
-                        ui.set_deploy_disable_cortana(config.disable_cortana);
-                        ui.set_deploy_disable_onedrive(config.disable_onedrive);
-                        ui.set_deploy_disable_teams(config.disable_teams);
-                        ui.set_deploy_disable_copilot(config.disable_copilot);
-                        ui.set_deploy_disable_widgets_service(config.disable_widgets_service);
-                        ui.set_deploy_join_domain(config.join_domain);
-                        ui.set_deploy_domain_name(config.domain_name.into());
-                        ui.set_deploy_domain_username(config.domain_username.into());
-                        ui.set_deploy_domain_password(config.domain_password.into());
-                        ui.set_deploy_workgroup(config.workgroup.into());
-                        let boot_str = match config.boot_mode {
-                            deploy::BootMode::UEFI => "UEFI",
-                            deploy::BootMode::BIOS => "BIOS",
-                        };
-                        ui.set_deploy_boot_mode(boot_str.into());
-
-                        ui.set_deploy_active_profile(name_str.clone().into());
+                        apply_deploy_config_to_ui(&ui, config);
+
+                        ui.set_deploy_active_profile(deploy::profile_label(&name_str).into());
                         ui.set_status_text(format!("Profile '{}' loaded", name_str).into());
                     }
                     Err(e) => {
@@ -2079,17 +2367,29 @@ fn main() -> Result<(), slint::PlatformError> {
         }
     });
 
-    // Callback: Import profile — opens file explorer to pick a .json profile from disk
+    // Callback: Import profile — opens file explorer to pick a .json profile
+    // or a legacy autounattend.xml from disk
     ui.on_deploy_import_profile({
         let ui = ui_handle.clone();
         move || {
             println!("Deploy: Import profile from file");
             if let Some(ui) = ui.upgrade() {
-                // Open file picker for .json profiles
+                // Open file picker for .json profiles / .xml answer files
                 if let Some(path) = deploy::pick_profile_file() {
-                    match deploy::load_profile_from_path(&path) {
-                        Ok(config) => {
-                            // Get the profile name from the filename (without .json)
+                    let is_xml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xml"));
+
+                    // Answer files go through the importer, which also reports
+                    // every setting it couldn't map onto a DeployConfig field
+                    let result = if is_xml {
+                        deploy::import_autounattend(&path)
+                            .map(|report| (report.config, report.unmapped))
+                    } else {
+                        deploy::load_profile_from_path(&path).map(|config| (config, Vec::new()))
+                    };
+
+                    match result {
+                        Ok((config, unmapped)) => {
+                            // Get the profile name from the filename (without extension)
                             let profile_name = path.file_stem()
                                 .map(|s| s.to_string_lossy().to_string())
                                 .unwrap_or_else(|| "Imported".to_string());
@@ -2098,65 +2398,27 @@ fn main() -> Result<(), slint::PlatformError> {
                             let _ = deploy::save_profile(&profile_name, &config);
 
                             // Apply all settings to the UI (same as select-profile)
-                            ui.set_deploy_computer_name(config.computer_name.into());
-                            ui.set_deploy_timezone(config.timezone.into());
-                            ui.set_deploy_language(config.language.into());
-                            ui.set_deploy_user_name(config.user_name.into());
-                            ui.set_deploy_user_password(config.user_password.into());
-                            ui.set_deploy_user_display_name(config.user_display_name.into());
-                            ui.set_deploy_user_is_admin(config.user_is_admin);
-                            ui.set_deploy_enable_autologon(config.enable_autologon);
-                            ui.set_deploy_skip_oobe(config.skip_oobe);
-                            ui.set_deploy_skip_eula(config.skip_eula);
-                            ui.set_deploy_skip_network(config.skip_network);
-                            ui.set_deploy_bypass_win11(config.bypass_win11);
-                            ui.set_deploy_disable_telemetry(config.disable_telemetry);
-                            ui.set_deploy_disable_location(config.disable_location);
-                            ui.set_deploy_disable_ads(config.disable_ads);
-                            ui.set_deploy_disable_suggested_apps(config.disable_suggested_apps);
-                            ui.set_deploy_disable_bing_search(config.disable_bing_search);
-                            ui.set_deploy_disable_smartscreen(config.disable_smartscreen);
-                            ui.set_deploy_enable_rdp(config.enable_rdp);
-                            ui.set_deploy_disable_uac(config.disable_uac);
-                            ui.set_deploy_disable_defender(config.disable_defender);
-                            ui.set_deploy_disable_firewall(config.disable_firewall);
-                            ui.set_deploy_disable_vbs(config.disable_vbs);
-                            ui.set_deploy_disable_bitlocker(config.disable_bitlocker);
-                            ui.set_deploy_disable_fast_startup(config.disable_fast_startup);
-                            ui.set_deploy_high_performance(config.high_performance);
-                            ui.set_deploy_disable_system_restore(config.disable_system_restore);
-                            ui.set_deploy_show_file_extensions(config.show_file_extensions);
-                            ui.set_deploy_show_hidden_files(config.show_hidden_files);
-                            ui.set_deploy_classic_context_menu(config.classic_context_menu);
-                            ui.set_deploy_taskbar_search_mode(config.taskbar_search_mode as i32);
-                            ui.set_deploy_hide_task_view(config.hide_task_view);
-                            ui.set_deploy_hide_widgets(config.hide_widgets);
-                            ui.set_deploy_taskbar_left_align(config.taskbar_left_align);
-                            ui.set_deploy_disable_cortana(config.disable_cortana);
-                            ui.set_deploy_disable_onedrive(config.disable_onedrive);
-                            ui.set_deploy_disable_teams(config.disable_teams);
-                            ui.set_deploy_disable_copilot(config.disable_copilot);
-                            ui.set_deploy_disable_widgets_service(config.disable_widgets_service);
-                            ui.set_deploy_join_domain(config.join_domain);
-                            ui.set_deploy_domain_name(config.domain_name.into());
-                            ui.set_deploy_domain_username(config.domain_username.into());
-                            ui.set_deploy_domain_password(config.domain_password.into());
-                            ui.set_deploy_workgroup(config.workgroup.into());
-                            let boot_str = match config.boot_mode {
-                                deploy::BootMode::UEFI => "UEFI",
-                                deploy::BootMode::BIOS => "BIOS",
-                            };
-                            ui.set_deploy_boot_mode(boot_str.into());
+                            apply_deploy_config_to_ui(&ui, config);
 
                             // Refresh the dropdown and set active profile
-                            ui.set_deploy_active_profile(profile_name.clone().into());
-                            let profiles = deploy::list_profiles();
-                            let model = std::rc::Rc::new(slint::VecModel::from(
-                                profiles.iter().map(|s| slint::SharedString::from(s.as_str())).collect::<Vec<_>>()
-                            ));
-                            ui.set_deploy_profile_list(model.into());
-
-                            ui.set_status_text(format!("Imported profile '{}'", profile_name).into());
+                            set_profile_lists_in_ui(&ui);
+                            ui.set_deploy_active_profile(deploy::profile_label(&profile_name).into());
+
+                            if unmapped.is_empty() {
+                                ui.set_status_text(format!("Imported profile '{}'", profile_name).into());
+                            } else {
+                                // Print the full list to the console so nothing is silently lost
+                                println!("Deploy: {} setting(s) from '{}' could not be imported:", unmapped.len(), path.display());
+                                for item in &unmapped {
+                                    println!("  - {}", item);
+                                }
+                                ui.set_status_text(format!(
+                                    "Imported profile '{}' — {} setting(s) not mapped (see console): {}",
+                                    profile_name,
+                                    unmapped.len(),
+                                    unmapped.first().map(|s| s.as_str()).unwrap_or("")
+                                ).into());
+                            }
                         }
                         Err(e) => {
                             ui.set_status_text(format!("Failed to import profile: {}", e).into());
@@ -2171,8 +2433,8 @@ fn main() -> Result<(), slint::PlatformError> {
     // Callback: Delete profile — removes the selected profile and refreshes dropdown
     ui.on_deploy_delete_profile({
         let ui = ui_handle.clone();
-        move |name| {
-            let name_str = name.to_string();
+        move |label| {
+            let name_str = deploy::profile_name_from_label(&label).to_string();
             println!("Deploy: Delete profile '{}'", name_str);
             if let Some(ui) = ui.upgrade() {
                 if name_str.trim().is_empty() {
@@ -2184,11 +2446,7 @@ fn main() -> Result<(), slint::PlatformError> {
                         ui.set_status_text(format!("Profile '{}' deleted", name_str).into());
                         // Clear active profile and refresh dropdown
                         ui.set_deploy_active_profile("".into());
-                        let profiles = deploy::list_profiles();
-                        let model = std::rc::Rc::new(slint::VecModel::from(
-                            profiles.iter().map(|s| slint::SharedString::from(s.as_str())).collect::<Vec<_>>()
-                        ));
-                        ui.set_deploy_profile_list(model.into());
+                        set_profile_lists_in_ui(&ui);
                     }
                     Err(e) => {
                         ui.set_status_text(format!("Failed to delete profile: {}", e).into());
@@ -2204,11 +2462,56 @@ fn main() -> Result<(), slint::PlatformError> {
         move || {
             println!("Deploy: Refresh profile list");
             if let Some(ui) = ui.upgrade() {
-                let profiles = deploy::list_profiles();
-                let model = std::rc::Rc::new(slint::VecModel::from(
-                    profiles.iter().map(|s| slint::SharedString::from(s.as_str())).collect::<Vec<_>>()
-                ));
-                ui.set_deploy_profile_list(model.into());
+                set_profile_lists_in_ui(&ui);
+            }
+        }
+    });
+
+    // Callback: Compare profiles — writes a diff of the selected profile
+    // against another (known-good) profile and opens it in Notepad
+    ui.on_deploy_diff_profiles({
+        let ui = ui_handle.clone();
+        move |active_label, compare_with| {
+            let active = deploy::profile_name_from_label(&active_label).to_string();
+            let compare_with = compare_with.to_string();
+            println!("Deploy: Diff profile '{}' against '{}'", active, compare_with);
+            if let Some(ui) = ui.upgrade() {
+                if active.is_empty() || compare_with.is_empty() {
+                    ui.set_status_text("Select a profile and a profile to compare it with".into());
+                    return;
+                }
+                let loaded = deploy::load_profile(&compare_with).and_then(|old| Ok((old, deploy::load_profile(&active)?)));
+                let (old, new) = match loaded {
+                    Ok(pair) => pair,
+                    Err(e) => {
+                        ui.set_status_text(format!("Failed to compare profiles: {}", e).into());
+                        return;
+                    }
+                };
+
+                let diff = deploy::diff_profiles(&old, &new);
+                let report = diff.to_text(&compare_with, &active);
+                print!("{}", report);
+                if diff.is_empty() {
+                    ui.set_status_text(format!("'{}' and '{}' are the same", active, compare_with).into());
+                    return;
+                }
+
+                // Save the report next to the EXE (like the XML preview) and open it
+                let diff_path = std::env::current_exe()
+                    .ok()
+                    .and_then(|p| p.parent().map(|d| d.to_path_buf()))
+                    .unwrap_or_else(std::env::temp_dir)
+                    .join("profile_diff.txt");
+                let summary = format!("{} field(s) differ from '{}'", diff.changes.len(), compare_with);
+                if std::fs::write(&diff_path, &report).is_ok() {
+                    ui.set_status_text(format!("{} — saved to {}", summary, diff_path.display()).into());
+                    let _ = std::process::Command::new("notepad.exe")
+                        .arg(&diff_path)
+                        .spawn();
+                } else {
+                    ui.set_status_text(format!("{} (see console)", summary).into());
+                }
             }
         }
     });
@@ -2234,10 +2537,12 @@ fn main() -> Result<(), slint::PlatformError> {
                         println!("Deploy: Added FirstLogon script: {:?}", path);
                         // Refresh the script list in the UI
                         if let Some(ui) = ui.upgrade() {
-                            let scripts = deploy::list_scripts("FirstLogon");
-                            ui.set_deploy_firstlogon_scripts(scripts.join(";").into());
-                            ui.set_status_text(format!("Added script: {}",
-                                path.file_name().unwrap_or_default().to_string_lossy()).into());
+                            let warnings = set_firstlogon_scripts_in_ui(&ui);
+                            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                            match warnings.iter().find(|w| w.contains(&name)) {
+                                Some(warning) => ui.set_status_text(format!("Added script: {} — {}", name, warning).into()),
+                                None => ui.set_status_text(format!("Added script: {}", name).into()),
+                            }
                         }
                     }
                     Err(e) => {
@@ -2259,8 +2564,7 @@ fn main() -> Result<(), slint::PlatformError> {
             match deploy::remove_script("FirstLogon", &filename) {
                 Ok(()) => {
                     if let Some(ui) = ui.upgrade() {
-                        let scripts = deploy::list_scripts("FirstLogon");
-                        ui.set_deploy_firstlogon_scripts(scripts.join(";").into());
+                        set_firstlogon_scripts_in_ui(&ui);
                         ui.set_status_text(format!("Removed script: {}", filename).into());
                     }
                 }
@@ -2279,8 +2583,10 @@ fn main() -> Result<(), slint::PlatformError> {
         move || {
             println!("Deploy: Refresh script list");
             if let Some(ui) = ui.upgrade() {
-                let firstlogon = deploy::list_scripts("FirstLogon");
-                ui.set_deploy_firstlogon_scripts(firstlogon.join(";").into());
+                let warnings = set_firstlogon_scripts_in_ui(&ui);
+                if let Some(warning) = warnings.first() {
+                    ui.set_status_text(format!("Script tags: {}", warning).into());
+                }
             }
         }
     });
@@ -2309,6 +2615,7 @@ fn main() -> Result<(), slint::PlatformError> {
                 ui.set_deploy_build_progress(0);
                 ui.set_deploy_build_status("Starting Normal Install...".into());
             }
+            let driver_paths = ui.upgrade().map(|ui| read_drivers_from_ui(&ui)).unwrap_or_default();
 
             // Run normal install in a background thread
             let ui_for_progress = ui.clone();
@@ -2319,9 +2626,10 @@ fn main() -> Result<(), slint::PlatformError> {
                 // Step 1: Find setup.exe on available drives
                 // Step 2: Validate setup.exe
                 // Step 3: Launch setup.exe interactively (wait for user to finish)
-                // Step 4: Copy post-install scripts to target drive
-                // Step 5: Reboot
-                let result = deploy::normal_execute(move |progress, status| {
+                // Step 4: Add driver folders to the installed Windows (DISM)
+                // Step 5: Copy post-install scripts to target drive
+                // Step 6: Reboot
+                let result = deploy::normal_execute(&driver_paths, move |progress, status| {
                     let ui_p = ui_for_progress.clone();
                     let s = status.to_string();
                     let _ = slint::invoke_from_event_loop(move || {
@@ -2357,20 +2665,26 @@ fn main() -> Result<(), slint::PlatformError> {
     // This starts the event loop - the window stays open and responds to clicks.
     // The program stays here until the user closes the window.
 
+    // Show the default account(s) on startup
+    set_accounts_in_ui(&ui, &deploy::DeployConfig::default().accounts);
+    set_tweaks_in_ui(&ui, &deploy::DeployConfig::default().enabled_tweaks);
+    let presets: Vec<slint::SharedString> = appx::PRESETS.iter().map(|p| p.name.into()).collect();
+    ui.set_deploy_appx_preset(presets[0].clone());
+    ui.set_deploy_appx_presets(std::rc::Rc::new(slint::VecModel::from(presets)).into());
+    let string_model = |items: Vec<String>| {
+        let items: Vec<slint::SharedString> = items.into_iter().map(Into::into).collect();
+        slint::ModelRc::from(std::rc::Rc::new(slint::VecModel::from(items)))
+    };
+    ui.set_deploy_ui_languages(string_model(locale::LOCALES.iter().filter(|l| l.language_pack).map(|l| l.name.to_string()).collect()));
+    ui.set_deploy_locales(string_model(locale::LOCALES.iter().map(|l| l.name.to_string()).collect()));
+    ui.set_deploy_keyboards(string_model(locale::keyboard_choices()));
+    ui.set_deploy_timezones(string_model(timezone::TIME_ZONES.iter().map(|z| z.id.to_string()).collect()));
+
     // Load the saved profile list into the ComboBox dropdown on startup
-    {
-        let profiles = deploy::list_profiles();
-        let model = std::rc::Rc::new(slint::VecModel::from(
-            profiles.iter().map(|s| slint::SharedString::from(s.as_str())).collect::<Vec<_>>()
-        ));
-        ui.set_deploy_profile_list(model.into());
-    }
+    set_profile_lists_in_ui(&ui);
 
     // Load the FirstLogon script list on startup so the UI shows any previously added scripts
-    {
-        let firstlogon = deploy::list_scripts("FirstLogon");
-        ui.set_deploy_firstlogon_scripts(firstlogon.join(";").into());
-    }
+    set_firstlogon_scripts_in_ui(&ui);
 
     // Check for saved product keys on startup (from a previous session)
     // If saved_keys.json exists next to the EXE, show the saved info in the UI.
@@ -2487,6 +2801,341 @@ fn detect_winpe_environment() -> bool {
     false
 }
 
+/// Show the offline Windows drives in the Backup tab, keeping the selection if it's still there
+fn set_offline_drives_in_ui(ui: &MainWindow, drives: Vec<String>) {
+    let selected = ui.get_backup_offline_drive().to_string();
+    if !drives.contains(&selected) {
+        ui.set_backup_offline_drive(drives.first().cloned().unwrap_or_default().into());
+    }
+    let drives: Vec<slint::SharedString> = drives.into_iter().map(Into::into).collect();
+    ui.set_backup_offline_drives(std::rc::Rc::new(slint::VecModel::from(drives)).into());
+}
+
+/// Apply every DeployConfig setting to the Deploy tab UI.
+/// Used when a profile is selected or imported.
+fn apply_deploy_config_to_ui(ui: &MainWindow, config: deploy::DeployConfig) {
+    let parent = if config.parent.is_empty() { NO_PARENT_PROFILE.to_string() } else { config.parent };
+    ui.set_deploy_parent_profile(parent.into());
+    ui.set_deploy_computer_name(config.computer_name.into());
+    ui.set_deploy_timezone(config.timezone.into());
+    ui.set_deploy_ui_language(config.ui_language.into());
+    ui.set_deploy_input_locale(config.input_locale.into());
+    ui.set_deploy_system_locale(config.system_locale.into());
+    ui.set_deploy_user_locale(config.user_locale.into());
+    set_accounts_in_ui(ui, &config.accounts);
+    ui.set_deploy_autologon_account(config.autologon_account.into());
+    ui.set_deploy_administrator_password(config.administrator_password.into());
+    ui.set_deploy_obfuscate_passwords(config.obfuscate_passwords);
+    ui.set_deploy_encrypt_secrets(config.encrypt_secrets);
+    ui.set_deploy_product_key_error(product_key_error(&config.product_key).into());
+    ui.set_deploy_product_key(config.product_key.into());
+    ui.set_deploy_organization(config.organization.into());
+    ui.set_deploy_owner_name(config.owner_name.into());
+    ui.set_deploy_skip_oobe(config.skip_oobe);
+    ui.set_deploy_skip_eula(config.skip_eula);
+    ui.set_deploy_skip_network(config.skip_network);
+    set_wifi_in_ui(ui, &config.wifi_profiles);
+    ui.set_deploy_bypass_win11(config.bypass_win11);
+    set_tweaks_in_ui(ui, &config.enabled_tweaks);
+    ui.set_deploy_remove_appx(config.remove_appx.join("\n").into());
+    ui.set_deploy_driver_paths(config.driver_paths.join("\n").into());
+    ui.set_deploy_oem_manufacturer(config.oem_info.manufacturer.as_str().into());
+    ui.set_deploy_oem_model(config.oem_info.model.as_str().into());
+    ui.set_deploy_oem_support_phone(config.oem_info.support_phone.as_str().into());
+    ui.set_deploy_oem_support_url(config.oem_info.support_url.as_str().into());
+    ui.set_deploy_oem_support_hours(config.oem_info.support_hours.as_str().into());
+    ui.set_deploy_oem_logo_path(config.oem_info.logo_path.as_str().into());
+    set_specialize_commands_in_ui(ui, &config.specialize_commands);
+    ui.set_deploy_appx_removal_mode(config.appx_removal_mode.as_str().into());
+    ui.set_deploy_join_domain(config.join_domain);
+    ui.set_deploy_domain_name(config.domain_name.into());
+    ui.set_deploy_domain_username(config.domain_username.into());
+    ui.set_deploy_domain_password(config.domain_password.into());
+    ui.set_deploy_domain_ou(config.domain_ou.into());
+    ui.set_deploy_unmatched_machine(config.unmatched_machine.as_str().into());
+    ui.set_deploy_workgroup(config.workgroup.into());
+    let boot_str = match config.boot_mode {
+        deploy::BootMode::UEFI => "UEFI",
+        deploy::BootMode::BIOS => "BIOS",
+    };
+    ui.set_deploy_boot_mode(boot_str.into());
+    set_disk_id_in_ui(ui, config.disk_id);
+    ui.set_deploy_architecture(config.architecture.as_str().into());
+    ui.set_deploy_multi_arch(config.multi_arch);
+    let layout = config.partition_layout;
+    ui.set_deploy_efi_size(layout.efi_size_mb.to_string().into());
+    // 0 = "not set" — shown as an empty field
+    let size_text = |mb: u64| if mb == 0 { String::new() } else { mb.to_string() };
+    ui.set_deploy_os_size(size_text(layout.os_size_mb).into());
+    ui.set_deploy_recovery_size(size_text(layout.recovery_size_mb).into());
+    ui.set_deploy_data_partition(layout.data_partition);
+    ui.set_deploy_data_label(layout.data_label.into());
+    ui.set_deploy_data_letter(layout.data_letter.into());
+}
+
+/// `--diff-profiles <A> <B>`: print how profile B differs from profile A.
+/// A and B are saved profile names, or paths to a profile .json / answer file .xml.
+/// Exit code: 0 = same, 1 = different, 2 = error (like `diff`).
+fn run_diff_profiles_cli(args: &[String]) -> i32 {
+    let [old_name, new_name, ..] = args else {
+        eprintln!("Usage: masterbooter --diff-profiles <profile A> <profile B>");
+        return 2;
+    };
+    let load = |name: &String| deploy::load_profile_for_diff(name).map_err(|e| eprintln!("{}: {}", name, e));
+    let (Ok(old), Ok(new)) = (load(old_name), load(new_name)) else {
+        return 2;
+    };
+
+    let diff = deploy::diff_profiles(&old, &new);
+    print!("{}", diff.to_text(old_name, new_name));
+    if diff.is_empty() { 0 } else { 1 }
+}
+
+/// "Based on" choice for a standalone profile
+const NO_PARENT_PROFILE: &str = "(none)";
+
+/// Refresh the profile dropdown (each entry shows the base profile it
+/// inherits from) and the "Based on" choices.
+fn set_profile_lists_in_ui(ui: &MainWindow) {
+    let labels: Vec<slint::SharedString> = deploy::list_profile_labels().iter().map(|s| s.as_str().into()).collect();
+    ui.set_deploy_profile_list(std::rc::Rc::new(slint::VecModel::from(labels)).into());
+
+    let names: Vec<slint::SharedString> = deploy::list_profiles().iter().map(|s| s.as_str().into()).collect();
+    let mut choices: Vec<slint::SharedString> = vec![NO_PARENT_PROFILE.into()];
+    choices.extend(names.iter().cloned());
+    ui.set_deploy_parent_choices(std::rc::Rc::new(slint::VecModel::from(choices)).into());
+    ui.set_deploy_profile_names(std::rc::Rc::new(slint::VecModel::from(names)).into());
+}
+
+/// The "Based on" profile chosen on the Deploy page (empty = standalone)
+fn parent_from_ui(ui: &MainWindow) -> String {
+    let parent = ui.get_deploy_parent_profile().to_string();
+    if parent == NO_PARENT_PROFILE { String::new() } else { parent }
+}
+
+/// Format problem shown under the product key (empty = OK or no key)
+fn product_key_error(key: &str) -> String {
+    match product_key::validate(key) {
+        Err(e) if !key.trim().is_empty() => format!("Product key {}", e),
+        _ => String::new(),
+    }
+}
+
+/// Boot mode chosen on the Deploy page
+fn boot_mode_from_ui(ui: &MainWindow) -> deploy::BootMode {
+    if ui.get_deploy_boot_mode() == "BIOS" { deploy::BootMode::BIOS } else { deploy::BootMode::UEFI }
+}
+
+/// Target disk chosen on the Deploy page (-1 = let Windows choose, or none selected)
+fn disk_id_from_ui(ui: &MainWindow) -> i32 {
+    if ui.get_deploy_let_windows_choose() {
+        return -1;
+    }
+    // The display string starts with "Disk N:" — "Disk 0: Samsung SSD (500 GB, GPT)" → 0
+    let selected_disk = ui.get_deploy_selected_disk_name().to_string();
+    match selected_disk.strip_prefix("Disk ") {
+        Some(rest) => rest.split(':').next().unwrap_or("").trim().parse::<i32>().unwrap_or(-1),
+        None => -1,
+    }
+}
+
+/// Select a profile's target disk on the Deploy page. Disks that haven't
+/// been detected yet are shown by number only ("Disk 1").
+fn set_disk_id_in_ui(ui: &MainWindow, disk_id: i32) {
+    if disk_id < 0 {
+        ui.set_deploy_selected_disk_name("".into());
+        return;
+    }
+    let prefix = format!("Disk {}:", disk_id);
+    let disks = ui.get_deploy_disk_list();
+    let name = (0..disks.row_count())
+        .filter_map(|i| disks.row_data(i))
+        .find(|name| name.starts_with(&prefix))
+        .unwrap_or_else(|| format!("Disk {}", disk_id).into());
+    ui.set_deploy_let_windows_choose(false);
+    ui.set_deploy_selected_disk_name(name);
+}
+
+/// Show the FirstLogon scripts in run order with their tags,
+/// one per line (e.g. "20 · setup.ps1 (online, reboot)").
+///
+/// # Returns
+/// Warnings about tags that were ignored
+fn set_firstlogon_scripts_in_ui(ui: &MainWindow) -> Vec<String> {
+    let (plan, warnings) = deploy::firstlogon_plan();
+    let labels: Vec<String> = plan.iter().map(|s| s.label()).collect();
+    ui.set_deploy_firstlogon_scripts(labels.join("\n").into());
+    warnings
+}
+
+/// Replace the account rows on the Deploy page.
+/// Uses a VecModel so edits made in the rows are written back into it.
+fn set_accounts_in_ui(ui: &MainWindow, accounts: &[deploy::UserAccount]) {
+    let rows: Vec<DeployAccount> = accounts
+        .iter()
+        .map(|a| DeployAccount {
+            name: a.name.as_str().into(),
+            display_name: a.display_name.as_str().into(),
+            password: a.password.as_str().into(),
+            group: a.group.as_str().into(),
+            never_expires: a.password_never_expires,
+        })
+        .collect();
+    ui.set_deploy_accounts(std::rc::Rc::new(slint::VecModel::from(rows)).into());
+}
+
+/// Read the account rows from the Deploy page.
+fn read_accounts_from_ui(ui: &MainWindow) -> Vec<deploy::UserAccount> {
+    ui.get_deploy_accounts()
+        .iter()
+        .map(|row| deploy::UserAccount {
+            name: row.name.trim().to_string(),
+            display_name: row.display_name.to_string(),
+            password: row.password.to_string(),
+            group: row.group.to_string(),
+            password_never_expires: row.never_expires,
+        })
+        .collect()
+}
+
+/// Replace the Wi-Fi rows on the Deploy page.
+fn set_wifi_in_ui(ui: &MainWindow, networks: &[wifi::WifiProfile]) {
+    let rows: Vec<DeployWifi> = networks
+        .iter()
+        .map(|n| DeployWifi {
+            ssid: n.ssid.as_str().into(),
+            auth: n.auth.as_str().into(),
+            key: n.key.as_str().into(),
+            hidden: n.hidden,
+        })
+        .collect();
+    ui.set_deploy_wifi_profiles(std::rc::Rc::new(slint::VecModel::from(rows)).into());
+}
+
+/// Read the Wi-Fi rows from the Deploy page. The SSID is kept as typed —
+/// SSIDs can start or end with spaces.
+fn read_wifi_from_ui(ui: &MainWindow) -> Vec<wifi::WifiProfile> {
+    ui.get_deploy_wifi_profiles()
+        .iter()
+        .map(|row| wifi::WifiProfile {
+            ssid: row.ssid.to_string(),
+            auth: wifi::WifiAuth::parse(&row.auth).unwrap_or_default(),
+            key: row.key.to_string(),
+            hidden: row.hidden,
+        })
+        .collect()
+}
+
+/// Replace the tweak rows on the Deploy page: every catalog tweak (built-in
+/// plus tweaks/*.toml), then enabled ids the catalog doesn't know, so they
+/// survive a save instead of silently disappearing.
+fn set_tweaks_in_ui(ui: &MainWindow, enabled: &[String]) {
+    let catalog = tweaks::load_catalog();
+    let mut rows: Vec<DeployTweak> = Vec::new();
+    let mut previous_category = String::new();
+    for tweak in &catalog.tweaks {
+        rows.push(DeployTweak {
+            id: tweak.id.as_str().into(),
+            category: tweak.category.as_str().into(),
+            name: tweak.name.as_str().into(),
+            description: tweak.description.as_str().into(),
+            enabled: enabled.contains(&tweak.id),
+            first_in_category: tweak.category != previous_category,
+        });
+        previous_category = tweak.category.clone();
+    }
+    for (i, id) in enabled.iter().filter(|id| catalog.get(id).is_none()).enumerate() {
+        rows.push(DeployTweak {
+            id: id.as_str().into(),
+            category: "Not in the catalog".into(),
+            name: id.as_str().into(),
+            description: "Skipped when deploying — the tweak file isn't on this drive".into(),
+            enabled: true,
+            first_in_category: i == 0,
+        });
+    }
+    ui.set_deploy_tweaks(std::rc::Rc::new(slint::VecModel::from(rows)).into());
+}
+
+/// Ids of the tweaks ticked on the Deploy page.
+fn read_tweaks_from_ui(ui: &MainWindow) -> Vec<String> {
+    ui.get_deploy_tweaks()
+        .iter()
+        .filter(|row| row.enabled)
+        .map(|row| row.id.to_string())
+        .collect()
+}
+
+/// Appx package families from the removal list (one per line; commas work too).
+fn read_appx_from_ui(ui: &MainWindow) -> Vec<String> {
+    ui.get_deploy_remove_appx()
+        .split(['\n', ','])
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Read the OEM information fields from the UI
+fn read_oem_from_ui(ui: &MainWindow) -> oem::OemInfo {
+    oem::OemInfo {
+        manufacturer: ui.get_deploy_oem_manufacturer().trim().to_string(),
+        model: ui.get_deploy_oem_model().trim().to_string(),
+        support_phone: ui.get_deploy_oem_support_phone().trim().to_string(),
+        support_url: ui.get_deploy_oem_support_url().trim().to_string(),
+        support_hours: ui.get_deploy_oem_support_hours().trim().to_string(),
+        logo_path: ui.get_deploy_oem_logo_path().trim().to_string(),
+    }
+}
+
+/// Replace the specialize command rows on the Deploy page.
+fn set_specialize_commands_in_ui(ui: &MainWindow, commands: &[deploy::SpecializeCommand]) {
+    let rows: Vec<DeploySpecializeCommand> = commands
+        .iter()
+        .map(|c| DeploySpecializeCommand {
+            order: c.order.to_string().into(),
+            path: c.path.as_str().into(),
+            description: c.description.as_str().into(),
+        })
+        .collect();
+    ui.set_deploy_specialize_commands(std::rc::Rc::new(slint::VecModel::from(rows)).into());
+}
+
+/// Read the specialize command rows from the Deploy page. An order that
+/// isn't a number becomes 0, which the answer file linter reports.
+fn read_specialize_commands_from_ui(ui: &MainWindow) -> Vec<deploy::SpecializeCommand> {
+    ui.get_deploy_specialize_commands()
+        .iter()
+        .map(|row| deploy::SpecializeCommand {
+            order: row.order.trim().parse().unwrap_or(0),
+            path: row.path.trim().to_string(),
+            description: row.description.trim().to_string(),
+        })
+        .collect()
+}
+
+/// Read the driver folder list (one folder per line) from the UI
+fn read_drivers_from_ui(ui: &MainWindow) -> Vec<String> {
+    ui.get_deploy_driver_paths()
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Read the partition layout fields from the UI.
+/// Empty or non-numeric sizes count as 0 (= fill disk / no recovery partition).
+fn read_partition_layout_from_ui(ui: &MainWindow) -> deploy::PartitionLayout {
+    let size = |text: slint::SharedString| text.trim().parse::<u64>().unwrap_or(0);
+    deploy::PartitionLayout {
+        efi_size_mb: size(ui.get_deploy_efi_size()),
+        os_size_mb: size(ui.get_deploy_os_size()),
+        recovery_size_mb: size(ui.get_deploy_recovery_size()),
+        data_partition: ui.get_deploy_data_partition(),
+        data_label: ui.get_deploy_data_label().to_string(),
+        data_letter: ui.get_deploy_data_letter().to_string(),
+    }
+}
+
 /// Update the PE tool status dots and summary in the UI.
 /// Scans the pe_tools folder to see which tools are downloaded (present on disk),
 /// then sets each pe-tool-*-present property and updates the pe-tools-summary text.
@@ -2494,7 +3143,7 @@ fn detect_winpe_environment() -> bool {
 /// # Arguments
 /// * `ui` - Reference to the main window
 /// * `enabled_count` - Number of enabled tools (used for summary text).
-///                     Pass 0 to auto-count from discovered tools.
+///   Pass 0 to auto-count from discovered tools.
 fn update_pe_tool_status(ui: &MainWindow, enabled_count: usize) {
     // Discover all PE tools on disk
     let discovered = tools::pe_tools::discover_pe_tools();
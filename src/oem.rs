@@ -0,0 +1,171 @@
+// ============================================
+// MasterBooter - oem.rs
+// ============================================
+// This module holds the OEM information shown under Settings > System >
+// About (and the old System control panel): manufacturer, model, support
+// phone/URL/hours and a logo.
+//
+// How it gets onto the installed Windows:
+//
+//   1. The answer file's specialize pass writes the text fields into
+//      Microsoft-Windows-Shell-Setup/OEMInformation, before any user logs on
+//   2. The logo is a file, so execute() copies it to
+//      <target>\Windows\System32\oobe\info\MasterBooterLogo.bmp after
+//      setup.exe has applied the image (copy_logo_to_target), and the
+//      answer file points <Logo> at that path (LOGO_PATH)
+//
+// Windows shows the logo at 120x120 pixels and only reads .bmp files.
+// ============================================
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the logo ends up on the installed Windows (the answer file's <Logo>)
+pub const LOGO_PATH: &str = r"C:\Windows\System32\oobe\info\MasterBooterLogo.bmp";
+/// Same file, relative to the target drive while it's mounted in WinPE
+const LOGO_TARGET: &str = r"Windows\System32\oobe\info\MasterBooterLogo.bmp";
+/// Logo size Windows displays, in pixels
+const LOGO_SIZE: u32 = 120;
+
+/// OEM details for System > About. Empty fields are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OemInfo {
+    /// e.g. "Contoso IT"
+    pub manufacturer: String,
+    /// e.g. "Office Desktop 2026"
+    pub model: String,
+    /// e.g. "+1 555 0100"
+    pub support_phone: String,
+    /// e.g. "https://support.contoso.com"
+    pub support_url: String,
+    /// e.g. "Mon-Fri 8:00-17:00"
+    pub support_hours: String,
+    /// .bmp file on the technician's side (relative = next to the EXE)
+    pub logo_path: String,
+}
+
+impl OemInfo {
+    /// No OEM information at all (nothing is written to the answer file)
+    pub fn is_empty(&self) -> bool {
+        *self == OemInfo::default()
+    }
+
+    /// Check the fields and the logo file.
+    ///
+    /// # Returns
+    /// * `Ok(warnings)` — usable; warnings for things Windows shows oddly
+    /// * `Err(String)` — the logo can't be used
+    pub fn validate(&self) -> Result<Vec<String>, String> {
+        let mut warnings = Vec::new();
+        let url = self.support_url.trim().to_ascii_lowercase();
+        if !url.is_empty() && !url.starts_with("http://") && !url.starts_with("https://") {
+            warnings.push(format!(
+                "support URL \"{}\" doesn't start with http:// or https:// (Windows won't make it a link)",
+                self.support_url
+            ));
+        }
+        if self.support_url.is_empty() && self.support_phone.is_empty() && !self.support_hours.is_empty() {
+            warnings.push("support hours are only shown together with a support phone or URL".to_string());
+        }
+
+        if !self.logo_path.is_empty() {
+            let path = self.resolved_logo();
+            let data = fs::read(&path).map_err(|e| format!("logo {}: {}", path.display(), e))?;
+            let (width, height) = bmp_size(&data).ok_or_else(|| format!("logo {} is not a .bmp file", path.display()))?;
+            if (width, height) != (LOGO_SIZE, LOGO_SIZE) {
+                warnings.push(format!(
+                    "logo is {}x{} pixels — Windows shows it at {}x{} and may stretch it",
+                    width, height, LOGO_SIZE, LOGO_SIZE
+                ));
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// The logo file, with relative paths resolved against the EXE's folder
+    fn resolved_logo(&self) -> PathBuf {
+        let path = PathBuf::from(&self.logo_path);
+        if path.is_absolute() || self.logo_path.get(1..2) == Some(":") {
+            return path;
+        }
+        std::env::current_exe()
+            .ok()
+            .and_then(|p| p.parent().map(|d| d.to_path_buf()))
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(path)
+    }
+}
+
+/// Width and height of a BMP file from its header, or None if it isn't one.
+/// Height is negative for top-down bitmaps, so its absolute value is used.
+fn bmp_size(data: &[u8]) -> Option<(u32, u32)> {
+    if data.len() < 26 || &data[0..2] != b"BM" {
+        return None;
+    }
+    let width = i32::from_le_bytes(data[18..22].try_into().ok()?);
+    let height = i32::from_le_bytes(data[22..26].try_into().ok()?);
+    Some((width.unsigned_abs(), height.unsigned_abs()))
+}
+
+/// Copy the logo to <target>\Windows\System32\oobe\info, where the answer
+/// file's <Logo> points. Does nothing without a logo.
+///
+/// # Arguments
+/// * `target_drive` — Drive of the installed Windows (e.g., "C:")
+pub fn copy_logo_to_target(target_drive: &str, info: &OemInfo) -> Result<(), String> {
+    if info.logo_path.is_empty() {
+        return Ok(());
+    }
+    let source = info.resolved_logo();
+    let destination = Path::new(&format!("{}\\", target_drive)).join(LOGO_TARGET);
+    if let Some(dir) = destination.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    fs::copy(&source, &destination)
+        .map_err(|e| format!("Failed to copy OEM logo {}: {}", source.display(), e))?;
+    println!("[Oem] Copied logo to {}", destination.display());
+    Ok(())
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oem_info() {
+        // 14-byte file header + the start of a BITMAPINFOHEADER (120 x -120, top-down)
+        let mut header = vec![0u8; 54];
+        header[0..2].copy_from_slice(b"BM");
+        header[18..22].copy_from_slice(&120i32.to_le_bytes());
+        header[22..26].copy_from_slice(&(-120i32).to_le_bytes());
+        assert_eq!(bmp_size(&header), Some((120, 120)));
+        assert_eq!(bmp_size(b"\x89PNG\r\n\x1a\n"), None);
+
+        let dir = std::env::temp_dir().join(format!("masterbooter-oem-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let logo = dir.join("logo.bmp");
+        header[18..22].copy_from_slice(&96i32.to_le_bytes());
+        fs::write(&logo, &header).unwrap();
+
+        let info = OemInfo {
+            manufacturer: "Contoso IT".to_string(),
+            support_url: "support.contoso.com".to_string(),
+            logo_path: logo.display().to_string(),
+            ..Default::default()
+        };
+        let warnings = info.validate().unwrap();
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[1].contains("96x120"));
+
+        let missing = OemInfo { logo_path: dir.join("none.bmp").display().to_string(), ..Default::default() };
+        assert!(missing.validate().is_err());
+        assert!(OemInfo::default().is_empty() && !info.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,253 @@
+// ============================================
+// MasterBooter - product_key.rs
+// ============================================
+// Windows product keys: decoding the installed key from the registry and
+// checking the format of a typed key.
+//
+// The installed key isn't stored as text. The registry value
+// HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion\DigitalProductId holds
+// it as a 15-byte number (little-endian) at offset 52, and the newer
+// DigitalProductId4 at offset 808. Decoding writes that number in base 24
+// using the 24 key characters "BCDFGHJKMPQRTVWXY2346789":
+//
+//   bytes 52..67 ──► 25 base-24 digits ──► BCDFG-HJKMP-...
+//
+// Windows 8 and later keys also contain one "N" (a character the base-24
+// alphabet doesn't have). Bit 3 of the last key byte marks those keys; the
+// first decoded digit then says where the "N" goes and is dropped.
+//
+// This used to be done by a PowerShell script, which doesn't exist in
+// minimal WinPE images — reg.exe plus this module works everywhere.
+// ============================================
+
+use std::process::Command;
+
+/// Registry key holding DigitalProductId, ProductName, ...
+pub const CURRENT_VERSION_KEY: &str = r"HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion";
+
+/// The 24 characters of the base-24 key alphabet
+const KEY_CHARS: &[u8; 24] = b"BCDFGHJKMPQRTVWXY2346789";
+
+/// Where the 15 key bytes start in DigitalProductId (164 bytes)
+const DPID_KEY_OFFSET: usize = 52;
+/// Where the 15 key bytes start in DigitalProductId4 (1272 bytes)
+const DPID4_KEY_OFFSET: usize = 808;
+/// Size of DigitalProductId4 — anything shorter is the older DigitalProductId
+const DPID4_SIZE: usize = 1272;
+/// Number of bytes holding the key
+const KEY_BYTES: usize = 15;
+
+/// Decode the product key from a DigitalProductId or DigitalProductId4
+/// registry value (told apart by size).
+///
+/// # Returns
+/// * `Ok(String)` — the key as XXXXX-XXXXX-XXXXX-XXXXX-XXXXX
+/// * `Err(String)` — the value is too short or holds no key
+pub fn decode_digital_product_id(blob: &[u8]) -> Result<String, String> {
+    let offset = if blob.len() >= DPID4_SIZE { DPID4_KEY_OFFSET } else { DPID_KEY_OFFSET };
+    let bytes: [u8; KEY_BYTES] = blob
+        .get(offset..offset + KEY_BYTES)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| format!("DigitalProductId is too short ({} bytes)", blob.len()))?;
+    if bytes.iter().all(|b| *b == 0) {
+        return Err("DigitalProductId holds no product key".to_string());
+    }
+    Ok(decode_key_bytes(bytes))
+}
+
+/// Decode the 15 key bytes into a formatted product key.
+fn decode_key_bytes(mut bytes: [u8; KEY_BYTES]) -> String {
+    // Windows 8+ flag. The widely copied scripts test (byte / 6) & 1, which
+    // is only right for some byte values — the flag is bit 3.
+    let is_win8 = bytes[KEY_BYTES - 1] & 0x08 != 0;
+    bytes[KEY_BYTES - 1] &= !0x08;
+
+    // Long division of the 120-bit number by 24, one digit per pass,
+    // least significant digit first
+    let mut digits = [0u8; 25];
+    for digit in digits.iter_mut().rev() {
+        let mut remainder: u32 = 0;
+        for byte in bytes.iter_mut().rev() {
+            let current = remainder * 256 + *byte as u32;
+            *byte = (current / 24) as u8;
+            remainder = current % 24;
+        }
+        *digit = remainder as u8;
+    }
+
+    let mut key: Vec<u8> = digits.iter().map(|d| KEY_CHARS[*d as usize]).collect();
+    if is_win8 {
+        // The most significant digit is the position of the "N"
+        let position = digits[0] as usize;
+        key.remove(0);
+        key.insert(position.min(key.len()), b'N');
+    }
+
+    key.chunks(5)
+        .map(|group| String::from_utf8_lossy(group).to_string())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Read and decode an installed product key with reg.exe
+/// (DigitalProductId, or DigitalProductId4 if that fails).
+///
+/// # Arguments
+/// * `current_version_key` — CURRENT_VERSION_KEY for the running Windows,
+///   or the same path inside a loaded offline SOFTWARE hive
+///
+/// # Returns
+/// * `Ok(String)` — the installed key
+/// * `Err(String)` — neither value could be read or decoded
+pub fn read_installed_key(current_version_key: &str) -> Result<String, String> {
+    let mut errors = Vec::new();
+    for value_name in ["DigitalProductId", "DigitalProductId4"] {
+        let output = Command::new("reg")
+            .args(["query", current_version_key, "/v", value_name])
+            .output()
+            .map_err(|e| format!("Failed to run reg.exe: {}", e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match parse_reg_binary(&stdout, value_name) {
+            Some(blob) => match decode_digital_product_id(&blob) {
+                Ok(key) => return Ok(key),
+                Err(e) => errors.push(format!("{}: {}", value_name, e)),
+            },
+            None => errors.push(format!("{}: not found", value_name)),
+        }
+    }
+    Err(errors.join("; "))
+}
+
+/// Find a value in `reg query` output and return its type and data:
+///
+/// ```text
+///     DigitalProductId    REG_BINARY    A40000000300000030...
+///     ProductName    REG_SZ    Windows 10 Pro
+/// ```
+fn reg_value<'a>(reg_output: &'a str, value_name: &str) -> Option<(&'a str, &'a str)> {
+    reg_output.lines().find_map(|line| {
+        let line = line.trim();
+        let rest = line.get(value_name.len()..).filter(|_| line[..value_name.len()].eq_ignore_ascii_case(value_name))?;
+        if !rest.starts_with([' ', '\t']) {
+            return None;
+        }
+        let rest = rest.trim_start();
+        let (value_type, data) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        value_type.starts_with("REG_").then(|| (value_type, data.trim()))
+    })
+}
+
+/// Read a REG_BINARY value from `reg query` output
+pub fn parse_reg_binary(reg_output: &str, value_name: &str) -> Option<Vec<u8>> {
+    let hex = reg_value(reg_output, value_name).filter(|(t, _)| *t == "REG_BINARY")?.1;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Read a REG_SZ / REG_EXPAND_SZ value from `reg query` output
+pub fn parse_reg_string(reg_output: &str, value_name: &str) -> Option<String> {
+    reg_value(reg_output, value_name)
+        .filter(|(t, _)| *t == "REG_SZ" || *t == "REG_EXPAND_SZ")
+        .map(|(_, data)| data.to_string())
+}
+
+/// Read a REG_DWORD value ("0x1") from `reg query` output
+pub fn parse_reg_dword(reg_output: &str, value_name: &str) -> Option<u32> {
+    let data = reg_value(reg_output, value_name).filter(|(t, _)| *t == "REG_DWORD")?.1;
+    u32::from_str_radix(data.trim_start_matches("0x"), 16).ok()
+}
+
+/// Uppercase a typed key and drop surrounding whitespace
+pub fn normalize(key: &str) -> String {
+    key.trim().to_ascii_uppercase()
+}
+
+/// Check a typed product key: five groups of five characters from the key
+/// alphabet (plus at most one "N"), separated by dashes. Doesn't say
+/// whether the key is genuine or matches the edition — only Windows can.
+pub fn validate(key: &str) -> Result<(), String> {
+    let key = normalize(key);
+    let groups: Vec<&str> = key.split('-').collect();
+    if groups.len() != 5 || groups.iter().any(|g| g.chars().count() != 5) {
+        return Err("must be 25 characters in five groups (XXXXX-XXXXX-XXXXX-XXXXX-XXXXX)".to_string());
+    }
+    if let Some(c) = key.chars().find(|c| *c != '-' && *c != 'N' && !(c.is_ascii() && KEY_CHARS.contains(&(*c as u8)))) {
+        return Err(format!("contains '{}', which product keys never use (no vowels except N, no 0, 1 or 5)", c));
+    }
+    if key.matches('N').count() > 1 {
+        return Err("contains more than one 'N'".to_string());
+    }
+    Ok(())
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A DigitalProductId (164 bytes) or DigitalProductId4 (1272 bytes)
+    /// with the given key bytes at the right offset
+    fn blob(size: usize, key: [u8; KEY_BYTES]) -> Vec<u8> {
+        let mut blob = vec![0u8; size];
+        blob[..4].copy_from_slice(&(size as u32).to_le_bytes());
+        let offset = if size >= DPID4_SIZE { DPID4_KEY_OFFSET } else { DPID_KEY_OFFSET };
+        blob[offset..offset + KEY_BYTES].copy_from_slice(&key);
+        blob
+    }
+
+    #[test]
+    fn test_decode_digital_product_id() {
+        // Windows 10/11 Pro generic key (Windows 8+ format, one "N")
+        let pro = [0xec, 0x0c, 0x00, 0x00, 0x00, 0x00, 0xa8, 0xd2, 0x7b, 0x6e, 0x89, 0x81, 0x4f, 0x6d, 0x09];
+        assert_eq!(decode_digital_product_id(&blob(164, pro)).unwrap(), "VK7JG-NPHTM-C97JM-9MPGT-3V66T");
+        assert_eq!(decode_digital_product_id(&blob(1272, pro)).unwrap(), "VK7JG-NPHTM-C97JM-9MPGT-3V66T");
+
+        // "N" in the last position of a group
+        let pro_kms = [0xef, 0x0c, 0x10, 0x00, 0x00, 0x00, 0x34, 0x3d, 0xc5, 0x39, 0x4e, 0xbd, 0x6e, 0x2f, 0x09];
+        assert_eq!(decode_digital_product_id(&blob(164, pro_kms)).unwrap(), "W269N-WFGWX-YVC9B-4J6C9-T83GX");
+
+        // Windows 7 key (no "N", flag bit clear)
+        let win7 = [0xc3, 0xaa, 0xbf, 0xa6, 0x5b, 0xba, 0x18, 0xb8, 0x87, 0x8e, 0x89, 0xd2, 0x4e, 0xd8, 0x00];
+        assert_eq!(decode_digital_product_id(&blob(164, win7)).unwrap(), "FJ82H-XT6CR-J8D7P-XQJJ2-GPDD4");
+
+        assert!(decode_digital_product_id(&blob(164, [0; KEY_BYTES])).is_err());
+        assert!(decode_digital_product_id(&[0xa4, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_parse_reg_binary() {
+        let output = "\r\nHKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\r\n    DigitalProductId    REG_BINARY    A4000000030000\r\n\r\n";
+        assert_eq!(parse_reg_binary(output, "DigitalProductId"), Some(vec![0xa4, 0, 0, 0, 3, 0, 0]));
+        assert_eq!(parse_reg_binary(output, "DigitalProductId4"), None);
+        assert_eq!(parse_reg_binary("    DigitalProductId    REG_BINARY    A40", "DigitalProductId"), None);
+
+        let output = "    ProductName    REG_SZ    Windows 10 Pro\r\n    EditionID    REG_SZ    Professional\r\n    Current    REG_DWORD    0x2\r\n";
+        assert_eq!(parse_reg_string(output, "ProductName").as_deref(), Some("Windows 10 Pro"));
+        assert_eq!(parse_reg_string(output, "Edition"), None);
+        assert_eq!(parse_reg_dword(output, "Current"), Some(2));
+        assert_eq!(parse_reg_dword(output, "ProductName"), None);
+    }
+
+    #[test]
+    fn test_validate_product_key() {
+        assert!(validate("VK7JG-NPHTM-C97JM-9MPGT-3V66T").is_ok());
+        assert!(validate(" vk7jg-nphtm-c97jm-9mpgt-3v66t ").is_ok());
+        assert!(validate("FJ82H-XT6CR-J8D7P-XQJJ2-GPDD4").is_ok());
+        assert!(validate("VK7JGNPHTMC97JM9MPGT3V66T").is_err());
+        assert!(validate("VK7JG-NPHTM-C97JM-9MPGT-3V66").is_err());
+        let err = validate("VK7JG-NPHTM-C97JM-9MPGT-3V60T").unwrap_err();
+        assert!(err.contains("'0'"), "{}", err);
+        assert!(validate("VK7JG-NPHTM-C97JM-9MPGT-3VNNT").is_err());
+        // 'ł' is U+0142, whose low byte is 'B'
+        let err = validate("VK7JG-NPHTM-C97JM-9MPGT-3V6łT").unwrap_err();
+        assert!(err.contains("'ł'"), "{}", err);
+    }
+}
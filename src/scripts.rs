@@ -0,0 +1,607 @@
+// ============================================
+// MasterBooter - scripts.rs
+// ============================================
+// This module reads the tags of FirstLogon scripts and writes the runner
+// that executes them on the first logon.
+//
+// Tags go in the file name, in square brackets, anywhere in the name.
+// Values follow an "=" (Windows doesn't allow ":" in file names):
+//
+//   [ORDER=20] Install Office [ONLINE][REBOOT].ps1
+//
+//   [ORDER=n]     position — lower runs first (default 50, ties by name)
+//   [ONLINE]      wait up to 5 minutes for internet, skip if there is none
+//   [ADMIN]       skip unless running with administrator rights
+//   [REBOOT]      restart after the script; the runner continues after logon
+//   [IF=LAPTOP]   only on laptops (also [IF=DESKTOP] and [IF=VM])
+//
+// Or in a sidecar manifest, FirstLogon\scripts.toml, for names that
+// should stay clean. Manifest values win over tags in the name:
+//
+//   ["Install Office.ps1"]
+//   order = 20
+//   online = true
+//   reboot = true
+//   if = ["laptop"]
+//
+// The runner is three files next to the scripts in C:\Temp\MasterBooter:
+//
+//   RunAll.bat          what the answer file / RunOnce start; it only
+//                       launches RunAll.ps1
+//   RunAll.ps1          runs the scripts, checks their tags, logs to
+//                       RunAll.log and writes RunAll.summary.txt at the end
+//   RunAll.state.json   the plan (written here) and the progress: status,
+//                       exit code and duration per script
+//
+// Before each script the runner registers itself in RunOnce. If the
+// machine restarts, it starts again at the next logon and continues
+// after the last finished script instead of losing the rest.
+// ============================================
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Manifest file name inside the FirstLogon folder
+pub const MANIFEST_FILE: &str = "scripts.toml";
+/// The runner RunAll.bat starts
+pub const RUNNER_SCRIPT: &str = "RunAll.ps1";
+/// Run plan and progress, next to the runner
+pub const STATE_FILE: &str = "RunAll.state.json";
+/// Position of scripts without an order tag
+pub const DEFAULT_ORDER: u32 = 50;
+/// Format version of RunAll.state.json
+const STATE_VERSION: u32 = 1;
+
+/// Machine type a script can be limited to ([IF=...])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Laptop,
+    Desktop,
+    VirtualMachine,
+}
+
+impl Condition {
+    /// Name used in tags and by the runner ("LAPTOP", "DESKTOP", "VM")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Condition::Laptop => "LAPTOP",
+            Condition::Desktop => "DESKTOP",
+            Condition::VirtualMachine => "VM",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Condition> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "LAPTOP" => Some(Condition::Laptop),
+            "DESKTOP" => Some(Condition::Desktop),
+            "VM" => Some(Condition::VirtualMachine),
+            _ => None,
+        }
+    }
+}
+
+/// A FirstLogon script with its tags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedScript {
+    /// File name in the FirstLogon folder
+    pub file: String,
+    pub order: u32,
+    /// Needs internet ([ONLINE])
+    pub online: bool,
+    /// Needs administrator rights ([ADMIN])
+    pub admin: bool,
+    /// Restart after it ([REBOOT])
+    pub reboot: bool,
+    /// Only run on these machine types (empty = everywhere)
+    pub conditions: Vec<Condition>,
+}
+
+impl TaggedScript {
+    /// Short description for the script list, e.g. "20 · setup.ps1 (online, laptop)"
+    pub fn label(&self) -> String {
+        let mut tags = Vec::new();
+        if self.online {
+            tags.push("online".to_string());
+        }
+        if self.admin {
+            tags.push("admin".to_string());
+        }
+        if self.reboot {
+            tags.push("reboot".to_string());
+        }
+        tags.extend(self.conditions.iter().map(|c| c.as_str().to_ascii_lowercase()));
+        if tags.is_empty() {
+            format!("{} · {}", self.order, self.file)
+        } else {
+            format!("{} · {} ({})", self.order, self.file, tags.join(", "))
+        }
+    }
+}
+
+/// One entry of scripts.toml (every field is optional)
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestEntry {
+    order: Option<u32>,
+    online: Option<bool>,
+    admin: Option<bool>,
+    reboot: Option<bool>,
+    #[serde(rename = "if")]
+    conditions: Option<Vec<String>>,
+}
+
+/// Read the tags in a script's file name.
+///
+/// # Returns
+/// The tagged script, and warnings for tags that couldn't be used
+fn parse_file_name(file: &str) -> (TaggedScript, Vec<String>) {
+    let mut script = TaggedScript {
+        file: file.to_string(),
+        order: DEFAULT_ORDER,
+        online: false,
+        admin: false,
+        reboot: false,
+        conditions: Vec::new(),
+    };
+    let mut warnings = Vec::new();
+
+    let mut rest = file;
+    while let Some(start) = rest.find('[') {
+        let Some(end) = rest[start..].find(']') else { break };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name.trim().to_ascii_uppercase(), Some(value.trim())),
+            None => (tag.trim().to_ascii_uppercase(), None),
+        };
+        match (name.as_str(), value) {
+            ("ONLINE", None) => script.online = true,
+            ("ADMIN", None) => script.admin = true,
+            ("REBOOT", None) => script.reboot = true,
+            ("ORDER", Some(value)) => match value.parse() {
+                Ok(order) => script.order = order,
+                Err(_) => warnings.push(format!("{}: [{}] is not a number — using order {}", file, tag, DEFAULT_ORDER)),
+            },
+            ("IF", Some(value)) => match Condition::parse(value) {
+                Some(condition) => script.conditions.push(condition),
+                None => warnings.push(format!("{}: [{}] is not LAPTOP, DESKTOP or VM — ignored", file, tag)),
+            },
+            _ => warnings.push(format!("{}: unknown tag [{}] — ignored", file, tag)),
+        }
+    }
+    (script, warnings)
+}
+
+/// Apply a scripts.toml entry on top of the file name tags
+fn apply_manifest(script: &mut TaggedScript, entry: &ManifestEntry, warnings: &mut Vec<String>) {
+    if let Some(order) = entry.order {
+        script.order = order;
+    }
+    script.online = entry.online.unwrap_or(script.online);
+    script.admin = entry.admin.unwrap_or(script.admin);
+    script.reboot = entry.reboot.unwrap_or(script.reboot);
+    if let Some(conditions) = &entry.conditions {
+        script.conditions.clear();
+        for value in conditions {
+            match Condition::parse(value) {
+                Some(condition) => script.conditions.push(condition),
+                None => warnings.push(format!("{} [{}]: if = \"{}\" is not laptop, desktop or vm — ignored", MANIFEST_FILE, script.file, value)),
+            }
+        }
+    }
+}
+
+/// Tag the scripts in a folder and put them in run order.
+///
+/// # Arguments
+/// * `dir` — The FirstLogon folder (for scripts.toml)
+/// * `files` — Script file names in that folder
+///
+/// # Returns
+/// The scripts sorted by order, then name, and warnings about tags or
+/// manifest entries that were ignored
+pub fn plan(dir: &Path, files: &[String]) -> (Vec<TaggedScript>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let manifest: BTreeMap<String, ManifestEntry> = match fs::read_to_string(dir.join(MANIFEST_FILE)) {
+        Ok(text) => toml::from_str(&text).unwrap_or_else(|e| {
+            warnings.push(format!("{} is invalid and was ignored: {}", MANIFEST_FILE, e));
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    };
+    let (scripts, manifest_warnings) = plan_with_manifest(files, &manifest);
+    warnings.extend(manifest_warnings);
+    (scripts, warnings)
+}
+
+fn plan_with_manifest(files: &[String], manifest: &BTreeMap<String, ManifestEntry>) -> (Vec<TaggedScript>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut scripts: Vec<TaggedScript> = files
+        .iter()
+        .map(|file| {
+            let (mut script, name_warnings) = parse_file_name(file);
+            warnings.extend(name_warnings);
+            if let Some((_, entry)) = manifest.iter().find(|(name, _)| name.eq_ignore_ascii_case(file)) {
+                apply_manifest(&mut script, entry, &mut warnings);
+            }
+            script
+        })
+        .collect();
+    for name in manifest.keys() {
+        if !files.iter().any(|f| f.eq_ignore_ascii_case(name)) {
+            warnings.push(format!("{}: there is no script named \"{}\"", MANIFEST_FILE, name));
+        }
+    }
+
+    scripts.sort_by(|a, b| a.order.cmp(&b.order).then_with(|| a.file.to_lowercase().cmp(&b.file.to_lowercase())));
+    (scripts, warnings)
+}
+
+/// Everything the runner needs to know about one script, plus what
+/// happened to it. One entry per script in RunAll.state.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptState {
+    pub file: String,
+    pub order: u32,
+    pub online: bool,
+    pub admin: bool,
+    pub reboot: bool,
+    /// Machine types it may run on ("LAPTOP", "DESKTOP", "VM"; empty = all)
+    pub conditions: Vec<String>,
+    /// pending, running, completed, failed, skipped or interrupted
+    /// (the machine restarted while it ran)
+    pub status: String,
+    pub exit_code: Option<i32>,
+    pub started: Option<String>,
+    pub finished: Option<String>,
+    pub duration_seconds: Option<f64>,
+    /// Why it was skipped or interrupted
+    pub note: String,
+}
+
+/// RunAll.state.json: the run plan MasterBooter writes, and the progress
+/// RunAll.ps1 records in it as it goes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunnerState {
+    /// Format version of this file
+    pub version: u32,
+    /// When MasterBooter wrote the plan
+    pub created: String,
+    /// When the runner first started / finished the last script
+    pub started: Option<String>,
+    pub finished: Option<String>,
+    /// How often the runner has started (one more after every restart)
+    pub runs: u32,
+    pub scripts: Vec<ScriptState>,
+}
+
+/// The state file for a run plan, with every script pending
+pub fn initial_state(plan: &[TaggedScript], created: &str) -> RunnerState {
+    RunnerState {
+        version: STATE_VERSION,
+        created: created.to_string(),
+        started: None,
+        finished: None,
+        runs: 0,
+        scripts: plan
+            .iter()
+            .map(|s| ScriptState {
+                file: s.file.clone(),
+                order: s.order,
+                online: s.online,
+                admin: s.admin,
+                reboot: s.reboot,
+                conditions: s.conditions.iter().map(|c| c.as_str().to_string()).collect(),
+                status: "pending".to_string(),
+                exit_code: None,
+                started: None,
+                finished: None,
+                duration_seconds: None,
+                note: String::new(),
+            })
+            .collect(),
+    }
+}
+
+/// Write the runner next to the copied scripts: RunAll.bat (what the answer
+/// file and RunOnce start), RunAll.ps1 and RunAll.state.json.
+///
+/// # Arguments
+/// * `dir` — The scripts folder on the target (<target>\Temp\MasterBooter)
+/// * `plan` — The scripts in run order (see plan())
+pub fn write_runner(dir: &Path, plan: &[TaggedScript]) -> Result<(), String> {
+    let state = initial_state(plan, &chrono::Local::now().format("%Y-%m-%d %H:%M:%S %:z").to_string());
+    let json = serde_json::to_string_pretty(&state).map_err(|e| format!("Failed to build {}: {}", STATE_FILE, e))?;
+    let files = [
+        ("RunAll.bat", LAUNCHER.replace('\n', "\r\n")),
+        (RUNNER_SCRIPT, RUNNER.replace('\n', "\r\n")),
+        (STATE_FILE, json),
+    ];
+    for (name, content) in files {
+        fs::write(dir.join(name), content).map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+/// RunAll.bat only starts the runner, so the answer file's FirstLogonCommand
+/// and the RunOnce entry don't need PowerShell quoting.
+const LAUNCHER: &str = r#"@echo off
+REM ============================================
+REM MasterBooter Post-Install Scripts
+REM This file was generated by MasterBooter.
+REM It starts RunAll.ps1, which runs the FirstLogon scripts and keeps
+REM its progress in RunAll.state.json so it can continue after a restart.
+REM ============================================
+powershell.exe -NoProfile -ExecutionPolicy Bypass -File "%~dp0RunAll.ps1"
+"#;
+
+/// RunAll.ps1 — the same for every deployment; the plan is in the state file
+const RUNNER: &str = r##"# ============================================
+# MasterBooter post-install runner
+# This file was generated by MasterBooter.
+# ============================================
+# Runs the FirstLogon scripts listed in RunAll.state.json in order and
+# records each one's status, exit code and duration in that file.
+# Before every script it registers itself in RunOnce, so if the machine
+# restarts (a [REBOOT] tag, or a script restarting by itself) it starts
+# again at the next logon and resumes after the last finished script.
+# When all scripts are done it writes RunAll.summary.txt.
+# ============================================
+
+$here = Split-Path -Parent $MyInvocation.MyCommand.Path
+$statePath = Join-Path $here 'RunAll.state.json'
+$logPath = Join-Path $here 'RunAll.log'
+$summaryPath = Join-Path $here 'RunAll.summary.txt'
+$runOnceName = 'MasterBooterScripts'
+$runOnceCommand = 'cmd /c "{0}"' -f (Join-Path $here 'RunAll.bat')
+
+function Get-Now { (Get-Date).ToString('yyyy-MM-dd HH:mm:ss zzz') }
+
+function Write-Log([string]$Message) {
+    $line = '[{0}] {1}' -f (Get-Date -Format 'HH:mm:ss'), $Message
+    Add-Content -Path $logPath -Value $line
+    Write-Host $line
+}
+
+function Save-State {
+    # UTF-8 without BOM
+    [IO.File]::WriteAllText($statePath, ($state | ConvertTo-Json -Depth 5))
+}
+
+# Start the runner at the next logon (HKCU when HKLM isn't writable), or stop doing so
+function Set-Resume([bool]$Enabled) {
+    foreach ($key in 'HKLM:\SOFTWARE\Microsoft\Windows\CurrentVersion\RunOnce', 'HKCU:\SOFTWARE\Microsoft\Windows\CurrentVersion\RunOnce') {
+        if (-not $Enabled) {
+            Remove-ItemProperty -Path $key -Name $runOnceName -ErrorAction SilentlyContinue
+            continue
+        }
+        try {
+            New-ItemProperty -Path $key -Name $runOnceName -Value $runOnceCommand -PropertyType String -Force -ErrorAction Stop | Out-Null
+            return
+        } catch {}
+    }
+}
+
+function Get-MachineType {
+    $system = Get-CimInstance Win32_ComputerSystem
+    if ("$($system.Manufacturer) $($system.Model)" -match 'VMware|VirtualBox|Virtual Machine|QEMU|KVM|Xen|Parallels') { return 'VM' }
+    # Same chassis types as machine::ChassisKind
+    $laptop = @(8, 9, 10, 11, 14, 30, 31, 32)
+    if ((Get-CimInstance Win32_SystemEnclosure).ChassisTypes | Where-Object { $laptop -contains $_ }) { return 'LAPTOP' }
+    return 'DESKTOP'
+}
+
+function Test-Admin {
+    $principal = New-Object Security.Principal.WindowsPrincipal([Security.Principal.WindowsIdentity]::GetCurrent())
+    return $principal.IsInRole([Security.Principal.WindowsBuiltInRole]::Administrator)
+}
+
+function Wait-Online {
+    # Windows' own connectivity test, every 5 seconds for up to 5 minutes.
+    # The deadline is measured, so slow requests don't stretch the wait.
+    $watch = [Diagnostics.Stopwatch]::StartNew()
+    while ($watch.Elapsed.TotalSeconds -lt 300) {
+        $left = [int][Math]::Ceiling(300 - $watch.Elapsed.TotalSeconds)
+        try {
+            $response = Invoke-WebRequest -UseBasicParsing -TimeoutSec ([Math]::Min(5, $left)) 'http://www.msftconnecttest.com/connecttest.txt'
+            if ($response.Content -eq 'Microsoft Connect Test') { return $true }
+        } catch {}
+        $left = 300 - $watch.Elapsed.TotalSeconds
+        if ($left -gt 0) { Start-Sleep -Milliseconds ([int][Math]::Min(5000, $left * 1000)) }
+    }
+    return $false
+}
+
+# Run one script with its output appended to the log; returns the exit code
+function Invoke-Script($entry) {
+    $path = Join-Path $here $entry.file
+    $command = switch ([IO.Path]::GetExtension($path).ToLower()) {
+        # -ExecutionPolicy Bypass: the default policy (Restricted) blocks all .ps1 files
+        '.ps1' { 'powershell.exe -ExecutionPolicy Bypass -NonInteractive -File "{0}"' -f $path }
+        # Registry files: import silently
+        '.reg' { 'reg import "{0}"' -f $path }
+        # Batch files, executables, VBS, etc: call them
+        default { 'call "{0}"' -f $path }
+    }
+    $process = Start-Process cmd.exe -ArgumentList ('/c "{0} >> "{1}" 2>&1"' -f $command, $logPath) -Wait -PassThru -NoNewWindow
+    return $process.ExitCode
+}
+
+function Write-Summary {
+    $lines = @(
+        '============================================',
+        'MasterBooter post-install summary',
+        "Started:  $($state.started)",
+        "Finished: $($state.finished)",
+        "Runs:     $($state.runs) (one more after every restart)",
+        '============================================',
+        ('{0,-6} {1,-12} {2,-10} {3,-10} {4}' -f 'Order', 'Status', 'Exit code', 'Duration', 'Script')
+    )
+    foreach ($entry in $state.scripts) {
+        $exitCode = if ($null -ne $entry.exit_code) { $entry.exit_code } else { '-' }
+        $duration = if ($null -ne $entry.duration_seconds) { '{0} s' -f $entry.duration_seconds } else { '-' }
+        $note = if ($entry.note) { " ($($entry.note))" } else { '' }
+        $lines += '{0,-6} {1,-12} {2,-10} {3,-10} {4}{5}' -f $entry.order, $entry.status, $exitCode, $duration, $entry.file, $note
+    }
+    Set-Content -Path $summaryPath -Value $lines
+    Add-Content -Path $logPath -Value $lines
+}
+
+if (-not (Test-Path $statePath)) {
+    Write-Log "No $statePath - nothing to run"
+    exit 1
+}
+$state = Get-Content -Raw -Path $statePath | ConvertFrom-Json
+if ($state.finished) {
+    Write-Log "All scripts already finished on $($state.finished) - see $summaryPath"
+    exit 0
+}
+$state.runs++
+if (-not $state.started) { $state.started = Get-Now }
+Save-State
+Write-Log "MasterBooter scripts - run $($state.runs)"
+
+$machine = $null
+foreach ($entry in $state.scripts) {
+    if ($entry.status -eq 'running') {
+        # The machine restarted while this script ran; don't start it again
+        $entry.status = 'interrupted'
+        $entry.note = 'the machine restarted while it ran'
+        Save-State
+        Write-Log "Interrupted by a restart: $($entry.file)"
+        continue
+    }
+    if ($entry.status -ne 'pending') { continue }
+
+    $reason = $null
+    if ($entry.conditions.Count -gt 0) {
+        if (-not $machine) {
+            $machine = Get-MachineType
+            Write-Log "Machine type: $machine"
+        }
+        if ($entry.conditions -notcontains $machine) { $reason = "only on $($entry.conditions -join '/') (this is $machine)" }
+    }
+    if (-not $reason -and $entry.admin -and -not (Test-Admin)) { $reason = 'needs administrator rights' }
+    if (-not $reason -and $entry.online) {
+        Write-Log "Waiting for internet: $($entry.file)"
+        if (-not (Wait-Online)) { $reason = 'no internet after 5 minutes' }
+    }
+    if ($reason) {
+        $entry.status = 'skipped'
+        $entry.note = $reason
+        Save-State
+        Write-Log "Skipped: $($entry.file) - $reason"
+        continue
+    }
+
+    # Registered before the script, in case it restarts the machine itself
+    Set-Resume $true
+    $entry.status = 'running'
+    $entry.started = Get-Now
+    Save-State
+    Write-Log "Running: $($entry.file)"
+    $watch = [Diagnostics.Stopwatch]::StartNew()
+    $entry.exit_code = Invoke-Script $entry
+    $entry.duration_seconds = [math]::Round($watch.Elapsed.TotalSeconds, 1)
+    $entry.finished = Get-Now
+    $entry.status = if ($entry.exit_code -eq 0) { 'completed' } else { 'failed' }
+    Save-State
+    Write-Log "Finished: $($entry.file) (exit code $($entry.exit_code), $($entry.duration_seconds) s)"
+
+    if ($entry.reboot) {
+        Write-Log "Restarting after: $($entry.file)"
+        shutdown.exe /r /t 10 /c "MasterBooter: restarting to continue post-install scripts"
+        exit 0
+    }
+}
+
+Set-Resume $false
+$state.finished = Get-Now
+Save-State
+Write-Summary
+Write-Log 'All scripts finished'
+"##;
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_tags_and_order() {
+        let files: Vec<String> = [
+            "cleanup.bat",
+            "[ORDER=20] Install Office [ONLINE][REBOOT].ps1",
+            "[order=10][IF=laptop] power.reg",
+            "Setup [v2] [ORDER=x].cmd",
+            "drivers.ps1",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let manifest: BTreeMap<String, ManifestEntry> = toml::from_str(
+            "[\"drivers.ps1\"]\norder = 5\nadmin = true\nif = [\"desktop\", \"vm\"]\n\n[\"missing.ps1\"]\nonline = true\n",
+        )
+        .unwrap();
+
+        let (scripts, warnings) = plan_with_manifest(&files, &manifest);
+        let order: Vec<(u32, &str)> = scripts.iter().map(|s| (s.order, s.file.as_str())).collect();
+        assert_eq!(order, vec![
+            (5, "drivers.ps1"),
+            (10, "[order=10][IF=laptop] power.reg"),
+            (20, "[ORDER=20] Install Office [ONLINE][REBOOT].ps1"),
+            (50, "cleanup.bat"),
+            (50, "Setup [v2] [ORDER=x].cmd"),
+        ]);
+        assert_eq!(scripts[0].conditions, vec![Condition::Desktop, Condition::VirtualMachine]);
+        assert!(scripts[0].admin);
+        assert!(scripts[2].online && scripts[2].reboot);
+        assert_eq!(scripts[1].label(), "10 · [order=10][IF=laptop] power.reg (laptop)");
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        assert!(warnings[0].contains("unknown tag [v2]"));
+        assert!(warnings[1].contains("[ORDER=x] is not a number"));
+        assert!(warnings[2].contains("no script named \"missing.ps1\""));
+    }
+
+    #[test]
+    fn test_write_runner() {
+        let (plan, _) = plan_with_manifest(
+            &["[IF=LAPTOP][ADMIN] power.reg".to_string(), "[ONLINE][REBOOT][ORDER=10] office.ps1".to_string()],
+            &BTreeMap::new(),
+        );
+        let dir = std::env::temp_dir().join(format!("masterbooter-runner-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        write_runner(&dir, &plan).unwrap();
+
+        let bat = fs::read_to_string(dir.join("RunAll.bat")).unwrap();
+        assert!(bat.contains("-ExecutionPolicy Bypass -File \"%~dp0RunAll.ps1\"\r\n"));
+        let runner = fs::read_to_string(dir.join(RUNNER_SCRIPT)).unwrap();
+        assert!(runner.contains("$statePath = Join-Path $here 'RunAll.state.json'"));
+        // RunOnce is registered before the script starts, and the state saved as running
+        let resume = runner.find("Set-Resume $true").unwrap();
+        assert!(resume < runner.find("$entry.exit_code = Invoke-Script $entry").unwrap());
+        assert!(runner.contains("$entry.status = 'interrupted'"));
+        // Wait-Online stops after 5 minutes of wall-clock time, however slow the requests are
+        assert!(runner.contains("while ($watch.Elapsed.TotalSeconds -lt 300) {"));
+        assert!(runner.ends_with("Write-Log 'All scripts finished'\r\n"));
+
+        let state: RunnerState = serde_json::from_str(&fs::read_to_string(dir.join(STATE_FILE)).unwrap()).unwrap();
+        assert_eq!(state.runs, 0);
+        assert_eq!(state.started, None);
+        let files: Vec<&str> = state.scripts.iter().map(|s| s.file.as_str()).collect();
+        assert_eq!(files, vec!["[ONLINE][REBOOT][ORDER=10] office.ps1", "[IF=LAPTOP][ADMIN] power.reg"]);
+        assert!(state.scripts[0].online && state.scripts[0].reboot);
+        assert_eq!(state.scripts[1].conditions, vec!["LAPTOP"]);
+        assert!(state.scripts.iter().all(|s| s.status == "pending" && s.exit_code.is_none()));
+        // The runner sets these, so they must be in the file even when empty
+        let json = fs::read_to_string(dir.join(STATE_FILE)).unwrap();
+        assert!(json.contains("\"exit_code\": null") && json.contains("\"duration_seconds\": null"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
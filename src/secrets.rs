@@ -0,0 +1,268 @@
+// ============================================
+// MasterBooter - secrets.rs
+// ============================================
+// This module encrypts secret values (passwords, product keys) at rest.
+//
+// Profiles and saved_keys.json live on a USB stick that can get lost, so
+// secret fields can be stored encrypted with a technician passphrase:
+// - Argon2id turns the passphrase + a random salt into a key
+// - ChaCha20-Poly1305 encrypts the value and detects any tampering
+// - The field name is bound to the ciphertext (AEAD associated data), so an
+//   encrypted value can't be copied into a different field
+//
+// Each encrypted value is self-contained, so a file can hold a mix of
+// encrypted and clear fields:
+//
+//   enc:v1:<base64( salt[16] | key check[8] | nonce[12] | ciphertext + tag )>
+//
+// The key check is derived together with the key. It tells a wrong passphrase
+// (check doesn't match) apart from a damaged/tampered value (check matches,
+// but authentication fails).
+//
+// The passphrase is entered once per session (unlock) and kept in memory
+// until the app exits. Derived keys are cached so the slow KDF runs once
+// per salt.
+// ============================================
+
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Prefix that marks an encrypted value
+const PREFIX: &str = "enc:v1:";
+const SALT_LEN: usize = 16;
+const CHECK_LEN: usize = 8;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// Shortest passphrase unlock() accepts
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+// ============================================
+// SESSION
+// ============================================
+
+/// The unlocked passphrase for this run of the app.
+struct Session {
+    passphrase: String,
+    /// Salt used for everything encrypted in this session (one KDF run)
+    salt: [u8; SALT_LEN],
+    /// Derived (key, key check) per salt — Argon2 is slow on purpose
+    keys: HashMap<[u8; SALT_LEN], ([u8; KEY_LEN], [u8; CHECK_LEN])>,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+/// Unlock secrets for this session with the technician passphrase.
+/// Calling it again replaces the passphrase.
+///
+/// The passphrase can't be checked here (there's nothing to compare it
+/// against) — a wrong one shows up as a clear error when decrypting.
+pub fn unlock(passphrase: &str) -> Result<(), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LEN));
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+
+    let mut session = SESSION.lock().map_err(|_| "Secrets session is unavailable".to_string())?;
+    *session = Some(Session {
+        passphrase: passphrase.to_string(),
+        salt,
+        keys: HashMap::new(),
+    });
+    println!("[Secrets] Unlocked for this session");
+    Ok(())
+}
+
+/// Forget the passphrase (and all derived keys).
+#[allow(dead_code)]
+pub fn lock() {
+    if let Ok(mut session) = SESSION.lock() {
+        *session = None;
+    }
+}
+
+/// Whether a passphrase has been entered this session
+pub fn is_unlocked() -> bool {
+    SESSION.lock().map(|s| s.is_some()).unwrap_or(false)
+}
+
+/// Whether a stored value is encrypted (vs. clear text)
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Encrypt a value with the session passphrase.
+///
+/// # Arguments
+/// * `plain` — Clear text value (empty values are left empty)
+/// * `field` — Field name the value belongs to (e.g., "domain_password")
+pub fn encrypt(plain: &str, field: &str) -> Result<String, String> {
+    if plain.is_empty() || is_encrypted(plain) {
+        return Ok(plain.to_string());
+    }
+    let mut guard = SESSION.lock().map_err(|_| "Secrets session is unavailable".to_string())?;
+    let session = guard.as_mut().ok_or_else(locked_error)?;
+    let salt = session.salt;
+    let (key, check) = session_key(session, &salt)?;
+    seal(&key, &check, &salt, plain, field)
+}
+
+/// Decrypt a value with the session passphrase.
+/// Clear text values are returned unchanged, so mixed files just work.
+///
+/// # Errors
+/// * locked — no passphrase entered yet
+/// * wrong passphrase — the value was encrypted with a different passphrase
+/// * tampered — the value was modified or damaged after encryption
+pub fn decrypt(value: &str, field: &str) -> Result<String, String> {
+    if !is_encrypted(value) {
+        return Ok(value.to_string());
+    }
+    let mut guard = SESSION.lock().map_err(|_| "Secrets session is unavailable".to_string())?;
+    let session = guard.as_mut().ok_or_else(locked_error)?;
+    let salt = read_salt(value, field)?;
+    let (key, check) = session_key(session, &salt)?;
+    open(&key, &check, value, field)
+}
+
+fn locked_error() -> String {
+    "Secrets are locked — enter the passphrase first".to_string()
+}
+
+/// Get (or derive and cache) the key for a salt
+fn session_key(session: &mut Session, salt: &[u8; SALT_LEN]) -> Result<([u8; KEY_LEN], [u8; CHECK_LEN]), String> {
+    if let Some(cached) = session.keys.get(salt) {
+        return Ok(*cached);
+    }
+    let derived = derive_key(&session.passphrase, salt)?;
+    session.keys.insert(*salt, derived);
+    Ok(derived)
+}
+
+// ============================================
+// CRYPTO
+// ============================================
+
+/// Argon2id(passphrase, salt) → 32-byte key + 8-byte key check
+fn derive_key(passphrase: &str, salt: &[u8; SALT_LEN]) -> Result<([u8; KEY_LEN], [u8; CHECK_LEN]), String> {
+    let mut output = [0u8; KEY_LEN + CHECK_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut output)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+
+    let mut key = [0u8; KEY_LEN];
+    let mut check = [0u8; CHECK_LEN];
+    key.copy_from_slice(&output[..KEY_LEN]);
+    check.copy_from_slice(&output[KEY_LEN..]);
+    Ok((key, check))
+}
+
+/// Encrypt with an already derived key
+fn seal(key: &[u8; KEY_LEN], check: &[u8; CHECK_LEN], salt: &[u8; SALT_LEN], plain: &str, field: &str) -> Result<String, String> {
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plain.as_bytes(), aad: field.as_bytes() })
+        .map_err(|_| format!("Failed to encrypt {}", field))?;
+
+    let mut blob = Vec::with_capacity(SALT_LEN + CHECK_LEN + NONCE_LEN + ciphertext.len());
+    blob.extend_from_slice(salt);
+    blob.extend_from_slice(check);
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", PREFIX, BASE64.encode(blob)))
+}
+
+/// Decrypt with an already derived key
+fn open(key: &[u8; KEY_LEN], check: &[u8; CHECK_LEN], value: &str, field: &str) -> Result<String, String> {
+    let blob = decode_blob(value, field)?;
+    let (stored_check, rest) = blob[SALT_LEN..].split_at(CHECK_LEN);
+    if stored_check != check {
+        return Err(format!("Wrong passphrase for {}", field));
+    }
+
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let plain = cipher
+        .decrypt(nonce.into(), Payload { msg: ciphertext, aad: field.as_bytes() })
+        .map_err(|_| format!("{} was tampered with or is damaged (authentication failed)", field))?;
+    String::from_utf8(plain).map_err(|_| format!("{} is not valid text after decryption", field))
+}
+
+/// Base64-decode an encrypted value and check it's long enough
+fn decode_blob(value: &str, field: &str) -> Result<Vec<u8>, String> {
+    let encoded = value.strip_prefix(PREFIX).unwrap_or(value);
+    let blob = BASE64
+        .decode(encoded.trim())
+        .map_err(|_| format!("{} was tampered with or is damaged (not valid base64)", field))?;
+    // salt + check + nonce + 16-byte Poly1305 tag at minimum
+    if blob.len() < SALT_LEN + CHECK_LEN + NONCE_LEN + 16 {
+        return Err(format!("{} was tampered with or is damaged (too short)", field));
+    }
+    Ok(blob)
+}
+
+fn read_salt(value: &str, field: &str) -> Result<[u8; SALT_LEN], String> {
+    let blob = decode_blob(value, field)?;
+    let mut salt = [0u8; SALT_LEN];
+    salt.copy_from_slice(&blob[..SALT_LEN]);
+    Ok(salt)
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encrypt without the global session (tests run in parallel)
+    fn encrypt_with(passphrase: &str, plain: &str, field: &str) -> String {
+        let salt = [7u8; SALT_LEN];
+        let (key, check) = derive_key(passphrase, &salt).unwrap();
+        seal(&key, &check, &salt, plain, field).unwrap()
+    }
+
+    fn decrypt_with(passphrase: &str, value: &str, field: &str) -> Result<String, String> {
+        let salt = read_salt(value, field)?;
+        let (key, check) = derive_key(passphrase, &salt)?;
+        open(&key, &check, value, field)
+    }
+
+    #[test]
+    fn test_roundtrip_and_errors() {
+        let value = encrypt_with("correct horse", "P@ss<word>", "domain_password");
+        assert!(is_encrypted(&value));
+        assert!(!value.contains("P@ss"));
+        assert_eq!(decrypt_with("correct horse", &value, "domain_password").unwrap(), "P@ss<word>");
+
+        // Wrong passphrase is reported as such
+        let err = decrypt_with("wrong horse", &value, "domain_password").unwrap_err();
+        assert!(err.contains("Wrong passphrase"), "{}", err);
+
+        // Flipping a ciphertext byte is detected as tampering
+        let mut blob = BASE64.decode(value.strip_prefix(PREFIX).unwrap()).unwrap();
+        let last = blob.len() - 1;
+        blob[last] ^= 0x01;
+        let tampered = format!("{}{}", PREFIX, BASE64.encode(&blob));
+        let err = decrypt_with("correct horse", &tampered, "domain_password").unwrap_err();
+        assert!(err.contains("tampered"), "{}", err);
+
+        // Moving a value into another field is detected too
+        let err = decrypt_with("correct horse", &value, "product_key").unwrap_err();
+        assert!(err.contains("tampered"), "{}", err);
+
+        // Truncated values
+        let err = decrypt_with("correct horse", "enc:v1:AAAA", "product_key").unwrap_err();
+        assert!(err.contains("too short"), "{}", err);
+    }
+}
@@ -0,0 +1,268 @@
+// ============================================
+// MasterBooter - timezone.rs
+// ============================================
+// Built-in table of Windows time zone ids. The answer file's <TimeZone>
+// takes a Windows id such as "Eastern Standard Time" — not an IANA name
+// like "America/New_York" and not the "(UTC-05:00) ..." display name.
+// Windows silently falls back to UTC when it doesn't know the id, so the
+// value is checked against this table before deploying.
+//
+// Each entry also lists its IANA (CLDR) equivalent and the countries it's
+// the usual choice for, which is how a time zone is suggested from the
+// selected locale ("fr-CA" → Canada → Eastern Standard Time).
+//
+// The table lists every Windows time zone in CLDR's windowsZones.xml (the
+// "001" mapping of each id to its main IANA zone). Ids Windows has dropped
+// ("Mid-Atlantic Standard Time", "Kamchatka Standard Time") aren't listed;
+// a value that isn't in the table only gets a warning, since Windows
+// updates can add new ids.
+// ============================================
+
+/// One Windows time zone
+pub struct TimeZone {
+    /// Windows id, written to the answer file (e.g., "Eastern Standard Time")
+    pub id: &'static str,
+    /// Name shown in Windows Settings
+    pub display_name: &'static str,
+    /// IANA / CLDR equivalent (e.g., "America/New_York")
+    pub iana: &'static str,
+    /// Countries (ISO 3166 codes) this is the suggested zone for
+    pub countries: &'static [&'static str],
+}
+
+/// Shorthand for the TIME_ZONES table
+const fn zone(id: &'static str, display_name: &'static str, iana: &'static str, countries: &'static [&'static str]) -> TimeZone {
+    TimeZone { id, display_name, iana, countries }
+}
+
+/// Windows time zones, ordered by UTC offset like the Windows picker
+pub const TIME_ZONES: &[TimeZone] = &[
+    zone("Dateline Standard Time", "(UTC-12:00) International Date Line West", "Etc/GMT+12", &[]),
+    zone("UTC-11", "(UTC-11:00) Coordinated Universal Time-11", "Etc/GMT+11", &[]),
+    zone("Aleutian Standard Time", "(UTC-10:00) Aleutian Islands", "America/Adak", &[]),
+    zone("Hawaiian Standard Time", "(UTC-10:00) Hawaii", "Pacific/Honolulu", &[]),
+    zone("Marquesas Standard Time", "(UTC-09:30) Marquesas Islands", "Pacific/Marquesas", &[]),
+    zone("Alaskan Standard Time", "(UTC-09:00) Alaska", "America/Anchorage", &[]),
+    zone("UTC-09", "(UTC-09:00) Coordinated Universal Time-09", "Etc/GMT+9", &[]),
+    zone("Pacific Standard Time (Mexico)", "(UTC-08:00) Baja California", "America/Tijuana", &[]),
+    zone("UTC-08", "(UTC-08:00) Coordinated Universal Time-08", "Etc/GMT+8", &[]),
+    zone("Pacific Standard Time", "(UTC-08:00) Pacific Time (US & Canada)", "America/Los_Angeles", &[]),
+    zone("US Mountain Standard Time", "(UTC-07:00) Arizona", "America/Phoenix", &[]),
+    zone("Mountain Standard Time (Mexico)", "(UTC-07:00) La Paz, Mazatlan", "America/Mazatlan", &[]),
+    zone("Mountain Standard Time", "(UTC-07:00) Mountain Time (US & Canada)", "America/Denver", &[]),
+    zone("Yukon Standard Time", "(UTC-07:00) Yukon", "America/Whitehorse", &[]),
+    zone("Central America Standard Time", "(UTC-06:00) Central America", "America/Guatemala", &["GT", "CR", "SV", "HN", "NI"]),
+    zone("Central Standard Time", "(UTC-06:00) Central Time (US & Canada)", "America/Chicago", &[]),
+    zone("Easter Island Standard Time", "(UTC-06:00) Easter Island", "Pacific/Easter", &[]),
+    zone("Central Standard Time (Mexico)", "(UTC-06:00) Guadalajara, Mexico City, Monterrey", "America/Mexico_City", &["MX"]),
+    zone("Canada Central Standard Time", "(UTC-06:00) Saskatchewan", "America/Regina", &[]),
+    zone("SA Pacific Standard Time", "(UTC-05:00) Bogota, Lima, Quito, Rio Branco", "America/Bogota", &["CO", "PE", "EC"]),
+    zone("Eastern Standard Time (Mexico)", "(UTC-05:00) Chetumal", "America/Cancun", &[]),
+    zone("Eastern Standard Time", "(UTC-05:00) Eastern Time (US & Canada)", "America/New_York", &["US", "CA"]),
+    zone("Haiti Standard Time", "(UTC-05:00) Haiti", "America/Port-au-Prince", &["HT"]),
+    zone("Cuba Standard Time", "(UTC-05:00) Havana", "America/Havana", &["CU"]),
+    zone("US Eastern Standard Time", "(UTC-05:00) Indiana (East)", "America/Indiana/Indianapolis", &[]),
+    zone("Turks And Caicos Standard Time", "(UTC-05:00) Turks and Caicos", "America/Grand_Turk", &["TC"]),
+    zone("Paraguay Standard Time", "(UTC-04:00) Asuncion", "America/Asuncion", &["PY"]),
+    zone("Atlantic Standard Time", "(UTC-04:00) Atlantic Time (Canada)", "America/Halifax", &[]),
+    zone("Venezuela Standard Time", "(UTC-04:00) Caracas", "America/Caracas", &["VE"]),
+    zone("Central Brazilian Standard Time", "(UTC-04:00) Cuiaba", "America/Cuiaba", &[]),
+    zone("SA Western Standard Time", "(UTC-04:00) Georgetown, La Paz, Manaus, San Juan", "America/La_Paz", &["BO", "GY", "PR", "DO"]),
+    zone("Pacific SA Standard Time", "(UTC-04:00) Santiago", "America/Santiago", &["CL"]),
+    zone("Newfoundland Standard Time", "(UTC-03:30) Newfoundland", "America/St_Johns", &[]),
+    zone("Tocantins Standard Time", "(UTC-03:00) Araguaina", "America/Araguaina", &[]),
+    zone("E. South America Standard Time", "(UTC-03:00) Brasilia", "America/Sao_Paulo", &["BR"]),
+    zone("SA Eastern Standard Time", "(UTC-03:00) Cayenne, Fortaleza", "America/Cayenne", &["GF", "SR"]),
+    zone("Argentina Standard Time", "(UTC-03:00) City of Buenos Aires", "America/Argentina/Buenos_Aires", &["AR"]),
+    zone("Montevideo Standard Time", "(UTC-03:00) Montevideo", "America/Montevideo", &["UY"]),
+    zone("Magallanes Standard Time", "(UTC-03:00) Punta Arenas", "America/Punta_Arenas", &[]),
+    zone("Saint Pierre Standard Time", "(UTC-03:00) Saint Pierre and Miquelon", "America/Miquelon", &["PM"]),
+    zone("Bahia Standard Time", "(UTC-03:00) Salvador", "America/Bahia", &[]),
+    zone("UTC-02", "(UTC-02:00) Coordinated Universal Time-02", "Etc/GMT+2", &[]),
+    zone("Greenland Standard Time", "(UTC-02:00) Greenland", "America/Nuuk", &["GL"]),
+    zone("Azores Standard Time", "(UTC-01:00) Azores", "Atlantic/Azores", &[]),
+    zone("Cape Verde Standard Time", "(UTC-01:00) Cabo Verde Is.", "Atlantic/Cape_Verde", &["CV"]),
+    zone("UTC", "(UTC) Coordinated Universal Time", "Etc/UTC", &[]),
+    zone("GMT Standard Time", "(UTC+00:00) Dublin, Edinburgh, Lisbon, London", "Europe/London", &["GB", "IE", "PT"]),
+    zone("Greenwich Standard Time", "(UTC+00:00) Monrovia, Reykjavik", "Atlantic/Reykjavik", &["IS"]),
+    zone("Sao Tome Standard Time", "(UTC+00:00) Sao Tome", "Africa/Sao_Tome", &["ST"]),
+    zone("Morocco Standard Time", "(UTC+01:00) Casablanca", "Africa/Casablanca", &["MA"]),
+    zone("W. Europe Standard Time", "(UTC+01:00) Amsterdam, Berlin, Bern, Rome, Stockholm, Vienna", "Europe/Berlin", &["DE", "AT", "CH", "IT", "NL", "SE", "NO", "LU"]),
+    zone("Central Europe Standard Time", "(UTC+01:00) Belgrade, Bratislava, Budapest, Ljubljana, Prague", "Europe/Budapest", &["HU", "CZ", "SK", "SI", "RS"]),
+    zone("Romance Standard Time", "(UTC+01:00) Brussels, Copenhagen, Madrid, Paris", "Europe/Paris", &["FR", "BE", "ES", "DK"]),
+    zone("Central European Standard Time", "(UTC+01:00) Sarajevo, Skopje, Warsaw, Zagreb", "Europe/Warsaw", &["PL", "HR"]),
+    zone("W. Central Africa Standard Time", "(UTC+01:00) West Central Africa", "Africa/Lagos", &["NG"]),
+    zone("GTB Standard Time", "(UTC+02:00) Athens, Bucharest", "Europe/Bucharest", &["GR", "RO"]),
+    zone("Middle East Standard Time", "(UTC+02:00) Beirut", "Asia/Beirut", &["LB"]),
+    zone("Egypt Standard Time", "(UTC+02:00) Cairo", "Africa/Cairo", &["EG"]),
+    zone("E. Europe Standard Time", "(UTC+02:00) Chisinau", "Europe/Chisinau", &["MD"]),
+    zone("West Bank Standard Time", "(UTC+02:00) Gaza, Hebron", "Asia/Hebron", &["PS"]),
+    zone("South Africa Standard Time", "(UTC+02:00) Harare, Pretoria", "Africa/Johannesburg", &["ZA"]),
+    zone("FLE Standard Time", "(UTC+02:00) Helsinki, Kyiv, Riga, Sofia, Tallinn, Vilnius", "Europe/Kyiv", &["FI", "UA", "LV", "BG", "EE", "LT"]),
+    zone("Israel Standard Time", "(UTC+02:00) Jerusalem", "Asia/Jerusalem", &["IL"]),
+    zone("South Sudan Standard Time", "(UTC+02:00) Juba", "Africa/Juba", &["SS"]),
+    zone("Kaliningrad Standard Time", "(UTC+02:00) Kaliningrad", "Europe/Kaliningrad", &[]),
+    zone("Sudan Standard Time", "(UTC+02:00) Khartoum", "Africa/Khartoum", &["SD"]),
+    zone("Libya Standard Time", "(UTC+02:00) Tripoli", "Africa/Tripoli", &["LY"]),
+    zone("Namibia Standard Time", "(UTC+02:00) Windhoek", "Africa/Windhoek", &["NA"]),
+    zone("Jordan Standard Time", "(UTC+03:00) Amman", "Asia/Amman", &["JO"]),
+    zone("Arabic Standard Time", "(UTC+03:00) Baghdad", "Asia/Baghdad", &["IQ"]),
+    zone("Syria Standard Time", "(UTC+03:00) Damascus", "Asia/Damascus", &["SY"]),
+    zone("Turkey Standard Time", "(UTC+03:00) Istanbul", "Europe/Istanbul", &["TR"]),
+    zone("Arab Standard Time", "(UTC+03:00) Kuwait, Riyadh", "Asia/Riyadh", &["SA", "KW"]),
+    zone("Belarus Standard Time", "(UTC+03:00) Minsk", "Europe/Minsk", &["BY"]),
+    zone("Russian Standard Time", "(UTC+03:00) Moscow, St. Petersburg", "Europe/Moscow", &["RU"]),
+    zone("E. Africa Standard Time", "(UTC+03:00) Nairobi", "Africa/Nairobi", &["KE"]),
+    zone("Volgograd Standard Time", "(UTC+03:00) Volgograd", "Europe/Volgograd", &[]),
+    zone("Iran Standard Time", "(UTC+03:30) Tehran", "Asia/Tehran", &["IR"]),
+    zone("Arabian Standard Time", "(UTC+04:00) Abu Dhabi, Muscat", "Asia/Dubai", &["AE", "OM"]),
+    zone("Astrakhan Standard Time", "(UTC+04:00) Astrakhan, Ulyanovsk", "Europe/Astrakhan", &[]),
+    zone("Azerbaijan Standard Time", "(UTC+04:00) Baku", "Asia/Baku", &["AZ"]),
+    zone("Russia Time Zone 3", "(UTC+04:00) Izhevsk, Samara", "Europe/Samara", &[]),
+    zone("Mauritius Standard Time", "(UTC+04:00) Port Louis", "Indian/Mauritius", &["MU"]),
+    zone("Saratov Standard Time", "(UTC+04:00) Saratov", "Europe/Saratov", &[]),
+    zone("Georgian Standard Time", "(UTC+04:00) Tbilisi", "Asia/Tbilisi", &["GE"]),
+    zone("Caucasus Standard Time", "(UTC+04:00) Yerevan", "Asia/Yerevan", &["AM"]),
+    zone("Afghanistan Standard Time", "(UTC+04:30) Kabul", "Asia/Kabul", &["AF"]),
+    zone("West Asia Standard Time", "(UTC+05:00) Ashgabat, Tashkent", "Asia/Tashkent", &["UZ", "TM", "KZ"]),
+    zone("Ekaterinburg Standard Time", "(UTC+05:00) Ekaterinburg", "Asia/Yekaterinburg", &[]),
+    zone("Pakistan Standard Time", "(UTC+05:00) Islamabad, Karachi", "Asia/Karachi", &["PK"]),
+    zone("Qyzylorda Standard Time", "(UTC+05:00) Qyzylorda", "Asia/Qyzylorda", &[]),
+    zone("India Standard Time", "(UTC+05:30) Chennai, Kolkata, Mumbai, New Delhi", "Asia/Kolkata", &["IN"]),
+    zone("Sri Lanka Standard Time", "(UTC+05:30) Sri Jayawardenepura", "Asia/Colombo", &["LK"]),
+    zone("Nepal Standard Time", "(UTC+05:45) Kathmandu", "Asia/Kathmandu", &["NP"]),
+    zone("Central Asia Standard Time", "(UTC+06:00) Bishkek", "Asia/Bishkek", &["KG"]),
+    zone("Bangladesh Standard Time", "(UTC+06:00) Dhaka", "Asia/Dhaka", &["BD"]),
+    zone("Omsk Standard Time", "(UTC+06:00) Omsk", "Asia/Omsk", &[]),
+    zone("Myanmar Standard Time", "(UTC+06:30) Yangon (Rangoon)", "Asia/Yangon", &["MM"]),
+    zone("SE Asia Standard Time", "(UTC+07:00) Bangkok, Hanoi, Jakarta", "Asia/Bangkok", &["TH", "VN", "ID"]),
+    zone("Altai Standard Time", "(UTC+07:00) Barnaul, Gorno-Altaysk", "Asia/Barnaul", &[]),
+    zone("W. Mongolia Standard Time", "(UTC+07:00) Hovd", "Asia/Hovd", &[]),
+    zone("North Asia Standard Time", "(UTC+07:00) Krasnoyarsk", "Asia/Krasnoyarsk", &[]),
+    zone("N. Central Asia Standard Time", "(UTC+07:00) Novosibirsk", "Asia/Novosibirsk", &[]),
+    zone("Tomsk Standard Time", "(UTC+07:00) Tomsk", "Asia/Tomsk", &[]),
+    zone("China Standard Time", "(UTC+08:00) Beijing, Chongqing, Hong Kong, Urumqi", "Asia/Shanghai", &["CN", "HK"]),
+    zone("North Asia East Standard Time", "(UTC+08:00) Irkutsk", "Asia/Irkutsk", &[]),
+    zone("Singapore Standard Time", "(UTC+08:00) Kuala Lumpur, Singapore", "Asia/Singapore", &["SG", "MY", "PH"]),
+    zone("W. Australia Standard Time", "(UTC+08:00) Perth", "Australia/Perth", &[]),
+    zone("Taipei Standard Time", "(UTC+08:00) Taipei", "Asia/Taipei", &["TW"]),
+    zone("Ulaanbaatar Standard Time", "(UTC+08:00) Ulaanbaatar", "Asia/Ulaanbaatar", &["MN"]),
+    zone("Aus Central W. Standard Time", "(UTC+08:45) Eucla", "Australia/Eucla", &[]),
+    zone("Transbaikal Standard Time", "(UTC+09:00) Chita", "Asia/Chita", &[]),
+    zone("Tokyo Standard Time", "(UTC+09:00) Osaka, Sapporo, Tokyo", "Asia/Tokyo", &["JP"]),
+    zone("North Korea Standard Time", "(UTC+09:00) Pyongyang", "Asia/Pyongyang", &["KP"]),
+    zone("Korea Standard Time", "(UTC+09:00) Seoul", "Asia/Seoul", &["KR"]),
+    zone("Yakutsk Standard Time", "(UTC+09:00) Yakutsk", "Asia/Yakutsk", &[]),
+    zone("Cen. Australia Standard Time", "(UTC+09:30) Adelaide", "Australia/Adelaide", &[]),
+    zone("AUS Central Standard Time", "(UTC+09:30) Darwin", "Australia/Darwin", &[]),
+    zone("E. Australia Standard Time", "(UTC+10:00) Brisbane", "Australia/Brisbane", &[]),
+    zone("AUS Eastern Standard Time", "(UTC+10:00) Canberra, Melbourne, Sydney", "Australia/Sydney", &["AU"]),
+    zone("West Pacific Standard Time", "(UTC+10:00) Guam, Port Moresby", "Pacific/Port_Moresby", &["PG", "GU"]),
+    zone("Tasmania Standard Time", "(UTC+10:00) Hobart", "Australia/Hobart", &[]),
+    zone("Vladivostok Standard Time", "(UTC+10:00) Vladivostok", "Asia/Vladivostok", &[]),
+    zone("Lord Howe Standard Time", "(UTC+10:30) Lord Howe Island", "Australia/Lord_Howe", &[]),
+    zone("Bougainville Standard Time", "(UTC+11:00) Bougainville Island", "Pacific/Bougainville", &[]),
+    zone("Russia Time Zone 10", "(UTC+11:00) Chokurdakh", "Asia/Srednekolymsk", &[]),
+    zone("Magadan Standard Time", "(UTC+11:00) Magadan", "Asia/Magadan", &[]),
+    zone("Norfolk Standard Time", "(UTC+11:00) Norfolk Island", "Pacific/Norfolk", &[]),
+    zone("Sakhalin Standard Time", "(UTC+11:00) Sakhalin", "Asia/Sakhalin", &[]),
+    zone("Central Pacific Standard Time", "(UTC+11:00) Solomon Is., New Caledonia", "Pacific/Guadalcanal", &[]),
+    zone("Russia Time Zone 11", "(UTC+12:00) Anadyr, Petropavlovsk-Kamchatsky", "Asia/Kamchatka", &[]),
+    zone("New Zealand Standard Time", "(UTC+12:00) Auckland, Wellington", "Pacific/Auckland", &["NZ"]),
+    zone("UTC+12", "(UTC+12:00) Coordinated Universal Time+12", "Etc/GMT-12", &[]),
+    zone("Fiji Standard Time", "(UTC+12:00) Fiji", "Pacific/Fiji", &["FJ"]),
+    zone("Chatham Islands Standard Time", "(UTC+12:45) Chatham Islands", "Pacific/Chatham", &[]),
+    zone("UTC+13", "(UTC+13:00) Coordinated Universal Time+13", "Etc/GMT-13", &[]),
+    zone("Tonga Standard Time", "(UTC+13:00) Nuku'alofa", "Pacific/Tongatapu", &["TO"]),
+    zone("Samoa Standard Time", "(UTC+13:00) Samoa", "Pacific/Apia", &["WS"]),
+    zone("Line Islands Standard Time", "(UTC+14:00) Kiritimati Island", "Pacific/Kiritimati", &[]),
+];
+
+/// Find a time zone by its Windows id (case-insensitive)
+pub fn find(id: &str) -> Option<&'static TimeZone> {
+    TIME_ZONES.iter().find(|z| z.id.eq_ignore_ascii_case(id.trim()))
+}
+
+/// Find the Windows time zone for an IANA name (e.g., "Europe/Berlin")
+pub fn from_iana(iana: &str) -> Option<&'static TimeZone> {
+    TIME_ZONES.iter().find(|z| z.iana.eq_ignore_ascii_case(iana.trim()))
+}
+
+/// Check a <TimeZone> value: a Windows id from the table, spelled exactly.
+/// IANA names, display names and wrong capitalization are errors with a
+/// hint; any other id that isn't in the table is a warning.
+///
+/// # Returns
+/// * `Ok(None)` — a known Windows id
+/// * `Ok(Some(warning))` — not in the table (Windows falls back to UTC if it doesn't know it either)
+/// * `Err(String)` — certainly wrong, with the id to use instead when there is one
+pub fn validate(id: &str) -> Result<Option<String>, String> {
+    match find(id) {
+        Some(zone) if zone.id == id => Ok(None),
+        Some(zone) => Err(format!("\"{}\" must be spelled \"{}\"", id, zone.id)),
+        None => match from_iana(id).or_else(|| TIME_ZONES.iter().find(|z| z.display_name == id.trim())) {
+            Some(zone) => Err(format!("\"{}\" is not a Windows time zone id — use \"{}\"", id, zone.id)),
+            None if id.trim().is_empty() || id.contains('/') => {
+                Err(format!("\"{}\" is not a Windows time zone id (e.g., Eastern Standard Time)", id))
+            }
+            None => Ok(Some(format!(
+                "\"{}\" is not in MasterBooter's time zone table — if Windows doesn't know it either, it uses UTC",
+                id
+            ))),
+        },
+    }
+}
+
+/// Suggest a time zone for a locale, from its country ("de-AT" → Austria →
+/// "W. Europe Standard Time"). Countries with several zones get the one
+/// most people live in (e.g., Eastern for the US and Canada).
+pub fn suggest_for_locale(locale: &str) -> Option<&'static TimeZone> {
+    let country = locale.rsplit('-').next()?.to_ascii_uppercase();
+    TIME_ZONES.iter().find(|z| z.countries.contains(&country.as_str()))
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale;
+
+    #[test]
+    fn test_validate_timezone() {
+        assert_eq!(validate("Eastern Standard Time"), Ok(None));
+        assert_eq!(validate("UTC"), Ok(None));
+        for id in ["Arabic Standard Time", "Kaliningrad Standard Time", "Eastern Standard Time (Mexico)", "Samoa Standard Time"] {
+            assert_eq!(validate(id), Ok(None), "{}", id);
+        }
+        assert!(TIME_ZONES.len() >= 135, "{}", TIME_ZONES.len());
+        let err = validate("eastern standard time").unwrap_err();
+        assert!(err.contains("\"Eastern Standard Time\""), "{}", err);
+        let err = validate("Europe/Berlin").unwrap_err();
+        assert!(err.contains("use \"W. Europe Standard Time\""), "{}", err);
+        assert!(validate("Eastern Time").unwrap().is_some()); // unknown, but maybe newer than the table
+        assert!(validate("America/Nowhere").is_err());
+        assert!(validate("").is_err());
+        assert_eq!(from_iana("Asia/Tokyo").map(|z| z.id), Some("Tokyo Standard Time"));
+
+        // Ids and IANA names are unique
+        for zone in TIME_ZONES {
+            assert_eq!(TIME_ZONES.iter().filter(|z| z.id == zone.id || z.iana == zone.iana).count(), 1, "{}", zone.id);
+        }
+    }
+
+    #[test]
+    fn test_suggest_for_locale() {
+        assert_eq!(suggest_for_locale("fr-CA").map(|z| z.id), Some("Eastern Standard Time"));
+        assert_eq!(suggest_for_locale("de-AT").map(|z| z.id), Some("W. Europe Standard Time"));
+        assert_eq!(suggest_for_locale("sr-Latn-RS").map(|z| z.id), Some("Central Europe Standard Time"));
+        assert!(suggest_for_locale("xx").is_none());
+
+        // Every built-in locale gets a suggestion
+        for l in locale::LOCALES {
+            assert!(suggest_for_locale(l.name).is_some(), "no time zone for {}", l.name);
+        }
+    }
+}
@@ -194,8 +194,8 @@ pub fn get_app_directory() -> PathBuf {
         if let Some(parent) = resolved.parent() {
             // Strip \\?\ prefix that canonicalize adds on Windows
             let parent_str = parent.to_string_lossy();
-            if parent_str.starts_with(r"\\?\") {
-                return PathBuf::from(&parent_str[4..]);
+            if let Some(stripped) = parent_str.strip_prefix(r"\\?\") {
+                return PathBuf::from(stripped);
             }
             return parent.to_path_buf();
         }
@@ -275,14 +275,13 @@ pub fn launch_tool(tool: &BundledTool) -> Result<()> {
             .current_dir(get_tool_path(tool))
             .spawn()
             .context(format!("Failed to launch {}", tool.display_name))?;
+        Ok(())
     }
 
     #[cfg(not(windows))]
     {
         anyhow::bail!("Tool launching only supported on Windows");
     }
-
-    Ok(())
 }
 
 /// Open the tool's folder in File Explorer
@@ -365,9 +364,8 @@ pub fn download_tool(tool: &BundledTool, progress_callback: impl Fn(u32)) -> Res
         file.write_all(&buffer[..bytes_read])?;
         downloaded += bytes_read as u64;
 
-        if total_size > 0 {
-            let percent = ((downloaded * 100) / total_size) as u32;
-            progress_callback(percent);
+        if let Some(percent) = (downloaded * 100).checked_div(total_size) {
+            progress_callback(percent as u32);
         }
     }
 
@@ -507,12 +505,7 @@ fn find_7zip() -> Option<PathBuf> {
         get_tools_base_path().join("7z.exe"),
     ];
 
-    for path in paths {
-        if path.exists() {
-            return Some(path);
-        }
-    }
-    None
+    paths.into_iter().find(|path| path.exists())
 }
 
 /// Process an MSI file - extract using 7-Zip or fallback to msiexec
@@ -541,22 +534,20 @@ fn process_msi_file(msi_path: &Path, dest_path: &Path) -> Result<()> {
             if out.status.success() {
                 // MSI files often contain a nested cab file (disk1.cab) with the actual EXEs
                 // We need to extract those too
-                for entry in walkdir::WalkDir::new(&temp_dir).max_depth(2) {
-                    if let Ok(entry) = entry {
-                        let path = entry.path();
-                        if path.extension().map(|e| e.eq_ignore_ascii_case("cab")).unwrap_or(false) {
-                            println!("Found nested cab file: {:?}, extracting...", path);
-
-                            let cab_output = Command::new(&seven_zip)
-                                .arg("x")
-                                .arg(path)
-                                .arg(format!("-o{}", temp_dir.to_string_lossy()))
-                                .arg("-y")
-                                .output();
-
-                            if let Ok(out) = cab_output {
-                                println!("Nested cab extraction exit code: {:?}", out.status.code());
-                            }
+                for entry in walkdir::WalkDir::new(&temp_dir).max_depth(2).into_iter().flatten() {
+                    let path = entry.path();
+                    if path.extension().map(|e| e.eq_ignore_ascii_case("cab")).unwrap_or(false) {
+                        println!("Found nested cab file: {:?}, extracting...", path);
+
+                        let cab_output = Command::new(&seven_zip)
+                            .arg("x")
+                            .arg(path)
+                            .arg(format!("-o{}", temp_dir.to_string_lossy()))
+                            .arg("-y")
+                            .output();
+
+                        if let Ok(out) = cab_output {
+                            println!("Nested cab extraction exit code: {:?}", out.status.code());
                         }
                     }
                 }
@@ -599,28 +590,26 @@ fn process_msi_file(msi_path: &Path, dest_path: &Path) -> Result<()> {
     let mut found_exe = false;
     println!("Searching for EXE files in: {:?}", temp_dir);
 
-    for entry in walkdir::WalkDir::new(&temp_dir) {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if path.extension().map(|e| e.eq_ignore_ascii_case("exe")).unwrap_or(false) {
-                let filename = entry.file_name().to_string_lossy().to_string();
-
-                // Skip icon/metadata EXEs and system files
-                let lowercase = filename.to_lowercase();
-                if lowercase.starts_with("icon.")
-                    || lowercase.starts_with("!")
-                    || lowercase == "msiexec.exe"
-                    || lowercase.contains("uninstall") {
-                    continue;
-                }
+    for entry in walkdir::WalkDir::new(&temp_dir).into_iter().flatten() {
+        let path = entry.path();
+        if path.extension().map(|e| e.eq_ignore_ascii_case("exe")).unwrap_or(false) {
+            let filename = entry.file_name().to_string_lossy().to_string();
+
+            // Skip icon/metadata EXEs and system files
+            let lowercase = filename.to_lowercase();
+            if lowercase.starts_with("icon.")
+                || lowercase.starts_with("!")
+                || lowercase == "msiexec.exe"
+                || lowercase.contains("uninstall") {
+                continue;
+            }
 
-                let dest_file = dest_path.join(&filename);
+            let dest_file = dest_path.join(&filename);
 
-                let _ = fs::remove_file(&dest_file);
-                if let Ok(_) = fs::copy(path, &dest_file) {
-                    println!("Extracted: {}", filename);
-                    found_exe = true;
-                }
+            let _ = fs::remove_file(&dest_file);
+            if fs::copy(path, &dest_file).is_ok() {
+                println!("Extracted: {}", filename);
+                found_exe = true;
             }
         }
     }
@@ -1515,9 +1504,8 @@ fallback_url = "https://github.com/Howweird/Masterbooter-Tools/releases/download
             downloaded += bytes_read as u64;
 
             // Report progress (0-80% for download, 80-100% for extraction)
-            if total_size > 0 {
-                let percent = ((downloaded * 80) / total_size) as u32;
-                progress(percent);
+            if let Some(percent) = (downloaded * 80).checked_div(total_size) {
+                progress(percent as u32);
             }
         }
 
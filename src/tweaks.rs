@@ -0,0 +1,391 @@
+// ============================================
+// TWEAK CATALOG
+// ============================================
+// The Windows tweaks on the Deploy page (telemetry, RDP, taskbar, ...)
+// are data, not code. Each tweak is a [[tweak]] entry in a TOML catalog
+// with the registry values and commands it runs at first logon.
+//
+// - The built-in catalog (assets/tweaks.toml) is compiled into the EXE
+// - *.toml files in the "tweaks" folder next to the EXE are merged in:
+//   a new id adds a tweak, an existing id replaces the built-in one
+// - Profiles store the ids of the enabled tweaks (DeployConfig::enabled_tweaks)
+//
+// Adding a tweak = adding an entry to the catalog. No struct fields,
+// UI properties or code changes needed.
+// ============================================
+
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// The catalog that ships with MasterBooter
+static BUILTIN_CATALOG: &str = include_str!("../assets/tweaks.toml");
+
+/// Registry value types `reg add` accepts
+const REG_TYPES: &[&str] = &["REG_DWORD", "REG_QWORD", "REG_SZ", "REG_EXPAND_SZ", "REG_MULTI_SZ", "REG_BINARY"];
+
+// ============================================
+// DATA STRUCTURES
+// ============================================
+
+/// Registry hive a tweak writes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Hive {
+    /// Machine-wide (HKEY_LOCAL_MACHINE)
+    HKLM,
+    /// The account that runs FirstLogonCommands (HKEY_CURRENT_USER)
+    HKCU,
+}
+
+/// One `reg add` a tweak performs
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryOp {
+    pub hive: Hive,
+    /// Key path without the hive (e.g., "SOFTWARE\Policies\Microsoft\Windows\DataCollection")
+    pub key: String,
+    /// Value name (empty = the key's default value)
+    #[serde(default)]
+    pub value: String,
+    /// Value type (REG_DWORD, REG_SZ, ...)
+    #[serde(rename = "type", default = "default_reg_type")]
+    pub reg_type: String,
+    /// Value data
+    #[serde(default)]
+    pub data: String,
+    /// FirstLogon command description (empty = the tweak's name)
+    #[serde(default)]
+    pub description: String,
+}
+
+impl RegistryOp {
+    /// Full key with the hive, e.g. "HKLM\SOFTWARE\..."
+    pub fn full_key(&self) -> String {
+        format!("{:?}\\{}", self.hive, self.key)
+    }
+}
+
+/// Helper function for serde default
+fn default_reg_type() -> String {
+    "REG_DWORD".to_string()
+}
+
+/// One non-registry command a tweak runs (netsh, powercfg, ...)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TweakCommand {
+    pub command: String,
+    /// FirstLogon command description (empty = the tweak's name)
+    #[serde(default)]
+    pub description: String,
+}
+
+/// One tweak from the catalog
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tweak {
+    /// Stable id stored in profiles (e.g., "disable_telemetry")
+    pub id: String,
+    /// Heading on the Deploy page (Privacy, Security, ...)
+    pub category: String,
+    /// Checkbox label
+    pub name: String,
+    /// One-line explanation shown under the label
+    #[serde(default)]
+    pub description: String,
+    /// Enabled in new profiles
+    #[serde(default)]
+    pub default: bool,
+    /// Lowest Windows build the tweak applies to (0 = no limit)
+    #[serde(default)]
+    pub min_build: u32,
+    /// Highest Windows build the tweak applies to (0 = no limit)
+    #[serde(default)]
+    pub max_build: u32,
+    /// Registry values to set, in order
+    #[serde(default)]
+    pub registry: Vec<RegistryOp>,
+    /// Commands to run after the registry values, in order
+    #[serde(default, rename = "command")]
+    pub commands: Vec<TweakCommand>,
+}
+
+impl Tweak {
+    /// Whether the tweak applies to a Windows build (0 = unknown, always applies)
+    pub fn applies_to_build(&self, build: u32) -> bool {
+        build == 0 || (build >= self.min_build && (self.max_build == 0 || build <= self.max_build))
+    }
+
+    /// Supported builds as text, e.g. "build 22000 and later"
+    pub fn build_range(&self) -> String {
+        match (self.min_build, self.max_build) {
+            (0, 0) => "all builds".to_string(),
+            (min, 0) => format!("build {} and later", min),
+            (0, max) => format!("builds up to {}", max),
+            (min, max) => format!("builds {} to {}", min, max),
+        }
+    }
+
+    /// Check the entry for mistakes a catalog author can make
+    fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty() || !self.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("invalid tweak id \"{}\" (use letters, digits and _)", self.id));
+        }
+        if self.registry.is_empty() && self.commands.is_empty() {
+            return Err(format!("tweak \"{}\" has no registry values or commands", self.id));
+        }
+        if self.max_build != 0 && self.max_build < self.min_build {
+            return Err(format!("tweak \"{}\": max_build is lower than min_build", self.id));
+        }
+        for op in &self.registry {
+            if op.key.trim().is_empty() {
+                return Err(format!("tweak \"{}\": registry key is empty", self.id));
+            }
+            if !REG_TYPES.contains(&op.reg_type.as_str()) {
+                return Err(format!("tweak \"{}\": unknown registry type {}", self.id, op.reg_type));
+            }
+        }
+        if self.commands.iter().any(|c| c.command.trim().is_empty()) {
+            return Err(format!("tweak \"{}\": command is empty", self.id));
+        }
+        Ok(())
+    }
+}
+
+/// Layout of a catalog file: a list of [[tweak]] tables
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogFile {
+    #[serde(default)]
+    tweak: Vec<Tweak>,
+}
+
+/// All known tweaks, in display (and FirstLogon) order
+#[derive(Debug, Clone, Default)]
+pub struct TweakCatalog {
+    pub tweaks: Vec<Tweak>,
+}
+
+// ============================================
+// CATALOG LOADING
+// ============================================
+
+impl TweakCatalog {
+    /// The built-in catalog only (no user files)
+    pub fn builtin() -> Self {
+        let tweaks = parse_catalog(BUILTIN_CATALOG).expect("built-in tweak catalog is valid");
+        TweakCatalog { tweaks }
+    }
+
+    /// Merge tweaks in: an existing id is replaced in place, a new id is appended
+    pub fn merge(&mut self, tweaks: Vec<Tweak>) {
+        for tweak in tweaks {
+            match self.tweaks.iter_mut().find(|t| t.id == tweak.id) {
+                Some(existing) => *existing = tweak,
+                None => self.tweaks.push(tweak),
+            }
+        }
+    }
+
+    /// Look up a tweak by id
+    pub fn get(&self, id: &str) -> Option<&Tweak> {
+        self.tweaks.iter().find(|t| t.id == id)
+    }
+
+    /// Ids of the tweaks that are on in new profiles
+    pub fn default_ids(&self) -> Vec<String> {
+        self.tweaks.iter().filter(|t| t.default).map(|t| t.id.clone()).collect()
+    }
+
+    /// The enabled tweaks, in catalog order (unknown ids are skipped)
+    pub fn enabled<'a>(&'a self, ids: &'a [String]) -> impl Iterator<Item = &'a Tweak> + 'a {
+        self.tweaks.iter().filter(move |t| ids.contains(&t.id))
+    }
+
+    /// Tweak with a registry value matching key/value/data (case-insensitive).
+    /// `key` includes the hive ("HKLM\...").
+    pub fn find_registry_op(&self, key: &str, value: &str, data: &str) -> Option<&Tweak> {
+        self.tweaks.iter().find(|t| {
+            t.registry.iter().any(|op| {
+                op.full_key().eq_ignore_ascii_case(key)
+                    && op.value.eq_ignore_ascii_case(value)
+                    && op.data.eq_ignore_ascii_case(data)
+            })
+        })
+    }
+
+    /// Tweak that runs exactly this command line (case-insensitive)
+    pub fn find_command(&self, command_line: &str) -> Option<&Tweak> {
+        self.tweaks
+            .iter()
+            .find(|t| t.commands.iter().any(|c| c.command.eq_ignore_ascii_case(command_line)))
+    }
+
+    /// Pairs of enabled tweaks that set the same registry value to different
+    /// data (e.g., taskbar search as icon and hidden) — the later one wins.
+    pub fn conflicts(&self, ids: &[String]) -> Vec<(String, String)> {
+        let enabled: Vec<&Tweak> = self.enabled(ids).collect();
+        let mut conflicts = Vec::new();
+        for (i, first) in enabled.iter().enumerate() {
+            for second in &enabled[i + 1..] {
+                let clash = first.registry.iter().any(|a| {
+                    second.registry.iter().any(|b| {
+                        a.full_key().eq_ignore_ascii_case(&b.full_key())
+                            && a.value.eq_ignore_ascii_case(&b.value)
+                            && a.data != b.data
+                    })
+                });
+                if clash {
+                    conflicts.push((first.id.clone(), second.id.clone()));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+/// Parse catalog TOML and check every entry
+fn parse_catalog(toml_text: &str) -> Result<Vec<Tweak>, String> {
+    let file: CatalogFile = toml::from_str(toml_text).map_err(|e| e.to_string())?;
+    for (i, tweak) in file.tweak.iter().enumerate() {
+        tweak.validate()?;
+        if file.tweak[..i].iter().any(|t| t.id == tweak.id) {
+            return Err(format!("tweak id \"{}\" is used twice", tweak.id));
+        }
+    }
+    Ok(file.tweak)
+}
+
+/// Folder for user catalog files (next to the EXE, so it stays on the USB drive)
+pub fn get_user_tweaks_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|d| d.to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("tweaks")
+}
+
+/// The built-in catalog with every tweaks/*.toml merged in (alphabetical
+/// order, so a later file can override an earlier one).
+/// A broken file is reported and skipped — the other tweaks still work.
+pub fn load_catalog() -> TweakCatalog {
+    let mut catalog = TweakCatalog::builtin();
+
+    let mut files: Vec<PathBuf> = fs::read_dir(get_user_tweaks_dir())
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml")))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+
+    for path in files {
+        let result = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| parse_catalog(&text));
+        match result {
+            Ok(tweaks) => {
+                println!("[Tweaks] Loaded {} tweak(s) from {}", tweaks.len(), path.display());
+                catalog.merge(tweaks);
+            }
+            Err(e) => println!("[Tweaks] Warning: Skipping {}: {}", path.display(), e),
+        }
+    }
+    catalog
+}
+
+/// Ids enabled in a new profile (built-in catalog defaults)
+pub fn default_tweak_ids() -> Vec<String> {
+    TweakCatalog::builtin().default_ids()
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_catalog() {
+        let catalog = TweakCatalog::builtin();
+        assert_eq!(catalog.tweaks.len(), 28);
+
+        let telemetry = catalog.get("disable_telemetry").unwrap();
+        assert_eq!(telemetry.category, "Privacy");
+        assert_eq!(telemetry.registry[1].full_key(), r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Privacy");
+        assert_eq!(telemetry.registry[0].reg_type, "REG_DWORD");
+        assert_eq!(catalog.find_command("POWERCFG /setactive 8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c").unwrap().id, "high_performance");
+        assert_eq!(
+            catalog.find_registry_op(r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Search", "SearchboxTaskbarMode", "1").unwrap().id,
+            "taskbar_search_icon"
+        );
+
+        // Windows 11-only tweaks are skipped on Windows 10 builds
+        let left_align = catalog.get("taskbar_left_align").unwrap();
+        assert!(left_align.applies_to_build(0) && left_align.applies_to_build(22631));
+        assert!(!left_align.applies_to_build(19045));
+        assert_eq!(left_align.build_range(), "build 22000 and later");
+
+        let ids = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            catalog.conflicts(&ids(&["taskbar_search_hidden", "disable_ads", "taskbar_search_icon"])),
+            vec![("taskbar_search_icon".to_string(), "taskbar_search_hidden".to_string())]
+        );
+        assert!(catalog.conflicts(&catalog.default_ids()).is_empty());
+    }
+
+    #[test]
+    fn test_merge_user_catalog() {
+        let user = parse_catalog(
+            r#"
+            [[tweak]]
+            id = "disable_ads"
+            category = "Privacy"
+            name = "Disable advertising ID (machine-wide)"
+
+            [[tweak.registry]]
+            hive = "HKLM"
+            key = 'SOFTWARE\Policies\Microsoft\Windows\AdvertisingInfo'
+            value = "DisabledByGroupPolicy"
+            data = "1"
+
+            [[tweak]]
+            id = "disable_lock_screen"
+            category = "Customer"
+            name = "Disable lock screen"
+            max_build = 22631
+
+            [[tweak.command]]
+            command = "reg add HKLM\\SOFTWARE\\Policies\\Microsoft\\Windows\\Personalization /v NoLockScreen /t REG_DWORD /d 1 /f"
+            "#,
+        )
+        .unwrap();
+
+        let mut catalog = TweakCatalog::builtin();
+        let position = catalog.tweaks.iter().position(|t| t.id == "disable_ads").unwrap();
+        catalog.merge(user);
+
+        // Same id replaces the built-in tweak in place, a new id is appended
+        assert_eq!(catalog.tweaks.len(), 29);
+        assert_eq!(catalog.tweaks[position].registry[0].hive, Hive::HKLM);
+        assert_eq!(catalog.tweaks.last().unwrap().id, "disable_lock_screen");
+        assert!(!catalog.tweaks.last().unwrap().applies_to_build(26100));
+
+        // Catalog mistakes are reported
+        for (bad, expected) in [
+            ("[[tweak]]\nid = \"a b\"\ncategory = \"X\"\nname = \"X\"", "invalid tweak id"),
+            ("[[tweak]]\nid = \"a\"\ncategory = \"X\"\nname = \"X\"", "no registry values or commands"),
+            ("[[tweak]]\nid = \"a\"\ncategory = \"X\"\nname = \"X\"\n[[tweak.registry]]\nhive = \"HKLM\"\nkey = \"K\"\ntype = \"DWORD\"", "unknown registry type"),
+            ("[[tweak]]\nid = \"a\"\ncategory = \"X\"\nname = \"X\"\n[[tweak.registry]]\nhive = \"HKU\"\nkey = \"K\"", "unknown variant"),
+        ] {
+            let err = parse_catalog(bad).unwrap_err();
+            assert!(err.contains(expected), "{}", err);
+        }
+    }
+}
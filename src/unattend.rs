@@ -0,0 +1,1257 @@
+// ============================================
+// unattend.rs — Typed Answer File Model
+// ============================================
+//
+// This module is a small typed model of a Windows answer file
+// (autounattend.xml / unattend.xml):
+//
+//   UnattendDocument
+//     └─ SettingsPass      (<settings pass="windowsPE">)
+//          └─ Component    (<component name="Microsoft-Windows-Setup" ...>)
+//               └─ Setting (<ComputerName>, <OOBE>, <UserAccounts>, ...)
+//     └─ Extension         (<Extensions><AppxRemoval>...</AppxRemoval>)
+//
+// deploy::generate_autounattend() maps a DeployConfig into this model and
+// then calls to_xml(). Adding a new answer file setting means adding a
+// field/struct here (or a Setting in deploy.rs) — no hand-indented strings.
+//
+// The same model can be parsed back from XML with UnattendDocument::parse(),
+// so other features (import, validation) can walk a user-supplied answer
+// file without caring about whitespace or formatting.
+// ============================================
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use crate::winpe::ValidationResult;
+
+/// Public key token used by every Microsoft component in an answer file.
+pub const PUBLIC_KEY_TOKEN: &str = "31bf3856ad364e35";
+
+/// XML namespace of the unattend schema (root element).
+const UNATTEND_NS: &str = "urn:schemas-microsoft-com:unattend";
+/// Namespace for the wcm: attributes (wcm:action="add", wcm:keyValue).
+const WCM_NS: &str = "http://schemas.microsoft.com/WMIConfig/2002/State";
+/// Namespace for xsi: attributes (declared on every component).
+const XSI_NS: &str = "http://www.w3.org/2001/XMLSchema-instance";
+/// Namespace for MasterBooter's own <Extensions> block.
+const EXTENSIONS_NS: &str = "urn:masterbooter:extensions";
+
+// ============================================
+// PASSES
+// ============================================
+
+/// Configuration pass that a <settings> block applies to.
+/// Windows Setup runs these passes in a fixed order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    WindowsPE,
+    OfflineServicing,
+    Generalize,
+    Specialize,
+    AuditSystem,
+    AuditUser,
+    OobeSystem,
+}
+
+impl Pass {
+    /// The value used in the pass="..." attribute.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Pass::WindowsPE => "windowsPE",
+            Pass::OfflineServicing => "offlineServicing",
+            Pass::Generalize => "generalize",
+            Pass::Specialize => "specialize",
+            Pass::AuditSystem => "auditSystem",
+            Pass::AuditUser => "auditUser",
+            Pass::OobeSystem => "oobeSystem",
+        }
+    }
+
+    /// Parse a pass="..." attribute value (case-sensitive, like Setup).
+    pub fn parse(value: &str) -> Option<Pass> {
+        match value {
+            "windowsPE" => Some(Pass::WindowsPE),
+            "offlineServicing" => Some(Pass::OfflineServicing),
+            "generalize" => Some(Pass::Generalize),
+            "specialize" => Some(Pass::Specialize),
+            "auditSystem" => Some(Pass::AuditSystem),
+            "auditUser" => Some(Pass::AuditUser),
+            "oobeSystem" => Some(Pass::OobeSystem),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Pass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// ============================================
+// DOCUMENT MODEL
+// ============================================
+
+/// A complete answer file: an ordered list of configuration passes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnattendDocument {
+    pub passes: Vec<SettingsPass>,
+    /// Extra data Windows Setup ignores but copies to
+    /// C:\Windows\Panther\unattend.xml, where specialize commands can read it
+    pub extensions: Vec<Extension>,
+}
+
+/// One element inside <Extensions> — a named block of text (e.g., a script).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    /// Element name (e.g., "AppxRemoval")
+    pub name: String,
+    /// Text content (unescaped — escaping happens on write)
+    pub text: String,
+}
+
+/// One <settings pass="..."> block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingsPass {
+    pub pass: Pass,
+    pub components: Vec<Component>,
+}
+
+/// One <component> inside a pass (e.g., Microsoft-Windows-Shell-Setup).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    /// Component name (e.g., "Microsoft-Windows-Shell-Setup")
+    pub name: String,
+    /// processorArchitecture attribute (e.g., "amd64")
+    pub architecture: String,
+    /// Top-level settings inside the component
+    pub settings: Vec<Setting>,
+}
+
+/// A single setting element.
+/// Either a leaf with a text value (<ComputerName>PC01</ComputerName>)
+/// or a group of child settings (<OOBE>...</OOBE>).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    /// Element name (e.g., "ComputerName")
+    pub name: String,
+    /// wcm:action attribute — "add" for list items
+    pub action: Option<String>,
+    /// wcm:keyValue attribute — used by a few keyed lists (e.g., DriverPaths)
+    pub key_value: Option<String>,
+    /// Text value for leaf settings (None for groups)
+    pub value: Option<String>,
+    /// Child settings for groups (empty for leaves)
+    pub children: Vec<Setting>,
+}
+
+impl UnattendDocument {
+    /// Get the settings block for a pass, creating it if needed.
+    /// Passes are kept in the order they were first added.
+    pub fn pass_mut(&mut self, pass: Pass) -> &mut SettingsPass {
+        if let Some(idx) = self.passes.iter().position(|p| p.pass == pass) {
+            &mut self.passes[idx]
+        } else {
+            self.passes.push(SettingsPass { pass, components: Vec::new() });
+            self.passes.last_mut().expect("pass was just pushed")
+        }
+    }
+
+    /// Find a component by pass and name.
+    pub fn component(&self, pass: Pass, name: &str) -> Option<&Component> {
+        self.passes
+            .iter()
+            .filter(|p| p.pass == pass)
+            .flat_map(|p| p.components.iter())
+            .find(|c| c.name == name)
+    }
+}
+
+impl UnattendDocument {
+    /// Replace every component with one copy per architecture
+    /// (e.g., ["amd64", "arm64", "x86"] for a multi-arch answer file).
+    /// Copies stay next to each other, in the given order.
+    pub fn expand_architectures(&mut self, architectures: &[&str]) {
+        for pass in &mut self.passes {
+            pass.components = pass
+                .components
+                .iter()
+                .flat_map(|component| {
+                    architectures.iter().map(move |arch| Component {
+                        architecture: arch.to_string(),
+                        ..component.clone()
+                    })
+                })
+                .collect();
+        }
+    }
+}
+
+impl SettingsPass {
+    /// Add a component to this pass.
+    pub fn add(&mut self, component: Component) {
+        self.components.push(component);
+    }
+}
+
+impl Component {
+    /// Create an empty component for the given architecture.
+    pub fn new(name: &str, architecture: &str) -> Self {
+        Component {
+            name: name.to_string(),
+            architecture: architecture.to_string(),
+            settings: Vec::new(),
+        }
+    }
+
+    /// Add a setting (builder style, returns self for chaining).
+    pub fn with(mut self, setting: Setting) -> Self {
+        self.settings.push(setting);
+        self
+    }
+
+    /// Add a setting in place.
+    pub fn push(&mut self, setting: Setting) {
+        self.settings.push(setting);
+    }
+
+    /// Find a setting by slash-separated path, e.g. "UserData/ProductKey/Key".
+    pub fn find(&self, path: &str) -> Option<&Setting> {
+        let mut parts = path.split('/');
+        let first = parts.next()?;
+        let mut current = self.settings.iter().find(|s| s.name == first)?;
+        for part in parts {
+            current = current.child(part)?;
+        }
+        Some(current)
+    }
+
+    /// Text value at a slash-separated path (None if missing or a group).
+    #[allow(dead_code)]
+    pub fn text(&self, path: &str) -> Option<&str> {
+        self.find(path).and_then(|s| s.value.as_deref())
+    }
+}
+
+impl Setting {
+    /// Leaf setting with a text value.
+    pub fn value(name: &str, value: impl Into<String>) -> Self {
+        Setting {
+            name: name.to_string(),
+            action: None,
+            key_value: None,
+            value: Some(value.into()),
+            children: Vec::new(),
+        }
+    }
+
+    /// Group setting with child settings.
+    pub fn group(name: &str, children: Vec<Setting>) -> Self {
+        Setting {
+            name: name.to_string(),
+            action: None,
+            key_value: None,
+            value: None,
+            children,
+        }
+    }
+
+    /// Mark this setting as a list item (wcm:action="add").
+    pub fn added(mut self) -> Self {
+        self.action = Some("add".to_string());
+        self
+    }
+
+    /// Give this list item a wcm:keyValue (e.g., "1" for DriverPaths entries).
+    pub fn keyed(mut self, key: impl Into<String>) -> Self {
+        self.key_value = Some(key.into());
+        self
+    }
+
+    /// First direct child with the given name.
+    pub fn child(&self, name: &str) -> Option<&Setting> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// Text value of a direct child.
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).and_then(|c| c.value.as_deref())
+    }
+
+    /// All direct children with the given name (list items).
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Setting> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+// ============================================
+// TYPED SETTINGS
+// ============================================
+// Structs for the nested settings that show up in more than one place
+// or have enough fields that building them by hand is error-prone.
+// Each converts into a Setting with into_setting().
+
+/// <Password> block used by LocalAccount, AutoLogon and AdministratorPassword.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Password {
+    /// Clear text, or the encoded form when plain_text is false
+    pub value: String,
+    pub plain_text: bool,
+}
+
+impl Password {
+    pub fn plain(value: &str) -> Self {
+        Password { value: value.to_string(), plain_text: true }
+    }
+
+    /// Windows' encoded form for the given element (<PlainText>false</PlainText>).
+    /// Same as what Windows System Image Manager writes with "Hide sensitive data".
+    pub fn encoded(value: &str, element: &str) -> Self {
+        Password { value: encode_password(value, element), plain_text: false }
+    }
+
+    /// The clear text password (decodes the encoded form).
+    /// Returns None if an encoded value is not valid.
+    pub fn clear_text(&self, element: &str) -> Option<String> {
+        if self.plain_text {
+            Some(self.value.clone())
+        } else {
+            decode_password(&self.value, element)
+        }
+    }
+
+    pub fn into_setting(self, element: &str) -> Setting {
+        Setting::group(element, vec![
+            Setting::value("Value", self.value),
+            Setting::value("PlainText", bool_str(self.plain_text)),
+        ])
+    }
+}
+
+/// Encode a password the way Windows Setup expects when <PlainText> is false:
+/// the password + the element name ("Password" or "AdministratorPassword")
+/// as UTF-16LE bytes, base64-encoded.
+///
+/// NOTE: this is obfuscation, not encryption — it only keeps passwords from
+/// being readable at a glance in the file and in Panther logs.
+pub fn encode_password(password: &str, element: &str) -> String {
+    let bytes: Vec<u8> = format!("{}{}", password, element)
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect();
+    BASE64.encode(bytes)
+}
+
+/// Decode a password written by encode_password() (or by Windows SIM).
+/// Returns None if the value isn't base64 UTF-16LE ending in the element name.
+pub fn decode_password(encoded: &str, element: &str) -> Option<String> {
+    let bytes = BASE64.decode(encoded.trim()).ok()?;
+    if bytes.len() % 2 != 0 {
+        return None;
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let text = String::from_utf16(&units).ok()?;
+    text.strip_suffix(element).map(str::to_string)
+}
+
+/// <AutoLogon> block in oobeSystem/Shell-Setup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoLogon {
+    pub username: String,
+    pub password: Option<Password>,
+    pub logon_count: u32,
+}
+
+impl AutoLogon {
+    pub fn into_setting(self) -> Setting {
+        let mut children = vec![
+            Setting::value("Enabled", "true"),
+            Setting::value("LogonCount", self.logon_count.to_string()),
+            Setting::value("Username", self.username),
+        ];
+        if let Some(password) = self.password {
+            children.push(password.into_setting("Password"));
+        }
+        Setting::group("AutoLogon", children)
+    }
+}
+
+/// <LocalAccount> entry inside <UserAccounts><LocalAccounts>.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalAccount {
+    pub name: String,
+    pub display_name: String,
+    pub group: String,
+    pub password: Option<Password>,
+}
+
+impl LocalAccount {
+    pub fn into_setting(self) -> Setting {
+        let mut children = vec![Setting::value("Name", self.name)];
+        if !self.display_name.is_empty() {
+            children.push(Setting::value("DisplayName", self.display_name));
+        }
+        children.push(Setting::value("Group", self.group));
+        if let Some(password) = self.password {
+            children.push(password.into_setting("Password"));
+        }
+        Setting::group("LocalAccount", children).added()
+    }
+}
+
+/// One command inside <FirstLogonCommands>.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SynchronousCommand {
+    /// Order number (1-based, must be unique within the list)
+    pub order: u32,
+    /// Command line to run (unescaped — escaping happens on write)
+    pub command_line: String,
+    /// Description shown in Setup logs
+    pub description: String,
+}
+
+impl SynchronousCommand {
+    pub fn into_setting(self) -> Setting {
+        // RequiresUserInput=false tells Windows this command doesn't need
+        // user interaction, preventing unnecessary delays during setup.
+        Setting::group("SynchronousCommand", vec![
+            Setting::value("Order", self.order.to_string()),
+            Setting::value("CommandLine", self.command_line),
+            Setting::value("Description", self.description),
+            Setting::value("RequiresUserInput", "false"),
+        ])
+        .added()
+    }
+}
+
+/// One command inside <RunSynchronous> (Microsoft-Windows-Deployment).
+/// Runs as SYSTEM during the specialize pass, before any user logs on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunSynchronousCommand {
+    /// Order number (1-based, must be unique within the list)
+    pub order: u32,
+    /// Command line to run (max 259 characters)
+    pub path: String,
+    /// Description shown in Setup logs
+    pub description: String,
+}
+
+impl RunSynchronousCommand {
+    pub fn into_setting(self) -> Setting {
+        Setting::group("RunSynchronousCommand", vec![
+            Setting::value("Order", self.order.to_string()),
+            Setting::value("Path", self.path),
+            Setting::value("Description", self.description),
+        ])
+        .added()
+    }
+}
+
+/// <CreatePartition> entry inside <CreatePartitions>.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatePartition {
+    pub order: u32,
+    /// Size in MB (None = use <Extend>true</Extend> to fill the disk)
+    pub size_mb: Option<u64>,
+    /// Partition type: "EFI", "MSR" or "Primary"
+    pub partition_type: String,
+}
+
+impl CreatePartition {
+    pub fn into_setting(self) -> Setting {
+        let mut children = vec![Setting::value("Order", self.order.to_string())];
+        match self.size_mb {
+            Some(size) => children.push(Setting::value("Size", size.to_string())),
+            None => children.push(Setting::value("Extend", "true")),
+        }
+        children.push(Setting::value("Type", self.partition_type));
+        Setting::group("CreatePartition", children).added()
+    }
+}
+
+/// <ModifyPartition> entry inside <ModifyPartitions>.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModifyPartition {
+    pub order: u32,
+    pub partition_id: u32,
+    pub active: bool,
+    pub format: Option<String>,
+    pub label: Option<String>,
+    pub letter: Option<String>,
+    /// Partition type ID: a GUID on GPT disks, "0x27"-style on MBR disks
+    pub type_id: Option<String>,
+}
+
+impl ModifyPartition {
+    pub fn into_setting(self) -> Setting {
+        let mut children = vec![
+            Setting::value("Order", self.order.to_string()),
+            Setting::value("PartitionID", self.partition_id.to_string()),
+        ];
+        if self.active {
+            children.push(Setting::value("Active", "true"));
+        }
+        if let Some(format) = self.format {
+            children.push(Setting::value("Format", format));
+        }
+        if let Some(label) = self.label {
+            children.push(Setting::value("Label", label));
+        }
+        if let Some(letter) = self.letter {
+            children.push(Setting::value("Letter", letter));
+        }
+        if let Some(type_id) = self.type_id {
+            children.push(Setting::value("TypeID", type_id));
+        }
+        Setting::group("ModifyPartition", children).added()
+    }
+}
+
+/// "true" / "false" as used by the unattend schema.
+pub fn bool_str(value: bool) -> &'static str {
+    if value { "true" } else { "false" }
+}
+
+// ============================================
+// XML WRITER
+// ============================================
+
+impl UnattendDocument {
+    /// Serialize the document to answer file XML.
+    /// Uses 4-space indentation, one element per line, and the
+    /// standard namespace declarations on every component.
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::new();
+
+        xml.push_str(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        xml.push('\n');
+        xml.push_str(&format!("<unattend xmlns=\"{}\">\n", UNATTEND_NS));
+
+        for pass in &self.passes {
+            xml.push_str(&format!("    <settings pass=\"{}\">\n", pass.pass.as_str()));
+            for component in &pass.components {
+                xml.push_str(&format!(
+                    "        <component name=\"{}\" processorArchitecture=\"{}\" publicKeyToken=\"{}\" language=\"neutral\" versionScope=\"nonSxS\" xmlns:wcm=\"{}\" xmlns:xsi=\"{}\">\n",
+                    escape_xml(&component.name),
+                    escape_xml(&component.architecture),
+                    PUBLIC_KEY_TOKEN,
+                    WCM_NS,
+                    XSI_NS
+                ));
+                for setting in &component.settings {
+                    write_setting(&mut xml, setting, 3);
+                }
+                xml.push_str("        </component>\n");
+            }
+            xml.push_str("    </settings>\n");
+        }
+
+        if !self.extensions.is_empty() {
+            xml.push_str(&format!("    <Extensions xmlns=\"{}\">\n", EXTENSIONS_NS));
+            for extension in &self.extensions {
+                xml.push_str(&format!(
+                    "        <{}>{}</{}>\n",
+                    extension.name,
+                    escape_xml(&extension.text),
+                    extension.name
+                ));
+            }
+            xml.push_str("    </Extensions>\n");
+        }
+
+        xml.push_str("</unattend>\n");
+        xml
+    }
+}
+
+/// Write one setting (and its children) at the given indentation depth.
+fn write_setting(xml: &mut String, setting: &Setting, depth: usize) {
+    let indent = "    ".repeat(depth);
+
+    let mut open = setting.name.clone();
+    if let Some(action) = &setting.action {
+        open.push_str(&format!(" wcm:action=\"{}\"", escape_xml(action)));
+    }
+    if let Some(key) = &setting.key_value {
+        open.push_str(&format!(" wcm:keyValue=\"{}\"", escape_xml(key)));
+    }
+
+    match &setting.value {
+        Some(value) if setting.children.is_empty() => {
+            xml.push_str(&format!(
+                "{}<{}>{}</{}>\n",
+                indent,
+                open,
+                escape_xml(value),
+                setting.name
+            ));
+        }
+        _ => {
+            xml.push_str(&format!("{}<{}>\n", indent, open));
+            for child in &setting.children {
+                write_setting(xml, child, depth + 1);
+            }
+            xml.push_str(&format!("{}</{}>\n", indent, setting.name));
+        }
+    }
+}
+
+/// Escape special XML characters in a string.
+/// Replaces: & < > " '
+pub fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// ============================================
+// XML PARSER
+// ============================================
+
+impl UnattendDocument {
+    /// Parse answer file XML into the typed model.
+    /// Whitespace and formatting are ignored. Elements with no child
+    /// elements become leaf settings (their text is trimmed).
+    ///
+    /// # Returns
+    /// * `Ok(UnattendDocument)` — parsed document
+    /// * `Err(String)` — XML syntax error or not an answer file
+    pub fn parse(xml: &str) -> Result<UnattendDocument, String> {
+        let tree = roxmltree::Document::parse(xml)
+            .map_err(|e| format!("Invalid XML: {}", e))?;
+
+        let root = tree.root_element();
+        if root.tag_name().name() != "unattend" {
+            return Err(format!(
+                "Not an answer file: root element is <{}>, expected <unattend>",
+                root.tag_name().name()
+            ));
+        }
+
+        let mut doc = UnattendDocument::default();
+
+        for settings in root.children().filter(|n| n.is_element()) {
+            if settings.tag_name().name() == "Extensions" {
+                for node in settings.children().filter(|n| n.is_element()) {
+                    doc.extensions.push(Extension {
+                        name: node.tag_name().name().to_string(),
+                        text: node.text().unwrap_or("").to_string(),
+                    });
+                }
+                continue;
+            }
+            if settings.tag_name().name() != "settings" {
+                continue;
+            }
+            let pass_name = settings.attribute("pass").unwrap_or("");
+            let pass = Pass::parse(pass_name)
+                .ok_or_else(|| format!("Unknown configuration pass: \"{}\"", pass_name))?;
+
+            let mut components = Vec::new();
+            for comp in settings.children().filter(|n| n.is_element()) {
+                if comp.tag_name().name() != "component" {
+                    continue;
+                }
+                components.push(Component {
+                    name: comp.attribute("name").unwrap_or("").to_string(),
+                    architecture: comp.attribute("processorArchitecture").unwrap_or("").to_string(),
+                    settings: comp
+                        .children()
+                        .filter(|n| n.is_element())
+                        .map(parse_setting)
+                        .collect(),
+                });
+            }
+
+            // Multiple <settings> blocks for the same pass are merged
+            doc.pass_mut(pass).components.extend(components);
+        }
+
+        Ok(doc)
+    }
+}
+
+/// Convert one XML element (and its descendants) into a Setting.
+fn parse_setting(node: roxmltree::Node) -> Setting {
+    let children: Vec<Setting> = node
+        .children()
+        .filter(|n| n.is_element())
+        .map(parse_setting)
+        .collect();
+
+    let value = if children.is_empty() {
+        let text: String = node
+            .children()
+            .filter(|n| n.is_text())
+            .filter_map(|n| n.text())
+            .collect();
+        Some(text.trim().to_string())
+    } else {
+        None
+    };
+
+    Setting {
+        name: node.tag_name().name().to_string(),
+        action: node.attribute((WCM_NS, "action")).map(|s| s.to_string()),
+        key_value: node.attribute((WCM_NS, "keyValue")).map(|s| s.to_string()),
+        value,
+        children,
+    }
+}
+
+// ============================================
+// VALIDATION (LINTER)
+// ============================================
+// Catches answer file mistakes before Windows Setup fails on them
+// halfway through a deployment:
+//   1. Components placed in a pass they don't support
+//   2. Settings placed in the wrong component or pass
+//   3. Duplicate / non-contiguous <Order> values in ordered lists
+//   4. Values of the wrong type (e.g., "yes" for a boolean)
+//   5. Invalid computer names (NetBIOS rules)
+//   6. Unknown processorArchitecture values
+//   7. Command lines longer than Setup accepts
+//
+// Image-specific checks (does the edition exist?) live in
+// deploy::validate_autounattend(), which calls validate() first.
+// ============================================
+
+/// processorArchitecture values Windows Setup understands.
+const VALID_ARCHITECTURES: &[&str] = &["amd64", "arm64", "x86", "wow64"];
+
+/// Passes each known component may appear in.
+const COMPONENT_PASSES: &[(&str, &[Pass])] = &[
+    ("Microsoft-Windows-International-Core-WinPE", &[Pass::WindowsPE]),
+    ("Microsoft-Windows-Setup", &[Pass::WindowsPE]),
+    ("Microsoft-Windows-PnpCustomizationsWinPE", &[Pass::WindowsPE]),
+    ("Microsoft-Windows-PnpCustomizationsNonWinPE", &[Pass::OfflineServicing, Pass::AuditSystem]),
+    ("Microsoft-Windows-International-Core", &[Pass::Specialize, Pass::OobeSystem]),
+    ("Microsoft-Windows-UnattendedJoin", &[Pass::Specialize]),
+    ("Microsoft-Windows-SecureStartup-FilterDriver", &[Pass::OfflineServicing, Pass::Specialize]),
+    ("Microsoft-Windows-Deployment", &[Pass::Generalize, Pass::Specialize, Pass::AuditUser, Pass::OobeSystem]),
+    ("Microsoft-Windows-Shell-Setup", &[
+        Pass::OfflineServicing, Pass::Specialize, Pass::AuditSystem, Pass::AuditUser, Pass::OobeSystem,
+    ]),
+];
+
+/// Top-level settings as (setting, component it belongs to, passes it's valid in).
+/// A setting can be listed more than once if several components accept it.
+const SETTING_PLACEMENT: &[(&str, &str, &[Pass])] = &[
+    // Language
+    ("SetupUILanguage", "Microsoft-Windows-International-Core-WinPE", &[Pass::WindowsPE]),
+    ("InputLocale", "Microsoft-Windows-International-Core-WinPE", &[Pass::WindowsPE]),
+    ("InputLocale", "Microsoft-Windows-International-Core", &[Pass::Specialize, Pass::OobeSystem]),
+    ("SystemLocale", "Microsoft-Windows-International-Core-WinPE", &[Pass::WindowsPE]),
+    ("SystemLocale", "Microsoft-Windows-International-Core", &[Pass::Specialize, Pass::OobeSystem]),
+    ("UILanguage", "Microsoft-Windows-International-Core-WinPE", &[Pass::WindowsPE]),
+    ("UILanguage", "Microsoft-Windows-International-Core", &[Pass::Specialize, Pass::OobeSystem]),
+    ("UserLocale", "Microsoft-Windows-International-Core-WinPE", &[Pass::WindowsPE]),
+    ("UserLocale", "Microsoft-Windows-International-Core", &[Pass::Specialize, Pass::OobeSystem]),
+    // Setup (windowsPE only)
+    ("DiskConfiguration", "Microsoft-Windows-Setup", &[Pass::WindowsPE]),
+    ("ImageInstall", "Microsoft-Windows-Setup", &[Pass::WindowsPE]),
+    ("UserData", "Microsoft-Windows-Setup", &[Pass::WindowsPE]),
+    ("RunSynchronous", "Microsoft-Windows-Setup", &[Pass::WindowsPE]),
+    ("RunSynchronous", "Microsoft-Windows-Deployment", &[Pass::Specialize, Pass::AuditUser]),
+    ("RunAsynchronous", "Microsoft-Windows-Deployment", &[Pass::Specialize, Pass::AuditUser]),
+    ("DriverPaths", "Microsoft-Windows-PnpCustomizationsWinPE", &[Pass::WindowsPE]),
+    ("DriverPaths", "Microsoft-Windows-PnpCustomizationsNonWinPE", &[Pass::OfflineServicing, Pass::AuditSystem]),
+    // Machine identity
+    ("ComputerName", "Microsoft-Windows-Shell-Setup", &[Pass::OfflineServicing, Pass::Specialize]),
+    ("CopyProfile", "Microsoft-Windows-Shell-Setup", &[Pass::Specialize]),
+    ("ProductKey", "Microsoft-Windows-Shell-Setup", &[Pass::Specialize]),
+    ("OEMInformation", "Microsoft-Windows-Shell-Setup", &[Pass::Specialize]),
+    ("TimeZone", "Microsoft-Windows-Shell-Setup", &[Pass::Specialize, Pass::AuditSystem, Pass::OobeSystem]),
+    ("RegisteredOrganization", "Microsoft-Windows-Shell-Setup", &[Pass::OfflineServicing, Pass::Specialize, Pass::AuditSystem, Pass::OobeSystem]),
+    ("RegisteredOwner", "Microsoft-Windows-Shell-Setup", &[Pass::OfflineServicing, Pass::Specialize, Pass::AuditSystem, Pass::OobeSystem]),
+    ("PreventDeviceEncryption", "Microsoft-Windows-SecureStartup-FilterDriver", &[Pass::OfflineServicing, Pass::Specialize]),
+    ("Identification", "Microsoft-Windows-UnattendedJoin", &[Pass::Specialize]),
+    // Users and first logon
+    ("AutoLogon", "Microsoft-Windows-Shell-Setup", &[Pass::Specialize, Pass::AuditSystem, Pass::OobeSystem]),
+    ("UserAccounts", "Microsoft-Windows-Shell-Setup", &[Pass::AuditSystem, Pass::OobeSystem]),
+    ("OOBE", "Microsoft-Windows-Shell-Setup", &[Pass::OobeSystem]),
+    ("FirstLogonCommands", "Microsoft-Windows-Shell-Setup", &[Pass::OobeSystem]),
+    ("LogonCommands", "Microsoft-Windows-Shell-Setup", &[Pass::OobeSystem]),
+];
+
+/// Ordered lists as (container, item). Every item needs a unique <Order>.
+const ORDERED_LISTS: &[(&str, &str)] = &[
+    ("FirstLogonCommands", "SynchronousCommand"),
+    ("LogonCommands", "AsynchronousCommand"),
+    ("RunSynchronous", "RunSynchronousCommand"),
+    ("RunAsynchronous", "RunAsynchronousCommand"),
+    ("CreatePartitions", "CreatePartition"),
+    ("ModifyPartitions", "ModifyPartition"),
+];
+
+/// Longest value Setup accepts, keyed by "Parent/Name".
+/// Longer commands are cut off or make Setup fail.
+const MAX_LENGTHS: &[(&str, usize)] = &[
+    ("RunSynchronousCommand/Path", 259),
+    ("SynchronousCommand/CommandLine", 1024),
+];
+
+/// Expected type of a setting's value.
+enum ValueKind {
+    /// "true" or "false"
+    Bool,
+    /// Non-negative whole number
+    UInt,
+    /// One of a fixed set of values (case-insensitive)
+    OneOf(&'static [&'static str]),
+}
+
+/// Value types for leaf settings, keyed by "Name" or "Parent/Name"
+/// (the parent form is used where a name means different things).
+const VALUE_TYPES: &[(&str, ValueKind)] = &[
+    ("AcceptEula", ValueKind::Bool),
+    ("WillWipeDisk", ValueKind::Bool),
+    ("Extend", ValueKind::Bool),
+    ("Active", ValueKind::Bool),
+    ("Enabled", ValueKind::Bool),
+    ("PlainText", ValueKind::Bool),
+    ("RequiresUserInput", ValueKind::Bool),
+    ("PreventDeviceEncryption", ValueKind::Bool),
+    ("CopyProfile", ValueKind::Bool),
+    ("HideEULAPage", ValueKind::Bool),
+    ("HideOEMRegistrationScreen", ValueKind::Bool),
+    ("HideOnlineAccountScreens", ValueKind::Bool),
+    ("HideWirelessSetupInOOBE", ValueKind::Bool),
+    ("HideLocalAccountScreen", ValueKind::Bool),
+    ("SkipMachineOOBE", ValueKind::Bool),
+    ("SkipUserOOBE", ValueKind::Bool),
+    ("DiskID", ValueKind::UInt),
+    ("PartitionID", ValueKind::UInt),
+    ("Size", ValueKind::UInt),
+    ("LogonCount", ValueKind::UInt),
+    ("WillShowUI", ValueKind::OneOf(&["Always", "OnError", "Never"])),
+    ("NetworkLocation", ValueKind::OneOf(&["Home", "Work", "Other"])),
+    ("ProtectYourPC", ValueKind::OneOf(&["1", "2", "3"])),
+    ("CreatePartition/Type", ValueKind::OneOf(&["Primary", "EFI", "MSR", "Extended", "Logical"])),
+    ("ModifyPartition/Format", ValueKind::OneOf(&["NTFS", "FAT32"])),
+];
+
+/// Characters Windows doesn't allow in a computer name.
+const ILLEGAL_COMPUTER_NAME_CHARS: &str = r#"`~!@#$%^&*()=+_[]{}\|;:.'",<>/? "#;
+
+/// Check a computer name against the NetBIOS rules Windows Setup enforces.
+/// "*" is allowed (Windows generates a random name).
+///
+/// # Returns
+/// * `Ok(())` — name is valid
+/// * `Err(String)` — why the name is invalid
+pub fn validate_computer_name(name: &str) -> Result<(), String> {
+    if name == "*" {
+        return Ok(());
+    }
+    if name.is_empty() {
+        return Err("Computer name is empty (use \"*\" to let Windows pick one)".to_string());
+    }
+    if name.chars().count() > 15 {
+        return Err(format!(
+            "Computer name \"{}\" is {} characters long (NetBIOS limit is 15)",
+            name,
+            name.chars().count()
+        ));
+    }
+    let illegal: String = name.chars().filter(|c| ILLEGAL_COMPUTER_NAME_CHARS.contains(*c)).collect();
+    if !illegal.is_empty() {
+        return Err(format!("Computer name \"{}\" contains illegal character(s): {}", name, illegal));
+    }
+    if name.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Computer name \"{}\" cannot be only numbers", name));
+    }
+    Ok(())
+}
+
+/// Validate answer file XML (generated by MasterBooter or user-supplied).
+///
+/// # Returns
+/// `ValidationResult` — errors will make Windows Setup fail, warnings
+/// are suspicious but Setup will usually accept them.
+pub fn validate(xml: &str) -> ValidationResult {
+    match UnattendDocument::parse(xml) {
+        Ok(doc) => validate_document(&doc),
+        Err(e) => ValidationResult {
+            valid: false,
+            errors: vec![e],
+            warnings: Vec::new(),
+        },
+    }
+}
+
+/// Validate an already-parsed answer file (see validate()).
+pub fn validate_document(doc: &UnattendDocument) -> ValidationResult {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    for pass in &doc.passes {
+        for component in &pass.components {
+            let location = format!("{}/{}", pass.pass, component.name);
+
+            // Setup silently skips components for an unknown architecture
+            if !VALID_ARCHITECTURES.contains(&component.architecture.as_str()) {
+                errors.push(format!(
+                    "{}: processorArchitecture=\"{}\" is not valid (expected: {})",
+                    location,
+                    component.architecture,
+                    VALID_ARCHITECTURES.join(", ")
+                ));
+            }
+
+            // 1. Component placement
+            if let Some((_, passes)) = COMPONENT_PASSES.iter().find(|(name, _)| *name == component.name) {
+                if !passes.contains(&pass.pass) {
+                    errors.push(format!(
+                        "{}: component is not valid in the {} pass (allowed: {})",
+                        location,
+                        pass.pass,
+                        pass_list(passes)
+                    ));
+                }
+            }
+
+            for setting in &component.settings {
+                // 2. Setting placement
+                check_setting_placement(pass.pass, component, setting, &mut errors);
+
+                // 3-5. Recursive checks on the setting tree
+                check_setting_tree(&location, None, setting, &mut errors, &mut warnings);
+            }
+        }
+    }
+
+    ValidationResult {
+        valid: errors.is_empty(),
+        errors,
+        warnings,
+    }
+}
+
+/// Check that a top-level setting sits in a component and pass that accept it.
+fn check_setting_placement(pass: Pass, component: &Component, setting: &Setting, errors: &mut Vec<String>) {
+    let rules: Vec<_> = SETTING_PLACEMENT
+        .iter()
+        .filter(|(name, _, _)| *name == setting.name)
+        .collect();
+    if rules.is_empty() {
+        return; // Not a setting we know placement rules for
+    }
+
+    let location = format!("{}/{}/{}", pass, component.name, setting.name);
+    match rules.iter().find(|(_, comp, _)| *comp == component.name) {
+        Some((_, _, passes)) if !passes.contains(&pass) => {
+            errors.push(format!(
+                "{}: setting is not valid in the {} pass (allowed: {})",
+                location,
+                pass,
+                pass_list(passes)
+            ));
+        }
+        Some(_) => {}
+        None => {
+            let expected: Vec<String> = rules
+                .iter()
+                .map(|(_, comp, passes)| format!("{} ({})", comp, pass_list(passes)))
+                .collect();
+            errors.push(format!(
+                "{}: setting belongs in {}",
+                location,
+                expected.join(" or ")
+            ));
+        }
+    }
+}
+
+/// Walk a setting and its children checking ordered lists, value types
+/// and computer names.
+fn check_setting_tree(
+    location: &str,
+    parent: Option<&str>,
+    setting: &Setting,
+    errors: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) {
+    let path = format!("{}/{}", location, setting.name);
+
+    // Ordered lists: every item needs a unique, contiguous <Order>
+    if let Some((_, item_name)) = ORDERED_LISTS.iter().find(|(list, _)| *list == setting.name) {
+        check_order_list(&path, setting, item_name, errors, warnings);
+    }
+
+    if let Some(value) = setting.value.as_deref().filter(|_| setting.children.is_empty()) {
+        // Value types
+        let qualified = parent.map(|p| format!("{}/{}", p, setting.name));
+        let kind = VALUE_TYPES
+            .iter()
+            .find(|(key, _)| qualified.as_deref() == Some(*key))
+            .or_else(|| VALUE_TYPES.iter().find(|(key, _)| *key == setting.name))
+            .map(|(_, kind)| kind);
+        if let Some(problem) = kind.and_then(|kind| check_value(value, kind)) {
+            errors.push(format!("{} = \"{}\": {}", path, value, problem));
+        }
+
+        // Command line lengths
+        let limit = MAX_LENGTHS
+            .iter()
+            .find(|(key, _)| qualified.as_deref() == Some(*key))
+            .map(|(_, limit)| *limit);
+        if let Some(limit) = limit.filter(|limit| value.chars().count() > *limit) {
+            errors.push(format!(
+                "{}: {} characters long (limit is {})",
+                path,
+                value.chars().count(),
+                limit
+            ));
+        }
+
+        // Computer name rules
+        if setting.name == "ComputerName" {
+            if let Err(e) = validate_computer_name(value) {
+                errors.push(format!("{}: {}", path, e));
+            }
+        }
+    }
+
+    for child in &setting.children {
+        check_setting_tree(&path, Some(&setting.name), child, errors, warnings);
+    }
+}
+
+/// Check <Order> values in one ordered list.
+/// Duplicates and missing/invalid values are errors (Setup rejects them);
+/// gaps are warnings (Setup runs them, but usually something was deleted).
+fn check_order_list(
+    path: &str,
+    list: &Setting,
+    item_name: &str,
+    errors: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) {
+    let mut orders: Vec<u32> = Vec::new();
+
+    for (i, item) in list.children_named(item_name).enumerate() {
+        match item.child_text("Order").map(|o| o.parse::<u32>()) {
+            None => errors.push(format!("{}/{} #{}: missing <Order>", path, item_name, i + 1)),
+            Some(Err(_)) | Some(Ok(0)) => errors.push(format!(
+                "{}/{} #{}: <Order> must be a positive number, got \"{}\"",
+                path,
+                item_name,
+                i + 1,
+                item.child_text("Order").unwrap_or("")
+            )),
+            Some(Ok(order)) => {
+                if orders.contains(&order) {
+                    errors.push(format!("{}: duplicate <Order>{}</Order>", path, order));
+                }
+                orders.push(order);
+            }
+        }
+    }
+
+    orders.sort_unstable();
+    orders.dedup();
+    let expected: Vec<u32> = (1..=orders.len() as u32).collect();
+    if orders != expected {
+        let found: Vec<String> = orders.iter().map(|o| o.to_string()).collect();
+        warnings.push(format!(
+            "{}: <Order> values are not contiguous from 1 (found: {})",
+            path,
+            found.join(", ")
+        ));
+    }
+}
+
+/// Check a value against its expected kind. Returns a problem description.
+fn check_value(value: &str, kind: &ValueKind) -> Option<String> {
+    match kind {
+        ValueKind::Bool => {
+            if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+                None
+            } else {
+                Some("expected true or false".to_string())
+            }
+        }
+        ValueKind::UInt => {
+            if value.parse::<u64>().is_ok() {
+                None
+            } else {
+                Some("expected a whole number".to_string())
+            }
+        }
+        ValueKind::OneOf(allowed) => {
+            if allowed.iter().any(|a| a.eq_ignore_ascii_case(value)) {
+                None
+            } else {
+                Some(format!("expected one of: {}", allowed.join(", ")))
+            }
+        }
+    }
+}
+
+/// Format a list of passes for messages (e.g., "specialize, oobeSystem").
+fn pass_list(passes: &[Pass]) -> String {
+    passes.iter().map(|p| p.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_then_parse_roundtrip() {
+        let mut doc = UnattendDocument::default();
+        doc.pass_mut(Pass::Specialize).add(
+            Component::new("Microsoft-Windows-Shell-Setup", "amd64")
+                .with(Setting::value("ComputerName", "PC&01"))
+                .with(Setting::group("OOBE", vec![Setting::value("HideEULAPage", "true")])),
+        );
+
+        let xml = doc.to_xml();
+        assert!(xml.contains("<ComputerName>PC&amp;01</ComputerName>"));
+
+        let parsed = UnattendDocument::parse(&xml).unwrap();
+        assert_eq!(parsed, doc);
+        let shell = parsed.component(Pass::Specialize, "Microsoft-Windows-Shell-Setup").unwrap();
+        assert_eq!(shell.text("ComputerName"), Some("PC&01"));
+        assert_eq!(shell.text("OOBE/HideEULAPage"), Some("true"));
+    }
+
+    #[test]
+    fn test_parse_rejects_non_unattend() {
+        assert!(UnattendDocument::parse("<root/>").is_err());
+        assert!(UnattendDocument::parse("not xml").is_err());
+    }
+
+    #[test]
+    fn test_list_items_keep_wcm_action() {
+        let cmd = SynchronousCommand {
+            order: 1,
+            command_line: r#"reg add "HKLM\X" /f"#.to_string(),
+            description: "Test".to_string(),
+        };
+        let mut doc = UnattendDocument::default();
+        doc.pass_mut(Pass::OobeSystem).add(
+            Component::new("Microsoft-Windows-Shell-Setup", "amd64")
+                .with(Setting::group("FirstLogonCommands", vec![cmd.into_setting()])),
+        );
+        let xml = doc.to_xml();
+        assert!(xml.contains(r#"<SynchronousCommand wcm:action="add">"#));
+        assert!(xml.contains("reg add &quot;HKLM\\X&quot; /f"));
+
+        let parsed = UnattendDocument::parse(&xml).unwrap();
+        let shell = parsed.component(Pass::OobeSystem, "Microsoft-Windows-Shell-Setup").unwrap();
+        let item = shell.find("FirstLogonCommands/SynchronousCommand").unwrap();
+        assert_eq!(item.action.as_deref(), Some("add"));
+    }
+
+    #[test]
+    fn test_extensions_and_run_synchronous() {
+        let mut doc = UnattendDocument::default();
+        doc.pass_mut(Pass::Specialize).add(
+            Component::new("Microsoft-Windows-Deployment", "amd64").with(Setting::group("RunSynchronous", vec![
+                RunSynchronousCommand {
+                    order: 1,
+                    path: "cmd /c echo ok".to_string(),
+                    description: "Short".to_string(),
+                }.into_setting(),
+                RunSynchronousCommand {
+                    order: 2,
+                    path: format!("cmd /c echo {}", "x".repeat(260)),
+                    description: "Too long".to_string(),
+                }.into_setting(),
+            ])),
+        );
+        doc.extensions.push(Extension {
+            name: "Script".to_string(),
+            text: "$a = 'x' -and 1 < 2\nWrite-Host \"done\"".to_string(),
+        });
+
+        let xml = doc.to_xml();
+        assert!(xml.contains(r#"<Extensions xmlns="urn:masterbooter:extensions">"#));
+        let parsed = UnattendDocument::parse(&xml).unwrap();
+        assert_eq!(parsed, doc);
+        assert_eq!(parsed.extensions[0].text, "$a = 'x' -and 1 < 2\nWrite-Host \"done\"");
+
+        let result = validate_document(&parsed);
+        assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+        assert!(result.errors[0].contains("RunSynchronousCommand/Path: 272 characters long (limit is 259)"), "{:?}", result.errors);
+    }
+
+    #[test]
+    fn test_validate_computer_name() {
+        assert!(validate_computer_name("*").is_ok());
+        assert!(validate_computer_name("LAB-PC07").is_ok());
+        assert!(validate_computer_name("ACCOUNTING-LAPTOP").is_err()); // 17 chars
+        assert!(validate_computer_name("MY PC").is_err());
+        assert!(validate_computer_name("PC_01").is_err());
+        assert!(validate_computer_name("12345").is_err());
+        assert!(validate_computer_name("").is_err());
+    }
+
+    #[test]
+    fn test_validate_catches_common_mistakes() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
+    <settings pass="windowsPE">
+        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64">
+            <ComputerName>BAD NAME</ComputerName>
+        </component>
+    </settings>
+    <settings pass="specialize">
+        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64">
+            <OOBE><HideEULAPage>yes</HideEULAPage></OOBE>
+            <DiskConfiguration><WillShowUI>OnError</WillShowUI></DiskConfiguration>
+        </component>
+    </settings>
+    <settings pass="oobeSystem">
+        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64">
+            <FirstLogonCommands>
+                <SynchronousCommand wcm:action="add"><Order>1</Order><CommandLine>a</CommandLine></SynchronousCommand>
+                <SynchronousCommand wcm:action="add"><Order>1</Order><CommandLine>b</CommandLine></SynchronousCommand>
+                <SynchronousCommand wcm:action="add"><Order>4</Order><CommandLine>c</CommandLine></SynchronousCommand>
+            </FirstLogonCommands>
+        </component>
+    </settings>
+</unattend>"#;
+
+        let result = validate(xml);
+        let errors = result.errors.join("\n");
+        assert!(!result.valid);
+        assert!(errors.contains("windowsPE/Microsoft-Windows-Shell-Setup: component is not valid in the windowsPE pass"), "{}", errors);
+        assert!(errors.contains("ComputerName: Computer name \"BAD NAME\" contains illegal"), "{}", errors);
+        assert!(errors.contains("specialize/Microsoft-Windows-Shell-Setup/OOBE: setting is not valid in the specialize pass"), "{}", errors);
+        assert!(errors.contains("HideEULAPage = \"yes\": expected true or false"), "{}", errors);
+        assert!(errors.contains("DiskConfiguration: setting belongs in Microsoft-Windows-Setup (windowsPE)"), "{}", errors);
+        assert!(errors.contains("FirstLogonCommands: duplicate <Order>1</Order>"), "{}", errors);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("not contiguous from 1 (found: 1, 4)"));
+    }
+
+    #[test]
+    fn test_password_encoding() {
+        // Same values Windows System Image Manager writes
+        assert_eq!(encode_password("P@ssw0rd", "Password"), "UABAAHMAcwB3ADAAcgBkAFAAYQBzAHMAdwBvAHIAZAA=");
+        assert_eq!(
+            decode_password("RwBlAGgAZQBpAG0AMQBBAGQAbQBpAG4AaQBzAHQAcgBhAHQAbwByAFAAYQBzAHMAdwBvAHIAZAA=", "AdministratorPassword"),
+            Some("Geheim1".to_string())
+        );
+
+        // Wrong element suffix or garbage doesn't decode
+        assert_eq!(decode_password(&encode_password("x", "Password"), "AdministratorPassword"), None);
+        assert_eq!(decode_password("not base64!", "Password"), None);
+
+        let password = Password::encoded("Grüße €", "Password");
+        assert!(!password.plain_text);
+        assert_eq!(password.clear_text("Password"), Some("Grüße €".to_string()));
+    }
+}
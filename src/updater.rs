@@ -333,9 +333,8 @@ pub fn download_and_replace_exe(
 
         // Update progress (0-90% for download, 90-100% for replace)
         downloaded += bytes_read as u64;
-        if total_size > 0 {
-            let percent = ((downloaded * 90) / total_size) as u32;
-            progress_callback(percent.min(90)); // Cap at 90% during download
+        if let Some(percent) = (downloaded * 90).checked_div(total_size) {
+            progress_callback((percent as u32).min(90)); // Cap at 90% during download
         }
     }
 
@@ -0,0 +1,248 @@
+// ============================================
+// MasterBooter - wifi.rs
+// ============================================
+// This module turns the Wi-Fi networks in a deploy profile into WLAN
+// profile XML (the format `netsh wlan export profile` writes) and gets
+// them onto the installed Windows.
+//
+// How a network ends up connected:
+//
+//   1. After setup.exe has applied the image, execute() writes one
+//      <target>\Temp\MasterBooter\Wifi\wifiN.xml per network
+//      (write_to_target)
+//   2. The answer file's specialize pass imports them for all users with
+//      `netsh wlan add profile ... user=all` and deletes the files, so the
+//      keys don't stay on disk (IMPORT_COMMAND / CLEANUP_COMMAND)
+//   3. Windows connects on its own (connectionMode auto) — OOBE and the
+//      FirstLogon scripts already have internet access
+//
+// Keys are profile secrets: encrypted with the technician passphrase like
+// passwords, and never written into the answer file itself.
+// ============================================
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::unattend::escape_xml;
+
+/// Folder on the target (relative to its drive) that holds the profile files
+pub const PROFILE_DIR: &str = r"Temp\MasterBooter\Wifi";
+
+/// Specialize command that imports every profile file for all users
+pub const IMPORT_COMMAND: &str = r#"cmd /c for %f in ("%SystemDrive%\Temp\MasterBooter\Wifi\*.xml") do netsh wlan add profile filename="%f" user=all"#;
+/// Specialize command that deletes the profile files (they hold the keys in clear text)
+pub const CLEANUP_COMMAND: &str = r#"cmd /c rmdir /s /q "%SystemDrive%\Temp\MasterBooter\Wifi""#;
+
+/// WLAN profile XML namespace
+const WLAN_NS: &str = "http://www.microsoft.com/networking/WLAN/profile/v1";
+
+/// Security of a Wi-Fi network
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WifiAuth {
+    /// No password
+    Open,
+    /// WPA2-Personal (pre-shared key)
+    #[default]
+    WPA2,
+    /// WPA3-Personal (SAE)
+    WPA3,
+}
+
+impl WifiAuth {
+    /// Name shown in the UI dropdown
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WifiAuth::Open => "Open",
+            WifiAuth::WPA2 => "WPA2-Personal",
+            WifiAuth::WPA3 => "WPA3-Personal",
+        }
+    }
+
+    /// Parse the UI dropdown value
+    pub fn parse(value: &str) -> Option<WifiAuth> {
+        let value = value.trim().to_ascii_lowercase();
+        if value == "open" {
+            Some(WifiAuth::Open)
+        } else if value.starts_with("wpa2") {
+            Some(WifiAuth::WPA2)
+        } else if value.starts_with("wpa3") {
+            Some(WifiAuth::WPA3)
+        } else {
+            None
+        }
+    }
+
+    /// <authentication> and <encryption> values in the WLAN profile
+    fn auth_encryption(&self) -> (&'static str, &'static str) {
+        match self {
+            WifiAuth::Open => ("open", "none"),
+            WifiAuth::WPA2 => ("WPA2PSK", "AES"),
+            WifiAuth::WPA3 => ("WPA3SAE", "AES"),
+        }
+    }
+}
+
+/// One Wi-Fi network to set up on the installed Windows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WifiProfile {
+    /// Network name (SSID), case-sensitive
+    pub ssid: String,
+    #[serde(default)]
+    pub auth: WifiAuth,
+    /// Passphrase (8-63 characters) or 64 hex digits; empty for open networks
+    #[serde(default)]
+    pub key: String,
+    /// The network doesn't broadcast its SSID
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+impl WifiProfile {
+    /// Check the SSID and key.
+    ///
+    /// # Returns
+    /// * `Ok(())` — Windows will accept the profile
+    /// * `Err(String)` — what's wrong with it
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ssid.is_empty() {
+            return Err("SSID is empty".to_string());
+        }
+        if self.ssid.len() > 32 {
+            return Err(format!("SSID is {} bytes long (limit is 32)", self.ssid.len()));
+        }
+        match self.auth {
+            WifiAuth::Open if !self.key.is_empty() => {
+                Err("open network has a key (pick WPA2 or WPA3, or clear the key)".to_string())
+            }
+            WifiAuth::Open => Ok(()),
+            WifiAuth::WPA2 | WifiAuth::WPA3 => {
+                let is_hex_key = self.key.len() == 64 && self.key.chars().all(|c| c.is_ascii_hexdigit());
+                let length = self.key.chars().count();
+                if is_hex_key || ((8..=63).contains(&length) && self.key.chars().all(|c| (' '..='~').contains(&c))) {
+                    Ok(())
+                } else {
+                    Err("key must be 8-63 printable ASCII characters or 64 hex digits".to_string())
+                }
+            }
+        }
+    }
+
+    /// WLAN profile XML, as accepted by `netsh wlan add profile`.
+    /// The key is stored in clear text (protected=false) — Windows encrypts
+    /// it with the machine key when importing.
+    pub fn to_wlan_xml(&self) -> String {
+        let ssid = escape_xml(&self.ssid);
+        let hex: String = self.ssid.bytes().map(|b| format!("{:02X}", b)).collect();
+        let (authentication, encryption) = self.auth.auth_encryption();
+
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\"?>\n");
+        xml.push_str(&format!("<WLANProfile xmlns=\"{}\">\n", WLAN_NS));
+        xml.push_str(&format!("    <name>{}</name>\n", ssid));
+        xml.push_str("    <SSIDConfig>\n");
+        xml.push_str("        <SSID>\n");
+        xml.push_str(&format!("            <hex>{}</hex>\n", hex));
+        xml.push_str(&format!("            <name>{}</name>\n", ssid));
+        xml.push_str("        </SSID>\n");
+        xml.push_str(&format!("        <nonBroadcast>{}</nonBroadcast>\n", self.hidden));
+        xml.push_str("    </SSIDConfig>\n");
+        xml.push_str("    <connectionType>ESS</connectionType>\n");
+        xml.push_str("    <connectionMode>auto</connectionMode>\n");
+        xml.push_str("    <MSM>\n");
+        xml.push_str("        <security>\n");
+        xml.push_str("            <authEncryption>\n");
+        xml.push_str(&format!("                <authentication>{}</authentication>\n", authentication));
+        xml.push_str(&format!("                <encryption>{}</encryption>\n", encryption));
+        xml.push_str("                <useOneX>false</useOneX>\n");
+        xml.push_str("            </authEncryption>\n");
+        if self.auth != WifiAuth::Open {
+            let key_type = if self.key.len() == 64 { "networkKey" } else { "passPhrase" };
+            xml.push_str("            <sharedKey>\n");
+            xml.push_str(&format!("                <keyType>{}</keyType>\n", key_type));
+            xml.push_str("                <protected>false</protected>\n");
+            xml.push_str(&format!("                <keyMaterial>{}</keyMaterial>\n", escape_xml(&self.key)));
+            xml.push_str("            </sharedKey>\n");
+        }
+        xml.push_str("        </security>\n");
+        xml.push_str("    </MSM>\n");
+        xml.push_str("</WLANProfile>\n");
+        xml
+    }
+}
+
+/// Write one WLAN profile file per network to <target>\Temp\MasterBooter\Wifi.
+/// Files are numbered (wifi1.xml, ...) because SSIDs can contain
+/// characters that aren't allowed in file names.
+///
+/// # Arguments
+/// * `target_drive` — Drive of the installed Windows (e.g., "C:")
+/// * `profiles` — Networks from the deploy profile
+pub fn write_to_target(target_drive: &str, profiles: &[WifiProfile]) -> Result<(), String> {
+    let dir = PathBuf::from(format!("{}\\{}", target_drive, PROFILE_DIR));
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    for (i, profile) in profiles.iter().enumerate() {
+        let path = dir.join(format!("wifi{}.xml", i + 1));
+        fs::write(&path, profile.to_wlan_xml())
+            .map_err(|e| format!("Failed to write Wi-Fi profile \"{}\": {}", profile.ssid, e))?;
+        println!("[Wifi] Wrote profile for \"{}\" to {}", profile.ssid, path.display());
+    }
+    Ok(())
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wlan_profile_xml() {
+        let profile = WifiProfile {
+            ssid: "Café & Co".to_string(),
+            auth: WifiAuth::WPA2,
+            key: "s3cret<pass>".to_string(),
+            hidden: true,
+        };
+        let xml = profile.to_wlan_xml();
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let text = |name: &str| {
+            doc.descendants().find(|n| n.has_tag_name(name)).and_then(|n| n.text()).unwrap_or("").to_string()
+        };
+        assert_eq!(text("name"), "Café & Co");
+        assert_eq!(text("hex"), "436166C3A9202620436F");
+        assert_eq!(text("nonBroadcast"), "true");
+        assert_eq!(text("authentication"), "WPA2PSK");
+        assert_eq!(text("keyType"), "passPhrase");
+        assert_eq!(text("keyMaterial"), "s3cret<pass>");
+
+        let open = WifiProfile { ssid: "Guest".to_string(), auth: WifiAuth::Open, key: String::new(), hidden: false };
+        let xml = open.to_wlan_xml();
+        assert!(xml.contains("<authentication>open</authentication>"));
+        assert!(!xml.contains("sharedKey"));
+    }
+
+    #[test]
+    fn test_validate_profile() {
+        let profile = |ssid: &str, auth, key: &str| WifiProfile {
+            ssid: ssid.to_string(),
+            auth,
+            key: key.to_string(),
+            hidden: false,
+        };
+        assert!(profile("Office", WifiAuth::WPA2, "longenough").validate().is_ok());
+        assert!(profile("Office", WifiAuth::WPA3, &"a1".repeat(32)).validate().is_ok());
+        assert!(profile("Guest", WifiAuth::Open, "").validate().is_ok());
+        assert!(profile("", WifiAuth::WPA2, "longenough").validate().is_err());
+        assert!(profile(&"x".repeat(33), WifiAuth::WPA2, "longenough").validate().is_err());
+        assert!(profile("Office", WifiAuth::WPA2, "short").validate().is_err());
+        assert!(profile("Guest", WifiAuth::Open, "longenough").validate().is_err());
+        assert_eq!(WifiAuth::parse("WPA3-Personal"), Some(WifiAuth::WPA3));
+        assert!(IMPORT_COMMAND.len() <= 259 && CLEANUP_COMMAND.len() <= 259);
+    }
+}
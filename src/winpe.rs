@@ -977,7 +977,7 @@ fn mount_recovery_partition_and_find_winre(_guid: &str, winre_subpath: &str) ->
                 return Some(winre_mixed);
             }
 
-            println!("WinRE.wim not found in {}", format!(r"{}\{}\", drive_letter, subpath));
+            println!(r"WinRE.wim not found in {}\{}\", drive_letter, subpath);
         }
     }
 
@@ -1762,7 +1762,7 @@ pub fn install_all_dependencies() -> DependencyInstallResult {
     println!("Installing All Missing Dependencies");
     println!("========================================");
     println!("This will install: ADK, WinPE Add-on, 7-Zip");
-    println!("");
+    println!();
 
     let mut all_success = true;
     let mut next_steps = Vec::new();
@@ -2164,7 +2164,7 @@ pub struct PeBuildResult {
 ///     ADK: https://go.microsoft.com/fwlink/?linkid=2243390
 ///   Windows ADK for Windows 11 version 22H2 - 10.1.22621.1
 ///     ADK: https://go.microsoft.com/fwlink/?linkid=2196127
-
+///
 /// Find oscdimg.exe from the Windows ADK
 /// oscdimg is used to create bootable ISO files
 fn find_oscdimg() -> Option<PathBuf> {
@@ -2173,13 +2173,7 @@ fn find_oscdimg() -> Option<PathBuf> {
         PathBuf::from(r"C:\Program Files\Windows Kits\10\Assessment and Deployment Kit\Deployment Tools\amd64\Oscdimg\oscdimg.exe"),
     ];
 
-    for path in adk_paths {
-        if path.exists() {
-            return Some(path);
-        }
-    }
-
-    None
+    adk_paths.into_iter().find(|path| path.exists())
 }
 
 /// Run MakeWinPEMedia to create a bootable ISO
@@ -3098,12 +3092,12 @@ pub fn build_pe_iso(
                         String::new()
                     };
 
-                    return PeBuildResult {
+                    PeBuildResult {
                         success: true,
                         message: format!("WinPE ISO created successfully!\nSize: {}\nPath: {}{}",
                             iso_size, config.output_path.display(), verify_note),
                         output_path: Some(config.output_path.clone()),
-                    };
+                    }
                 } else {
                     let stderr = String::from_utf8_lossy(&out.stderr);
                     let stdout = String::from_utf8_lossy(&out.stdout);
@@ -3112,20 +3106,20 @@ pub fn build_pe_iso(
                     println!("stderr: {}", stderr);
 
                     let _ = fs::remove_dir_all(&work_dir);
-                    return PeBuildResult {
+                    PeBuildResult {
                         success: false,
                         message: format!("oscdimg failed: {}\n{}", stdout, stderr),
                         output_path: None,
-                    };
+                    }
                 }
             }
             Err(e) => {
                 let _ = fs::remove_dir_all(&work_dir);
-                return PeBuildResult {
+                PeBuildResult {
                     success: false,
                     message: format!("Failed to run oscdimg: {}", e),
                     output_path: None,
-                };
+                }
             }
         }
     } else {
@@ -3166,7 +3160,7 @@ pub fn build_pe_iso(
 
         progress_callback(100, "Build complete (folder only)");
 
-        return PeBuildResult {
+        PeBuildResult {
             success: true,
             message: format!(
                 "PE files created but ISO not built (oscdimg not found).\n\
@@ -3175,7 +3169,7 @@ pub fn build_pe_iso(
                 final_folder.display()
             ),
             output_path: Some(final_folder),
-        };
+        }
     }
 }
 
@@ -5352,7 +5346,7 @@ pub fn extract_wifi_drivers_from_local_windows() -> Result<PathBuf, String> {
                     // Look for .sys references in the INF
                     if trimmed.ends_with(".sys") || trimmed.contains(".sys,") || trimmed.contains(".sys ") {
                         // Extract the .sys filename
-                        let parts: Vec<&str> = line.split(|c: char| c == '=' || c == ',' || c == ';' || c == ' ')
+                        let parts: Vec<&str> = line.split(['=', ',', ';', ' '])
                             .map(|s| s.trim())
                             .filter(|s| s.to_lowercase().ends_with(".sys"))
                             .collect();
@@ -5378,7 +5372,7 @@ pub fn extract_wifi_drivers_from_local_windows() -> Result<PathBuf, String> {
                 let cat_name = format!("{}.cat", inf_stem);
                 let cat_source = inf_dir.join(&cat_name);
                 if cat_source.exists() {
-                    let _ = fs::copy(&cat_source, &mfr_dir.join(&cat_name));
+                    let _ = fs::copy(&cat_source, mfr_dir.join(&cat_name));
                 }
             }
 
@@ -5648,7 +5642,7 @@ pub fn inject_wifi_support(mount_dir: &Path, source_windows_dir: &Path) -> Resul
             let mut schema_count = 0;
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.extension().map_or(false, |e| e.to_string_lossy().to_lowercase() == "xsd") {
+                if path.extension().is_some_and(|e| e.to_string_lossy().to_lowercase() == "xsd") {
                     let dest = l2schemas_dest.join(entry.file_name());
                     let _ = fs::copy(&path, &dest);
                     schema_count += 1;
@@ -5666,7 +5660,7 @@ pub fn inject_wifi_support(mount_dir: &Path, source_windows_dir: &Path) -> Resul
         if let Ok(entries) = fs::read_dir(&avail_net_src) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.extension().map_or(false, |e| e.to_string_lossy().to_lowercase() == "xsd") {
+                if path.extension().is_some_and(|e| e.to_string_lossy().to_lowercase() == "xsd") {
                     let dest = avail_net_dest.join(entry.file_name());
                     let _ = fs::copy(&path, &dest);
                 }